@@ -5,17 +5,18 @@ use gmio::{
     window::{Event, Window, WindowBuilder},
 };
 use shared::{
-    input,
+    input::{self, JoystickAxis, JoystickEvent, JoystickInfo},
     message::{self, Information, InstanceDetails, MessageStream},
     types::{Colour, ID},
 };
 use std::{net::TcpStream, path::PathBuf};
 
 const WINDOW_WIDTH: u32 = 350;
-const WINDOW_HEIGHT: u32 = 750;
+const WINDOW_HEIGHT: u32 = 860;
 
 const KEY_BUTTON_SIZE: usize = 48;
 const SAVE_BUTTON_SIZE: usize = 32;
+const JOYSTICK_STICK_SIZE: usize = 64;
 
 pub struct ControlPanel {
     pub window: Window,
@@ -27,6 +28,9 @@ pub struct ControlPanel {
     pub key_buttons: Vec<KeyButton>,
     pub mouse_buttons: Vec<MouseButton>,
     pub mouse_position_button: MousePositionButton,
+    pub joystick_buttons: Vec<JoystickButton>,
+    pub joystick_stick: JoystickStick,
+    pub joystick_connected: bool,
     pub big_save_button: BigSaveButton,
     pub save_buttons: Vec<SaveButton>,
     pub seed_changer: SeedChanger,
@@ -58,6 +62,7 @@ pub struct ControlPanel {
     button_outline: AtlasRef,
 
     menu_context: Option<MenuContext>,
+    profiling: bool,
 
     pub read_buffer: Vec<u8>,
     pub project_dir: PathBuf,
@@ -67,6 +72,7 @@ pub struct ControlPanel {
 pub enum MenuContext {
     KeyButton(input::Key),
     MouseButton(input::MouseButton),
+    JoystickButton(u8),
     SaveButton(String),
     BigSaveButton,
 }
@@ -94,6 +100,22 @@ pub struct MouseButton {
     pub state: ButtonState,
 }
 
+#[derive(Clone, Copy)]
+pub struct JoystickButton {
+    pub x: i32,
+    pub y: i32,
+    pub button: u8,
+    pub state: ButtonState,
+}
+
+#[derive(Clone, Copy)]
+pub struct JoystickStick {
+    pub x: i32,
+    pub y: i32,
+    pub pos: (f64, f64),
+    pub new_pos: Option<(f64, f64)>,
+}
+
 #[derive(Clone, Copy)]
 pub struct MousePositionButton {
     pub x: i32,
@@ -164,6 +186,28 @@ impl MouseButton {
     }
 }
 
+impl JoystickButton {
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < (self.x + KEY_BUTTON_SIZE as i32) && y >= self.y && y < (self.y + KEY_BUTTON_SIZE as i32)
+    }
+}
+
+impl JoystickStick {
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && x < (self.x + JOYSTICK_STICK_SIZE as i32)
+            && y >= self.y
+            && y < (self.y + JOYSTICK_STICK_SIZE as i32)
+    }
+
+    /// Converts a point inside the stick area to axis positions from -1.0 to 1.0
+    pub fn point_to_pos(&self, x: i32, y: i32) -> (f64, f64) {
+        let half = JOYSTICK_STICK_SIZE as f64 / 2.0;
+        let to_axis = |p: i32, origin: i32| ((f64::from(p - origin) + 0.5 - half) / half).max(-1.0).min(1.0);
+        (to_axis(x, self.x), to_axis(y, self.y))
+    }
+}
+
 impl MousePositionButton {
     pub fn contains_point(&self, x: i32, y: i32) -> bool {
         x >= self.x && x < (self.x + 25 as i32) && y >= self.y && y < (self.y + 25 as i32)
@@ -298,6 +342,11 @@ impl ControlPanel {
                 MouseButton { x: 108, y: 248, button: input::MouseButton::Right, state: ButtonState::Neutral },
             ],
             mouse_position_button: MousePositionButton { x: 310, y: 250, active: false },
+            joystick_buttons: (0..4)
+                .map(|i| JoystickButton { x: 4 + 52 * i, y: 790, button: i as u8, state: ButtonState::Neutral })
+                .collect(),
+            joystick_stick: JoystickStick { x: 216, y: 782, pos: (0.0, 0.0), new_pos: None },
+            joystick_connected: false,
             big_save_button: BigSaveButton { x: 125, y: 400 },
             save_buttons,
             seed_changer: SeedChanger { x: 8, y: 540 },
@@ -329,6 +378,7 @@ impl ControlPanel {
             button_outline,
 
             menu_context: None,
+            profiling: false,
             read_buffer: Vec::new(),
             project_dir,
         })
@@ -398,6 +448,26 @@ impl ControlPanel {
                         }
                     }
 
+                    for button in self.joystick_buttons.iter_mut() {
+                        if button.contains_point(self.mouse_x, self.mouse_y) {
+                            button.state = match button.state {
+                                ButtonState::Neutral => ButtonState::NeutralWillPress,
+                                ButtonState::NeutralWillPress
+                                | ButtonState::NeutralWillPR
+                                | ButtonState::NeutralWillPRP => ButtonState::Neutral,
+                                ButtonState::Held => ButtonState::HeldWillRelease,
+                                ButtonState::HeldWillRelease | ButtonState::HeldWillRP | ButtonState::HeldWillRPR => {
+                                    ButtonState::Held
+                                },
+                            };
+                        }
+                    }
+
+                    if self.joystick_stick.contains_point(self.mouse_x, self.mouse_y) {
+                        self.joystick_stick.new_pos =
+                            Some(self.joystick_stick.point_to_pos(self.mouse_x, self.mouse_y));
+                    }
+
                     for button in self.save_buttons.iter_mut() {
                         if button.contains_point(self.mouse_x, self.mouse_y) {
                             self.stream.send_message(&message::Message::Save { filename: button.filename.clone() })?;
@@ -485,6 +555,39 @@ impl ControlPanel {
                         }
                     }
 
+                    for button in self.joystick_buttons.iter_mut() {
+                        if button.contains_point(self.mouse_x, self.mouse_y) {
+                            let options = match button.state {
+                                ButtonState::Neutral
+                                | ButtonState::NeutralWillPress
+                                | ButtonState::NeutralWillPR
+                                | ButtonState::NeutralWillPRP => [
+                                    ("Press-Release-Press\0".into(), 3),
+                                    ("Press-Release\0".into(), 2),
+                                    ("Press\0".into(), 1),
+                                    ("Reset\0".into(), 0),
+                                ],
+                                ButtonState::Held
+                                | ButtonState::HeldWillRelease
+                                | ButtonState::HeldWillRP
+                                | ButtonState::HeldWillRPR => [
+                                    ("Release-Press-Release\0".into(), 7),
+                                    ("Release-Press\0".into(), 6),
+                                    ("Release\0".into(), 5),
+                                    ("Reset\0".into(), 4),
+                                ],
+                            };
+                            self.window.show_context_menu(&options);
+                            self.menu_context = Some(MenuContext::JoystickButton(button.button));
+                            break 'evloop
+                        }
+                    }
+
+                    if self.joystick_stick.contains_point(self.mouse_x, self.mouse_y) {
+                        // Right-clicking the stick recentres it
+                        self.joystick_stick.new_pos = Some((0.0, 0.0));
+                    }
+
                     for button in self.save_buttons.iter() {
                         if button.contains_point(self.mouse_x, self.mouse_y) && button.exists {
                             self.window.show_context_menu(&[("Load\0".into(), 1), ("Save\0".into(), 0)]);
@@ -542,6 +645,26 @@ impl ControlPanel {
                             }
                         },
 
+                        Some(MenuContext::JoystickButton(target_button)) => {
+                            let new_state = match option {
+                                0 => ButtonState::Neutral,
+                                1 => ButtonState::NeutralWillPress,
+                                2 => ButtonState::NeutralWillPR,
+                                3 => ButtonState::NeutralWillPRP,
+                                4 => ButtonState::Held,
+                                5 => ButtonState::HeldWillRelease,
+                                6 => ButtonState::HeldWillRP,
+                                7 => ButtonState::HeldWillRPR,
+                                _ => continue,
+                            };
+
+                            for button in self.joystick_buttons.iter_mut() {
+                                if button.button == *target_button {
+                                    button.state = new_state;
+                                }
+                            }
+                        },
+
                         Some(MenuContext::SaveButton(filename)) => {
                             match option {
                                 0 => {
@@ -606,6 +729,17 @@ impl ControlPanel {
                 println!("Loaded");
             },
 
+            input::Key::P => {
+                self.profiling = !self.profiling;
+                self.stream.send_message(&message::Message::SetProfiling { enabled: self.profiling })?;
+                println!("Profiler {}", if self.profiling { "started" } else { "stopped" });
+            },
+
+            input::Key::O => {
+                self.stream.send_message(&message::Message::ProfileReport)?;
+                self.await_profile_report()?;
+            },
+
             _ => (),
         }
 
@@ -673,10 +807,60 @@ impl ControlPanel {
             }
         }
 
+        let mut joystick_inputs = Vec::new();
+
+        for button in self.joystick_buttons.iter() {
+            let (down, up) = (JoystickEvent::ButtonDown(0, button.button), JoystickEvent::ButtonUp(0, button.button));
+            match button.state {
+                ButtonState::Neutral | ButtonState::Held => (),
+                ButtonState::NeutralWillPress => joystick_inputs.push(down),
+                ButtonState::HeldWillRelease => joystick_inputs.push(up),
+                ButtonState::NeutralWillPR => {
+                    joystick_inputs.push(down);
+                    joystick_inputs.push(up);
+                },
+                ButtonState::NeutralWillPRP => {
+                    joystick_inputs.push(down.clone());
+                    joystick_inputs.push(up);
+                    joystick_inputs.push(down);
+                },
+                ButtonState::HeldWillRP => {
+                    joystick_inputs.push(up);
+                    joystick_inputs.push(down);
+                },
+                ButtonState::HeldWillRPR => {
+                    joystick_inputs.push(up.clone());
+                    joystick_inputs.push(down);
+                    joystick_inputs.push(up);
+                },
+            }
+        }
+
+        if let Some((x, y)) = self.joystick_stick.new_pos {
+            if x != self.joystick_stick.pos.0 {
+                joystick_inputs.push(JoystickEvent::Axis(0, JoystickAxis::X, x));
+            }
+            if y != self.joystick_stick.pos.1 {
+                joystick_inputs.push(JoystickEvent::Axis(0, JoystickAxis::Y, y));
+            }
+        }
+
+        // The game only sees a joystick once the user touches one, so games which check for one behave as normal
+        if !self.joystick_connected && !joystick_inputs.is_empty() {
+            let info = JoystickInfo {
+                name: "Control Panel".into(),
+                axes: 2,
+                buttons: self.joystick_buttons.len() as u8,
+                has_pov: false,
+            };
+            joystick_inputs.insert(0, JoystickEvent::Connect(0, info));
+        }
+
         self.stream.send_message(message::Message::Advance {
             key_inputs,
             mouse_inputs,
             mouse_location: self.game_mouse_pos,
+            joystick_inputs,
             keys_requested,
             mouse_buttons_requested,
             instance_requested: self.watched_id,
@@ -696,6 +880,7 @@ impl ControlPanel {
                     frame_count,
                     seed,
                     instance,
+                    joystick,
                 }))) => {
                     self.frame_count = frame_count;
                     self.game_mouse_pos = mouse_location;
@@ -716,6 +901,16 @@ impl ControlPanel {
                             button.state = ButtonState::Neutral;
                         }
                     }
+                    self.joystick_connected = joystick.is_some();
+                    self.joystick_stick.pos = joystick.as_ref().map_or((0.0, 0.0), |j| (j.x, j.y));
+                    self.joystick_stick.new_pos = None;
+                    for button in self.joystick_buttons.iter_mut() {
+                        if joystick.as_ref().map_or(false, |j| j.buttons_held.contains(&button.button)) {
+                            button.state = ButtonState::Held;
+                        } else {
+                            button.state = ButtonState::Neutral;
+                        }
+                    }
                     break Ok(true)
                 },
                 Err(e) => break Err(e.into()),
@@ -724,6 +919,32 @@ impl ControlPanel {
         }
     }
 
+    /// Waits for a profile report, then prints it and saves it as profile.folded in the project directory.
+    fn await_profile_report(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (entries, folded) = loop {
+            match self.stream.receive_message::<Information>(&mut self.read_buffer)? {
+                Some(Some(Information::ProfileReport { entries, folded })) => break (entries, folded),
+                Some(_) => std::thread::yield_now(),
+                None => return Ok(()),
+            }
+        };
+        println!("{:>12} {:>12} {:>10}  name", "inclusive ms", "exclusive ms", "calls");
+        for entry in entries.iter().take(30) {
+            println!(
+                "{:>12.3} {:>12.3} {:>10}  {}",
+                entry.inclusive.as_secs_f64() * 1000.0,
+                entry.exclusive.as_secs_f64() * 1000.0,
+                entry.calls,
+                entry.name
+            );
+        }
+        let mut path = self.project_dir.clone();
+        path.push("profile.folded");
+        std::fs::write(&path, folded)?;
+        println!("Saved folded stacks to {}", path.display());
+        Ok(())
+    }
+
     pub fn draw(&mut self) {
         self.renderer.set_view(
             0,
@@ -847,6 +1068,88 @@ impl ControlPanel {
             );
         }
 
+        for button in self.joystick_buttons.iter() {
+            let alpha = if button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 };
+            let atlas_ref_l = match button.state {
+                ButtonState::Neutral
+                | ButtonState::NeutralWillPress
+                | ButtonState::NeutralWillPR
+                | ButtonState::NeutralWillPRP => &self.key_button_l_neutral,
+                ButtonState::Held
+                | ButtonState::HeldWillRelease
+                | ButtonState::HeldWillRP
+                | ButtonState::HeldWillRPR => &self.key_button_l_held,
+            };
+            let atlas_ref_r = match button.state {
+                ButtonState::Neutral | ButtonState::HeldWillRelease => &self.key_button_r_neutral,
+                ButtonState::Held | ButtonState::NeutralWillPress => &self.key_button_r_held,
+                ButtonState::NeutralWillPR => &self.key_button_r_held2,
+                ButtonState::NeutralWillPRP => &self.key_button_r_held3,
+                ButtonState::HeldWillRP => &self.key_button_r_neutral2,
+                ButtonState::HeldWillRPR => &self.key_button_r_neutral3,
+            };
+            self.renderer.draw_sprite(atlas_ref_l, button.x as _, button.y as _, 1.0, 1.0, 0.0, 0xFFFFFF, alpha);
+            self.renderer.draw_sprite(
+                atlas_ref_r,
+                (button.x + atlas_ref_l.w) as _,
+                button.y as _,
+                1.0,
+                1.0,
+                0.0,
+                0xFFFFFF,
+                alpha,
+            );
+            draw_text(
+                &mut self.renderer,
+                &(button.button + 1).to_string(),
+                f64::from(button.x) + 18.0,
+                f64::from(button.y) + 30.0,
+                &self.font,
+                0,
+                alpha,
+            );
+            self.renderer.draw_sprite(
+                &self.button_outline,
+                f64::from(button.x),
+                f64::from(button.y),
+                1.0,
+                1.0,
+                0.0,
+                0xFFFFFF,
+                alpha,
+            );
+        }
+
+        let stick = self.joystick_stick;
+        let stick_alpha = if stick.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 };
+        let (stick_x1, stick_y1) = (f64::from(stick.x), f64::from(stick.y));
+        let size = JOYSTICK_STICK_SIZE as f64;
+        let (stick_x2, stick_y2) = (stick_x1 + size - 1.0, stick_y1 + size - 1.0);
+        let half = size / 2.0;
+        self.renderer.draw_rectangle(stick_x1, stick_y1, stick_x2, stick_y2, 0xFFFFFF, stick_alpha);
+        self.renderer.draw_rectangle_outline(stick_x1, stick_y1, stick_x2, stick_y2, 0, stick_alpha);
+        let (pos_x, pos_y) = stick.pos;
+        self.renderer.draw_rectangle(
+            stick_x1 + half * (pos_x + 1.0) - 2.0,
+            stick_y1 + half * (pos_y + 1.0) - 2.0,
+            stick_x1 + half * (pos_x + 1.0) + 1.0,
+            stick_y1 + half * (pos_y + 1.0) + 1.0,
+            0,
+            stick_alpha,
+        );
+        if let Some((new_x, new_y)) = stick.new_pos {
+            self.renderer.draw_rectangle(
+                stick_x1 + half * (new_x + 1.0) - 2.0,
+                stick_y1 + half * (new_y + 1.0) - 2.0,
+                stick_x1 + half * (new_x + 1.0) + 1.0,
+                stick_y1 + half * (new_y + 1.0) + 1.0,
+                0xFF,
+                stick_alpha,
+            );
+        }
+        draw_text(&mut self.renderer, &format!("x: {:.2}", pos_x), 288.0, 800.0, &self.font_small, 0, 1.0);
+        draw_text(&mut self.renderer, &format!("y: {:.2}", pos_y), 288.0, 820.0, &self.font_small, 0, 1.0);
+
         self.renderer.draw_sprite(
             &self.mouse_pos_normal,
             self.mouse_position_button.x as _,
@@ -896,6 +1199,15 @@ impl ControlPanel {
         draw_text(&mut self.renderer, "Keyboard", 123.0, 82.0, &self.font, 0, 1.0);
         draw_text(&mut self.renderer, "Mouse", 143.0, 236.0, &self.font, 0, 1.0);
         draw_text(&mut self.renderer, "Saves", 143.0, 390.0, &self.font, 0, 1.0);
+        draw_text(
+            &mut self.renderer,
+            if self.joystick_connected { "Joystick" } else { "Joystick (not connected)" },
+            4.0,
+            775.0,
+            &self.font,
+            0,
+            1.0,
+        );
 
         let (seed, seed_col) = if let Some(s) = self.new_seed { (s, 0xFF) } else { (self.seed, 0) };
         draw_text(
@@ -1,7 +1,9 @@
 use gl_generator::{Api, Fallbacks, GlobalGenerator, Profile, Registry};
 use std::{
+    collections::HashMap,
     env,
     error::Error,
+    fmt::Write,
     fs::{self, File},
     path::Path,
 };
@@ -40,6 +42,12 @@ fn main() -> Result<(), Box<dyn Error>> {
         fs::write(aa_macro_path, &aa_macro)?;
     }
 
+    // arg counts and which functions are implemented, worked out from the kernel itself
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/gml/kernel.rs");
+    println!("cargo:rerun-if-changed=src/gml/compiler/mappings.rs");
+    fs::write(Path::new(&out).join("kernel_info.rs"), kernel_info()?)?;
+
     // opengl bindings
     let mut bindings = File::create(&Path::new(&out).join("gl_bindings.rs"))?;
     Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, &OPENGL_EXTENSIONS)
@@ -76,3 +84,73 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+/// What a kernel function's source says about it.
+struct KernelFunction {
+    /// The number of arguments it expects, if it always expects the same number
+    arg_count: Option<usize>,
+    implemented: bool,
+}
+
+/// Generates `Function::arg_count` and `Function::is_implemented` from the kernel functions' source.
+/// A function's arg count comes from its `expect_args!`, or from the `// Expected arg count` note if it's
+/// unimplemented. The kernel function each `Function` calls is found from `Function::call` in mappings.rs.
+fn kernel_info() -> Result<String, Box<dyn Error>> {
+    let kernel = fs::read_to_string("src/gml/kernel.rs")?;
+    let mappings = fs::read_to_string("src/gml/compiler/mappings.rs")?;
+
+    let mut functions = HashMap::new();
+    for body in kernel.split("\n    pub fn ").skip(1) {
+        let name = &body[..body.find('(').ok_or("kernel function without arguments")?];
+        let mut arg_counts = body.match_indices("expect_args!(args, [").map(|(i, pattern)| {
+            let args = &body[i + pattern.len()..];
+            args[..args.find(']').unwrap_or(0)].split(',').filter(|arg| !arg.trim().is_empty()).count()
+        });
+        let arg_count = match arg_counts.next() {
+            Some(first) => Some(first).filter(|_| arg_counts.all(|count| count == first)),
+            None => body
+                .split("// Expected arg count: ")
+                .nth(1)
+                .and_then(|note| note.lines().next())
+                .map(|count| count.trim().parse())
+                .transpose()?,
+        };
+        functions.insert(name, KernelFunction { arg_count, implemented: !body.contains("unimplemented!(") });
+    }
+
+    let mut arg_counts = String::new();
+    let mut unimplemented = Vec::new();
+    for line in mappings.lines() {
+        let arm = match line.trim().strip_prefix("Self::").and_then(|arm| arm.strip_suffix(',')) {
+            Some(arm) => arm,
+            None => continue,
+        };
+        if let Some((variant, kernel_name)) = arm.split_once(" => Game::") {
+            let function = functions.get(kernel_name).ok_or_else(|| format!("no kernel function {}", kernel_name))?;
+            if let Some(count) = function.arg_count {
+                writeln!(arg_counts, "            Self::{} => Some({}),", variant, count)?;
+            }
+            if !function.implemented {
+                unimplemented.push(format!("Self::{}", variant));
+            }
+        }
+    }
+
+    let mut info = String::new();
+    writeln!(info, "impl Function {{")?;
+    writeln!(info, "    /// The number of arguments this function expects, if it always expects the same number.")?;
+    writeln!(info, "    pub fn arg_count(&self) -> Option<usize> {{")?;
+    writeln!(info, "        match self {{\n{}            _ => None,\n        }}\n    }}\n", arg_counts)?;
+    writeln!(
+        info,
+        "    /// Whether the emulator implements this function yet. Calling one which it doesn't crashes the emulator."
+    )?;
+    writeln!(info, "    pub fn is_implemented(&self) -> bool {{")?;
+    if unimplemented.is_empty() {
+        writeln!(info, "        true")?;
+    } else {
+        writeln!(info, "        !matches!(self, {})", unimplemented.join(" | "))?;
+    }
+    writeln!(info, "    }}\n}}")?;
+    Ok(info)
+}
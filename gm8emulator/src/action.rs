@@ -1,14 +1,16 @@
 use crate::{
-    game::Game,
+    game::{profiler::Timed, Game},
     gml::{
         self,
+        bytecode::Program,
         compiler::{mappings, Compiler},
-        runtime::{Instruction, Node},
+        runtime::{CodeSource, Node},
         Context, Value,
     },
 };
 use gm8exe::asset::etc::CodeAction;
 use serde::{Deserialize, Serialize};
+use shared::message::CodeLocation;
 use std::{cell::RefCell, rc::Rc, str};
 
 /// Consts which match those used in GM8
@@ -44,6 +46,10 @@ pub struct Action {
     /// If this is a question action, this flag means the bool result will be inverted.
     pub invert_condition: bool,
 
+    /// The library this action came from and its ID in that library.
+    /// A value of None means it wasn't made from a D&D action, for example code added by object_event_add.
+    pub library_action: Option<(u32, u32)>,
+
     /// Body of this action. Body type depends on the action_kind.
     pub body: Body,
 }
@@ -79,7 +85,7 @@ pub enum Body {
 #[derive(Serialize, Deserialize)]
 pub enum GmlBody {
     Function(gml::Function),
-    Code(Rc<[Instruction]>),
+    Code(Rc<Program>),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -156,6 +162,7 @@ impl Tree {
                                     target: if action.applies_to_something { Some(action.applies_to) } else { None },
                                     relative: action.is_relative,
                                     invert_condition: action.invert_condition,
+                                    library_action: Some((action.lib_id, action.id)),
                                     body: Body::Normal {
                                         args: Self::compile_params(
                                             compiler,
@@ -180,6 +187,7 @@ impl Tree {
                                 target: if action.applies_to_something { Some(action.applies_to) } else { None },
                                 relative: action.is_relative,
                                 invert_condition: action.invert_condition,
+                                library_action: Some((action.lib_id, action.id)),
                                 body: Body::Normal {
                                     args: Self::compile_params(
                                         compiler,
@@ -205,6 +213,7 @@ impl Tree {
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
+                        library_action: Some((action.lib_id, action.id)),
                         body: Body::Exit,
                     });
                 },
@@ -217,6 +226,7 @@ impl Tree {
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
+                        library_action: Some((action.lib_id, action.id)),
                         body: Body::Repeat {
                             count: compiler.compile_expression(&action.param_strings[0].0).map_err(|e| e.message)?,
                             body: body.into_boxed_slice(),
@@ -233,6 +243,7 @@ impl Tree {
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
+                        library_action: Some((action.lib_id, action.id)),
                         body: Body::Normal {
                             args: Box::new([]),
                             body: GmlBody::Code(compiler.compile(&code).map_err(|e| e.message)?),
@@ -247,6 +258,7 @@ impl Tree {
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
+                        library_action: Some((action.lib_id, action.id)),
                         body: Body::Normal {
                             args: Box::new([]),
                             body: GmlBody::Code(compiler.compile(&action.param_strings[0].0).map_err(|e| e.message)?),
@@ -286,23 +298,44 @@ impl Tree {
             .into_boxed_slice())
     }
 
-    pub fn new_from_code(code: Rc<[Instruction]>) -> Rc<RefCell<Self>> {
+    /// The top-level actions in this tree.
+    pub fn actions(&self) -> &[Action] {
+        &self.0
+    }
+
+    pub fn new_from_code(code: Rc<Program>) -> Rc<RefCell<Self>> {
         let mut tree = Self(Vec::new());
         tree.push_code(code);
         Rc::new(RefCell::new(tree))
     }
 
-    pub fn push_code(&mut self, code: Rc<[Instruction]>) {
+    pub fn push_code(&mut self, code: Rc<Program>) {
         self.0.push(Action {
             index: self.0.len(),
             target: None,
             relative: false,
             invert_condition: false,
+            library_action: None,
             body: Body::Normal { args: Box::new([]), body: GmlBody::Code(code), if_else: None },
         });
     }
 }
 
+/// Gets the GML an action runs, the same way it's put together when the action is compiled.
+pub fn action_code(action: &CodeAction) -> Option<Vec<u8>> {
+    match action.action_kind {
+        kind::CODE => Some(action.param_strings[0].0.to_vec()),
+        kind::VARIABLE => {
+            let mut code = action.param_strings[0].0.to_vec();
+            code.extend_from_slice(if action.is_relative { b"+=" } else { b"=" });
+            code.extend_from_slice(&action.param_strings[1].0);
+            Some(code)
+        },
+        kind::NORMAL if action.execution_type == execution_type::CODE => Some(action.fn_code.0.to_vec()),
+        _ => None,
+    }
+}
+
 impl Game {
     /// Executes all the actions in a tree.
     pub fn execute_tree(
@@ -314,7 +347,10 @@ impl Game {
         event_number: usize,
         as_object: i32,
     ) -> gml::Result<()> {
-        self.exec_slice(&tree.borrow().0, this, other, event_type, event_number, as_object)?;
+        let timed = Timed::Event { object: as_object, event_type, event_number };
+        self.profile(timed, |game| {
+            game.exec_slice(&tree.borrow().0, this, other, event_type, event_number, as_object)
+        })?;
         Ok(())
     }
 
@@ -355,58 +391,14 @@ impl Game {
                     }
                     */
 
-                    let mut returned_value = Default::default();
-                    match action.target {
-                        None | Some(gml::SELF) | Some(gml::OTHER) => {
-                            if action.target == Some(gml::OTHER) {
-                                context.this = other;
-                                context.other = this;
-                            }
-
-                            let mut arg_values: [Value; 16] = Default::default();
-                            for (dest, src) in arg_values.iter_mut().zip(args.iter()) {
-                                *dest = self.eval(src, &mut context)?;
-                            }
-
-                            returned_value = match gml_body {
-                                GmlBody::Function(f) => f.call(self, &mut context, &arg_values[..args.len()])?,
-                                GmlBody::Code(code) => {
-                                    context.arguments = arg_values;
-                                    context.argument_count = args.len();
-                                    self.execute(code, &mut context)?;
-                                    context.return_value
-                                },
-                            };
+                    // If the player ignores an error, the action carries on as if it returned 0
+                    let returned_value = match self.exec_action_body(args, gml_body, action.target, &mut context) {
+                        Ok(value) => value,
+                        Err(e) => {
+                            self.handle_error(e.in_code(self.action_source(action, event_type, event_number, as_object)))?;
+                            Default::default()
                         },
-                        Some(i) if i < 0 => (),
-                        Some(i) => {
-                            if let Some(Some(object)) = self.assets.objects.get(i as usize) {
-                                context.other = this;
-                                let ids = object.children.clone();
-                                let mut iter = self.instance_list.iter_by_identity(ids);
-                                while let Some(instance) = iter.next(&self.instance_list) {
-                                    context.this = instance;
-
-                                    let mut arg_values: [Value; 16] = Default::default();
-                                    for (dest, src) in arg_values.iter_mut().zip(args.iter()) {
-                                        *dest = self.eval(src, &mut context)?;
-                                    }
-
-                                    returned_value = match gml_body {
-                                        GmlBody::Function(f) => {
-                                            f.call(self, &mut context, &arg_values[..args.len()])?
-                                        },
-                                        GmlBody::Code(code) => {
-                                            context.arguments = arg_values;
-                                            context.argument_count = args.len();
-                                            self.execute(code, &mut context)?;
-                                            context.return_value.clone()
-                                        },
-                                    };
-                                }
-                            }
-                        },
-                    }
+                    };
 
                     if let Some((if_body, else_body)) = if_else {
                         let target =
@@ -431,7 +423,13 @@ impl Game {
                         locals: Default::default(),
                         return_value: Default::default(),
                     };
-                    let mut count = i32::from(self.eval(count, &mut context)?);
+                    let mut count = match self.eval(count, &mut context) {
+                        Ok(count) => i32::from(count),
+                        Err(e) => {
+                            self.handle_error(e.in_code(self.action_source(action, event_type, event_number, as_object)))?;
+                            0
+                        },
+                    };
                     while count > 0 {
                         match self.exec_slice(body, this, other, event_type, event_number, as_object)? {
                             ReturnType::Continue => (),
@@ -446,4 +444,81 @@ impl Game {
 
         Ok(ReturnType::Continue)
     }
+
+    /// Runs the function or code of an action on its targets, returning what it returned for the last one.
+    fn exec_action_body(
+        &mut self,
+        args: &[Node],
+        gml_body: &GmlBody,
+        target: Option<i32>,
+        context: &mut Context,
+    ) -> gml::Result<Value> {
+        let (this, other) = (context.this, context.other);
+        let location = CodeLocation::Action {
+            object: context.event_object,
+            event_type: context.event_type,
+            event_number: context.event_number,
+            action: context.event_action,
+        };
+        let mut returned_value = Default::default();
+        match target {
+            None | Some(gml::SELF) | Some(gml::OTHER) => {
+                if target == Some(gml::OTHER) {
+                    context.this = other;
+                    context.other = this;
+                }
+
+                let mut arg_values: [Value; 16] = Default::default();
+                for (dest, src) in arg_values.iter_mut().zip(args.iter()) {
+                    *dest = self.eval(src, context)?;
+                }
+
+                returned_value = match gml_body {
+                    GmlBody::Function(f) => f.call(self, context, &arg_values[..args.len()])?,
+                    GmlBody::Code(code) => {
+                        context.arguments = arg_values;
+                        context.argument_count = args.len();
+                        self.execute_at(location, code, context)?;
+                        context.return_value.clone()
+                    },
+                };
+            },
+            Some(i) if i < 0 => (),
+            Some(i) => {
+                if let Some(Some(object)) = self.assets.objects.get(i as usize) {
+                    context.other = this;
+                    let ids = object.children.clone();
+                    let mut iter = self.instance_list.iter_by_identity(ids);
+                    while let Some(instance) = iter.next(&self.instance_list) {
+                        context.this = instance;
+
+                        let mut arg_values: [Value; 16] = Default::default();
+                        for (dest, src) in arg_values.iter_mut().zip(args.iter()) {
+                            *dest = self.eval(src, context)?;
+                        }
+
+                        returned_value = match gml_body {
+                            GmlBody::Function(f) => f.call(self, context, &arg_values[..args.len()])?,
+                            GmlBody::Code(code) => {
+                                context.arguments = arg_values;
+                                context.argument_count = args.len();
+                                self.execute_at(location.clone(), code, context)?;
+                                context.return_value.clone()
+                            },
+                        };
+                    }
+                }
+            },
+        }
+        Ok(returned_value)
+    }
+
+    /// Describes an action for the call stack of an error which happened in it.
+    fn action_source(&self, action: &Action, event_type: usize, event_number: usize, as_object: i32) -> CodeSource {
+        CodeSource::Action {
+            action: action.index,
+            event: self.event_name(event_type, event_number),
+            object: self.object_name(as_object),
+        }
+    }
 }
@@ -5,6 +5,10 @@ use gmio::{
     render::{AtlasRef, Renderer},
 };
 use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Font {
@@ -126,3 +130,138 @@ pub fn create_chars_from_sprite(sprite: &Sprite, prop: bool, sep: i32, renderer:
     }
     chars.into_boxed_slice()
 }
+
+/// Collects every TrueType or OpenType file in a directory and its subdirectories.
+fn collect_font_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_font_files(&path, out);
+            } else if path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.eq_ignore_ascii_case("ttf") || e.eq_ignore_ascii_case("otf"))
+                .unwrap_or(false)
+            {
+                out.push(path);
+            }
+        }
+    }
+}
+
+fn system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(windir) = env::var_os("WINDIR") {
+        dirs.push(Path::new(&windir).join("Fonts"));
+    }
+    if let Some(local) = env::var_os("LOCALAPPDATA") {
+        dirs.push(Path::new(&local).join("Microsoft").join("Windows").join("Fonts"));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        let home = Path::new(&home);
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join(".local").join("share").join("fonts"));
+        dirs.push(home.join("Library").join("Fonts"));
+    }
+    for dir in &["/usr/share/fonts", "/usr/local/share/fonts", "/Library/Fonts", "/System/Library/Fonts"] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// Finds the file for a font, either by its path or by its family name.
+/// The given directories are searched first, so fonts bundled with the game take priority over installed ones.
+/// Font files are matched by their file name, such as "arial.ttf", "arialbd.ttf" or "Arimo-Bold.ttf".
+/// Returns the path and whether it's a bold face, since GDI emboldens regular faces when there's no bold one.
+pub fn find_font_file(name: &str, bold: bool, italic: bool, search_dirs: &[PathBuf]) -> Option<(PathBuf, bool)> {
+    if Path::new(name).is_file() {
+        return Some((name.into(), false))
+    }
+    let family = name.replace(' ', "");
+    let mut candidates = Vec::new();
+    match (bold, italic) {
+        (true, true) => {
+            candidates.extend(["-BoldItalic", "bi", "z"].iter().map(|s| (format!("{}{}", family, s), true)));
+            candidates.extend(["-Italic", "i"].iter().map(|s| (format!("{}{}", family, s), false)));
+        },
+        (true, false) => candidates.extend(["-Bold", "bd", "b"].iter().map(|s| (format!("{}{}", family, s), true))),
+        (false, true) => candidates.extend(["-Italic", "i"].iter().map(|s| (format!("{}{}", family, s), false))),
+        (false, false) => (),
+    }
+    candidates.push((format!("{}-Regular", family), false));
+    candidates.push((family, false));
+
+    let mut files = Vec::new();
+    for dir in search_dirs.iter().cloned().chain(system_font_dirs()) {
+        collect_font_files(&dir, &mut files);
+    }
+    candidates.into_iter().find_map(|(stem, is_bold)| {
+        files
+            .iter()
+            .find(|f| f.file_stem().and_then(|s| s.to_str()).map(|s| s.eq_ignore_ascii_case(&stem)).unwrap_or(false))
+            .map(|f| (f.clone(), is_bold))
+    })
+}
+
+/// Rasterizes characters from a TrueType font, laid out the way GM8 bakes fonts with GDI.
+/// The size is in points at 96 DPI. Each character's image starts at the top of the line,
+/// and is cropped to the glyph's width and bottom edge, or is an empty cell if the glyph has no outline.
+/// Returns the characters and the tallest one's height.
+pub fn create_chars_from_ttf(
+    data: &[u8],
+    size: u32,
+    embolden: bool,
+    first: u8,
+    last: u8,
+    encoding: &'static Encoding,
+    renderer: &mut Renderer,
+) -> Result<(Box<[Character]>, u32), String> {
+    let font = rusttype::Font::try_from_bytes(data).ok_or("invalid font file")?;
+    // rusttype scales so that ascent - descent fits the given height, but GDI scales the em square
+    let em_size = (size * 96 + 36) / 72;
+    let unscaled = font.v_metrics_unscaled();
+    let scale = rusttype::Scale::uniform(
+        em_size as f32 * (unscaled.ascent - unscaled.descent) / f32::from(font.units_per_em().max(1)),
+    );
+    let v_metrics = font.v_metrics(scale);
+    let ascent = v_metrics.ascent.round() as i32;
+    let cell_height = (ascent + (-v_metrics.descent).round() as i32).max(1);
+    let bold_width = if embolden { 1 } else { 0 };
+
+    let mut tallest_char_height = 0;
+    let mut chars = Vec::with_capacity(usize::from(last.saturating_sub(first)) + 1);
+    for byte in first..=last {
+        let ch = encoding.decode_without_bom_handling(&[byte]).0.chars().next().unwrap_or('\0');
+        let glyph = font.glyph(ch).scaled(scale);
+        let offset = glyph.h_metrics().advance_width.round() as i32 + bold_width;
+        let glyph = glyph.positioned(rusttype::point(0.0, ascent as f32));
+        let (width, height, distance, data) = match glyph.pixel_bounding_box() {
+            Some(bbox) => {
+                let width = bbox.width() + bold_width;
+                let height = bbox.max.y.max(1);
+                let mut data = [0xFF, 0xFF, 0xFF, 0x00].repeat((width * height) as usize);
+                glyph.draw(|x, y, a| {
+                    let py = bbox.min.y + y as i32;
+                    if py >= 0 && py < height {
+                        let alpha = (a * 255.0).round() as u8;
+                        // GDI emboldens by drawing the glyph again one pixel to the right
+                        for px in x as i32..=x as i32 + bold_width {
+                            let i = ((py * width + px) * 4 + 3) as usize;
+                            data[i] = data[i].max(alpha);
+                        }
+                    }
+                });
+                (width, height, bbox.min.x, data)
+            },
+            None => {
+                let width = offset.max(1);
+                (width, cell_height, 0, [0xFF, 0xFF, 0xFF, 0x00].repeat((width * cell_height) as usize))
+            },
+        };
+        tallest_char_height = tallest_char_height.max(height as u32);
+        let atlas_ref = renderer.upload_sprite(data.into_boxed_slice(), width, height, 0, 0)?;
+        chars.push(Character { offset, distance, atlas_ref });
+    }
+    Ok((chars.into_boxed_slice(), tallest_char_height))
+}
@@ -105,6 +105,77 @@ impl Path {
         }
     }
 
+    /// Returns the centre of the bounding box of this Path's points, which transformations are relative to
+    pub fn center(&self) -> (Real, Real) {
+        let mut points = self.points.iter();
+        match points.next() {
+            Some(first) => {
+                let (left, top, right, bottom) = points.fold((first.x, first.y, first.x, first.y), |bbox, p| {
+                    (bbox.0.min(p.x), bbox.1.min(p.y), bbox.2.max(p.x), bbox.3.max(p.y))
+                });
+                ((left + right) / Real::from(2.0), (top + bottom) / Real::from(2.0))
+            },
+            None => (Real::from(0.0), Real::from(0.0)),
+        }
+    }
+
+    /// Reverses the order of the points
+    pub fn reverse(&mut self) {
+        self.points.reverse();
+        self.update();
+    }
+
+    /// Mirrors the points horizontally around the centre
+    pub fn mirror(&mut self) {
+        let (center_x, _) = self.center();
+        for point in self.points.iter_mut() {
+            point.x = center_x + center_x - point.x;
+        }
+        self.update();
+    }
+
+    /// Flips the points vertically around the centre
+    pub fn flip(&mut self) {
+        let (_, center_y) = self.center();
+        for point in self.points.iter_mut() {
+            point.y = center_y + center_y - point.y;
+        }
+        self.update();
+    }
+
+    /// Rotates the points counter-clockwise around the centre by the given angle in degrees
+    pub fn rotate(&mut self, angle: Real) {
+        let (center_x, center_y) = self.center();
+        let angle = angle.to_radians();
+        let (sin, cos) = (angle.sin(), angle.cos());
+        for point in self.points.iter_mut() {
+            let x = point.x - center_x;
+            let y = point.y - center_y;
+            point.x = center_x + x * cos + y * sin;
+            point.y = center_y - x * sin + y * cos;
+        }
+        self.update();
+    }
+
+    /// Scales the points relative to the centre
+    pub fn scale(&mut self, xscale: Real, yscale: Real) {
+        let (center_x, center_y) = self.center();
+        for point in self.points.iter_mut() {
+            point.x = center_x + (point.x - center_x) * xscale;
+            point.y = center_y + (point.y - center_y) * yscale;
+        }
+        self.update();
+    }
+
+    /// Moves all the points by the given offset
+    pub fn shift(&mut self, xshift: Real, yshift: Real) {
+        for point in self.points.iter_mut() {
+            point.x += xshift;
+            point.y += yshift;
+        }
+        self.update();
+    }
+
     /// Returns a Point on the path at the given offset, where 0 is the beginning and 1 is the end
     pub fn get_point(&self, offset: Real) -> Point {
         match &*self.control_nodes {
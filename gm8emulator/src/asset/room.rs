@@ -1,6 +1,7 @@
 use crate::{
     game::{string::RCStr, Background, View},
-    gml::runtime::Instruction,
+    gml::bytecode::Program,
+    math::Real,
     tile::Tile,
 };
 use serde::{Deserialize, Serialize};
@@ -17,7 +18,7 @@ pub struct Room {
     pub persistent: bool,
     pub bg_colour: Colour,
     pub clear_screen: bool,
-    pub creation_code: Result<Rc<[Instruction]>, String>,
+    pub creation_code: Result<Rc<Program>, String>,
 
     pub backgrounds: Vec<Background>,
     pub views_enabled: bool,
@@ -33,5 +34,59 @@ pub struct Instance {
     pub y: i32,
     pub object: i32,
     pub id: ID,
-    pub creation: Result<Rc<[Instruction]>, String>,
+    pub creation: Result<Rc<Program>, String>,
+}
+
+impl Room {
+    /// Creates an empty room with the same settings as a new room in the GM8 editor.
+    pub fn new(name: RCStr) -> Self {
+        let background = Background {
+            visible: false,
+            is_foreground: false,
+            background_id: -1,
+            x_offset: Real::from(0.0),
+            y_offset: Real::from(0.0),
+            tile_horizontal: true,
+            tile_vertical: true,
+            hspeed: Real::from(0.0),
+            vspeed: Real::from(0.0),
+            xscale: Real::from(1.0),
+            yscale: Real::from(1.0),
+            blend: 0xFFFFFF,
+            alpha: Real::from(1.0),
+        };
+        let view = View {
+            visible: false,
+            source_x: 0,
+            source_y: 0,
+            source_w: 640,
+            source_h: 480,
+            port_x: 0,
+            port_y: 0,
+            port_w: 640,
+            port_h: 480,
+            angle: Real::from(0.0),
+            follow_target: -1,
+            follow_hborder: 32,
+            follow_vborder: 32,
+            follow_hspeed: -1,
+            follow_vspeed: -1,
+        };
+        Self {
+            name,
+            caption: "".into(),
+            width: 640,
+            height: 480,
+            speed: 30,
+            persistent: false,
+            bg_colour: (192, 192, 192).into(),
+            clear_screen: true,
+            creation_code: Ok(Rc::new([])),
+            backgrounds: vec![background; 8],
+            views_enabled: false,
+            views: vec![view; 8],
+            instances: Vec::new(),
+            tiles: Vec::new(),
+        }
+    }
 }
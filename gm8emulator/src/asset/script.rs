@@ -1,4 +1,4 @@
-use crate::{game::string::RCStr, gml::runtime::Instruction};
+use crate::{game::string::RCStr, gml::bytecode::Program};
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 
@@ -6,5 +6,5 @@ use std::rc::Rc;
 pub struct Script {
     pub name: RCStr,
     pub source: RCStr,
-    pub compiled: Rc<[Instruction]>,
+    pub compiled: Rc<Program>,
 }
@@ -266,6 +266,36 @@ pub fn make_colliders_shaped(
         .collect()
 }
 
+/// Makes colliders using a sprite's collision settings, as set with sprite_collision_mask.
+/// The kind is 0 for precise, 1 for rectangle, 2 for ellipse and 3 for diamond.
+/// If a bounding box is given, it's used for every collider, even with precise collision.
+pub fn make_colliders(
+    frames: &[RgbaImage],
+    kind: i32,
+    tolerance: u8,
+    sepmasks: bool,
+    bbox: Option<BoundingBox>,
+) -> Vec<Collider> {
+    let mut colliders = match kind {
+        0 => make_colliders_precise(frames, tolerance, sepmasks),
+        _ => make_colliders_shaped(frames, tolerance, sepmasks, bbox, match kind {
+            1 => Some(ColliderShape::Rectangle),
+            2 => Some(ColliderShape::Ellipse),
+            3 => Some(ColliderShape::Diamond),
+            _ => None,
+        }),
+    };
+    if let Some(bbox) = bbox {
+        for c in &mut colliders {
+            c.bbox_left = bbox.left;
+            c.bbox_top = bbox.top;
+            c.bbox_right = bbox.right;
+            c.bbox_bottom = bbox.bottom;
+        }
+    }
+    colliders
+}
+
 // used for adding frames to sprites
 pub fn scale(input: &mut RgbaImage, width: u32, height: u32) {
     if input.dimensions() != (width, height) {
@@ -302,6 +332,14 @@ impl Sprite {
         }
     }
 
+    /// Sets the sprite's bounding box to fit around all of its colliders.
+    pub fn update_bbox(&mut self) {
+        self.bbox_left = self.colliders.iter().map(|c| c.bbox_left).min().unwrap_or(0);
+        self.bbox_top = self.colliders.iter().map(|c| c.bbox_top).min().unwrap_or(0);
+        self.bbox_right = self.colliders.iter().map(|c| c.bbox_right).max().unwrap_or(0);
+        self.bbox_bottom = self.colliders.iter().map(|c| c.bbox_bottom).max().unwrap_or(0);
+    }
+
     pub fn get_atlas_ref(&self, image_index: Real) -> Option<&AtlasRef> {
         if let Some(image_index) = self.get_image_index(image_index) {
             self.frames.get(image_index).map(|x| &x.atlas_ref)
@@ -1,5 +1,10 @@
 pub mod background;
+pub mod compat;
+pub mod console;
+pub mod debugger;
+pub mod dialog;
 pub mod draw;
+pub mod error;
 pub mod events;
 pub mod external;
 pub mod gm_save;
@@ -8,17 +13,24 @@ pub mod model;
 pub mod movement;
 pub mod particle;
 pub mod pathfinding;
+pub mod profiler;
 pub mod replay;
+pub mod room;
 pub mod savestate;
+pub mod splash;
 pub mod string;
 pub mod surface;
 pub mod transition;
 pub mod view;
+pub mod window_state;
 
 pub use background::Background;
+pub use error::ErrorPolicy;
 pub use replay::Replay;
+pub use room::RoomState;
 pub use savestate::SaveState;
 pub use view::View;
+pub use window_state::WindowState;
 
 use crate::{
     action::Tree,
@@ -31,7 +43,13 @@ use crate::{
         trigger::{self, Trigger},
         Object, Script, Timeline,
     },
-    gml::{self, ds, ev, file, rand::Random, Compiler, Context},
+    gml::{
+        self, ds, ev, file,
+        network::Multiplayer,
+        rand::Random,
+        runtime::{CodeSource, Node},
+        Compiler, Context,
+    },
     handleman::{HandleArray, HandleList},
     input::InputManager,
     instance::{DummyFieldHolder, Instance, InstanceState},
@@ -43,6 +61,8 @@ use encoding_rs::Encoding;
 use gm8exe::asset::PascalString;
 use gmio::{
     atlas::AtlasBuilder,
+    clipboard::Clipboard,
+    joystick::Joysticks,
     render::{Renderer, RendererOptions, Scaling},
     window::{self, Window, WindowBuilder},
 };
@@ -50,8 +70,8 @@ use includedfile::IncludedFile;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use shared::{
-    input::MouseButton,
-    message::{self, Message, MessageStream},
+    input::{JoystickAxis, MouseButton},
+    message::{self, CodeLocation, Message, MessageStream},
     types::{Colour, ID},
 };
 use std::{
@@ -77,6 +97,8 @@ pub struct Game {
     pub tile_list: TileList,
     pub rand: Random,
     pub input_manager: InputManager,
+    pub joysticks: Joysticks,
+    pub clipboard: Clipboard,
     pub assets: Assets,
     pub event_holders: [IndexMap<u32, Rc<RefCell<Vec<ID>>>>; 12],
     pub custom_draw_objects: HashSet<ID>,
@@ -103,6 +125,7 @@ pub struct Game {
     pub room_height: i32,
     pub room_order: Box<[i32]>,
     pub room_speed: u32,
+    pub persistent_rooms: HashMap<i32, RoomState>,
     pub scene_change: Option<SceneChange>, // Queued scene change which has been requested by GML, if any
     pub user_transitions: HashMap<i32, transition::UserTransition>,
 
@@ -134,6 +157,11 @@ pub struct Game {
     pub uninit_args_are_zero: bool,
 
     pub potential_step_settings: pathfinding::PotentialStepSettings,
+    pub message_settings: dialog::MessageSettings,
+    pub splash_settings: splash::SplashSettings,
+    pub splash: Option<splash::Splash>,
+    pub game_info: RCStr,
+    pub game_info_colour: i32,
 
     pub fps: u32,                 // initially 0
     pub transition_kind: i32,     // default 0
@@ -161,11 +189,16 @@ pub struct Game {
     pub open_ini: Option<(ini::Ini, RCStr)>, // keep the filename for writing
     pub open_file: Option<file::TextHandle>, // for legacy file functions from GM <= 5.1
     pub file_finder: Option<Box<dyn Iterator<Item = PathBuf>>>,
+    pub mplay: Multiplayer,
     pub spoofed_time_nanos: Option<u128>, // use this instead of real time if this is set
     pub parameters: Vec<String>,
     pub encoding: &'static Encoding,
 
     pub esc_close_game: bool,
+    pub show_error_messages: bool,
+    pub log_errors: bool,
+    pub always_abort: bool,
+    pub error_policy: ErrorPolicy,
 
     // window caption
     pub caption: RCStr,
@@ -173,11 +206,13 @@ pub struct Game {
 
     pub play_type: PlayType,
     pub stored_events: VecDeque<replay::Event>,
+    pub debugger: Option<debugger::Debugger>,
+    pub profiler: Option<profiler::Profiler>,
+    pub console: console::Console,
 
     // winit windowing
     pub window: Window,
-    pub window_border: bool,
-    pub window_icons: bool,
+    pub window_state: WindowState,
     // Scaling type
     pub scaling: Scaling,
     // Width the window is supposed to have, assuming it hasn't been resized by the user
@@ -229,6 +264,67 @@ impl From<PascalString> for RCStr {
     }
 }
 
+/// Sets up a GML compiler which knows all of a game's asset names, scripts and constants.
+pub fn new_compiler(assets: &gm8exe::GameAssets) -> Compiler {
+    let mut compiler = Compiler::new();
+    compiler.reserve_scripts(assets.scripts.iter().flatten().count());
+    compiler.reserve_constants(
+        assets.backgrounds.iter().flatten().count()
+            + assets.fonts.iter().flatten().count()
+            + assets.objects.iter().flatten().count()
+            + assets.paths.iter().flatten().count()
+            + assets.rooms.iter().flatten().count()
+            + assets.scripts.iter().flatten().count()
+            + assets.sounds.iter().flatten().count()
+            + assets.sprites.iter().flatten().count()
+            + assets.timelines.iter().flatten().count()
+            + assets.triggers.iter().flatten().count(),
+    );
+    compiler.reserve_user_constants(assets.constants.len());
+
+    // Helper fn for registering asset names as constants
+    fn register_all<T>(compiler: &mut Compiler, assets: &[Option<T>], get_name: fn(&T) -> &PascalString) {
+        assets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
+            .for_each(|(i, x)| compiler.register_constant(get_name(x).0.clone(), i as f64))
+    }
+
+    // Register all asset names
+    // These are in order of asset precedence, please don't change the order
+    register_all(&mut compiler, &assets.objects, |x| &x.name);
+    register_all(&mut compiler, &assets.sprites, |x| &x.name);
+    register_all(&mut compiler, &assets.sounds, |x| &x.name);
+    register_all(&mut compiler, &assets.backgrounds, |x| &x.name);
+    register_all(&mut compiler, &assets.paths, |x| &x.name);
+    register_all(&mut compiler, &assets.fonts, |x| &x.name);
+    register_all(&mut compiler, &assets.timelines, |x| &x.name);
+    register_all(&mut compiler, &assets.scripts, |x| &x.name);
+    register_all(&mut compiler, &assets.rooms, |x| &x.name);
+    register_all(&mut compiler, &assets.triggers, |x| &x.constant_name);
+
+    // Register scripts
+    assets
+        .scripts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
+        .for_each(|(i, x)| compiler.register_script(x.name.0.clone(), i));
+
+    // Register user constants
+    assets.constants.iter().enumerate().for_each(|(i, x)| compiler.register_user_constant(x.name.0.clone(), i));
+
+    // Constants which don't need the game to work out can be folded into code as it's compiled.
+    // The rest are evaluated once the game is running, along with these again.
+    for (i, constant) in assets.constants.iter().enumerate() {
+        if let Ok(Node::Literal { value }) = compiler.compile_expression(&constant.expression.0) {
+            compiler.set_user_constant_value(i, value);
+        }
+    }
+    compiler
+}
+
 impl Game {
     pub fn launch(
         assets: gm8exe::GameAssets,
@@ -261,10 +357,14 @@ impl Game {
             winapi::um::timeapi::timeBeginPeriod(1);
         }
 
+        // Set up a GML compiler
+        let mut compiler = new_compiler(&assets);
+
         // Destructure assets
         let gm8exe::GameAssets {
             game_id,
             backgrounds,
+            help_dialog,
             constants,
             fonts,
             icon_data: _,
@@ -392,55 +492,6 @@ impl Game {
             .collect::<Result<Vec<_>, std::io::Error>>()
             .expect("failed to extract included files");
 
-        // Set up a GML compiler
-        let mut compiler = Compiler::new();
-        compiler.reserve_scripts(scripts.iter().flatten().count());
-        compiler.reserve_constants(
-            backgrounds.iter().flatten().count()
-                + fonts.iter().flatten().count()
-                + objects.iter().flatten().count()
-                + paths.iter().flatten().count()
-                + rooms.iter().flatten().count()
-                + scripts.iter().flatten().count()
-                + sounds.iter().flatten().count()
-                + sprites.iter().flatten().count()
-                + timelines.iter().flatten().count()
-                + triggers.iter().flatten().count(),
-        );
-        compiler.reserve_user_constants(constants.len());
-
-        // Helper fn for registering asset names as constants
-        fn register_all<T>(compiler: &mut Compiler, assets: &[Option<T>], get_name: fn(&T) -> &PascalString) {
-            assets
-                .iter()
-                .enumerate()
-                .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
-                .for_each(|(i, x)| compiler.register_constant(get_name(x).0.clone(), i as f64))
-        }
-
-        // Register all asset names
-        // These are in order of asset precedence, please don't change the order
-        register_all(&mut compiler, &objects, |x| &x.name);
-        register_all(&mut compiler, &sprites, |x| &x.name);
-        register_all(&mut compiler, &sounds, |x| &x.name);
-        register_all(&mut compiler, &backgrounds, |x| &x.name);
-        register_all(&mut compiler, &paths, |x| &x.name);
-        register_all(&mut compiler, &fonts, |x| &x.name);
-        register_all(&mut compiler, &timelines, |x| &x.name);
-        register_all(&mut compiler, &scripts, |x| &x.name);
-        register_all(&mut compiler, &rooms, |x| &x.name);
-        register_all(&mut compiler, &triggers, |x| &x.constant_name);
-
-        // Register scripts
-        scripts
-            .iter()
-            .enumerate()
-            .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
-            .for_each(|(i, x)| compiler.register_script(x.name.0.clone(), i));
-
-        // Register user constants
-        constants.iter().enumerate().for_each(|(i, x)| compiler.register_user_constant(x.name.0.clone(), i));
-
         // Set up a Renderer
         let options = RendererOptions {
             size: (room1_width, room1_height),
@@ -451,21 +502,25 @@ impl Game {
         };
 
         let (width, height) = options.size;
-        let window_border = !settings.dont_draw_border;
-        let window_icons = !settings.dont_show_buttons;
-        let wb = WindowBuilder::new().with_size(width, height).with_style(if play_type == PlayType::Record {
-            window::Style::Regular
-        } else {
-            match (window_border, window_icons) {
-                (true, true) => window::Style::Regular,
-                (true, false) => window::Style::Undecorated,
-                (false, _) => window::Style::Borderless,
-            }
-        });
-
-        // TODO: specific flags here (make wb mutable)
+        let window_state = WindowState {
+            border: !settings.dont_draw_border,
+            icons: !settings.dont_show_buttons,
+            fullscreen: settings.fullscreen,
+            sizeable: settings.allow_resize,
+            stay_on_top: settings.window_on_top,
+            cursor: if settings.display_cursor { 0 } else { -1 },
+            region_size: None,
+            display: Default::default(),
+        };
+        let wb = WindowBuilder::new()
+            .with_size(width, height)
+            .with_cursor(window_state.cursor())
+            .with_style(if play_type == PlayType::Record { window::Style::Regular } else { window_state.style() });
 
-        let window = wb.build().expect("oh no");
+        let mut window = wb.build().expect("oh no");
+        if play_type != PlayType::Record && window_state.stay_on_top {
+            window.set_topmost(true);
+        }
         let mut renderer = Renderer::new((), &options, &window, settings.clear_colour.into())?;
 
         let mut atlases = AtlasBuilder::new(renderer.max_texture_size() as _);
@@ -903,6 +958,8 @@ impl Game {
             room_colour: room1_colour,
             show_room_colour: room1_show_colour,
             input_manager: InputManager::new(),
+            joysticks: Joysticks::new(),
+            clipboard: Clipboard::new(),
             assets: Assets { backgrounds, fonts, objects, paths, rooms, scripts, sprites, timelines, triggers },
             event_holders,
             custom_draw_objects,
@@ -916,6 +973,7 @@ impl Game {
             room_height: room1_height as i32,
             room_order: room_order.into_boxed_slice(),
             room_speed: room1_speed,
+            persistent_rooms: HashMap::new(),
             scene_change: None,
             user_transitions: HashMap::new(),
             constants: Vec::with_capacity(constants.len()),
@@ -945,6 +1003,11 @@ impl Game {
             uninit_fields_are_zero: settings.zero_uninitialized_vars,
             uninit_args_are_zero: !settings.error_on_uninitialized_args,
             potential_step_settings: Default::default(),
+            message_settings: Default::default(),
+            splash_settings: Default::default(),
+            splash: None,
+            game_info: splash::rtf_to_text(help_dialog.info.0.as_ref()).into(),
+            game_info_colour: help_dialog.bg_colour.as_decimal() as i32,
             transition_kind: 0,
             transition_steps: 80,
             cursor_sprite: -1,
@@ -963,11 +1026,16 @@ impl Game {
             open_ini: None,
             open_file: None,
             file_finder: None,
+            mplay: Multiplayer::new(),
             spoofed_time_nanos,
             fps: 0,
             parameters: game_arguments,
             encoding,
             esc_close_game: settings.esc_close_game,
+            show_error_messages: settings.show_error_messages,
+            log_errors: settings.log_errors,
+            always_abort: settings.always_abort,
+            error_policy: ErrorPolicy::default(),
             caption: "".to_string().into(),
             caption_stale: false,
             score_capt_d: false,
@@ -976,11 +1044,13 @@ impl Game {
             error_occurred: false,
             error_last: "".to_string().into(),
             window,
-            window_border,
-            window_icons,
+            window_state,
             scaling,
             play_type,
             stored_events: VecDeque::new(),
+            debugger: None,
+            profiler: None,
+            console: Default::default(),
 
             // load_room sets this
             unscaled_width: 0,
@@ -1008,6 +1078,7 @@ impl Game {
                 locals: Default::default(),
                 return_value: Default::default(),
             })?;
+            game.compiler.set_user_constant_value(game.constants.len(), value.clone());
             game.constants.push(value);
             game.instance_list.remove_dummy(dummy_instance);
         }
@@ -1109,15 +1180,50 @@ impl Game {
         if self.unscaled_width != width || self.unscaled_height != height {
             self.unscaled_width = width;
             self.unscaled_height = height;
+            let (width, height) = self.window_region_size();
             self.renderer.resize_framebuffer(width, height);
-            let (width, height) = match self.scaling {
-                Scaling::Fixed(scale) => ((f64::from(width) * scale) as u32, (f64::from(height) * scale) as u32),
-                _ => (width, height),
-            };
-            self.window.resize(width, height);
+            self.window_default_size();
         }
     }
 
+    /// Size of the region of the window which gets drawn to, before scaling, which is also the framebuffer's size.
+    /// This is the room or view size unless the game changed it with window_set_region_size.
+    pub fn window_region_size(&self) -> (u32, u32) {
+        self.window_state.region_size.unwrap_or((self.unscaled_width, self.unscaled_height))
+    }
+
+    /// Whether changes to the window state should be applied to the real window.
+    /// While recording, the window is laid out by the recorder instead.
+    pub fn controls_window(&self) -> bool {
+        self.play_type != PlayType::Record
+    }
+
+    /// Applies the border, icon, sizeable and fullscreen settings to the real window.
+    pub fn update_window_style(&mut self) {
+        if self.controls_window() {
+            self.window.set_style(self.window_state.style());
+        }
+    }
+
+    /// Applies all of the window state to the real window, such as after it's been replaced by loading a savestate.
+    pub fn apply_window_state(&mut self) {
+        if self.controls_window() {
+            self.window.set_style(self.window_state.style());
+            self.window.set_topmost(self.window_state.stay_on_top);
+            self.window.set_cursor(self.window_state.cursor());
+        }
+    }
+
+    /// Resizes the window to fit the drawing region at the current scale, like it is at the start of a room.
+    pub fn window_default_size(&mut self) {
+        let (width, height) = self.window_region_size();
+        let (width, height) = match self.scaling {
+            Scaling::Fixed(scale) => ((f64::from(width) * scale) as u32, (f64::from(height) * scale) as u32),
+            _ => (width, height),
+        };
+        self.window.resize(width, height);
+    }
+
     pub fn decode_str<'a>(&self, string: &'a [u8]) -> Cow<'a, str> {
         match self.gm_version {
             Version::GameMaker8_0 => self.encoding.decode_without_bom_handling(string).0,
@@ -1149,16 +1255,10 @@ impl Game {
         let transition_kind = self.transition_kind;
         let (trans_surf_old, trans_surf_new) = if self.get_transition(transition_kind).is_some() {
             let (width, height) = self.window.get_inner_size();
-            let old_surf = surface::Surface {
-                width,
-                height,
-                atlas_ref: self.renderer.create_surface(width as _, height as _, true)?,
-            };
-            let new_surf = surface::Surface {
-                width,
-                height,
-                atlas_ref: self.renderer.create_surface(width as _, height as _, true)?,
-            };
+            let old_surf =
+                surface::Surface::new(width, height, self.renderer.create_surface(width as _, height as _, true)?);
+            let new_surf =
+                surface::Surface::new(width, height, self.renderer.create_surface(width as _, height as _, true)?);
             self.renderer.set_target(&old_surf.atlas_ref);
             self.draw()?;
             self.renderer.set_target(&new_surf.atlas_ref);
@@ -1176,70 +1276,88 @@ impl Game {
             self.run_instance_event(ev::OTHER, 5, instance, instance, None)?;
         }
 
-        // Delete non-persistent instances and all tiles
-        // TODO: back up remaining instances and put them at the END of insertion order after making new ones
-        self.instance_list.remove_with(|instance| !instance.persistent.get());
-        self.tile_list.clear();
+        // Persistent instances come along to the new room, so take them out of the old one
+        self.instance_list.remove_with(|instance| instance.state.get() == InstanceState::Deleted);
+        let persistent_instances = self.instance_list.take_with(|instance| instance.persistent.get());
+
+        // If the old room is persistent, keep everything else so it can be restored later
+        // There's nothing to keep when the game is (re)starting, since the old room was never actually entered
+        let old_room_persistent = self.assets.rooms.get_asset(self.room_id).map(|r| r.persistent).unwrap_or(false);
+        if old_room_persistent && !self.game_start {
+            let state = self.take_room_state();
+            self.persistent_rooms.insert(self.room_id, state);
+        } else {
+            self.instance_list.remove_with(|_| true);
+            self.tile_list.clear();
+        }
+
+        // Restore the new room's state if it's been visited and is still persistent, otherwise set it up from scratch
+        let restored_state = self.persistent_rooms.remove(&room_id).filter(|_| room.persistent);
+        let restoring = restored_state.is_some();
+        match restored_state {
+            Some(state) => self.restore_room_state(state),
+            None => {
+                self.room_width = room.width as _;
+                self.room_height = room.height as _;
+                self.room_speed = room.speed;
+                self.caption = room.caption.clone();
+                self.room_colour = room.bg_colour;
+                self.show_room_colour = room.clear_screen;
+
+                // Using clear() followed by extend_from_slice() guarantees re-using vec capacity and avoids allocs
+                self.views_enabled = room.views_enabled;
+                self.views.clear();
+                self.views.extend_from_slice(&room.views);
+                self.backgrounds.clear();
+                self.backgrounds.extend_from_slice(&room.backgrounds);
+
+                // Load all tiles in new room
+                for tile in room.tiles.iter() {
+                    self.tile_list.insert(tile.clone());
+                }
+            },
+        }
 
         // Update renderer
         let (view_width, view_height) = {
-            if !room.views_enabled {
-                (room.width, room.height)
+            if !self.views_enabled {
+                (self.room_width as u32, self.room_height as u32)
             } else {
                 let xw = |view: &View| view.port_x + (view.port_w as i32);
                 let yh = |view: &View| view.port_y + (view.port_h as i32);
-                let x_max = room
+                let x_max = self
                     .views
                     .iter()
                     .filter(|view| view.visible)
                     .max_by(|v1, v2| xw(v1).cmp(&xw(v2)))
                     .map(xw)
-                    .unwrap_or(room.width as i32);
-                let y_max = room
+                    .unwrap_or(self.room_width);
+                let y_max = self
                     .views
                     .iter()
                     .filter(|view| view.visible)
                     .max_by(|v1, v2| yh(v1).cmp(&yh(v2)))
                     .map(yh)
-                    .unwrap_or(room.height as i32);
+                    .unwrap_or(self.room_height);
                 if x_max < 0 || y_max < 0 {
                     return Err(format!("Bad room width/height {},{} loading room {}", x_max, y_max, room_id).into())
                 }
                 (x_max as u32, y_max as u32)
             }
         };
-
         self.resize_window(view_width, view_height);
-        self.room_colour = room.bg_colour;
-        self.show_room_colour = room.clear_screen;
-
-        // Update views, backgrounds
-        // Using clear() followed by extend_from_slice() guarantees re-using vec capacity and avoids unnecessary allocs
-        self.views_enabled = room.views_enabled;
-        self.views.clear();
-        self.views.extend_from_slice(&room.views);
-        self.backgrounds.clear();
-        self.backgrounds.extend_from_slice(&room.backgrounds);
 
         // Update some stored vars
         self.room_id = room_id;
-        self.room_width = room.width as _;
-        self.room_height = room.height as _;
-        self.room_speed = room.speed;
-        self.caption = room.caption;
         self.input_manager.clear_presses();
         self.particles.effect_clear();
         self.cursor_sprite_frame = 0;
 
-        // Load all tiles in new room
-        for tile in room.tiles.iter() {
-            self.tile_list.insert(tile.clone());
-        }
-
         // Load all instances in new room, unless they already exist due to persistence
+        // A restored room already has its instances, and none of them get their creation code or create event again
         let mut new_handles: Vec<(usize, &asset::room::Instance)> = Vec::new();
-        for instance in room.instances.iter() {
-            if self.instance_list.get_by_instid(instance.id).is_none() {
+        for instance in room.instances.iter().filter(|_| !restoring) {
+            if persistent_instances.iter().all(|p| p.id.get() != instance.id) {
                 // Get object
                 let object = match self.assets.objects.get(instance.object as usize) {
                     Some(&Some(ref o)) => o.as_ref(),
@@ -1259,10 +1377,17 @@ impl Game {
                 ));
             }
         }
+
+        // Persistent instances go after the room's own instances in insertion order
+        for instance in persistent_instances {
+            self.instance_list.insert(instance);
+        }
+
         for (handle, instance) in &new_handles {
             if self.instance_list.get(*handle).is_active() {
                 // Run this instance's room creation code
-                self.execute(&instance.creation.clone()?, &mut Context {
+                let location = CodeLocation::InstanceCreation { instance: instance.id };
+                self.execute_at(location, &instance.creation.clone()?, &mut Context {
                     this: *handle,
                     other: *handle,
                     event_action: 0,
@@ -1274,7 +1399,8 @@ impl Game {
                     argument_count: 0,
                     locals: Default::default(),
                     return_value: Default::default(),
-                })?;
+                })
+                .or_else(|e| self.handle_error(e.in_code(CodeSource::InstanceCreation(instance.id))))?;
 
                 // Run create event for this instance
                 self.run_instance_event(ev::CREATE, 0, *handle, *handle, None)?;
@@ -1290,23 +1416,29 @@ impl Game {
             self.game_start = false;
         }
 
-        // Run room creation code
-        let dummy_instance =
-            self.instance_list.insert_dummy(Instance::new_dummy(self.assets.objects.get_asset(0).map(|x| x.as_ref())));
-        self.execute(&room.creation_code?, &mut Context {
-            this: dummy_instance,
-            other: dummy_instance,
-            event_action: 0,
-            relative: false,
-            event_type: 11,
-            event_number: 0,
-            event_object: 0,
-            arguments: Default::default(),
-            argument_count: 0,
-            locals: Default::default(),
-            return_value: Default::default(),
-        })?;
-        self.instance_list.remove_dummy(dummy_instance);
+        // Run room creation code, unless the room is being restored
+        if !restoring {
+            let dummy_instance = self
+                .instance_list
+                .insert_dummy(Instance::new_dummy(self.assets.objects.get_asset(0).map(|x| x.as_ref())));
+            let room_name = self.decode_str(room.name.as_ref()).into_owned();
+            let location = CodeLocation::RoomCreation { room: room_id };
+            self.execute_at(location, &room.creation_code?, &mut Context {
+                this: dummy_instance,
+                other: dummy_instance,
+                event_action: 0,
+                relative: false,
+                event_type: 11,
+                event_number: 0,
+                event_object: 0,
+                arguments: Default::default(),
+                argument_count: 0,
+                locals: Default::default(),
+                return_value: Default::default(),
+            })
+            .or_else(|e| self.handle_error(e.in_code(CodeSource::RoomCreation(room_name))))?;
+            self.instance_list.remove_dummy(dummy_instance);
+        }
 
         // Run room start event for each instance
         let mut iter = self.instance_list.iter_by_insertion();
@@ -1385,6 +1517,7 @@ impl Game {
 
         // Clear some stored variables
         self.instance_list = InstanceList::new();
+        self.persistent_rooms.clear();
         self.globals = DummyFieldHolder::new();
         self.game_start = true;
 
@@ -1399,6 +1532,9 @@ impl Game {
             return Ok(())
         }
 
+        // Count down or dismiss a splash screen which isn't interrupting the game
+        self.update_splash();
+
         // Update xprevious and yprevious for all instances
         let mut iter = self.instance_list.iter_by_insertion();
         while let Some(instance) = iter.next(&self.instance_list).map(|x| self.instance_list.get(x)) {
@@ -1441,12 +1577,15 @@ impl Game {
                                     instance.timeline_position.set(new_position)
                                 }
 
-                                for (_, tree) in moments
+                                // Collect these first, since moments may add or clear other moments
+                                let trees = moments
                                     .borrow()
                                     .iter()
                                     .filter(|(&x, _)| Real::from(x) >= old_position && Real::from(x) < new_position)
-                                {
-                                    self.execute_tree(tree.clone(), handle, handle, 0, 0, object_index)?;
+                                    .map(|(_, tree)| tree.clone())
+                                    .collect::<Vec<_>>();
+                                for tree in trees {
+                                    self.execute_tree(tree, handle, handle, 0, 0, object_index)?;
                                 }
                             },
                             x if x < Real::from(0) => {
@@ -1456,13 +1595,15 @@ impl Game {
                                     instance.timeline_position.set(new_position)
                                 }
 
-                                for (_, tree) in moments
+                                let trees = moments
                                     .borrow()
                                     .iter()
                                     .filter(|(&x, _)| Real::from(x) > new_position && Real::from(x) <= old_position)
                                     .rev()
-                                {
-                                    self.execute_tree(tree.clone(), handle, handle, 0, 0, object_index)?;
+                                    .map(|(_, tree)| tree.clone())
+                                    .collect::<Vec<_>>();
+                                for tree in trees {
+                                    self.execute_tree(tree, handle, handle, 0, 0, object_index)?;
                                 }
                             },
                             _ => {},
@@ -1592,8 +1733,17 @@ impl Game {
                 self.input_manager.mouse_update_previous();
                 for event in self.window.process_events().copied() {
                     match event {
+                        // While the console is open it gets all key presses, but releases still reach the game
+                        // so nothing held when it opened gets stuck down
+                        Event::KeyboardDown(key) if self.console.enabled && key == console::TOGGLE_KEY => {
+                            self.console.toggle()
+                        },
+                        Event::KeyboardDown(key) if self.console.open => self.console.key_press(key),
                         Event::KeyboardDown(key) => self.input_manager.key_press(key),
-                        Event::KeyboardUp(key) => self.input_manager.key_release(key),
+                        Event::KeyboardUp(key) => {
+                            self.console.key_release(key);
+                            self.input_manager.key_release(key);
+                        },
                         Event::MenuOption(_) => (),
                         Event::MouseMove(x, y) => self.input_manager.set_mouse_pos(x.into(), y.into()),
                         Event::MouseButtonDown(button) => self.input_manager.mouse_press(button),
@@ -1603,6 +1753,9 @@ impl Game {
                         Event::Resize(w, h) => println!("user resize: width={}, height={}", w, h),
                     }
                 }
+                for event in self.joysticks.process_events() {
+                    self.input_manager.joystick_event(event);
+                }
             },
             _ => (),
         }
@@ -1614,6 +1767,8 @@ impl Game {
         let mut frame_counter = 0;
         loop {
             self.process_window_events();
+            self.poll_debugger();
+            self.poll_console();
 
             self.frame()?;
             match self.scene_change {
@@ -1695,6 +1850,15 @@ impl Game {
             }
         }
 
+        // Helper fn: InputManager -> JoystickDetails
+        fn joystick_details(input_manager: &InputManager) -> Option<message::JoystickDetails> {
+            input_manager.joystick_info(0).map(|info| message::JoystickDetails {
+                x: input_manager.joystick_axis(0, JoystickAxis::X),
+                y: input_manager.joystick_axis(0, JoystickAxis::Y),
+                buttons_held: (0..info.buttons).filter(|b| input_manager.joystick_check_button(0, (*b).into())).collect(),
+            })
+        }
+
         let mut stream = TcpStream::connect(&SocketAddr::from(([127, 0, 0, 1], tcp_port)))?;
         stream.set_nonblocking(true)?;
         let mut read_buffer: Vec<u8> = Vec::new();
@@ -1735,6 +1899,7 @@ impl Game {
                             frame_count: replay.frame_count(),
                             seed: self.rand.seed(),
                             instance: None,
+                            joystick: joystick_details(&self.input_manager),
                         })?;
                         break
                     },
@@ -1748,6 +1913,10 @@ impl Game {
         let mut game_mousey = 0;
         let mut do_update_mouse = false;
         self.play_type = PlayType::Record;
+        if self.debugger.is_none() {
+            let client = debugger::control::ControlClient::new(stream.try_clone()?);
+            self.debugger = Some(debugger::Debugger::new(Box::new(client)));
+        }
         let mut frame_counter = 0;
 
         loop {
@@ -1758,6 +1927,7 @@ impl Game {
                         key_inputs,
                         mouse_inputs,
                         mouse_location,
+                        joystick_inputs,
                         keys_requested,
                         mouse_buttons_requested,
                         instance_requested,
@@ -1792,6 +1962,10 @@ impl Game {
                                 frame.inputs.push(replay::Input::MouseRelease(button));
                             }
                         }
+                        for event in joystick_inputs.into_iter() {
+                            self.input_manager.joystick_event(&event);
+                            frame.inputs.push(replay::Input::Joystick(event));
+                        }
                         self.input_manager.mouse_update_previous();
                         self.input_manager.set_mouse_pos(mouse_location.0, mouse_location.1);
 
@@ -1826,6 +2000,7 @@ impl Game {
                                 instance.update_bbox(self.get_instance_mask_sprite(x));
                                 instance_details(&self.assets, instance)
                             }),
+                            joystick: joystick_details(&self.input_manager),
                         })?
                     },
 
@@ -1867,6 +2042,28 @@ impl Game {
                                 instance.update_bbox(self.get_instance_mask_sprite(x));
                                 instance_details(&self.assets, instance)
                             }),
+                            joystick: joystick_details(&self.input_manager),
+                        })?;
+                    },
+
+                    m @ Message::SetBreakpoints { .. }
+                    | m @ Message::DebugPause
+                    | m @ Message::DebugResume { .. }
+                    | m @ Message::DebugInspect
+                    | m @ Message::DebugEvaluate { .. } => {
+                        if let Some(debugger) = self.debugger.as_mut() {
+                            debugger.handle_message(m, &mut stream)?;
+                        }
+                    },
+
+                    Message::SetProfiling { enabled } => {
+                        self.profiler = if enabled { Some(Default::default()) } else { None };
+                    },
+
+                    Message::ProfileReport => {
+                        stream.send_message(&message::Information::ProfileReport {
+                            entries: self.profile_report(),
+                            folded: self.profile_folded(),
                         })?;
                     },
 
@@ -1875,6 +2072,8 @@ impl Game {
                 None => break Ok(()),
             }
 
+            self.poll_console();
+
             for event in self.window.process_events().copied() {
                 match event {
                     Event::MouseMove(x, y) => {
@@ -1947,7 +2146,7 @@ impl Game {
     }
 
     // Replays some recorded inputs to the game
-    pub fn replay(mut self, replay: Replay) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn replay(&mut self, replay: Replay) -> Result<(), Box<dyn std::error::Error>> {
         let mut frame_count: usize = 0;
         self.rand.set_seed(replay.start_seed);
         self.spoofed_time_nanos = Some(replay.start_time);
@@ -1981,10 +2180,13 @@ impl Game {
                         replay::Input::MouseRelease(b) => self.input_manager.mouse_release(*b),
                         replay::Input::MouseWheelUp => self.input_manager.mouse_scroll_up(),
                         replay::Input::MouseWheelDown => self.input_manager.mouse_scroll_down(),
+                        replay::Input::Joystick(e) => self.input_manager.joystick_event(e),
                     }
                 }
             }
 
+            self.poll_debugger();
+            self.poll_console();
             self.frame()?;
             match self.scene_change {
                 Some(SceneChange::Room(id)) => self.load_room(id)?,
@@ -0,0 +1,228 @@
+//! Compatibility reports, which list everything built in that a game's code uses and whether the emulator supports it.
+
+use crate::{
+    action::{Action, Body, GmlBody, Tree},
+    game,
+    gml::{
+        bytecode::{Op, Program},
+        compiler::mappings,
+        runtime::{ArrayAccessor, InstanceIdentifier, Node},
+        Function, InstanceVariable,
+    },
+};
+use gm8exe::GameAssets;
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+
+/// Everything built in which some code uses, with how many times each is used.
+#[derive(Default)]
+struct Usage {
+    functions: HashMap<Function, usize>,
+    variables: HashMap<InstanceVariable, usize>,
+
+    /// D&D actions by library ID and action ID, with how many times they're used and whether they're implemented
+    actions: HashMap<(u32, u32), (usize, bool)>,
+}
+
+impl Usage {
+    fn is_implemented(&self) -> bool {
+        self.functions.keys().all(Function::is_implemented)
+            && self.variables.keys().all(InstanceVariable::is_implemented)
+            && self.actions.values().all(|(_, implemented)| *implemented)
+    }
+
+    fn merge(&mut self, other: Usage) {
+        for (function, uses) in other.functions {
+            *self.functions.entry(function).or_default() += uses;
+        }
+        for (var, uses) in other.variables {
+            *self.variables.entry(var).or_default() += uses;
+        }
+        for (action, (uses, implemented)) in other.actions {
+            // An action only counts as implemented if it is everywhere it's used
+            let entry = self.actions.entry(action).or_insert((0, true));
+            entry.0 += uses;
+            entry.1 &= implemented;
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        for op in program.ops.iter() {
+            match op {
+                Op::Call { function, .. } => *self.functions.entry(*function).or_default() += 1,
+                Op::GetVariable { var, .. } | Op::SetVariable { var, .. } => {
+                    *self.variables.entry(*var).or_default() += 1
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Function { args, function } => {
+                *self.functions.entry(*function).or_default() += 1;
+                args.iter().for_each(|arg| self.node(arg));
+            },
+            Node::Script { args, .. } => args.iter().for_each(|arg| self.node(arg)),
+            Node::Field { accessor } => self.accessor(&accessor.array, &accessor.owner),
+            Node::Variable { accessor } => {
+                *self.variables.entry(accessor.var).or_default() += 1;
+                self.accessor(&accessor.array, &accessor.owner);
+            },
+            Node::Binary { left, right, .. } => {
+                self.node(left);
+                self.node(right);
+            },
+            Node::Unary { child, .. } => self.node(child),
+            Node::Literal { .. } | Node::Constant { .. } | Node::RuntimeError { .. } => (),
+        }
+    }
+
+    fn accessor(&mut self, array: &ArrayAccessor, owner: &InstanceIdentifier) {
+        match array {
+            ArrayAccessor::None => (),
+            ArrayAccessor::Single(index) => self.node(index),
+            ArrayAccessor::Double(index1, index2) => {
+                self.node(index1);
+                self.node(index2);
+            },
+        }
+        if let InstanceIdentifier::Expression(node) = owner {
+            self.node(node);
+        }
+    }
+
+    fn actions(&mut self, actions: &[Action]) {
+        for action in actions {
+            // An action counts as implemented if everything it runs itself is, not counting the actions under it
+            let mut usage = Usage::default();
+            match &action.body {
+                Body::Normal { args, body, if_else } => {
+                    args.iter().for_each(|arg| usage.node(arg));
+                    match body {
+                        GmlBody::Function(function) => *usage.functions.entry(*function).or_default() += 1,
+                        GmlBody::Code(program) => usage.program(program),
+                    }
+                    if let Some((if_body, else_body)) = if_else {
+                        self.actions(if_body);
+                        self.actions(else_body);
+                    }
+                },
+                Body::Repeat { count, body } => {
+                    usage.node(count);
+                    self.actions(body);
+                },
+                Body::Exit => (),
+            }
+            if let Some(library_action) = action.library_action {
+                let implemented = usage.is_implemented();
+                usage.actions.insert(library_action, (1, implemented));
+            }
+            self.merge(usage);
+        }
+    }
+}
+
+/// Builds a report of every builtin function, builtin variable and D&D action used anywhere in a game's code,
+/// and whether each of them is implemented, as JSON. Code which doesn't compile is left out.
+pub fn report(assets: &GameAssets) -> Json {
+    let mut compiler = game::new_compiler(assets);
+    let mut usage = Usage::default();
+    for trigger in assets.triggers.iter().flatten() {
+        if let Ok(program) = compiler.compile(&trigger.condition.0) {
+            usage.program(&program);
+        }
+    }
+    for script in assets.scripts.iter().flatten() {
+        if let Ok(program) = compiler.compile(&script.source.0) {
+            usage.program(&program);
+        }
+    }
+    for timeline in assets.timelines.iter().flatten() {
+        for (_, actions) in timeline.moments.iter() {
+            if let Ok(tree) = Tree::from_list(actions, &mut compiler) {
+                usage.actions(tree.actions());
+            }
+        }
+    }
+    for object in assets.objects.iter().flatten() {
+        for (_, actions) in object.events.iter().flatten() {
+            if let Ok(tree) = Tree::from_list(actions, &mut compiler) {
+                usage.actions(tree.actions());
+            }
+        }
+    }
+    for room in assets.rooms.iter().flatten() {
+        if let Ok(program) = compiler.compile(&room.creation_code.0) {
+            usage.program(&program);
+        }
+        for instance in room.instances.iter() {
+            if let Ok(program) = compiler.compile(&instance.creation_code.0) {
+                usage.program(&program);
+            }
+        }
+    }
+
+    let mut functions = usage
+        .functions
+        .iter()
+        .map(|(function, uses)| {
+            let name = mappings::FUNCTIONS.iter().find(|(_, f, _)| f == function).map_or("", |(name, _, _)| *name);
+            json!({ "name": name, "uses": uses, "implemented": function.is_implemented() })
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    let mut variables = usage
+        .variables
+        .iter()
+        .map(|(var, uses)| {
+            let name = mappings::INSTANCE_VARIABLES.iter().find(|(_, v)| v == var).map_or("", |(name, _)| *name);
+            json!({ "name": name, "uses": uses, "implemented": var.is_implemented() })
+        })
+        .collect::<Vec<_>>();
+    variables.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    let mut actions = usage.actions.iter().collect::<Vec<_>>();
+    actions.sort_by_key(|(id, _)| **id);
+    let actions = actions
+        .into_iter()
+        .map(|((library, id), (uses, implemented))| {
+            json!({ "library": library, "id": id, "uses": uses, "implemented": implemented })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "compatible": usage.is_implemented(),
+        "functions": functions,
+        "variables": variables,
+        "actions": actions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action_usage(implemented: bool) -> Usage {
+        let mut usage = Usage::default();
+        usage.actions.insert((1, 101), (1, implemented));
+        usage
+    }
+
+    #[test]
+    fn merge_actions() {
+        for order in [[true, false], [false, true]].iter() {
+            let mut usage = Usage::default();
+            for implemented in order.iter() {
+                usage.merge(action_usage(*implemented));
+            }
+            assert_eq!(usage.actions[&(1, 101)], (2, false));
+            assert!(!usage.is_implemented());
+        }
+
+        let mut usage = action_usage(true);
+        usage.merge(action_usage(true));
+        assert_eq!(usage.actions[&(1, 101)], (2, true));
+        assert!(usage.is_implemented());
+    }
+}
@@ -0,0 +1,258 @@
+//! GML console for investigating a game while it runs. Code can be typed into an overlay drawn over the game,
+//! or read from stdin, and runs in the context of a chosen instance or of global.
+
+use crate::{
+    game::{dialog::key_to_char, draw::Halign, Game, GetAsset, PlayType},
+    gml::{
+        compiler::{
+            ast::AST,
+            lexer::Lexer,
+            token::{Operator, Token},
+        },
+        Context, Value,
+    },
+    instance::Instance,
+};
+use shared::{
+    input::Key,
+    types::{Colour, ID},
+};
+use std::{
+    io::{self, BufRead},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// The key which shows and hides the console overlay.
+pub const TOGGLE_KEY: Key = Key::OEM3;
+
+/// How many lines of history the overlay shows above the input line.
+const VISIBLE_LINES: usize = 12;
+const MARGIN: i32 = 4;
+
+/// What code typed into the console runs as.
+#[derive(Clone, Copy)]
+pub enum Target {
+    /// A dummy instance, like room creation code, so only globals are useful
+    Global,
+
+    /// The instance with this ID
+    Instance(ID),
+}
+
+pub struct Console {
+    /// Whether the console was asked for. If it wasn't, the toggle key reaches the game like any other key.
+    pub enabled: bool,
+
+    /// Whether the overlay is showing. While it is, keyboard input goes to the console instead of the game.
+    pub open: bool,
+    pub target: Target,
+    input: Vec<u8>,
+    shift: bool,
+
+    /// Lines entered in the overlay which haven't been run yet
+    pending: Vec<Vec<u8>>,
+
+    /// Everything entered and printed, oldest first
+    history: Vec<String>,
+
+    /// Lines read from stdin by a background thread, if stdin mode is on
+    stdin: Option<Receiver<String>>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            open: false,
+            target: Target::Global,
+            input: Vec::new(),
+            shift: false,
+            pending: Vec::new(),
+            history: Vec::new(),
+            stdin: None,
+        }
+    }
+}
+
+impl Console {
+    /// Starts reading lines of GML from stdin. Results are printed to stdout as well as the overlay.
+    pub fn read_stdin(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) if sender.send(line).is_ok() => (),
+                    _ => break,
+                }
+            }
+        });
+        self.stdin = Some(receiver);
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.shift = false;
+    }
+
+    /// Handles a key being pressed while the overlay is open.
+    pub fn key_press(&mut self, key: Key) {
+        match key {
+            Key::Shift | Key::LeftShift | Key::RightShift => self.shift = true,
+            Key::Escape => self.open = false,
+            Key::Enter => self.pending.push(std::mem::take(&mut self.input)),
+            Key::Backspace => {
+                self.input.pop();
+            },
+            key => {
+                if let Some(c) = key_to_char(key, self.shift) {
+                    self.input.push(c);
+                }
+            },
+        }
+    }
+
+    /// Handles a key being released while the overlay is open.
+    pub fn key_release(&mut self, key: Key) {
+        if let Key::Shift | Key::LeftShift | Key::RightShift = key {
+            self.shift = false;
+        }
+    }
+
+    fn print(&mut self, line: String) {
+        if self.stdin.is_some() {
+            println!("{}", line);
+        }
+        self.history.push(line);
+    }
+}
+
+impl Game {
+    /// Runs whatever has been entered into the console since the last call.
+    pub fn poll_console(&mut self) {
+        let mut lines = std::mem::take(&mut self.console.pending);
+        if let Some(stdin) = &self.console.stdin {
+            lines.extend(stdin.try_iter().map(|line| {
+                self.encode_str_maybe(&line).unwrap_or_else(|| self.encoding.encode(&line).0).into_owned()
+            }));
+        }
+        for line in lines {
+            let echo = format!("> {}", self.decode_str(&line));
+            self.console.print(echo);
+            let output = if self.play_type != PlayType::Normal {
+                // Anything run here would change the game without being in the replay,
+                // so a recording wouldn't play back and a replay would desync
+                "the console can't be used while recording or replaying".into()
+            } else {
+                self.console_command(&line)
+            };
+            self.console.print(output);
+        }
+    }
+
+    /// Runs a line entered into the console, returning what to print.
+    /// `:global` and `:self ID` choose what code runs as. Anything else is run as GML.
+    fn console_command(&mut self, line: &[u8]) -> String {
+        let start = line.iter().position(|c| !c.is_ascii_whitespace()).unwrap_or(line.len());
+        let end = line.iter().rposition(|c| !c.is_ascii_whitespace()).map_or(start, |i| i + 1);
+        let line = &line[start..end];
+        if line == b":global" {
+            self.console.target = Target::Global;
+            return "running as global".into()
+        }
+        if line.starts_with(b":self ") {
+            return match std::str::from_utf8(&line[6..]).ok().and_then(|id| id.trim().parse::<ID>().ok()) {
+                Some(id) if self.instance_list.get_by_instid(id).is_some() => {
+                    self.console.target = Target::Instance(id);
+                    format!("running as instance {}", id)
+                },
+                Some(id) => format!("there's no instance {}", id),
+                None => "usage: :self ID".into(),
+            }
+        }
+        match self.console_execute(line) {
+            Ok(value) => value.to_string(),
+            Err(message) => message,
+        }
+    }
+
+    /// Compiles and runs some GML as the console's target, returning what it returns.
+    /// Anything which is a single expression is run for its value, so it can be printed. Anything else runs as code.
+    pub fn console_execute(&mut self, code: &[u8]) -> Result<Value, String> {
+        let mut expression = b"return ".to_vec();
+        expression.extend_from_slice(code);
+        let program = match self.compiler.compile(&expression) {
+            Ok(program) if is_expression(&expression) => program,
+            _ => self.compiler.compile(code).map_err(|e| e.to_string())?,
+        };
+
+        let (instance, dummy) = match self.console.target {
+            Target::Global => {
+                let object = self.assets.objects.get_asset(0).map(|x| x.as_ref());
+                (self.instance_list.insert_dummy(Instance::new_dummy(object)), true)
+            },
+            Target::Instance(id) => match self.instance_list.get_by_instid(id) {
+                Some(instance) => (instance, false),
+                None => return Err(format!("instance {} no longer exists", id)),
+            },
+        };
+        let mut context = Context {
+            this: instance,
+            other: instance,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object: self.instance_list.get(instance).object_index.get(),
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: Default::default(),
+            return_value: Default::default(),
+        };
+        let result = self.execute(&program, &mut context);
+        if dummy {
+            self.instance_list.remove_dummy(instance);
+        }
+        result.map(|_| context.return_value).map_err(|e| e.to_string())
+    }
+
+    /// Draws the console overlay over the top of the screen.
+    pub fn draw_console(&mut self) {
+        let line_height = self.default_font.tallest_char_height as i32;
+        let width = self.window_region_size().0 as i32;
+        let lines = self.console.history.len().min(VISIBLE_LINES);
+        let height = (lines as i32 + 1) * line_height + MARGIN * 2;
+        self.renderer.draw_rectangle(0.0, 0.0, f64::from(width - 1), f64::from(height - 1), 0, 0.75);
+
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let start = self.console.history.len() - lines;
+        for (i, line) in self.console.history[start..].to_vec().into_iter().enumerate() {
+            self.draw_splash_string(MARGIN, MARGIN + i as i32 * line_height, line.into(), None, Halign::Left, white);
+        }
+        let mut prompt = b"> ".to_vec();
+        prompt.extend_from_slice(&self.console.input);
+        prompt.push(b'_');
+        let y = MARGIN + lines as i32 * line_height;
+        self.draw_splash_string(MARGIN, y, prompt.into(), None, Halign::Left, white);
+    }
+}
+
+// Whether some code starting with `return` is just that one statement, so what it returns is a whole expression.
+// An `=` means it's really an assignment, which would otherwise be read as a comparison.
+fn is_expression(code: &[u8]) -> bool {
+    let assigns = Lexer::new(code).any(|token| match token {
+        Token::Operator(op) => matches!(
+            op,
+            Operator::Assign
+                | Operator::AssignAdd
+                | Operator::AssignSubtract
+                | Operator::AssignMultiply
+                | Operator::AssignDivide
+                | Operator::AssignBitwiseAnd
+                | Operator::AssignBitwiseOr
+                | Operator::AssignBitwiseXor
+        ),
+        _ => false,
+    });
+    !assigns && AST::new(code).map_or(false, |ast| ast.len() == 1)
+}
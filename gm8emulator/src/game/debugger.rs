@@ -0,0 +1,260 @@
+pub mod control;
+pub mod dap;
+
+use crate::{
+    game::{Game, GetAsset, PlayType},
+    gml::{
+        self,
+        bytecode::Program,
+        runtime::{ArrayAccessor, CodeSource, InstanceIdentifier, Node, ReturnType, StackFrame},
+        Context, Value,
+    },
+    instance::{Field, FieldMap},
+};
+use shared::message::{Breakpoint, CodeLocation, DebugStep};
+use std::io;
+
+/// Something which drives the debugger, such as the controller or a DAP client.
+pub trait DebugClient {
+    /// Handles anything the client has sent while the game was running.
+    fn poll(&mut self, game: &mut Game, state: &mut DebugState) -> io::Result<()>;
+
+    /// Tells the client the game has paused, then answers its requests until it says to carry on.
+    /// If this fails, the client is assumed to be gone and the debugger is dropped.
+    fn paused(
+        &mut self,
+        game: &mut Game,
+        state: &mut DebugState,
+        context: &mut Context,
+        reason: StopReason,
+    ) -> io::Result<()>;
+}
+
+/// GML debugger, which can pause the game before any line of GML and let a client look around.
+pub struct Debugger {
+    client: Box<dyn DebugClient>,
+    state: DebugState,
+}
+
+/// Everything the debugger tracks apart from the client driving it.
+#[derive(Default)]
+pub struct DebugState {
+    breakpoints: Vec<Breakpoint>,
+
+    /// The code currently running, innermost last, along with the last line which ran in each
+    frames: Vec<(CodeLocation, usize)>,
+
+    /// Pause at the next new line whose code is at most this deep in the call stack
+    step: Option<(usize, StopReason)>,
+}
+
+/// Why the debugger paused the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint,
+    Step,
+    Pause,
+}
+
+/// The variables visible to some paused code, as (name, value) pairs.
+pub struct Variables {
+    pub locals: Vec<(String, String)>,
+    pub arguments: Vec<String>,
+    pub self_fields: Vec<(String, String)>,
+    pub other_fields: Vec<(String, String)>,
+}
+
+impl Debugger {
+    pub fn new(client: Box<dyn DebugClient>) -> Self {
+        Self { client, state: Default::default() }
+    }
+}
+
+impl DebugState {
+    /// The code currently running, innermost first, along with the line it's on.
+    pub fn call_stack(&self) -> impl Iterator<Item = &(CodeLocation, usize)> {
+        self.frames.iter().rev()
+    }
+
+    pub fn set_breakpoints(&mut self, breakpoints: Vec<Breakpoint>) {
+        self.breakpoints = breakpoints;
+    }
+
+    /// Pauses the game at the next line of GML it runs.
+    pub fn pause(&mut self) {
+        self.step = Some((usize::MAX, StopReason::Pause));
+    }
+
+    /// Sets up stepping from the current position.
+    pub fn resume(&mut self, step: DebugStep) {
+        let depth = self.frames.len();
+        self.step = match step {
+            DebugStep::Continue => None,
+            DebugStep::Into => Some((usize::MAX, StopReason::Step)),
+            DebugStep::Over => Some((depth, StopReason::Step)),
+            DebugStep::Out => Some((depth.saturating_sub(1), StopReason::Step)),
+        };
+    }
+
+    /// Records that a line of code is about to run, and checks whether the game should pause before it.
+    /// Only moving onto a new line can cause a pause, so a line with several statements is only stopped at once.
+    fn check_line(&mut self, line: usize) -> Option<StopReason> {
+        let depth = self.frames.len();
+        let (location, last_line) = self.frames.last_mut()?;
+        if *last_line == line {
+            return None
+        }
+        *last_line = line;
+        match self.step {
+            Some((max_depth, reason)) if depth <= max_depth => Some(reason),
+            _ if self.breakpoints.iter().any(|bp| bp.line == line && &bp.location == location) => {
+                Some(StopReason::Breakpoint)
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Game {
+    /// Executes a piece of code, keeping track of where it is for the debugger if there is one.
+    pub fn execute_at(
+        &mut self,
+        location: CodeLocation,
+        program: &Program,
+        context: &mut Context,
+    ) -> gml::Result<ReturnType> {
+        match self.debugger.as_mut() {
+            Some(debugger) => {
+                debugger.state.frames.push((location, 0));
+                let result = self.execute(program, context);
+                if let Some(debugger) = self.debugger.as_mut() {
+                    debugger.state.frames.pop();
+                }
+                result
+            },
+            None => self.execute(program, context),
+        }
+    }
+
+    /// Called before each instruction runs while there's a debugger. Pauses the game there if it should.
+    pub fn debug_line(&mut self, line: usize, context: &mut Context) {
+        if let Some(mut debugger) = self.debugger.take() {
+            // The debugger is held here while paused, so anything evaluated meanwhile isn't debugged
+            let keep = match debugger.state.check_line(line) {
+                Some(reason) => {
+                    debugger.state.step = None;
+                    debugger.client.paused(self, &mut debugger.state, context, reason).is_ok()
+                },
+                None => true,
+            };
+            if keep {
+                self.debugger = Some(debugger);
+            }
+        }
+    }
+
+    /// Lets the debugger's client deal with anything it's sent while the game is running. Called once per frame.
+    pub fn poll_debugger(&mut self) {
+        if let Some(mut debugger) = self.debugger.take() {
+            if debugger.client.poll(self, &mut debugger.state).is_ok() {
+                self.debugger = Some(debugger);
+            }
+        }
+    }
+
+    /// Gets the locals, arguments and self and other fields visible to some paused code.
+    pub fn debug_variables(&self, context: &Context) -> Variables {
+        let field_list = |fields: &FieldMap| {
+            let mut list = fields
+                .iter()
+                .flat_map(|(id, field)| {
+                    let name = self.compiler.get_field_name(*id).unwrap_or_else(|| format!("<field {}>", id));
+                    match field {
+                        Field::Single(value) => vec![(name, value.to_string())],
+                        Field::Array(values) => {
+                            let mut values = values.iter().collect::<Vec<_>>();
+                            values.sort_by_key(|(index, _)| **index);
+                            values
+                                .into_iter()
+                                .map(|(index, value)| match (index / 32000, index % 32000) {
+                                    (0, i) => (format!("{}[{}]", name, i), value.to_string()),
+                                    (i, j) => (format!("{}[{},{}]", name, i, j), value.to_string()),
+                                })
+                                .collect()
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+            list.sort();
+            list
+        };
+        Variables {
+            locals: field_list(&context.locals.fields),
+            arguments: context.arguments.iter().take(context.argument_count).map(Value::to_string).collect(),
+            self_fields: field_list(&*self.instance_list.get(context.this).fields.borrow()),
+            other_fields: field_list(&*self.instance_list.get(context.other).fields.borrow()),
+        }
+    }
+
+    /// Compiles and evaluates an expression as if it were part of the paused code.
+    /// While recording or replaying, only expressions which can't change anything are allowed,
+    /// since any changes wouldn't be in the replay.
+    pub fn debug_evaluate(&mut self, expression: &str, context: &mut Context) -> Result<String, String> {
+        let source = self.encode_str_maybe(expression).unwrap_or_else(|| self.encoding.encode(expression).0);
+        let node = self.compiler.compile_expression(source.as_ref()).map_err(|e| e.to_string())?;
+        if self.play_type != PlayType::Normal && !is_read_only(&node) {
+            return Err("function and script calls can't be evaluated while recording or replaying".into())
+        }
+        self.eval(&node, context).map(|value| value.to_string()).map_err(|e| e.to_string())
+    }
+
+    /// Describes a line of code the way error messages do.
+    pub fn describe_line(&self, location: &CodeLocation, line: usize) -> String {
+        StackFrame { source: self.location_source(location), line: Some(line) }.to_string()
+    }
+
+    /// Names a code location the way error messages do.
+    fn location_source(&self, location: &CodeLocation) -> CodeSource {
+        let name = |name: Option<&[u8]>, id: i32| match name {
+            Some(name) => self.decode_str(name).into_owned(),
+            None => id.to_string(),
+        };
+        match location {
+            CodeLocation::Action { object, event_type, event_number, action } => CodeSource::Action {
+                action: *action,
+                event: self.event_name(*event_type, *event_number),
+                object: self.object_name(*object),
+            },
+            CodeLocation::Script { script } => {
+                CodeSource::Script(name(self.assets.scripts.get_asset(*script).map(|s| s.name.as_ref()), *script))
+            },
+            CodeLocation::Trigger { trigger } => {
+                CodeSource::Trigger(name(self.assets.triggers.get_asset(*trigger).map(|t| t.name.as_ref()), *trigger))
+            },
+            CodeLocation::RoomCreation { room } => {
+                CodeSource::RoomCreation(name(self.assets.rooms.get_asset(*room).map(|r| r.name.as_ref()), *room))
+            },
+            CodeLocation::InstanceCreation { instance } => CodeSource::InstanceCreation(*instance),
+        }
+    }
+}
+
+// Whether evaluating an expression only reads the game's state. Function and script calls could change it.
+fn is_read_only(node: &Node) -> bool {
+    let accessor = |array: &ArrayAccessor, owner: &InstanceIdentifier| {
+        let array = match array {
+            ArrayAccessor::None => true,
+            ArrayAccessor::Single(index) => is_read_only(index),
+            ArrayAccessor::Double(index1, index2) => is_read_only(index1) && is_read_only(index2),
+        };
+        array && if let InstanceIdentifier::Expression(owner) = owner { is_read_only(owner) } else { true }
+    };
+    match node {
+        Node::Literal { .. } | Node::Constant { .. } | Node::RuntimeError { .. } => true,
+        Node::Function { .. } | Node::Script { .. } => false,
+        Node::Field { accessor: field } => accessor(&field.array, &field.owner),
+        Node::Variable { accessor: var } => accessor(&var.array, &var.owner),
+        Node::Binary { left, right, .. } => is_read_only(left) && is_read_only(right),
+        Node::Unary { child, .. } => is_read_only(child),
+    }
+}
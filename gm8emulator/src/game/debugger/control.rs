@@ -0,0 +1,104 @@
+use crate::{
+    game::{
+        debugger::{DebugClient, DebugState, Debugger, StopReason},
+        Game,
+    },
+    gml::Context,
+};
+use shared::message::{self, DebugFrame, Message, MessageStream};
+use std::{io, net::TcpStream};
+
+/// Debugger client for the controller which is recording the game.
+///
+/// It keeps its own handle to the controller's stream, since it has to be able to talk to it
+/// from deep inside a frame whenever the game pauses. While the game is running, the controller's
+/// messages are read by the record loop and passed on through `Debugger::handle_message`.
+pub struct ControlClient {
+    stream: TcpStream,
+    read_buffer: Vec<u8>,
+}
+
+impl ControlClient {
+    pub fn new(stream: TcpStream) -> Self {
+        Self { stream, read_buffer: Vec::new() }
+    }
+
+    /// Answers the controller's requests while the game is paused, until it says to carry on.
+    fn answer_requests(&mut self, game: &mut Game, state: &mut DebugState, context: &mut Context) -> io::Result<()> {
+        loop {
+            let message = match self.stream.receive_message::<Message>(&mut self.read_buffer)? {
+                Some(Some(m)) => m,
+                Some(None) => continue,
+                None => return Err(io::ErrorKind::ConnectionAborted.into()),
+            };
+            match message {
+                Message::DebugResume { step } => {
+                    state.resume(step);
+                    break Ok(())
+                },
+                Message::DebugInspect => {
+                    let variables = game.debug_variables(context);
+                    self.stream.send_message(&message::Information::DebugVariables {
+                        locals: variables.locals,
+                        arguments: variables.arguments,
+                        self_fields: variables.self_fields,
+                        other_fields: variables.other_fields,
+                    })?;
+                },
+                Message::DebugEvaluate { expression } => {
+                    let result = game.debug_evaluate(&expression, context);
+                    self.stream.send_message(&message::Information::DebugEvaluated { result })?;
+                },
+                Message::SetBreakpoints { breakpoints } => state.set_breakpoints(breakpoints),
+                Message::DebugPause => (),
+                m => self.stream.send_message(&message::Information::GameError {
+                    error: format!("Can't handle {:?} while paused in the debugger", m),
+                })?,
+            }
+        }
+    }
+}
+
+impl Debugger {
+    /// Handles a debugger message which the controller sent while the game was running.
+    pub fn handle_message(&mut self, message: Message, stream: &mut TcpStream) -> io::Result<()> {
+        match message {
+            Message::SetBreakpoints { breakpoints } => self.state.set_breakpoints(breakpoints),
+            Message::DebugPause => self.state.pause(),
+            m => stream.send_message(&message::Information::GameError {
+                error: format!("Can't handle {:?} while the debugger isn't paused", m),
+            })?,
+        }
+        Ok(())
+    }
+}
+
+impl DebugClient for ControlClient {
+    fn poll(&mut self, _game: &mut Game, _state: &mut DebugState) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn paused(
+        &mut self,
+        game: &mut Game,
+        state: &mut DebugState,
+        context: &mut Context,
+        _reason: StopReason,
+    ) -> io::Result<()> {
+        let call_stack = state
+            .call_stack()
+            .map(|(location, line)| DebugFrame {
+                location: location.clone(),
+                line: *line,
+                description: game.describe_line(location, *line),
+            })
+            .collect();
+        self.stream.send_message(&message::Information::DebugPaused { call_stack })?;
+
+        // Nothing else happens until the controller says to carry on, so block on the stream until then
+        self.stream.set_nonblocking(false)?;
+        let result = self.answer_requests(game, state, context);
+        self.stream.set_nonblocking(true)?;
+        result
+    }
+}
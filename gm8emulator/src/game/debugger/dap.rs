@@ -0,0 +1,486 @@
+use crate::{
+    action::action_code,
+    game::{
+        debugger::{DebugClient, DebugState, StopReason, Variables},
+        Game, GetAsset,
+    },
+    gml::Context,
+};
+use serde_json::{json, Value as Json};
+use shared::{
+    message::{Breakpoint, CodeLocation, DebugStep},
+    types::ID,
+};
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+/// The only thread a DAP client is told about, since GML only ever runs on one.
+const THREAD_ID: i64 = 1;
+
+/// Debugger client speaking the Debug Adapter Protocol, so editors can debug the game's GML.
+///
+/// GM8 games don't carry their project files, so the code is served to the editor as virtual documents,
+/// one per script, trigger, object event and room, which are regenerated from the game's assets.
+pub struct DapClient {
+    stream: TcpStream,
+    read_buffer: Vec<u8>,
+    seq: i64,
+    sources: Vec<Source>,
+
+    /// Breakpoints the client has set, by index into `sources`
+    breakpoints: HashMap<usize, Vec<Breakpoint>>,
+
+    /// Whether the client has finished setting up - the game doesn't start until it has
+    configured: bool,
+
+    /// The variables of the paused code, if the game is paused
+    variables: Option<Variables>,
+}
+
+/// A virtual document containing some of the game's GML.
+pub struct Source {
+    kind: SourceKind,
+    code: Vec<u8>,
+    segments: Vec<Segment>,
+}
+
+enum SourceKind {
+    Script(ID),
+    Trigger(ID),
+    Event { object: ID, event_type: usize, event_number: usize },
+    Room(ID),
+}
+
+/// A piece of code which was compiled on its own, and where it starts in its document.
+struct Segment {
+    location: CodeLocation,
+    first_line: usize,
+    line_count: usize,
+}
+
+impl Source {
+    fn new(kind: SourceKind) -> Self {
+        Self { kind, code: Vec::new(), segments: Vec::new() }
+    }
+
+    /// Adds a comment line to the document.
+    fn push_comment(&mut self, comment: &str) {
+        self.code.extend_from_slice(b"// ");
+        self.code.extend_from_slice(comment.as_bytes());
+        self.code.push(b'\n');
+    }
+
+    /// Adds a piece of code to the document, remembering which lines it's on.
+    fn push_code(&mut self, location: CodeLocation, code: &[u8]) {
+        let first_line = self.code.iter().filter(|&&ch| ch == b'\n').count() + 1;
+        let line_count = code.iter().filter(|&&ch| ch == b'\n').count() + 1;
+        self.segments.push(Segment { location, first_line, line_count });
+        self.code.extend_from_slice(code);
+        self.code.push(b'\n');
+    }
+
+    /// Finds which code a line of the document belongs to, and which line of that code it is.
+    fn code_line(&self, line: usize) -> Option<(&CodeLocation, usize)> {
+        self.segments
+            .iter()
+            .find(|s| (s.first_line..s.first_line + s.line_count).contains(&line))
+            .map(|s| (&s.location, line - s.first_line + 1))
+    }
+
+    /// Finds the line of the document a line of some code is on, if that code is in this document.
+    fn document_line(&self, location: &CodeLocation, line: usize) -> Option<usize> {
+        self.segments.iter().find(|s| &s.location == location).map(|s| s.first_line + line - 1)
+    }
+
+    fn name(&self, game: &Game) -> String {
+        let name = |name: Option<&[u8]>, id: ID| match name {
+            Some(name) => game.decode_str(name).into_owned(),
+            None => id.to_string(),
+        };
+        match &self.kind {
+            SourceKind::Script(id) => name(game.assets.scripts.get_asset(*id).map(|s| s.name.as_ref()), *id),
+            SourceKind::Trigger(id) => {
+                format!("trigger {}", name(game.assets.triggers.get_asset(*id).map(|t| t.name.as_ref()), *id))
+            },
+            SourceKind::Event { object, event_type, event_number } => {
+                format!("{}: {}", game.object_name(*object), game.event_name(*event_type, *event_number))
+            },
+            SourceKind::Room(id) => {
+                format!("room {}", name(game.assets.rooms.get_asset(*id).map(|r| r.name.as_ref()), *id))
+            },
+        }
+    }
+}
+
+/// Regenerates the GML documents for a game from its assets. This has to happen before the game is launched,
+/// since only the compiled code is kept after that.
+pub fn collect_sources(assets: &gm8exe::GameAssets) -> Vec<Source> {
+    let mut sources = Vec::new();
+    for (id, script) in assets.scripts.iter().enumerate() {
+        if let Some(script) = script {
+            let mut source = Source::new(SourceKind::Script(id as ID));
+            source.push_code(CodeLocation::Script { script: id as ID }, &script.source.0[..]);
+            sources.push(source);
+        }
+    }
+    for (id, trigger) in assets.triggers.iter().enumerate() {
+        if let Some(trigger) = trigger {
+            let mut source = Source::new(SourceKind::Trigger(id as ID));
+            source.push_code(CodeLocation::Trigger { trigger: id as ID }, &trigger.condition.0[..]);
+            sources.push(source);
+        }
+    }
+    for (id, object) in assets.objects.iter().enumerate() {
+        if let Some(object) = object {
+            for (event_type, events) in object.events.iter().enumerate() {
+                for (event_number, actions) in events {
+                    let object = id as ID;
+                    let event_number = *event_number as usize;
+                    let mut source = Source::new(SourceKind::Event { object, event_type, event_number });
+                    for (action, code_action) in actions.iter().enumerate() {
+                        if let Some(code) = action_code(code_action) {
+                            source.push_comment(&format!("Action {}", action + 1));
+                            source.push_code(CodeLocation::Action { object, event_type, event_number, action }, &code);
+                        }
+                    }
+                    if !source.segments.is_empty() {
+                        sources.push(source);
+                    }
+                }
+            }
+        }
+    }
+    for (id, room) in assets.rooms.iter().enumerate() {
+        if let Some(room) = room {
+            let mut source = Source::new(SourceKind::Room(id as ID));
+            if !room.creation_code.0.is_empty() {
+                source.push_comment("Creation code");
+                source.push_code(CodeLocation::RoomCreation { room: id as ID }, &room.creation_code.0[..]);
+            }
+            for instance in room.instances.iter().filter(|i| !i.creation_code.0.is_empty()) {
+                source.push_comment(&format!("Creation code of instance {}", instance.id));
+                let location = CodeLocation::InstanceCreation { instance: instance.id };
+                source.push_code(location, &instance.creation_code.0[..]);
+            }
+            if !source.segments.is_empty() {
+                sources.push(source);
+            }
+        }
+    }
+    sources
+}
+
+/// What the game should do after a request has been handled.
+#[derive(PartialEq)]
+enum Flow {
+    Stay,
+    Resume,
+}
+
+impl DapClient {
+    pub fn new(stream: TcpStream, sources: Vec<Source>) -> Self {
+        Self {
+            stream,
+            read_buffer: Vec::new(),
+            seq: 0,
+            sources,
+            breakpoints: HashMap::new(),
+            configured: false,
+            variables: None,
+        }
+    }
+
+    /// Reads the next message from the client, or None if there isn't one yet and the stream is nonblocking.
+    fn read_message(&mut self) -> io::Result<Option<Json>> {
+        let mut buf = [0u8; 4096];
+        loop {
+            if let Some(message) = self.parse_message()? {
+                break Ok(Some(message))
+            }
+            match self.stream.read(&mut buf) {
+                Ok(0) => break Err(io::ErrorKind::ConnectionAborted.into()),
+                Ok(n) => self.read_buffer.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => break Err(e),
+            }
+        }
+    }
+
+    /// Takes a whole message out of the read buffer if there is one. Each is a JSON object after
+    /// some HTTP-like headers, which give the length of the object as Content-Length.
+    fn parse_message(&mut self) -> io::Result<Option<Json>> {
+        let header_end = match self.read_buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+        let invalid = |e: &dyn std::fmt::Display| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let headers = std::str::from_utf8(&self.read_buffer[..header_end]).map_err(|e| invalid(&e))?;
+        let length = headers
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, ':');
+                Some((parts.next()?, parts.next()?))
+            })
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("Content-Length"))
+            .ok_or_else(|| invalid(&"missing Content-Length"))?
+            .1
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| invalid(&e))?;
+        let body_start = header_end + 4;
+        if self.read_buffer.len() < body_start + length {
+            return Ok(None)
+        }
+        let message = serde_json::from_slice(&self.read_buffer[body_start..body_start + length]);
+        self.read_buffer.drain(..body_start + length);
+        message.map(Some).map_err(|e| invalid(&e))
+    }
+
+    fn send(&mut self, mut message: Json) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = self.seq.into();
+        let body = message.to_string();
+        write!(self.stream, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stream.flush()
+    }
+
+    fn send_event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = message.into(),
+        }
+        self.send(response)
+    }
+
+    /// Finds the document a DAP Source object refers to.
+    fn find_source(&self, game: &Game, source: &Json) -> Option<usize> {
+        match source["sourceReference"].as_u64() {
+            Some(reference) if reference > 0 => Some(reference as usize - 1).filter(|i| *i < self.sources.len()),
+            _ => {
+                let name = source["name"].as_str()?;
+                self.sources.iter().position(|s| s.name(game) == name)
+            },
+        }
+    }
+
+    fn source_json(&self, game: &Game, index: usize) -> Json {
+        json!({ "name": self.sources[index].name(game), "sourceReference": index + 1 })
+    }
+
+    /// Handles a request from the client. The context is only given while the game is paused.
+    fn handle_request(
+        &mut self,
+        game: &mut Game,
+        state: &mut DebugState,
+        context: Option<&mut Context>,
+        request: Json,
+    ) -> io::Result<Flow> {
+        let args = &request["arguments"];
+        let paused = context.is_some();
+        let mut flow = Flow::Stay;
+        let result = match request["command"].as_str().unwrap_or("") {
+            "initialize" => {
+                self.respond(
+                    &request,
+                    Ok(json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsLoadedSourcesRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    })),
+                )?;
+                return self.send_event("initialized", json!({})).map(|()| Flow::Stay)
+            },
+            "attach" | "launch" | "setExceptionBreakpoints" => Ok(json!({})),
+            "configurationDone" => {
+                self.configured = true;
+                Ok(json!({}))
+            },
+            "disconnect" => {
+                self.respond(&request, Ok(json!({})))?;
+                return Err(io::ErrorKind::ConnectionAborted.into())
+            },
+            "setBreakpoints" => match self.find_source(game, &args["source"]) {
+                Some(index) => {
+                    let lines = args["breakpoints"]
+                        .as_array()
+                        .map(|bps| bps.iter().filter_map(|bp| bp["line"].as_u64()).collect::<Vec<_>>())
+                        .unwrap_or_default();
+                    let mut breakpoints = Vec::new();
+                    let mut results = Vec::new();
+                    for line in lines {
+                        let code_line = self.sources[index].code_line(line as usize);
+                        if let Some((location, line)) = code_line {
+                            breakpoints.push(Breakpoint { location: location.clone(), line });
+                        }
+                        results.push(json!({
+                            "verified": code_line.is_some(),
+                            "line": line,
+                            "source": self.source_json(game, index),
+                        }));
+                    }
+                    self.breakpoints.insert(index, breakpoints);
+                    state.set_breakpoints(self.breakpoints.values().flatten().cloned().collect());
+                    Ok(json!({ "breakpoints": results }))
+                },
+                None => Err("Unknown source".into()),
+            },
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "Game" }] })),
+            "stackTrace" => {
+                let frames = state
+                    .call_stack()
+                    .enumerate()
+                    .map(|(id, (location, line))| {
+                        let mut frame = json!({
+                            "id": id,
+                            "name": game.describe_line(location, *line),
+                            "line": 0,
+                            "column": 0,
+                        });
+                        let document = self.sources.iter().enumerate().find_map(|(index, source)| {
+                            source.document_line(location, *line).map(|line| (index, line))
+                        });
+                        if let Some((index, line)) = document {
+                            frame["source"] = self.source_json(game, index);
+                            frame["line"] = line.into();
+                            frame["column"] = 1.into();
+                        }
+                        frame
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({ "totalFrames": frames.len(), "stackFrames": frames }))
+            },
+            "scopes" => {
+                // Only the innermost code's variables can be seen, as that's the only context which is kept
+                let scopes = if paused && args["frameId"].as_u64() == Some(0) {
+                    json!([
+                        { "name": "Locals", "variablesReference": 1, "expensive": false },
+                        { "name": "Arguments", "variablesReference": 2, "expensive": false },
+                        { "name": "Self", "variablesReference": 3, "expensive": false },
+                        { "name": "Other", "variablesReference": 4, "expensive": false },
+                    ])
+                } else {
+                    json!([])
+                };
+                Ok(json!({ "scopes": scopes }))
+            },
+            "variables" => {
+                let variable =
+                    |name: &str, value: &str| json!({ "name": name, "value": value, "variablesReference": 0 });
+                let variables = match (&self.variables, args["variablesReference"].as_u64()) {
+                    (Some(v), Some(1)) => v.locals.iter().map(|(n, v)| variable(n, v)).collect(),
+                    (Some(v), Some(2)) => {
+                        v.arguments.iter().enumerate().map(|(i, v)| variable(&format!("argument{}", i), v)).collect()
+                    },
+                    (Some(v), Some(3)) => v.self_fields.iter().map(|(n, v)| variable(n, v)).collect(),
+                    (Some(v), Some(4)) => v.other_fields.iter().map(|(n, v)| variable(n, v)).collect(),
+                    _ => Vec::new(),
+                };
+                Ok(json!({ "variables": variables }))
+            },
+            "evaluate" => match (context, args["expression"].as_str()) {
+                (Some(context), Some(expression)) => game
+                    .debug_evaluate(expression, context)
+                    .map(|result| json!({ "result": result, "variablesReference": 0 })),
+                (None, _) => Err("The game isn't paused".into()),
+                (_, None) => Err("No expression given".into()),
+            },
+            "source" => match args["sourceReference"].as_u64().or_else(|| args["source"]["sourceReference"].as_u64()) {
+                Some(reference) if reference > 0 && (reference as usize) <= self.sources.len() => {
+                    let content = game.decode_str(&self.sources[reference as usize - 1].code).into_owned();
+                    Ok(json!({ "content": content, "mimeType": "text/x-gml" }))
+                },
+                _ => Err("Unknown source".into()),
+            },
+            "loadedSources" => {
+                let sources = (0..self.sources.len()).map(|i| self.source_json(game, i)).collect::<Vec<_>>();
+                Ok(json!({ "sources": sources }))
+            },
+            "pause" => {
+                if !paused {
+                    state.pause();
+                }
+                Ok(json!({}))
+            },
+            command @ "continue" | command @ "next" | command @ "stepIn" | command @ "stepOut" => {
+                if paused {
+                    state.resume(match command {
+                        "next" => DebugStep::Over,
+                        "stepIn" => DebugStep::Into,
+                        "stepOut" => DebugStep::Out,
+                        _ => DebugStep::Continue,
+                    });
+                    flow = Flow::Resume;
+                }
+                Ok(json!({ "allThreadsContinued": true }))
+            },
+            command => Err(format!("Unsupported request: {}", command)),
+        };
+        self.respond(&request, result)?;
+        Ok(flow)
+    }
+}
+
+impl DebugClient for DapClient {
+    fn poll(&mut self, game: &mut Game, state: &mut DebugState) -> io::Result<()> {
+        // Wait for the client to set its breakpoints before the game gets going
+        while !self.configured {
+            let request = self.read_message()?.ok_or(io::ErrorKind::WouldBlock)?;
+            self.handle_request(game, state, None, request)?;
+        }
+
+        self.stream.set_nonblocking(true)?;
+        let result = (|| {
+            while let Some(request) = self.read_message()? {
+                self.handle_request(game, state, None, request)?;
+            }
+            Ok(())
+        })();
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+
+    fn paused(
+        &mut self,
+        game: &mut Game,
+        state: &mut DebugState,
+        context: &mut Context,
+        reason: StopReason,
+    ) -> io::Result<()> {
+        let reason = match reason {
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+            StopReason::Pause => "pause",
+        };
+        self.variables = Some(game.debug_variables(context));
+        self.send_event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }))?;
+
+        let result = loop {
+            let request = match self.read_message() {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => break Err(e),
+            };
+            match self.handle_request(game, state, Some(&mut *context), request) {
+                Ok(Flow::Resume) => break Ok(()),
+                Ok(Flow::Stay) => (),
+                Err(e) => break Err(e),
+            }
+        };
+        self.variables = None;
+        result
+    }
+}
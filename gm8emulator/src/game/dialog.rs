@@ -0,0 +1,819 @@
+use crate::{
+    game::{
+        draw::{Halign, Valign},
+        replay::Event,
+        string::RCStr,
+        Game, GetAsset, PlayType,
+    },
+    gml::{self, Value},
+    math::Real,
+};
+use gmio::render::Scaling;
+use serde::{Deserialize, Serialize};
+use shared::{
+    input::{Key, MouseButton},
+    types::{Colour, ID},
+};
+use std::time::Duration;
+
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+const BOX_MARGIN: i32 = 16;
+const BOX_MIN_WIDTH: i32 = 200;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 24;
+const BUTTON_SPACING: i32 = 8;
+const INPUT_HEIGHT: i32 = 22;
+const CAPTION_HEIGHT: i32 = 20;
+const MENU_ITEM_HEIGHT: i32 = 20;
+const MENU_PADDING: i32 = 6;
+
+const COLOUR_FACE: i32 = 0xC0C0C0;
+const COLOUR_LIGHT: i32 = 0xFFFFFF;
+const COLOUR_SHADOW: i32 = 0x808080;
+const COLOUR_CAPTION: i32 = 0x800000;
+const COLOUR_HIGHLIGHT: i32 = 0x6A240A;
+
+/// Font settings for one of the three text styles a message box uses.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MessageFont {
+    pub name: RCStr,
+    pub size: i32,
+    pub colour: i32,
+    pub style: i32,
+}
+
+/// Appearance of GM8's message boxes, as configured by the message_* functions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MessageSettings {
+    pub background: ID,
+    pub button_sprite: ID,
+    pub alpha: Real,
+    pub text_font: MessageFont,
+    pub button_font: MessageFont,
+    pub input_font: MessageFont,
+    pub mouse_colour: i32,
+    pub input_colour: i32,
+    pub position: (i32, i32),
+    pub size: (i32, i32),
+    pub show_caption: bool,
+    pub caption: RCStr,
+}
+
+impl Default for MessageSettings {
+    fn default() -> Self {
+        let font = |colour| MessageFont { name: "Arial".into(), size: 12, colour, style: 0 };
+        Self {
+            background: -1,
+            button_sprite: -1,
+            alpha: Real::from(1.0),
+            text_font: font(0x000000),
+            button_font: font(0x000000),
+            input_font: font(0x000000),
+            mouse_colour: 0x800000,
+            input_colour: 0xFFFFFF,
+            position: (-1, -1),
+            size: (-1, -1),
+            show_caption: true,
+            caption: "".into(),
+        }
+    }
+}
+
+/// A modal dialog which can be requested by GML.
+pub enum Dialog {
+    /// show_message(): text with an OK button.
+    Message(RCStr),
+    /// show_question(): text with Yes and No buttons.
+    Question(RCStr),
+    /// show_message_ext(): text with up to three custom buttons. Empty labels are not shown.
+    MessageExt(RCStr, [RCStr; 3]),
+    /// get_integer(): text with an input field, OK and Cancel.
+    Integer(RCStr, Value),
+    /// get_string(): text with an input field, OK and Cancel.
+    String(RCStr, Value),
+    /// show_menu() and show_menu_pos(): a popup menu at the given position, or at the mouse if None.
+    Menu(Vec<RCStr>, Option<(i32, i32)>, Value),
+    /// A runtime error or show_error(): text with Ignore and Abort buttons, or only Abort if the bool is set.
+    Error(RCStr, bool),
+}
+
+/// What the user did to dismiss a dialog.
+enum Response {
+    Button(usize),
+    Closed,
+}
+
+impl Dialog {
+    /// Converts the dialog's GML return value into the event stored in replays.
+    fn to_event(&self, value: Value) -> Event {
+        match self {
+            Self::Message(_) => Event::ShowMessage,
+            Self::Question(_) => Event::ShowQuestion(value),
+            Self::MessageExt(..) => Event::ShowMessageExt(value),
+            Self::Integer(..) => Event::GetInteger(value),
+            Self::String(..) => Event::GetString(value),
+            Self::Menu(..) => Event::ShowMenu(value),
+            Self::Error(..) => Event::ShowError(value),
+        }
+    }
+
+    /// Gets the GML return value out of a stored event, if it's the right kind of event for this dialog.
+    fn from_event(&self, event: Event) -> Option<Value> {
+        match (self, event) {
+            (Self::Message(_), Event::ShowMessage) => Some(Default::default()),
+            (Self::Question(_), Event::ShowQuestion(v))
+            | (Self::MessageExt(..), Event::ShowMessageExt(v))
+            | (Self::Integer(..), Event::GetInteger(v))
+            | (Self::String(..), Event::GetString(v))
+            | (Self::Menu(..), Event::ShowMenu(v))
+            | (Self::Error(..), Event::ShowError(v)) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Strips Windows-style '&' accelerator markers from a button label. "&&" becomes a literal '&'.
+fn strip_accelerators(label: &[u8]) -> RCStr {
+    let mut out = Vec::with_capacity(label.len());
+    let mut iter = label.iter().copied().peekable();
+    while let Some(c) = iter.next() {
+        if c == b'&' {
+            if iter.peek() == Some(&b'&') {
+                iter.next();
+                out.push(b'&');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out.into()
+}
+
+/// Gets the character typed by a key press, if any. Only handles what a US keyboard would produce.
+pub(super) fn key_to_char(key: Key, shift: bool) -> Option<u8> {
+    let code = key as u8;
+    let numpad = (Key::Numpad0 as u8)..=(Key::Numpad9 as u8);
+    Some(match key {
+        _ if code.is_ascii_uppercase() => {
+            if shift {
+                code
+            } else {
+                code.to_ascii_lowercase()
+            }
+        },
+        _ if code.is_ascii_digit() => {
+            if shift {
+                b")!@#$%^&*("[(code - b'0') as usize]
+            } else {
+                code
+            }
+        },
+        _ if numpad.contains(&code) => b'0' + (code - Key::Numpad0 as u8),
+        Key::Space => b' ',
+        Key::Minus => {
+            if shift {
+                b'_'
+            } else {
+                b'-'
+            }
+        },
+        Key::Plus => {
+            if shift {
+                b'+'
+            } else {
+                b'='
+            }
+        },
+        Key::Add => b'+',
+        Key::Subtract => b'-',
+        Key::Multiply => b'*',
+        Key::Divide => b'/',
+        Key::Decimal => b'.',
+        Key::Period => {
+            if shift {
+                b'>'
+            } else {
+                b'.'
+            }
+        },
+        Key::Comma => {
+            if shift {
+                b'<'
+            } else {
+                b','
+            }
+        },
+        Key::OEM1 => {
+            if shift {
+                b':'
+            } else {
+                b';'
+            }
+        },
+        Key::OEM2 => {
+            if shift {
+                b'?'
+            } else {
+                b'/'
+            }
+        },
+        Key::OEM4 => {
+            if shift {
+                b'{'
+            } else {
+                b'['
+            }
+        },
+        Key::OEM5 => {
+            if shift {
+                b'|'
+            } else {
+                b'\\'
+            }
+        },
+        Key::OEM6 => {
+            if shift {
+                b'}'
+            } else {
+                b']'
+            }
+        },
+        Key::OEM7 => {
+            if shift {
+                b'"'
+            } else {
+                b'\''
+            }
+        },
+        _ => return None,
+    })
+}
+
+/// Mutable state of a dialog while it's on screen.
+struct DialogState {
+    input: Vec<u8>,
+    hover: Option<usize>,
+    pressed: Option<usize>,
+    shift: bool,
+}
+
+/// Layout of a message box, in framebuffer coordinates.
+struct MessageLayout {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    text_y: i32,
+    input_y: Option<i32>,
+    buttons: Vec<(RCStr, usize, i32, i32)>, // label, response index, x, y
+}
+
+impl Game {
+    /// Shows a dialog and returns its GML result. In record mode, the result is stored as a replay event.
+    /// In replay mode, nothing is shown and the result is read from the replay instead.
+    pub fn show_dialog(&mut self, function: &str, dialog: Dialog) -> gml::Result<Value> {
+        match self.play_type {
+            PlayType::Normal => self.run_dialog(&dialog),
+            PlayType::Record => {
+                let value = self.run_dialog(&dialog)?;
+                self.stored_events.push_back(dialog.to_event(value.clone()));
+                Ok(value)
+            },
+            PlayType::Replay => match self.stored_events.pop_front().and_then(|ev| dialog.from_event(ev)) {
+                Some(value) => Ok(value),
+                None => Err(gml::Error::ReplayError(function.into())),
+            },
+        }
+    }
+
+    /// Displays the dialog and blocks until the user dismisses it, then converts the response to a GML value.
+    fn run_dialog(&mut self, dialog: &Dialog) -> gml::Result<Value> {
+        let default_input = match dialog {
+            Dialog::Integer(_, def) | Dialog::String(_, def) => match def {
+                Value::Real(r) => r.to_string().into_bytes(),
+                Value::Str(s) => s.as_ref().to_vec(),
+            },
+            _ => Vec::new(),
+        };
+        let mut state = DialogState { input: default_input, hover: None, pressed: None, shift: false };
+        let response = self.dialog_loop(dialog, &mut state)?;
+
+        Ok(match (dialog, response) {
+            (Dialog::Message(_), _) => Default::default(),
+            (Dialog::Question(_), Response::Button(0)) => gml::TRUE.into(),
+            (Dialog::Question(_), _) => gml::FALSE.into(),
+            (Dialog::MessageExt(..), Response::Button(i)) => (i as i32 + 1).into(),
+            (Dialog::MessageExt(..), Response::Closed) => 0.into(),
+            (Dialog::Integer(_, def), Response::Button(0)) => {
+                match String::from_utf8_lossy(&state.input).trim().parse::<f64>() {
+                    Ok(n) => n.into(),
+                    Err(_) => def.clone(),
+                }
+            },
+            (Dialog::String(..), Response::Button(0)) => state.input.into(),
+            (Dialog::Integer(_, def), _) | (Dialog::String(_, def), _) => def.clone(),
+            (Dialog::Menu(..), Response::Button(i)) => i.into(),
+            (Dialog::Menu(_, _, def), Response::Closed) => def.clone(),
+            (Dialog::Error(..), Response::Button(0)) => gml::FALSE.into(),
+            (Dialog::Error(..), _) => gml::TRUE.into(),
+        })
+    }
+
+    /// Converts a window position into a framebuffer position, undoing the current scaling.
+    fn window_to_framebuffer(&self, x: i32, y: i32) -> (i32, i32) {
+        let (win_w, win_h) = self.window.get_inner_size();
+        let (win_w, win_h) = (win_w as i32, win_h as i32);
+        let (fb_w, fb_h) = self.window_region_size();
+        let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+        let (off_x, off_y, w, h) = match self.scaling {
+            Scaling::Fixed(scale) => {
+                let w = (f64::from(fb_w) * scale) as i32;
+                let h = (f64::from(fb_h) * scale) as i32;
+                ((win_w - w) / 2, (win_h - h) / 2, w, h)
+            },
+            Scaling::Aspect(_) if fb_w > 0 && fb_h > 0 => {
+                let fixed_width = win_h * fb_w / fb_h;
+                if fixed_width < win_w {
+                    ((win_w - fixed_width) / 2, 0, fixed_width, win_h)
+                } else {
+                    let fixed_height = win_w * fb_h / fb_w;
+                    (0, (win_h - fixed_height) / 2, win_w, fixed_height)
+                }
+            },
+            _ => (0, 0, win_w, win_h),
+        };
+        ((x - off_x) * fb_w / w.max(1), (y - off_y) * fb_h / h.max(1))
+    }
+
+    fn dialog_loop(&mut self, dialog: &Dialog, state: &mut DialogState) -> gml::Result<Response> {
+        use gmio::window::Event as WindowEvent;
+
+        let (fb_w, fb_h) = self.window_region_size();
+        let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+
+        // Take a copy of the current frame to draw the dialog over
+        if self.surface_target.is_some() {
+            self.renderer.reset_target();
+        }
+        self.renderer.flush_queue();
+        let backdrop = self
+            .renderer
+            .upload_sprite(self.renderer.get_pixels(0, 0, fb_w, fb_h), fb_w, fb_h, 0, 0)
+            .map_err(|e| gml::Error::FunctionError("show_dialog".into(), e))?;
+
+        // Store draw settings we're going to overwrite
+        let old_font = self.draw_font_id;
+        let old_colour = self.draw_colour;
+        let old_halign = self.draw_halign;
+        let old_valign = self.draw_valign;
+        let old_3d = self.renderer.get_3d();
+        self.draw_font_id = -1;
+        self.renderer.set_3d(false);
+        self.renderer.set_view(0, 0, fb_w, fb_h, 0.0, 0, 0, fb_w, fb_h);
+
+        let (mouse_x, mouse_y) = self.input_manager.mouse_get_location();
+        let mut mouse = self.window_to_framebuffer(mouse_x as i32, mouse_y as i32);
+        let menu_origin = mouse;
+
+        let response = loop {
+            let events = self.window.process_events().copied().collect::<Vec<_>>();
+            if self.window.close_requested() {
+                break Response::Closed
+            }
+
+            let layout = match dialog {
+                Dialog::Menu(..) => None,
+                _ => Some(self.message_layout(dialog)),
+            };
+            let button_at = |x: i32, y: i32| -> Option<usize> {
+                match (&layout, dialog) {
+                    (Some(layout), _) => layout
+                        .buttons
+                        .iter()
+                        .find(|(_, _, bx, by)| x >= *bx && x < bx + BUTTON_WIDTH && y >= *by && y < by + BUTTON_HEIGHT)
+                        .map(|(_, i, _, _)| *i),
+                    (None, Dialog::Menu(options, pos, _)) => {
+                        let (mx, my, mw, _) = self.menu_rect(options, pos.unwrap_or(menu_origin));
+                        if x >= mx && x < mx + mw && y >= my + MENU_PADDING {
+                            let index = ((y - my - MENU_PADDING) / MENU_ITEM_HEIGHT) as usize;
+                            if index < options.len() { Some(index) } else { None }
+                        } else {
+                            None
+                        }
+                    },
+                    _ => None,
+                }
+            };
+
+            let mut result = None;
+            for event in events {
+                match event {
+                    WindowEvent::MouseMove(x, y) => {
+                        mouse = self.window_to_framebuffer(x, y);
+                    },
+                    WindowEvent::MouseButtonDown(MouseButton::Left) => {
+                        state.pressed = button_at(mouse.0, mouse.1);
+                        if state.pressed.is_none() {
+                            if let Dialog::Menu(..) = dialog {
+                                // Clicking outside a popup menu dismisses it
+                                result = Some(Response::Closed);
+                            }
+                        }
+                    },
+                    WindowEvent::MouseButtonUp(MouseButton::Left) => {
+                        if let Some(i) = state.pressed.take() {
+                            if button_at(mouse.0, mouse.1) == Some(i) {
+                                result = Some(Response::Button(i));
+                            }
+                        }
+                    },
+                    WindowEvent::KeyboardDown(key) => match key {
+                        Key::Shift | Key::LeftShift | Key::RightShift => state.shift = true,
+                        Key::Escape => result = Some(Response::Closed),
+                        Key::Enter => {
+                            result = Some(match (dialog, state.hover) {
+                                (Dialog::Menu(..), Some(i)) => Response::Button(i),
+                                (Dialog::Menu(..), None) => Response::Closed,
+                                _ => Response::Button(
+                                    layout.as_ref().and_then(|l| l.buttons.first()).map(|b| b.1).unwrap_or(0),
+                                ),
+                            })
+                        },
+                        Key::Backspace => {
+                            state.input.pop();
+                        },
+                        Key::Up | Key::Down => {
+                            if let Dialog::Menu(options, ..) = dialog {
+                                let count = options.len();
+                                if count != 0 {
+                                    state.hover = Some(match (key, state.hover) {
+                                        (Key::Up, Some(i)) => (i + count - 1) % count,
+                                        (Key::Up, None) => count - 1,
+                                        (_, Some(i)) => (i + 1) % count,
+                                        (_, None) => 0,
+                                    });
+                                }
+                            }
+                        },
+                        key => match dialog {
+                            Dialog::Integer(..) | Dialog::String(..) => {
+                                if let Some(c) = key_to_char(key, state.shift) {
+                                    state.input.push(c);
+                                }
+                            },
+                            _ => (),
+                        },
+                    },
+                    WindowEvent::KeyboardUp(Key::Shift)
+                    | WindowEvent::KeyboardUp(Key::LeftShift)
+                    | WindowEvent::KeyboardUp(Key::RightShift) => state.shift = false,
+                    _ => (),
+                }
+            }
+            if let Some(result) = result {
+                break result
+            }
+            if let Some(i) = button_at(mouse.0, mouse.1) {
+                state.hover = Some(i);
+            } else if layout.is_some() {
+                state.hover = None;
+            }
+
+            // Draw everything
+            self.renderer.draw_sprite(&backdrop, 0.0, 0.0, 1.0, 1.0, 0.0, 0xFFFFFF, 1.0);
+            match (dialog, &layout) {
+                (Dialog::Menu(options, pos, _), _) => self.draw_menu(options, pos.unwrap_or(menu_origin), state),
+                (_, Some(layout)) => self.draw_message_box(dialog, layout, state),
+                _ => (),
+            }
+            let (width, height) = self.window.get_inner_size();
+            self.renderer.present(width, height, self.scaling);
+            gml::datetime::sleep(FRAME_TIME);
+        };
+
+        // Put the frame back the way we found it
+        self.renderer.draw_sprite(&backdrop, 0.0, 0.0, 1.0, 1.0, 0.0, 0xFFFFFF, 1.0);
+        self.renderer.flush_queue();
+        self.renderer.delete_sprite(backdrop);
+        self.renderer.set_3d(old_3d);
+        self.draw_font_id = old_font;
+        self.draw_colour = old_colour;
+        self.draw_halign = old_halign;
+        self.draw_valign = old_valign;
+        if let Some(surf) = self.surface_target.and_then(|id| self.surfaces.get_asset(id)) {
+            self.renderer.set_target(&surf.atlas_ref);
+        }
+
+        Ok(response)
+    }
+
+    /// Works out where everything in a message box goes, based on the message settings.
+    fn message_layout(&mut self, dialog: &Dialog) -> MessageLayout {
+        let settings = self.message_settings.clone();
+        let (fb_w, fb_h) = self.window_region_size();
+        let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+
+        let (text, labels, has_input): (&RCStr, Vec<(RCStr, usize)>, bool) = match dialog {
+            Dialog::Message(text) => (text, vec![("OK".into(), 0)], false),
+            Dialog::Question(text) => (text, vec![("Yes".into(), 0), ("No".into(), 1)], false),
+            Dialog::MessageExt(text, buttons) => (
+                text,
+                buttons
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| !b.as_ref().is_empty())
+                    .map(|(i, b)| (strip_accelerators(b.as_ref()), i))
+                    .collect(),
+                false,
+            ),
+            Dialog::Integer(text, _) | Dialog::String(text, _) => {
+                (text, vec![("OK".into(), 0), ("Cancel".into(), 1)], true)
+            },
+            Dialog::Error(text, true) => (text, vec![("Abort".into(), 1)], false),
+            Dialog::Error(text, false) => (text, vec![("Ignore".into(), 0), ("Abort".into(), 1)], false),
+            Dialog::Menu(..) => unreachable!("menus don't use message box layout"),
+        };
+
+        let max_text_width = (fb_w - BOX_MARGIN * 4).max(BOX_MIN_WIDTH - BOX_MARGIN * 2);
+        let (text_font, text_scale) = self.message_font(&settings.text_font);
+        let old_font = self.draw_font_id;
+        self.draw_font_id = text_font;
+        let (text_w, text_h) =
+            self.get_string_size(text.clone(), None, Some((f64::from(max_text_width) / text_scale) as i32));
+        self.draw_font_id = old_font;
+        let (text_w, text_h) = ((f64::from(text_w) * text_scale) as i32, (f64::from(text_h) * text_scale) as i32);
+        let buttons_w = labels.len() as i32 * (BUTTON_WIDTH + BUTTON_SPACING) - BUTTON_SPACING;
+        let caption_h = if settings.show_caption { CAPTION_HEIGHT } else { 0 };
+        let input_h = if has_input { INPUT_HEIGHT + BOX_MARGIN } else { 0 };
+
+        let width = match settings.size.0 {
+            w if w > 0 => w,
+            _ => (text_w.max(buttons_w) + BOX_MARGIN * 2).max(BOX_MIN_WIDTH),
+        };
+        let height = match settings.size.1 {
+            h if h > 0 => h,
+            _ => caption_h + BOX_MARGIN * 3 + text_h + input_h + BUTTON_HEIGHT,
+        };
+        let x = if settings.position.0 < 0 { (fb_w - width) / 2 } else { settings.position.0 };
+        let y = if settings.position.1 < 0 { (fb_h - height) / 2 } else { settings.position.1 };
+
+        let buttons_y = y + height - BOX_MARGIN - BUTTON_HEIGHT;
+        let mut button_x = x + (width - buttons_w) / 2;
+        let buttons = labels
+            .into_iter()
+            .map(|(label, i)| {
+                let b = (label, i, button_x, buttons_y);
+                button_x += BUTTON_WIDTH + BUTTON_SPACING;
+                b
+            })
+            .collect();
+
+        MessageLayout {
+            x,
+            y,
+            width,
+            height,
+            text_y: y + caption_h + BOX_MARGIN,
+            input_y: if has_input { Some(buttons_y - BOX_MARGIN - INPUT_HEIGHT) } else { None },
+            buttons,
+        }
+    }
+
+    /// Draws a message box's frame, text, input field and buttons.
+    fn draw_message_box(&mut self, dialog: &Dialog, layout: &MessageLayout, state: &DialogState) {
+        let settings = self.message_settings.clone();
+        let alpha: f64 = settings.alpha.into();
+        let (x, y, w, h) = (layout.x, layout.y, layout.width, layout.height);
+        let (x1, y1, x2, y2) = (f64::from(x), f64::from(y), f64::from(x + w - 1), f64::from(y + h - 1));
+
+        // Box background
+        match self.assets.backgrounds.get_asset(settings.background).and_then(|bg| {
+            bg.atlas_ref.map(|atlas_ref| (atlas_ref, f64::from(bg.width), f64::from(bg.height)))
+        }) {
+            Some((atlas_ref, bg_w, bg_h)) => self.renderer.draw_sprite(
+                &atlas_ref,
+                x1,
+                y1,
+                f64::from(w) / bg_w,
+                f64::from(h) / bg_h,
+                0.0,
+                0xFFFFFF,
+                alpha,
+            ),
+            None => {
+                self.renderer.draw_rectangle(x1, y1, x2, y2, COLOUR_FACE, alpha);
+                self.renderer.draw_rectangle_outline(x1, y1, x2, y2, COLOUR_SHADOW, alpha);
+                self.renderer.draw_line(x1, y1, x2, y1, None, COLOUR_LIGHT, COLOUR_LIGHT, alpha);
+                self.renderer.draw_line(x1, y1, x1, y2, None, COLOUR_LIGHT, COLOUR_LIGHT, alpha);
+            },
+        }
+
+        // Caption bar
+        if settings.show_caption {
+            let caption = if settings.caption.as_ref().is_empty() { self.caption.clone() } else { settings.caption };
+            self.renderer.draw_rectangle(
+                x1 + 2.0,
+                y1 + 2.0,
+                x2 - 2.0,
+                y1 + f64::from(CAPTION_HEIGHT) - 1.0,
+                COLOUR_CAPTION,
+                alpha,
+            );
+            self.draw_font_id = -1;
+            self.draw_colour = Colour::from(0xFFFFFF_u32);
+            self.draw_halign = Halign::Left;
+            self.draw_valign = Valign::Middle;
+            self.draw_string(
+                Real::from(x + 6),
+                Real::from(y + 2 + CAPTION_HEIGHT / 2),
+                caption,
+                None,
+                Some(w - 12),
+                Real::from(1.0),
+                Real::from(1.0),
+                Real::from(0.0),
+                None,
+                Real::from(alpha),
+            );
+        }
+
+        // Message text
+        let text = match dialog {
+            Dialog::Message(text)
+            | Dialog::Question(text)
+            | Dialog::MessageExt(text, _)
+            | Dialog::Integer(text, _)
+            | Dialog::String(text, _)
+            | Dialog::Error(text, _) => text.clone(),
+            Dialog::Menu(..) => return,
+        };
+        let (font, scale) = self.message_font(&settings.text_font);
+        self.draw_font_id = font;
+        self.draw_colour = Colour::from(settings.text_font.colour as u32);
+        self.draw_halign = Halign::Middle;
+        self.draw_valign = Valign::Top;
+        self.draw_string(
+            Real::from(x + w / 2),
+            Real::from(layout.text_y),
+            text,
+            None,
+            Some((f64::from(w - BOX_MARGIN * 2) / scale) as i32),
+            Real::from(scale),
+            Real::from(scale),
+            Real::from(0.0),
+            None,
+            Real::from(alpha),
+        );
+
+        // Input field
+        if let Some(input_y) = layout.input_y {
+            let (ix1, iy1) = (f64::from(x + BOX_MARGIN), f64::from(input_y));
+            let (ix2, iy2) = (f64::from(x + w - BOX_MARGIN - 1), f64::from(input_y + INPUT_HEIGHT - 1));
+            self.renderer.draw_rectangle(ix1, iy1, ix2, iy2, settings.input_colour, alpha);
+            self.renderer.draw_rectangle_outline(ix1, iy1, ix2, iy2, COLOUR_SHADOW, alpha);
+            let mut input = state.input.clone();
+            input.push(b'|');
+            let (font, scale) = self.message_font(&settings.input_font);
+            self.draw_font_id = font;
+            self.draw_colour = Colour::from(settings.input_font.colour as u32);
+            self.draw_halign = Halign::Left;
+            self.draw_valign = Valign::Middle;
+            self.draw_string(
+                Real::from(x + BOX_MARGIN + 4),
+                Real::from(input_y + INPUT_HEIGHT / 2),
+                input.into(),
+                None,
+                None,
+                Real::from(scale),
+                Real::from(scale),
+                Real::from(0.0),
+                None,
+                Real::from(alpha),
+            );
+        }
+
+        // Buttons
+        let (button_font, button_scale) = self.message_font(&settings.button_font);
+        for (label, i, bx, by) in layout.buttons.iter() {
+            let (bx1, by1) = (f64::from(*bx), f64::from(*by));
+            let (bx2, by2) = (f64::from(bx + BUTTON_WIDTH - 1), f64::from(by + BUTTON_HEIGHT - 1));
+            let hovered = state.hover == Some(*i);
+            let pressed = hovered && state.pressed == Some(*i);
+            let sprite = self.assets.sprites.get_asset(settings.button_sprite).and_then(|sprite| {
+                let frame = if hovered && sprite.frames.len() > 1 { 1 } else { 0 };
+                sprite.frames.get(frame).map(|f| (f.atlas_ref, f64::from(f.width), f64::from(f.height)))
+            });
+            match sprite {
+                Some((atlas_ref, sw, sh)) => self.renderer.draw_sprite(
+                    &atlas_ref,
+                    bx1,
+                    by1,
+                    f64::from(BUTTON_WIDTH) / sw,
+                    f64::from(BUTTON_HEIGHT) / sh,
+                    0.0,
+                    0xFFFFFF,
+                    alpha,
+                ),
+                None => {
+                    let (light, shadow) = if pressed { (COLOUR_SHADOW, COLOUR_LIGHT) } else { (COLOUR_LIGHT, COLOUR_SHADOW) };
+                    self.renderer.draw_rectangle(bx1, by1, bx2, by2, COLOUR_FACE, alpha);
+                    self.renderer.draw_rectangle_outline(bx1, by1, bx2, by2, shadow, alpha);
+                    self.renderer.draw_line(bx1, by1, bx2, by1, None, light, light, alpha);
+                    self.renderer.draw_line(bx1, by1, bx1, by2, None, light, light, alpha);
+                },
+            }
+            let colour = if hovered { settings.mouse_colour } else { settings.button_font.colour };
+            self.draw_font_id = button_font;
+            self.draw_colour = Colour::from(colour as u32);
+            self.draw_halign = Halign::Middle;
+            self.draw_valign = Valign::Middle;
+            self.draw_string(
+                Real::from(bx + BUTTON_WIDTH / 2),
+                Real::from(by + BUTTON_HEIGHT / 2),
+                label.clone(),
+                None,
+                None,
+                Real::from(button_scale),
+                Real::from(button_scale),
+                Real::from(0.0),
+                None,
+                Real::from(alpha),
+            );
+        }
+    }
+
+    /// Picks a font and scale to draw message box text with, as set by message_text_font and friends.
+    /// Only fonts built into the game can be drawn, so the game font with the requested typeface and style is used
+    /// if there is one, or the default font otherwise. Either way it's scaled to the requested size.
+    fn message_font(&self, font: &MessageFont) -> (ID, f64) {
+        let (bold, italic) = (font.style & 1 != 0, font.style & 2 != 0);
+        let found = self
+            .assets
+            .fonts
+            .iter()
+            .enumerate()
+            .filter_map(|(id, f)| f.as_ref().map(|f| (id as ID, f)))
+            .filter(|(_, f)| {
+                f.sys_name.as_ref().eq_ignore_ascii_case(font.name.as_ref()) && f.bold == bold && f.italic == italic
+            })
+            .min_by_key(|(_, f)| (f.size as i32 - font.size).abs());
+        let (id, size) = match found {
+            Some((id, f)) => (id, f.size),
+            None => (-1, self.default_font.size),
+        };
+        let scale = if font.size > 0 && size > 0 { f64::from(font.size) / f64::from(size) } else { 1.0 };
+        (id, scale)
+    }
+
+    /// Gets the rectangle of a popup menu opened at the given position, moved to fit inside the window.
+    fn menu_rect(&self, options: &[RCStr], (x, y): (i32, i32)) -> (i32, i32, i32, i32) {
+        let (fb_w, fb_h) = self.window_region_size();
+        let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+        let width = options.iter().map(|o| self.get_string_size(o.clone(), None, None).0).max().unwrap_or(0)
+            + MENU_PADDING * 4;
+        let height = options.len() as i32 * MENU_ITEM_HEIGHT + MENU_PADDING * 2;
+        let x = if x + width > fb_w { (fb_w - width).max(0) } else { x };
+        let y = if y + height > fb_h { (fb_h - height).max(0) } else { y };
+        (x, y, width, height)
+    }
+
+    /// Draws a popup menu, highlighting the hovered option.
+    fn draw_menu(&mut self, options: &[RCStr], pos: (i32, i32), state: &DialogState) {
+        let (x, y, w, h) = self.menu_rect(options, pos);
+        let (x1, y1, x2, y2) = (f64::from(x), f64::from(y), f64::from(x + w - 1), f64::from(y + h - 1));
+        self.renderer.draw_rectangle(x1, y1, x2, y2, COLOUR_FACE, 1.0);
+        self.renderer.draw_rectangle_outline(x1, y1, x2, y2, COLOUR_SHADOW, 1.0);
+        self.draw_halign = Halign::Left;
+        self.draw_valign = Valign::Middle;
+        for (i, option) in options.iter().enumerate() {
+            let item_y = y + MENU_PADDING + i as i32 * MENU_ITEM_HEIGHT;
+            let colour = if state.hover == Some(i) {
+                self.renderer.draw_rectangle(
+                    x1 + 2.0,
+                    f64::from(item_y),
+                    x2 - 2.0,
+                    f64::from(item_y + MENU_ITEM_HEIGHT - 1),
+                    COLOUR_HIGHLIGHT,
+                    1.0,
+                );
+                COLOUR_LIGHT
+            } else {
+                self.message_settings.text_font.colour
+            };
+            self.draw_colour = Colour::from(colour as u32);
+            self.draw_string(
+                Real::from(x + MENU_PADDING * 2),
+                Real::from(item_y + MENU_ITEM_HEIGHT / 2),
+                option.clone(),
+                None,
+                None,
+                Real::from(1.0),
+                Real::from(1.0),
+                Real::from(0.0),
+                None,
+                Real::from(1.0),
+            );
+        }
+    }
+}
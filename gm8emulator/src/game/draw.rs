@@ -203,22 +203,35 @@ impl Game {
             self.draw_view(0, 0, self.room_width, self.room_height, 0, 0, self.room_width, self.room_height, 0.0)?;
         }
 
+        // Draw a splash screen which isn't interrupting the game over everything else
+        if let Some(splash) = self.splash.clone() {
+            let (fb_w, fb_h) = self.window_region_size();
+            let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+            let using_3d = self.renderer.get_3d();
+            self.renderer.set_3d(false);
+            self.renderer.set_view(0, 0, fb_w, fb_h, 0.0, 0, 0, fb_w, fb_h);
+            self.draw_splash(&splash);
+            self.renderer.set_3d(using_3d);
+        }
+
+        // Draw the console over the top of that
+        if self.console.open {
+            let (fb_w, fb_h) = self.window_region_size();
+            let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+            let using_3d = self.renderer.get_3d();
+            self.renderer.set_3d(false);
+            self.renderer.set_view(0, 0, fb_w, fb_h, 0.0, 0, 0, fb_w, fb_h);
+            self.draw_console();
+            self.renderer.set_3d(using_3d);
+        }
+
         // Tell renderer to finish the frame
         let (width, height) = self.window.get_inner_size();
         self.renderer.present(width, height, self.scaling);
 
         // Reset viewport
-        self.renderer.set_view(
-            0,
-            0,
-            self.unscaled_width as _,
-            self.unscaled_height as _,
-            0.0,
-            0,
-            0,
-            self.unscaled_width as _,
-            self.unscaled_height as _,
-        );
+        let (fb_w, fb_h) = self.window_region_size();
+        self.renderer.set_view(0, 0, fb_w as _, fb_h as _, 0.0, 0, 0, fb_w as _, fb_h as _);
 
         // Apply room caption
         if self.score_capt_d || self.lives_capt_d {
@@ -251,6 +264,15 @@ impl Game {
         port_h: i32,
         angle: f64,
     ) -> gml::Result<()> {
+        // If the game has changed the size of the drawing region, the room or views are stretched to fill it
+        let (region_w, region_h) = self.window_region_size();
+        let scale = |n: i32, region: u32, unscaled: u32| match unscaled {
+            0 => n,
+            unscaled => (i64::from(n) * i64::from(region) / i64::from(unscaled)) as i32,
+        };
+        let (unscaled_w, unscaled_h) = (self.unscaled_width, self.unscaled_height);
+        let (port_x, port_w) = (scale(port_x, region_w, unscaled_w), scale(port_w, region_w, unscaled_w));
+        let (port_y, port_h) = (scale(port_y, region_h, unscaled_h), scale(port_h, region_h, unscaled_h));
         self.renderer.set_view(src_x, src_y, src_w, src_h, angle, port_x, port_y, port_w, port_h);
 
         if self.show_room_colour {
@@ -289,9 +311,11 @@ impl Game {
             }
         }
 
-        fn draw_tile(game: &mut Game, idx: usize) {
+        fn draw_tile(game: &mut Game, idx: usize, view: (f64, f64, f64, f64)) {
             let tile = game.tile_list.get(idx);
-            if tile.visible.get() {
+            let (left, top, right, bottom) = tile.bbox();
+            let on_screen = left < view.2 && right > view.0 && top < view.3 && bottom > view.1;
+            if tile.visible.get() && on_screen {
                 if let Some(Some(background)) = game.assets.backgrounds.get(tile.background_index.get() as usize) {
                     if let Some(atlas) = &background.atlas_ref {
                         game.renderer.set_depth(tile.depth.get().into_inner() as f32);
@@ -338,6 +362,17 @@ impl Game {
             }
         }
 
+        // area of the room which can be seen through this view, used for culling tiles
+        let view_rect = {
+            let (x, y, w, h) = (f64::from(src_x), f64::from(src_y), f64::from(src_w), f64::from(src_h));
+            if angle == 0.0 {
+                (x, y, x + w, y + h)
+            } else {
+                let (cx, cy, radius) = (x + w / 2.0, y + h / 2.0, w.hypot(h) / 2.0);
+                (cx - radius, cy - radius, cx + radius, cy + radius)
+            }
+        };
+
         self.instance_list.draw_sort();
         let mut iter_inst = self.instance_list.iter_by_drawing();
         let mut iter_inst_v = iter_inst.next(&self.instance_list);
@@ -358,9 +393,9 @@ impl Game {
                     break
                 },
                 (None, Some(idx_tile), None) => {
-                    draw_tile(self, idx_tile);
+                    draw_tile(self, idx_tile, view_rect);
                     while let Some(idx_tile) = iter_tile.next(&self.tile_list) {
-                        draw_tile(self, idx_tile);
+                        draw_tile(self, idx_tile, view_rect);
                     }
                     break
                 },
@@ -377,7 +412,7 @@ impl Game {
                     let part_depth = idx_opt_part.map(|h| self.particles.get_system(h).unwrap().depth);
                     if part_depth < inst_depth && part_depth < tile_depth {
                         if inst_depth < tile_depth {
-                            draw_tile(self, idx_opt_tile.unwrap());
+                            draw_tile(self, idx_opt_tile.unwrap(), view_rect);
                             iter_tile_v = iter_tile.next(&self.tile_list);
                         } else {
                             draw_instance(self, idx_opt_inst.unwrap())?;
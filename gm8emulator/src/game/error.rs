@@ -0,0 +1,72 @@
+use crate::{
+    game::{dialog::Dialog, replay::Event, string::RCStr, Game, PlayType},
+    gml,
+};
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+/// What to do with a GML error which the player would normally be asked about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Show GM8's error message box and let the player choose.
+    Ask,
+    /// Carry on as if the player pressed Ignore.
+    Ignore,
+    /// End the game as if the player pressed Abort.
+    Abort,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        Self::Ask
+    }
+}
+
+impl Game {
+    /// Deals with an error which happened in a piece of GML code, the way GM8 does.
+    /// Returns Ok if the game should carry on after the code, or the error to end the game with otherwise.
+    pub fn handle_error(&mut self, error: gml::Error) -> gml::Result<()> {
+        if error.is_fatal() {
+            return Err(error)
+        }
+        if self.report_error(&error.to_string(), false)? { Err(gml::Error::Aborted(Box::new(error))) } else { Ok(()) }
+    }
+
+    /// Sets error_occurred and error_last, logs the error if the game wants that, and works out whether to abort.
+    /// If the player gets to decide, their choice is stored in the replay while recording and read back from it
+    /// while replaying, so it doesn't matter which policy either of them was using.
+    pub fn report_error(&mut self, message: &str, abort: bool) -> gml::Result<bool> {
+        let text: RCStr =
+            self.encode_str_maybe(message).unwrap_or_else(|| self.encoding.encode(message).0).as_ref().into();
+        self.error_occurred = true;
+        self.error_last = text.clone();
+        if self.log_errors {
+            self.log_error(message);
+        }
+
+        let abort = abort || self.always_abort;
+        if !self.show_error_messages {
+            return Ok(abort)
+        }
+        match (self.play_type.clone(), self.error_policy) {
+            (PlayType::Replay, _) | (_, ErrorPolicy::Ask) => {
+                Ok(self.show_dialog("show_error", Dialog::Error(text, abort))?.is_truthy())
+            },
+            (play_type, policy) => {
+                let abort = abort || policy == ErrorPolicy::Abort;
+                if play_type == PlayType::Record {
+                    self.stored_events.push_back(Event::ShowError(abort.into()));
+                }
+                Ok(abort)
+            },
+        }
+    }
+
+    /// Appends an error message to game_errors.log in the game's directory. Failing to write it isn't an error.
+    fn log_error(&self, message: &str) {
+        let mut path = PathBuf::from(self.decode_str(self.program_directory.as_ref()).into_owned());
+        path.push("game_errors.log");
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = writeln!(file, "{}\r\n", message.replace('\n', "\r\n"));
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use crate::{
     asset::trigger::TriggerTime,
     game::{Game, GetAsset},
-    gml,
+    gml::{self, runtime::CodeSource},
+    input::JOYSTICK_THRESHOLD,
     instance::Instance,
 };
-use shared::{input::MouseButton, types::ID};
+use shared::{
+    input::{JoystickAxis, Key, MouseButton},
+    message::CodeLocation,
+    types::ID,
+};
 
 impl Game {
     /// Runs an event for all objects which hold the given event.
@@ -117,7 +122,12 @@ impl Game {
                                 locals: Default::default(),
                                 return_value: Default::default(),
                             };
-                            self.execute(&trigger.condition, &mut context)?;
+                            let location = CodeLocation::Trigger { trigger: trigger_id as _ };
+                            if let Err(e) = self.execute_at(location, &trigger.condition, &mut context) {
+                                let name = self.decode_str(trigger.name.as_ref()).into_owned();
+                                self.handle_error(e.in_code(CodeSource::Trigger(name)))?;
+                                continue
+                            }
                             if context.return_value.is_truthy() {
                                 self.run_instance_event(gml::ev::TRIGGER, trigger_id, handle, handle, None)?;
                             }
@@ -346,6 +356,24 @@ impl Game {
             }
         }
 
+        // Joystick events, 16-28 for joystick 1 and 31-43 for joystick 2
+        for (id, base) in [(0, 16), (1, 31)].iter().copied() {
+            if self.input_manager.joystick_info(id).is_none() {
+                continue
+            }
+            let x = self.input_manager.joystick_axis(id, JoystickAxis::X);
+            let y = self.input_manager.joystick_axis(id, JoystickAxis::Y);
+            let directions = [x < -JOYSTICK_THRESHOLD, x > JOYSTICK_THRESHOLD, y < -JOYSTICK_THRESHOLD, y > JOYSTICK_THRESHOLD];
+            for (sub, _) in directions.iter().enumerate().filter(|(_, held)| **held) {
+                self.run_object_event(gml::ev::MOUSE, base + sub as u32, None)?;
+            }
+            for button in 0..8 {
+                if self.input_manager.joystick_check_button(id, button) {
+                    self.run_object_event(gml::ev::MOUSE, base + 5 + button as u32, None)?;
+                }
+            }
+        }
+
         // Global left button
         if self.input_manager.mouse_check(MouseButton::Left) {
             self.run_object_event(gml::ev::MOUSE, 50, None)?;
@@ -636,4 +664,106 @@ impl Game {
 
         Ok(())
     }
+
+    /// Describes an event the way GM8's error messages do, such as "Step Event" or "Alarm Event for alarm 0".
+    pub fn event_name(&self, event_type: usize, event_number: usize) -> String {
+        let trigger_name = |id: ID| match self.assets.triggers.get_asset(id) {
+            Some(trigger) => self.decode_str(trigger.name.as_ref()).into_owned(),
+            None => id.to_string(),
+        };
+        describe_event(event_type, event_number, |id| self.object_name(id), trigger_name)
+    }
+
+    /// Gets the name of an object to use in error messages.
+    pub fn object_name(&self, object_id: ID) -> String {
+        match self.assets.objects.get_asset(object_id) {
+            Some(object) => self.decode_str(object.name.as_ref()).into_owned(),
+            None => format!("<undefined {}>", object_id),
+        }
+    }
+}
+
+/// Describes an event the way GM8's error messages do, such as "Step Event" or "Alarm Event for alarm 0".
+/// Collision and trigger events are described with the given functions for naming objects and triggers.
+pub fn describe_event(
+    event_type: usize,
+    event_number: usize,
+    object_name: impl Fn(ID) -> String,
+    trigger_name: impl Fn(ID) -> String,
+) -> String {
+    let key_name = |key: usize| match key {
+        0 => "<no key>".into(),
+        1 => "<any key>".into(),
+        _ => match Key::from_winapi(key as u8) {
+            Some(key) if format!("{:?}", key).len() == 1 => format!("{:?}", key),
+            Some(key) => format!("<{:?}>", key),
+            None => format!("<{}>", key),
+        },
+    };
+    match event_type {
+        gml::ev::CREATE => "Create Event".into(),
+        gml::ev::DESTROY => "Destroy Event".into(),
+        gml::ev::ALARMS => format!("Alarm Event for alarm {}", event_number),
+        gml::ev::STEP => match event_number {
+            1 => "Begin Step Event".into(),
+            2 => "End Step Event".into(),
+            _ => "Step Event".into(),
+        },
+        gml::ev::COLLISION => format!("Collision Event with object {}", object_name(event_number as _)),
+        gml::ev::KEYBOARD => format!("Keyboard Event for {} Key", key_name(event_number)),
+        gml::ev::MOUSE => {
+            let name = match event_number {
+                0 => "Left Button",
+                1 => "Right Button",
+                2 => "Middle Button",
+                3 => "No Button",
+                4 => "Left Pressed",
+                5 => "Right Pressed",
+                6 => "Middle Pressed",
+                7 => "Left Released",
+                8 => "Right Released",
+                9 => "Middle Released",
+                10 => "Mouse Enter",
+                11 => "Mouse Leave",
+                50 => "Glob Left Button",
+                51 => "Glob Right Button",
+                52 => "Glob Middle Button",
+                53 => "Glob Left Pressed",
+                54 => "Glob Right Pressed",
+                55 => "Glob Middle Pressed",
+                56 => "Glob Left Released",
+                57 => "Glob Right Released",
+                58 => "Glob Middle Released",
+                60 => "Mouse Wheel Up",
+                61 => "Mouse Wheel Down",
+                _ => return format!("Mouse Event for Joystick Event {}", event_number),
+            };
+            format!("Mouse Event for {}", name)
+        },
+        gml::ev::OTHER => {
+            let name = match event_number {
+                0 => "Outside Room".into(),
+                1 => "Intersect Boundary".into(),
+                2 => "Game Start".into(),
+                3 => "Game End".into(),
+                4 => "Room Start".into(),
+                5 => "Room End".into(),
+                6 => "No More Lives".into(),
+                7 => "Animation End".into(),
+                8 => "End of Path".into(),
+                9 => "No More Health".into(),
+                10..=25 => format!("User Defined {}", event_number - 10),
+                30 => "Close Button".into(),
+                40..=47 => format!("Outside View {}", event_number - 40),
+                50..=57 => format!("Boundary View {}", event_number - 50),
+                _ => event_number.to_string(),
+            };
+            format!("Other Event: {}", name)
+        },
+        gml::ev::DRAW => "Draw Event".into(),
+        gml::ev::KEYPRESS => format!("Key Press Event for {} Key", key_name(event_number)),
+        gml::ev::KEYRELEASE => format!("Key Release Event for {} Key", key_name(event_number)),
+        gml::ev::TRIGGER => format!("Trigger Event: {}", trigger_name(event_number as _)),
+        _ => format!("Event {} {}", event_type, event_number),
+    }
 }
@@ -1,11 +1,11 @@
 use crate::{
-    game::{string::RCStr, Background, Game, View},
+    game::{string::RCStr, Background, Game, GetAsset, RoomState, View},
     instance::DummyFieldHolder,
     instancelist::{InstanceList, TileList},
 };
 use serde::{Deserialize, Serialize};
 use shared::types::Colour;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// A save file for use with game_save() and game_load().
 /// The manual explicitly recommends against using save files between sessions, so this may be acceptable.
@@ -22,7 +22,7 @@ pub struct GMSave {
     auto_draw: bool,
     globalvars: HashSet<usize>,
     globals: DummyFieldHolder,
-    // TODO persistent stuff
+    persistent_rooms: HashMap<i32, RoomState>,
     room: GMRoomSave,
     last_instance_id: i32,
     last_tile_id: i32,
@@ -60,12 +60,13 @@ impl GMSave {
             auto_draw: game.auto_draw,
             globalvars: game.globalvars.clone(),
             globals: game.globals.clone(),
+            persistent_rooms: game.persistent_rooms.clone(),
             room: GMRoomSave {
                 caption: game.caption.clone(),
                 width: game.room_width,
                 height: game.room_height,
                 room_speed: game.room_speed,
-                persistent: false, // TODO
+                persistent: game.assets.rooms.get_asset(game.room_id).map(|r| r.persistent).unwrap_or(false),
                 bgcol: game.room_colour,
                 show_bgcol: game.show_room_colour,
                 show_windowcol: true, // TODO
@@ -95,6 +96,10 @@ impl GMSave {
         game.auto_draw = self.auto_draw;
         game.globalvars = self.globalvars;
         game.globals = self.globals;
+        game.persistent_rooms = self.persistent_rooms;
+        if let Some(room) = game.assets.rooms.get_asset_mut(game.room_id) {
+            room.persistent = self.room.persistent;
+        }
         game.caption = self.room.caption;
         game.room_width = self.room.width;
         game.room_height = self.room.height;
@@ -20,6 +20,35 @@ impl Default for PotentialStepSettings {
     }
 }
 
+/// Takes a step straight towards the given position, without trying to go around anything in the way.
+/// Returns whether the position was reached; the instance stays where it is if the step would collide.
+pub fn linear_step(x: Real, y: Real, step_size: Real, instance: &Instance, coll: impl Fn() -> bool) -> bool {
+    let old_x = instance.x.get();
+    let old_y = instance.y.get();
+    if old_x == x && old_y == y {
+        return true
+    }
+    let distance = (old_x - x).into_inner().hypot((old_y - y).into());
+    let (new_x, new_y, reached) = if distance <= step_size.into_inner() {
+        (x, y, true)
+    } else {
+        let ratio = step_size / distance.into();
+        (old_x + (x - old_x) * ratio, old_y + (y - old_y) * ratio, false)
+    };
+    instance.x.set(new_x);
+    instance.y.set(new_y);
+    instance.bbox_is_stale.set(true);
+    if coll() {
+        instance.x.set(old_x);
+        instance.y.set(old_y);
+        instance.bbox_is_stale.set(true);
+        false
+    } else {
+        instance.direction.set((old_y - y).arctan2(x - old_x).to_degrees().rem_euclid(360.into()));
+        reached
+    }
+}
+
 pub fn potential_step(
     x: Real,
     y: Real,
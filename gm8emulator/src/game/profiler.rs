@@ -0,0 +1,141 @@
+use crate::{
+    game::{Game, GetAsset},
+    gml::{compiler::mappings, Function},
+};
+use shared::{message::ProfileEntry, types::ID};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Something the profiler keeps track of the time spent in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Timed {
+    /// An object event. The object is the one the event belongs to, which may be a parent of the running instance.
+    Event { object: ID, event_type: usize, event_number: usize },
+
+    /// A script, by index
+    Script(usize),
+
+    /// A builtin function
+    Function(Function),
+}
+
+/// Opt-in profiler, which times object events, scripts and builtin functions as the game runs them.
+#[derive(Default)]
+pub struct Profiler {
+    /// Everything being timed right now, innermost last, with when it started and how long its callees took
+    stack: Vec<(Timed, Instant, Duration)>,
+
+    /// The number of calls and total time spent in each thing timed
+    totals: HashMap<Timed, Totals>,
+
+    /// The exclusive time spent in each call stack which has been seen, outermost first
+    stacks: HashMap<Vec<Timed>, Duration>,
+}
+
+#[derive(Default)]
+struct Totals {
+    calls: u64,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+impl Profiler {
+    fn enter(&mut self, timed: Timed) {
+        self.stack.push((timed, Instant::now(), Duration::default()));
+    }
+
+    fn exit(&mut self) {
+        if let Some((timed, start, callees)) = self.stack.pop() {
+            let elapsed = start.elapsed();
+            let exclusive = elapsed.checked_sub(callees).unwrap_or_default();
+            if let Some((_, _, parent_callees)) = self.stack.last_mut() {
+                *parent_callees += elapsed;
+            }
+
+            // Recursive calls are already counted in the inclusive time of the outermost call
+            let recursive = self.stack.iter().any(|(t, ..)| *t == timed);
+            let totals = self.totals.entry(timed).or_default();
+            totals.calls += 1;
+            totals.exclusive += exclusive;
+            if !recursive {
+                totals.inclusive += elapsed;
+            }
+
+            let mut path = self.stack.iter().map(|(t, ..)| *t).collect::<Vec<_>>();
+            path.push(timed);
+            *self.stacks.entry(path).or_default() += exclusive;
+        }
+    }
+}
+
+impl Game {
+    /// Runs something, timing it if the profiler is on.
+    pub fn profile<T>(&mut self, timed: Timed, f: impl FnOnce(&mut Self) -> T) -> T {
+        match self.profiler.as_mut() {
+            Some(profiler) => {
+                profiler.enter(timed);
+                let result = f(self);
+                if let Some(profiler) = self.profiler.as_mut() {
+                    profiler.exit();
+                }
+                result
+            },
+            None => f(self),
+        }
+    }
+
+    /// Gets the profiler's totals for everything it's timed, slowest first.
+    pub fn profile_report(&self) -> Vec<ProfileEntry> {
+        let mut entries = match &self.profiler {
+            Some(profiler) => profiler
+                .totals
+                .iter()
+                .map(|(timed, totals)| ProfileEntry {
+                    name: self.timed_name(timed),
+                    calls: totals.calls,
+                    inclusive: totals.inclusive,
+                    exclusive: totals.exclusive,
+                })
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+        entries.sort_by(|a, b| b.inclusive.cmp(&a.inclusive).then_with(|| a.name.cmp(&b.name)));
+        entries
+    }
+
+    /// Writes out the profiler's call stacks in the folded format used by flamegraph tools.
+    /// Each line is a call stack separated by semicolons, then the microseconds spent in its innermost call.
+    pub fn profile_folded(&self) -> String {
+        let mut lines = match &self.profiler {
+            Some(profiler) => profiler
+                .stacks
+                .iter()
+                .map(|(path, time)| {
+                    let names = path.iter().map(|t| self.timed_name(t).replace(';', ",")).collect::<Vec<_>>();
+                    format!("{} {}", names.join(";"), time.as_micros())
+                })
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    fn timed_name(&self, timed: &Timed) -> String {
+        match timed {
+            Timed::Event { object, event_type, event_number } => {
+                format!("{}: {}", self.object_name(*object), self.event_name(*event_type, *event_number))
+            },
+            Timed::Script(id) => match self.assets.scripts.get_asset(*id as ID) {
+                Some(script) => self.decode_str(script.name.as_ref()).into_owned(),
+                None => format!("<script {}>", id),
+            },
+            Timed::Function(function) => match mappings::FUNCTIONS.iter().find(|(_, f, _)| f == function) {
+                Some((name, ..)) => (*name).into(),
+                None => format!("{:?}", function),
+            },
+        }
+    }
+}
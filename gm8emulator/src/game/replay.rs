@@ -1,6 +1,6 @@
 use crate::gml::Value;
 use serde::{Deserialize, Serialize};
-use shared::input::{Key, MouseButton};
+use shared::input::{JoystickEvent, Key, MouseButton};
 
 // Represents an entire replay (TAS) file
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,12 +31,19 @@ pub struct Frame {
 // Stored events for certain things which must always happen the same way during replay
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Event {
-    GetInteger(Value),   // value returned from get_integer()
-    GetString(Value),    // value returned from get_string()
-    Randomize(i32),      // value assigned to seed by randomize()
-    ShowMenu(Value),     // value returned from show_menu()
-    ShowMessage,         // acknowledges that a show_message() does not need to be shown during replay
-    ShowQuestion(Value), // value returned from show_question()
+    ClipboardGetText(Value),  // value returned from clipboard_get_text()
+    ClipboardHasText(Value),  // value returned from clipboard_has_text()
+    GetInteger(Value),        // value returned from get_integer()
+    GetString(Value),         // value returned from get_string()
+    Multiplayer(Value),       // value returned from an mplay_* function, which depends on other players
+    Randomize(i32),           // value assigned to seed by randomize()
+    ShowError(Value),         // whether the game was aborted (true) or carried on (false) after an error message
+    ShowMenu(Value),          // value returned from show_menu()
+    ShowMessage,              // acknowledges that a show_message() does not need to be shown during replay
+    ShowMessageExt(Value),    // value returned from show_message_ext()
+    ShowQuestion(Value),      // value returned from show_question()
+    ShowSplash,               // acknowledges that a blocking splash screen does not need to be shown during replay
+    WindowPosition(i32, i32), // position of the window on the display, which is up to the host
 }
 
 // An input event which takes place during a frame
@@ -48,6 +55,7 @@ pub enum Input {
     MouseRelease(MouseButton),
     MouseWheelUp,
     MouseWheelDown,
+    Joystick(JoystickEvent),
 }
 
 impl Replay {
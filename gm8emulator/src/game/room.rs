@@ -0,0 +1,65 @@
+use crate::{
+    game::{string::RCStr, Background, Game, View},
+    instancelist::{InstanceList, TileList},
+};
+use serde::{Deserialize, Serialize};
+use shared::types::Colour;
+use std::mem;
+
+/// The state of a persistent room which has been left, kept so it can be restored when it's entered again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoomState {
+    /// Room settings, which may have been changed while in the room
+    pub caption: RCStr,
+    pub width: i32,
+    pub height: i32,
+    pub speed: u32,
+    pub colour: Colour,
+    pub show_colour: bool,
+
+    /// Backgrounds and views as they were when the room was left
+    pub backgrounds: Vec<Background>,
+    pub views_enabled: bool,
+    pub views: Vec<View>,
+
+    /// Every non-persistent instance which was in the room, in insertion order
+    pub instances: InstanceList,
+
+    /// Every tile which was in the room
+    pub tiles: TileList,
+}
+
+impl Game {
+    /// Takes the current room's state out of the game, leaving no instances or tiles behind.
+    /// Persistent instances should be taken out first, since they don't belong to the room.
+    pub fn take_room_state(&mut self) -> RoomState {
+        RoomState {
+            caption: self.caption.clone(),
+            width: self.room_width,
+            height: self.room_height,
+            speed: self.room_speed,
+            colour: self.room_colour,
+            show_colour: self.show_room_colour,
+            backgrounds: mem::take(&mut self.backgrounds),
+            views_enabled: self.views_enabled,
+            views: mem::take(&mut self.views),
+            instances: mem::replace(&mut self.instance_list, InstanceList::new()),
+            tiles: mem::replace(&mut self.tile_list, TileList::new()),
+        }
+    }
+
+    /// Puts a stored room's state back into the game, replacing its instances and tiles.
+    pub fn restore_room_state(&mut self, state: RoomState) {
+        self.caption = state.caption;
+        self.room_width = state.width;
+        self.room_height = state.height;
+        self.room_speed = state.speed;
+        self.room_colour = state.colour;
+        self.show_room_colour = state.show_colour;
+        self.backgrounds = state.backgrounds;
+        self.views_enabled = state.views_enabled;
+        self.views = state.views;
+        self.instance_list = state.instances;
+        self.tile_list = state.tiles;
+    }
+}
@@ -1,16 +1,19 @@
 use crate::{
     game::{
-        background, draw,
+        background,
+        dialog::MessageSettings,
+        draw,
         external::{DefineInfo, External},
         includedfile::IncludedFile,
         model::Model,
         particle,
         pathfinding::PotentialStepSettings,
+        splash::{Splash, SplashSettings},
         string::RCStr,
         surface::Surface,
         transition::UserTransition,
         view::View,
-        Assets, Game, Replay, Version,
+        Assets, Game, Replay, RoomState, Version, WindowState,
     },
     gml::{ds, rand::Random, Compiler},
     handleman::HandleList,
@@ -68,6 +71,7 @@ pub struct SaveState {
     pub room_height: i32,
     pub room_order: Box<[i32]>,
     pub room_speed: u32,
+    pub persistent_rooms: HashMap<i32, RoomState>,
     pub user_transitions: HashMap<i32, UserTransition>,
 
     pub globals: DummyFieldHolder,
@@ -109,6 +113,11 @@ pub struct SaveState {
     pub uninit_args_are_zero: bool,
 
     pub potential_step_settings: PotentialStepSettings,
+    pub message_settings: MessageSettings,
+    pub splash_settings: SplashSettings,
+    pub splash: Option<Splash>,
+    pub game_info: RCStr,
+    pub game_info_colour: i32,
 
     pub transition_kind: i32,
     pub transition_steps: i32,
@@ -134,6 +143,7 @@ pub struct SaveState {
     pub caption: RCStr,
     pub caption_stale: bool,
 
+    window_state: WindowState,
     scaling: Scaling,
     unscaled_width: u32,
     unscaled_height: u32,
@@ -148,7 +158,8 @@ pub struct SaveState {
 impl SaveState {
     pub fn from(game: &Game, replay: Replay) -> Self {
         let (window_width, window_height) = game.window.get_inner_size();
-        let screenshot = game.renderer.get_pixels(0, 0, game.unscaled_width as _, game.unscaled_height as _);
+        let (fb_w, fb_h) = game.window_region_size();
+        let screenshot = game.renderer.get_pixels(0, 0, fb_w as _, fb_h as _);
         let zbuffer = game.renderer.dump_zbuffer();
 
         Self {
@@ -182,6 +193,7 @@ impl SaveState {
             room_height: game.room_height.clone(),
             room_order: game.room_order.clone(),
             room_speed: game.room_speed.clone(),
+            persistent_rooms: game.persistent_rooms.clone(),
             user_transitions: game.user_transitions.clone(),
             globals: game.globals.clone(),
             globalvars: game.globalvars.clone(),
@@ -218,6 +230,11 @@ impl SaveState {
             uninit_fields_are_zero: game.uninit_fields_are_zero.clone(),
             uninit_args_are_zero: game.uninit_args_are_zero.clone(),
             potential_step_settings: game.potential_step_settings.clone(),
+            message_settings: game.message_settings.clone(),
+            splash_settings: game.splash_settings.clone(),
+            splash: game.splash.clone(),
+            game_info: game.game_info.clone(),
+            game_info_colour: game.game_info_colour.clone(),
             transition_kind: game.transition_kind.clone(),
             transition_steps: game.transition_steps.clone(),
             cursor_sprite: game.cursor_sprite.clone(),
@@ -240,6 +257,7 @@ impl SaveState {
             spoofed_time_nanos: game.spoofed_time_nanos,
             caption: game.caption.clone(),
             caption_stale: game.caption_stale.clone(),
+            window_state: game.window_state.clone(),
             scaling: game.scaling,
             unscaled_width: game.unscaled_width,
             unscaled_height: game.unscaled_height,
@@ -256,11 +274,12 @@ impl SaveState {
 
         game.renderer.upload_dynamic_textures(&self.textures);
 
+        let (fb_w, fb_h) = self.window_state.region_size.unwrap_or((self.unscaled_width, self.unscaled_height));
         game.renderer.draw_raw_frame(
             self.screenshot,
             self.zbuffer,
-            self.unscaled_width as _,
-            self.unscaled_height as _,
+            fb_w as _,
+            fb_h as _,
             self.window_width as _,
             self.window_height as _,
             self.scaling,
@@ -317,6 +336,7 @@ impl SaveState {
         game.room_height = self.room_height;
         game.room_order = self.room_order;
         game.room_speed = self.room_speed;
+        game.persistent_rooms = self.persistent_rooms;
         game.user_transitions = self.user_transitions;
         game.globals = self.globals;
         game.globalvars = self.globalvars;
@@ -352,6 +372,11 @@ impl SaveState {
         game.uninit_fields_are_zero = self.uninit_fields_are_zero;
         game.uninit_args_are_zero = self.uninit_args_are_zero;
         game.potential_step_settings = self.potential_step_settings;
+        game.message_settings = self.message_settings;
+        game.splash_settings = self.splash_settings;
+        game.splash = self.splash;
+        game.game_info = self.game_info;
+        game.game_info_colour = self.game_info_colour;
         game.transition_kind = self.transition_kind;
         game.transition_steps = self.transition_steps;
         game.cursor_sprite = self.cursor_sprite;
@@ -374,9 +399,11 @@ impl SaveState {
         game.spoofed_time_nanos = self.spoofed_time_nanos;
         game.caption = self.caption;
         game.caption_stale = self.caption_stale;
+        game.window_state = self.window_state;
         game.scaling = self.scaling;
         game.unscaled_width = self.unscaled_width;
         game.unscaled_height = self.unscaled_height;
+        game.apply_window_state();
         self.replay
     }
 
@@ -0,0 +1,400 @@
+use crate::{
+    game::{
+        draw::{Halign, Valign},
+        replay::Event,
+        string::RCStr,
+        Game, GetAsset, PlayType,
+    },
+    gml::{self, file},
+    math::Real,
+};
+use gmio::atlas::AtlasRef;
+use serde::{Deserialize, Serialize};
+use shared::{input::Key, types::Colour};
+use std::time::{Duration, Instant};
+
+const FRAME_TIME: Duration = Duration::from_millis(16);
+const TEXT_MARGIN: i32 = 8;
+const CAPTION_HEIGHT: i32 = 20;
+
+/// Settings for splash screens, as configured by the splash_set_* functions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SplashSettings {
+    pub caption: RCStr,
+    pub fullscreen: bool,
+    pub border: bool,
+    pub size: (i32, i32),
+    pub position: (i32, i32),
+    pub adapt: bool,
+    pub top: bool,
+    pub colour: i32,
+    pub main: bool,
+    pub scale: i32,
+    pub cursor: bool,
+    pub interrupt: bool,
+    pub stop_key: bool,
+    pub close_button: bool,
+    pub stop_mouse: bool,
+}
+
+impl Default for SplashSettings {
+    fn default() -> Self {
+        Self {
+            caption: "".into(),
+            fullscreen: false,
+            border: true,
+            size: (640, 480),
+            position: (-1, -1),
+            adapt: true,
+            top: true,
+            colour: 0x000000,
+            main: true,
+            scale: 0,
+            cursor: true,
+            interrupt: true,
+            stop_key: true,
+            close_button: true,
+            stop_mouse: true,
+        }
+    }
+}
+
+/// What a splash screen is showing.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum SplashContent {
+    Image { atlas_ref: AtlasRef, width: i32, height: i32 },
+    Text { text: RCStr, background: i32 },
+}
+
+/// A splash screen. If the game isn't interrupted by it, it stays in Game and is drawn over every frame.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Splash {
+    pub content: SplashContent,
+    pub full: bool,
+    pub frames_left: Option<u32>,
+}
+
+/// Converts an RTF document to plain text, keeping paragraph breaks. Anything that isn't RTF is returned as-is.
+pub fn rtf_to_text(rtf: &[u8]) -> Vec<u8> {
+    if !rtf.starts_with(b"{\\rtf") {
+        return rtf.to_vec()
+    }
+
+    // Destinations whose contents aren't part of the document text
+    const SKIPPED: &[&[u8]] =
+        &[b"fonttbl", b"colortbl", b"stylesheet", b"info", b"pict", b"header", b"footer", b"listtable", b"generator"];
+
+    let mut out = Vec::with_capacity(rtf.len());
+    // For each open group, whether its contents are being skipped
+    let mut groups: Vec<bool> = Vec::new();
+    let mut skipping = false;
+    let mut pos = 0;
+    while let Some(&c) = rtf.get(pos) {
+        pos += 1;
+        match c {
+            b'{' => groups.push(skipping),
+            b'}' => skipping = groups.pop().unwrap_or(false),
+            b'\\' => match rtf.get(pos).copied() {
+                Some(c @ b'\\') | Some(c @ b'{') | Some(c @ b'}') => {
+                    pos += 1;
+                    if !skipping {
+                        out.push(c);
+                    }
+                },
+                Some(b'*') => {
+                    // Optional destination which we don't understand
+                    pos += 1;
+                    skipping = true;
+                },
+                Some(b'\'') => {
+                    // Hex-encoded byte in the document's codepage
+                    let hex = rtf.get(pos + 1..pos + 3).and_then(|h| std::str::from_utf8(h).ok());
+                    if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                        if !skipping {
+                            out.push(byte);
+                        }
+                    }
+                    pos += 3;
+                },
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let start = pos;
+                    while rtf.get(pos).map_or(false, u8::is_ascii_alphabetic) {
+                        pos += 1;
+                    }
+                    let word = &rtf[start..pos];
+                    // Skip the numeric parameter and the delimiting space, if any
+                    if rtf.get(pos) == Some(&b'-') {
+                        pos += 1;
+                    }
+                    while rtf.get(pos).map_or(false, u8::is_ascii_digit) {
+                        pos += 1;
+                    }
+                    if rtf.get(pos) == Some(&b' ') {
+                        pos += 1;
+                    }
+                    if SKIPPED.contains(&word) {
+                        skipping = true;
+                    } else if !skipping {
+                        match word {
+                            b"par" | b"line" => out.extend_from_slice(b"\r\n"),
+                            b"tab" => out.push(b'\t'),
+                            _ => (),
+                        }
+                    }
+                },
+                _ => pos += 1,
+            },
+            b'\r' | b'\n' => (),
+            c => {
+                if !skipping {
+                    out.push(c);
+                }
+            },
+        }
+    }
+    out
+}
+
+impl Game {
+    /// Loads an image file into a splash screen's content.
+    pub fn load_splash_image(&mut self, function: &str, fname: &str) -> gml::Result<SplashContent> {
+        let image = file::load_image(fname).map_err(|e| gml::Error::FunctionError(function.into(), e.to_string()))?;
+        let (width, height) = image.dimensions();
+        let atlas_ref = self
+            .renderer
+            .upload_sprite(image.into_raw().into_boxed_slice(), width as _, height as _, 0, 0)
+            .map_err(|e| gml::Error::FunctionError(function.into(), e))?;
+        Ok(SplashContent::Image { atlas_ref, width: width as _, height: height as _ })
+    }
+
+    /// Loads an RTF or plain text file into a splash screen's content.
+    pub fn load_splash_text(&mut self, function: &str, fname: &str, background: i32) -> gml::Result<SplashContent> {
+        let text = std::fs::read(fname).map_err(|e| gml::Error::FunctionError(function.into(), e.to_string()))?;
+        Ok(SplashContent::Text { text: rtf_to_text(&text).into(), background })
+    }
+
+    /// Shows a splash screen. If the interrupt setting is on, this blocks until it's dismissed or its delay
+    /// (in milliseconds) runs out. Otherwise it's drawn over the game until it's dismissed by the game's input.
+    pub fn show_splash(&mut self, function: &str, content: SplashContent, full: bool, delay: i32) -> gml::Result<()> {
+        if !self.splash_settings.interrupt {
+            // Count in frames rather than real time so the splash disappears at the same point in replays
+            let frames_left = if delay > 0 {
+                Some(((i64::from(delay) * i64::from(self.room_speed) + 999) / 1000) as u32)
+            } else {
+                None
+            };
+            self.clear_splash();
+            self.splash = Some(Splash { content, full, frames_left });
+            return Ok(())
+        }
+
+        self.show_blocking_splash(function, Splash { content, full, frames_left: None }, delay)
+    }
+
+    /// Shows a splash screen which blocks until it's dismissed or its delay (in milliseconds) runs out,
+    /// regardless of the interrupt setting. The splash's image is freed afterwards.
+    pub fn show_blocking_splash(&mut self, function: &str, splash: Splash, delay: i32) -> gml::Result<()> {
+        match self.play_type {
+            PlayType::Normal => self.run_splash(&splash, delay)?,
+            PlayType::Record => {
+                self.run_splash(&splash, delay)?;
+                self.stored_events.push_back(Event::ShowSplash);
+            },
+            PlayType::Replay => {
+                if !matches!(self.stored_events.pop_front(), Some(Event::ShowSplash)) {
+                    return Err(gml::Error::ReplayError(function.into()))
+                }
+            },
+        }
+        if let SplashContent::Image { atlas_ref, .. } = splash.content {
+            self.renderer.delete_sprite(atlas_ref);
+        }
+        Ok(())
+    }
+
+    /// Removes the current non-interrupting splash screen, if there is one.
+    pub fn clear_splash(&mut self) {
+        if let Some(Splash { content: SplashContent::Image { atlas_ref, .. }, .. }) = self.splash.take() {
+            self.renderer.delete_sprite(atlas_ref);
+        }
+    }
+
+    /// Counts down the non-interrupting splash screen and dismisses it if the game's input says so.
+    /// Should be called at the start of each frame.
+    pub fn update_splash(&mut self) {
+        let dismissed = match &mut self.splash {
+            Some(splash) => {
+                let settings = &self.splash_settings;
+                let input = &self.input_manager;
+                let expired = match &mut splash.frames_left {
+                    Some(0) => true,
+                    Some(frames) => {
+                        *frames -= 1;
+                        false
+                    },
+                    None => false,
+                };
+                expired
+                    || (settings.stop_key && input.key_check_any_pressed())
+                    || (settings.stop_mouse && input.mouse_check_any_pressed())
+                    || (settings.close_button && input.key_check_pressed(Key::Escape as usize))
+            },
+            None => false,
+        };
+        if dismissed {
+            self.clear_splash();
+        }
+    }
+
+    /// Displays a blocking splash screen until the user dismisses it or the delay runs out.
+    fn run_splash(&mut self, splash: &Splash, delay: i32) -> gml::Result<()> {
+        use gmio::window::Event as WindowEvent;
+
+        let (fb_w, fb_h) = self.window_region_size();
+        let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+        if self.surface_target.is_some() {
+            self.renderer.reset_target();
+        }
+        self.renderer.flush_queue();
+        let backdrop = self
+            .renderer
+            .upload_sprite(self.renderer.get_pixels(0, 0, fb_w, fb_h), fb_w, fb_h, 0, 0)
+            .map_err(|e| gml::Error::FunctionError("show_splash".into(), e))?;
+        let old_3d = self.renderer.get_3d();
+        self.renderer.set_3d(false);
+        self.renderer.set_view(0, 0, fb_w, fb_h, 0.0, 0, 0, fb_w, fb_h);
+
+        let settings = self.splash_settings.clone();
+        let end_time = if delay > 0 { Some(Instant::now() + Duration::from_millis(delay as u64)) } else { None };
+        loop {
+            let dismissed = self.window.process_events().any(|event| match event {
+                WindowEvent::KeyboardDown(Key::Escape) => settings.close_button || settings.stop_key,
+                WindowEvent::KeyboardDown(_) => settings.stop_key,
+                WindowEvent::MouseButtonDown(_) => settings.stop_mouse,
+                _ => false,
+            });
+            if dismissed || self.window.close_requested() || end_time.map_or(false, |t| Instant::now() >= t) {
+                break
+            }
+
+            self.renderer.draw_sprite(&backdrop, 0.0, 0.0, 1.0, 1.0, 0.0, 0xFFFFFF, 1.0);
+            self.draw_splash(splash);
+            let (width, height) = self.window.get_inner_size();
+            self.renderer.present(width, height, self.scaling);
+            gml::datetime::sleep(FRAME_TIME);
+        }
+
+        self.renderer.draw_sprite(&backdrop, 0.0, 0.0, 1.0, 1.0, 0.0, 0xFFFFFF, 1.0);
+        self.renderer.flush_queue();
+        self.renderer.delete_sprite(backdrop);
+        self.renderer.set_3d(old_3d);
+        if let Some(surf) = self.surface_target.and_then(|id| self.surfaces.get_asset(id)) {
+            self.renderer.set_target(&surf.atlas_ref);
+        }
+        Ok(())
+    }
+
+    /// Draws a splash screen over the current view, which should cover the whole framebuffer.
+    pub fn draw_splash(&mut self, splash: &Splash) {
+        let settings = self.splash_settings.clone();
+        let (fb_w, fb_h) = self.window_region_size();
+        let (fb_w, fb_h) = (fb_w as i32, fb_h as i32);
+
+        let (w, h) = match &splash.content {
+            _ if splash.full => (fb_w, fb_h),
+            SplashContent::Image { width, height, .. } if settings.adapt => (*width, *height),
+            _ => settings.size,
+        };
+        let (w, h) = (w.min(fb_w), h.min(fb_h));
+        let x = if splash.full || settings.position.0 < 0 { (fb_w - w) / 2 } else { settings.position.0 };
+        let y = if splash.full || settings.position.1 < 0 { (fb_h - h) / 2 } else { settings.position.1 };
+        let (x1, y1, x2, y2) = (f64::from(x), f64::from(y), f64::from(x + w - 1), f64::from(y + h - 1));
+
+        match &splash.content {
+            SplashContent::Image { atlas_ref, width, height } => {
+                self.renderer.draw_rectangle(x1, y1, x2, y2, settings.colour, 1.0);
+                // A positive scale is a fixed multiplier, otherwise fit the image inside the splash
+                let scale = if settings.scale > 0 {
+                    f64::from(settings.scale)
+                } else {
+                    (f64::from(w) / f64::from(*width)).min(f64::from(h) / f64::from(*height))
+                };
+                let draw_x = f64::from(x) + (f64::from(w) - f64::from(*width) * scale) / 2.0;
+                let draw_y = f64::from(y) + (f64::from(h) - f64::from(*height) * scale) / 2.0;
+                self.renderer.draw_sprite(atlas_ref, draw_x, draw_y, scale, scale, 0.0, 0xFFFFFF, 1.0);
+            },
+            SplashContent::Text { text, background } => {
+                self.renderer.draw_rectangle(x1, y1, x2, y2, *background, 1.0);
+                self.draw_splash_string(
+                    x + TEXT_MARGIN,
+                    y + TEXT_MARGIN,
+                    text.clone(),
+                    Some(w - TEXT_MARGIN * 2),
+                    Halign::Left,
+                    Colour::new(0.0, 0.0, 0.0),
+                );
+            },
+        }
+
+        if settings.border && !splash.full {
+            self.renderer.draw_rectangle_outline(x1, y1, x2, y2, 0x808080, 1.0);
+            if !settings.caption.as_ref().is_empty() {
+                self.renderer.draw_rectangle(x1, y1, x2, y1 + f64::from(CAPTION_HEIGHT) - 1.0, 0x800000, 1.0);
+                self.draw_splash_string(
+                    x + 4,
+                    y + 2,
+                    settings.caption.clone(),
+                    None,
+                    Halign::Left,
+                    Colour::new(1.0, 1.0, 1.0),
+                );
+                if settings.close_button {
+                    self.draw_splash_string(
+                        x + w - 4,
+                        y + 2,
+                        "X".into(),
+                        None,
+                        Halign::Right,
+                        Colour::new(1.0, 1.0, 1.0),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draws text in the default font without disturbing the game's draw settings.
+    pub(super) fn draw_splash_string(
+        &mut self,
+        x: i32,
+        y: i32,
+        text: RCStr,
+        max_width: Option<i32>,
+        halign: Halign,
+        colour: Colour,
+    ) {
+        let old_font = self.draw_font_id;
+        let old_colour = self.draw_colour;
+        let old_halign = self.draw_halign;
+        let old_valign = self.draw_valign;
+        self.draw_font_id = -1;
+        self.draw_colour = colour;
+        self.draw_halign = halign;
+        self.draw_valign = Valign::Top;
+        self.draw_string(
+            Real::from(x),
+            Real::from(y),
+            text,
+            None,
+            max_width,
+            Real::from(1.0),
+            Real::from(1.0),
+            Real::from(0.0),
+            None,
+            Real::from(1.0),
+        );
+        self.draw_font_id = old_font;
+        self.draw_colour = old_colour;
+        self.draw_halign = old_halign;
+        self.draw_valign = old_valign;
+    }
+}
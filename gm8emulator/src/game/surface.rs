@@ -6,4 +6,15 @@ pub struct Surface {
     pub width: u32,
     pub height: u32,
     pub atlas_ref: AtlasRef,
+
+    /// RGBA pixels read back for surface_getpixel, so the GPU doesn't have to be stalled on every call.
+    /// This must be cleared whenever the surface might be drawn to.
+    #[serde(skip)]
+    pub pixel_cache: Option<Box<[u8]>>,
+}
+
+impl Surface {
+    pub fn new(width: u32, height: u32, atlas_ref: AtlasRef) -> Self {
+        Self { width, height, atlas_ref, pixel_cache: None }
+    }
 }
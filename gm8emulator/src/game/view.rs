@@ -78,4 +78,24 @@ impl View {
         );
         (util::ieee_round(x), util::ieee_round(y))
     }
+    /// Transforms a point in room-space to the point on screen where this view shows it
+    pub fn untransform_point(&self, x: i32, y: i32) -> (i32, i32) {
+        let src_x = f64::from(self.source_x);
+        let src_y = f64::from(self.source_y);
+        let src_w = f64::from(self.source_w);
+        let src_h = f64::from(self.source_h);
+        let (mut x, mut y) = (f64::from(x), f64::from(y));
+        let angle = self.angle.to_radians();
+        util::rotate_around(
+            &mut x,
+            &mut y,
+            src_x + (src_w / 2.0),
+            src_y + (src_h / 2.0),
+            (-angle.sin()).into(),
+            angle.cos().into(),
+        );
+        let x = f64::from(self.port_x) + (f64::from(self.port_w) * (x - src_x) / src_w);
+        let y = f64::from(self.port_y) + (f64::from(self.port_h) * (y - src_y) / src_h);
+        (util::ieee_round(x), util::ieee_round(y))
+    }
 }
@@ -0,0 +1,91 @@
+use crate::gml::compiler::mappings::constants as gml_constants;
+use gmio::window::{Cursor, Style};
+use serde::{Deserialize, Serialize};
+
+/// Window and display settings which can be changed from GML.
+///
+/// GML always reads these back from here instead of asking the real window, so the game sees the same values
+/// no matter what the host window manager does with them. While recording, the window belongs to the recorder,
+/// so these are kept track of but never applied to it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WindowState {
+    pub border: bool,
+    pub icons: bool,
+    pub fullscreen: bool,
+    pub sizeable: bool,
+    pub stay_on_top: bool,
+
+    /// The cr_* constant last given to window_set_cursor
+    pub cursor: i32,
+
+    /// Size of the drawing region set by window_set_region_size, overriding the room or view size
+    pub region_size: Option<(u32, u32)>,
+
+    /// Display mode requested by the display_set_* functions
+    pub display: DisplayMode,
+}
+
+/// A display mode requested by the game. Fields which are None haven't been changed from the real display's.
+///
+/// The host's actual display mode is never changed, but the display_get_* functions report these values.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DisplayMode {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub colour_depth: Option<i32>,
+    pub frequency: Option<i32>,
+}
+
+impl WindowState {
+    /// The gmio window style matching these settings.
+    pub fn style(&self) -> Style {
+        if self.fullscreen {
+            Style::BorderlessFullscreen
+        } else {
+            match (self.border, self.icons) {
+                (true, true) if self.sizeable => Style::Resizable,
+                (true, true) => Style::Regular,
+                (true, false) => Style::Undecorated,
+                (false, _) => Style::Borderless,
+            }
+        }
+    }
+
+    /// The gmio cursor closest to the current cr_* constant.
+    pub fn cursor(&self) -> Cursor {
+        match f64::from(self.cursor) {
+            c if c == gml_constants::CR_NONE => Cursor::Invisible,
+            c if c == gml_constants::CR_CROSS => Cursor::Cross,
+            c if c == gml_constants::CR_BEAM => Cursor::Beam,
+            c if c == gml_constants::CR_SIZE_NESW => Cursor::SizeNESW,
+            c if c == gml_constants::CR_SIZE_NS || c == gml_constants::CR_HSPLIT => Cursor::SizeNS,
+            c if c == gml_constants::CR_SIZE_NWSE => Cursor::SizeNWSE,
+            c if c == gml_constants::CR_SIZE_WE || c == gml_constants::CR_VSPLIT => Cursor::SizeWE,
+            c if c == gml_constants::CR_UPARROW => Cursor::Up,
+            c if c == gml_constants::CR_HOURGLASS || c == gml_constants::CR_SQLWAIT => Cursor::Hourglass,
+            c if c == gml_constants::CR_APPSTART => Cursor::AppStart,
+            c if c == gml_constants::CR_HANDPOINT => Cursor::Hand,
+            c if c == gml_constants::CR_SIZE_ALL => Cursor::SizeAll,
+            _ => Cursor::Arrow,
+        }
+    }
+}
+
+impl DisplayMode {
+    /// Checks whether a display_set_all call could succeed. -1 means "don't change".
+    pub fn is_valid(width: i32, height: i32, colour_depth: i32, frequency: i32) -> bool {
+        (width == -1 || width > 0)
+            && (height == -1 || height > 0)
+            && matches!(colour_depth, -1 | 16 | 32)
+            && (frequency == -1 || frequency > 0)
+    }
+
+    /// Applies a display_set_all call, where -1 means "don't change".
+    pub fn set(&mut self, width: i32, height: i32, colour_depth: i32, frequency: i32) {
+        let requested = |value: i32, old: Option<i32>| if value == -1 { old } else { Some(value) };
+        self.width = requested(width, self.width);
+        self.height = requested(height, self.height);
+        self.colour_depth = requested(colour_depth, self.colour_depth);
+        self.frequency = requested(frequency, self.frequency);
+    }
+}
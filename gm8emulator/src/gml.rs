@@ -1,3 +1,4 @@
+pub mod bytecode;
 pub mod compiler;
 pub mod context;
 pub mod datetime;
@@ -214,3 +215,10 @@ pub enum InstanceVariable {
     DisplayAa,
     AsyncLoad,
 }
+
+impl InstanceVariable {
+    /// Whether the emulator can read and write this variable, rather than panicking when a game uses it.
+    pub fn is_implemented(&self) -> bool {
+        !matches!(self, Self::KeyboardLastchar | Self::KeyboardString)
+    }
+}
@@ -0,0 +1,520 @@
+use crate::gml::{
+    self,
+    runtime::{
+        array_index, ArrayAccessor, BinaryOperator, Error, InstanceIdentifier, Instruction, InstructionKind, Node,
+        ReturnType, UnaryOperator,
+    },
+    InstanceVariable, Value,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A piece of GML compiled to flat bytecode, which `Game::execute` runs.
+///
+/// The code runs on a stack of values. Statements are flattened into jumps between ops, so running code
+/// never has to recurse except when it calls a script.
+#[derive(Serialize, Deserialize)]
+pub struct Program {
+    pub ops: Box<[Op]>,
+}
+
+/// A bytecode operation.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Op {
+    /// Marks the start of a statement, and which line of source code it's on
+    Line(usize),
+
+    /// Pushes a value
+    Push(Value),
+
+    /// Pushes the value of a constant
+    Constant(usize),
+
+    /// Pops the given number of arguments and calls a builtin function with them, pushing the result
+    Call { function: gml::Function, argc: usize },
+
+    /// Pops the given number of arguments and calls a script with them, pushing the result
+    CallScript { script_id: usize, argc: usize },
+
+    /// Pops an instance ID, object ID or keyword and works out which instances it refers to,
+    /// for the next field or variable op whose owner is `Owner::Stack`
+    Target,
+
+    /// Pops one or two array indices and checks them, for the next field or variable op which is indexed
+    ArrayIndex(u8),
+
+    /// An array index which was already checked when compiling, for the next field or variable op which is indexed
+    Index(u32),
+
+    /// Pushes the value of a field
+    GetField { index: usize, owner: Owner, indexed: bool },
+
+    /// Pops a value and sets a field to it
+    SetField { index: usize, owner: Owner, indexed: bool },
+
+    /// Pushes the value of an instance variable
+    GetVariable { var: InstanceVariable, owner: Owner, indexed: bool },
+
+    /// Pops a value and sets an instance variable to it
+    SetVariable { var: InstanceVariable, owner: Owner, indexed: bool },
+
+    /// Pops the right then left operands and pushes the result
+    Binary(BinaryOperator),
+
+    /// Pops the operand and pushes the result
+    Unary(UnaryOperator),
+
+    /// Pops a value and stores it as the context's return value
+    SetReturnValue,
+
+    /// Pops a value and throws it away
+    Discard,
+
+    /// Carries on running from the given op
+    Jump(usize),
+
+    /// Pops a value, jumping to the given op if it's false
+    JumpIfFalse(usize),
+
+    /// Pops a switch case and compares it with the switch's input below it.
+    /// If they match, pops the input as well and jumps to the given op
+    Case(usize),
+
+    /// Pops the number of times to run a repeat loop, and starts counting it down
+    RepeatStart,
+
+    /// Ends the innermost repeat loop by jumping to the given op if its count has reached 0
+    RepeatCheck(usize),
+
+    /// Counts the innermost repeat loop down by one
+    RepeatDecrement,
+
+    /// Throws away the innermost repeat loop's count
+    RepeatEnd,
+
+    /// Pops the target of a with statement and starts going through the instances it refers to
+    WithStart,
+
+    /// Moves the innermost with statement onto its next instance, or jumps to the given op if there are no more
+    WithNext(usize),
+
+    /// Ends the innermost with statement, putting self and other back how they were
+    WithEnd,
+
+    /// Declares some fields as global
+    GlobalVar(Vec<usize>),
+
+    /// Stops running the code
+    Return(ReturnType),
+
+    /// Causes a runtime error
+    Error(Error),
+}
+
+/// Which instances a field or variable op reads or writes.
+/// A keyword or object ID known when compiling is kept as `Owner::Id`, and anything else is worked out by an
+/// `Op::Target` beforehand.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Owner {
+    Unknown,
+    Own,
+    Other,
+    Global,
+    Local,
+    Id(i32),
+    Stack,
+}
+
+/// Lowers compiled instruction trees to bytecode.
+struct Assembler {
+    ops: Vec<Op>,
+
+    /// The loops and switches around the code being assembled, innermost last
+    breakables: Vec<Breakable>,
+}
+
+/// The jumps which need pointing at the end of a loop or switch, or the place it continues from.
+struct Breakable {
+    is_switch: bool,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+impl Program {
+    pub fn new(instructions: &[Instruction]) -> Self {
+        let mut assembler = Assembler { ops: Vec::new(), breakables: Vec::new() };
+        assembler.body(instructions);
+        Self { ops: assembler.ops.into_boxed_slice() }
+    }
+}
+
+impl Assembler {
+    /// Adds a jump whose destination will be filled in later, returning its position.
+    fn jump(&mut self, op: fn(usize) -> Op) -> usize {
+        self.ops.push(op(usize::MAX));
+        self.ops.len() - 1
+    }
+
+    /// Points a jump at the next op to be added.
+    fn land(&mut self, jump: usize) {
+        let here = self.ops.len();
+        match &mut self.ops[jump] {
+            Op::Jump(target) | Op::JumpIfFalse(target) | Op::Case(target) => *target = here,
+            Op::RepeatCheck(target) | Op::WithNext(target) => *target = here,
+            op => unreachable!("can't land {:?}", op),
+        }
+    }
+
+    /// Assembles the body of a loop or switch, then returns the jumps which break out of it or continue it.
+    fn breakable(&mut self, is_switch: bool, f: impl FnOnce(&mut Self)) -> Breakable {
+        self.breakables.push(Breakable { is_switch, breaks: Vec::new(), continues: Vec::new() });
+        f(self);
+        self.breakables.pop().unwrap()
+    }
+
+    /// Assembles a loop body, pointing its continues at `continue_to`. Returns the jumps which break out of it.
+    fn loop_body(&mut self, body: &[Instruction], continue_to: usize) -> Vec<usize> {
+        let labels = self.breakable(false, |asm| asm.body(body));
+        for jump in labels.continues {
+            self.ops[jump] = Op::Jump(continue_to);
+        }
+        labels.breaks
+    }
+
+    fn body(&mut self, instructions: &[Instruction]) {
+        instructions.iter().for_each(|instruction| self.instruction(instruction));
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        // Loops jump back to this, so errors in their conditions are reported on the loop's line
+        let start = self.ops.len();
+        self.ops.push(Op::Line(instruction.line));
+        match &instruction.kind {
+            InstructionKind::SetField { accessor, value } => {
+                let owner = self.owner(&accessor.owner);
+                let indexed = self.array(&accessor.array);
+                self.expr(value);
+                self.ops.push(Op::SetField { index: accessor.index, owner, indexed });
+            },
+            InstructionKind::SetVariable { accessor, value } => {
+                let owner = self.owner(&accessor.owner);
+                let indexed = self.array(&accessor.array);
+                self.expr(value);
+                self.ops.push(Op::SetVariable { var: accessor.var, owner, indexed });
+            },
+            InstructionKind::EvalExpression { node: value } | InstructionKind::SetReturnValue { value } => {
+                self.expr(value);
+                self.ops.push(Op::SetReturnValue);
+            },
+            InstructionKind::IfElse { cond, if_body, else_body } => {
+                self.expr(cond);
+                let to_else = self.jump(Op::JumpIfFalse);
+                self.body(if_body);
+                if else_body.is_empty() {
+                    self.land(to_else);
+                } else {
+                    let to_end = self.jump(Op::Jump);
+                    self.land(to_else);
+                    self.body(else_body);
+                    self.land(to_end);
+                }
+            },
+            InstructionKind::LoopUntil { cond, body } => {
+                // Continuing an until loop skips the condition, like when it was interpreted
+                let breaks = self.loop_body(body, start);
+                self.ops.push(Op::Line(instruction.line));
+                self.expr(cond);
+                self.ops.push(Op::JumpIfFalse(start));
+                breaks.into_iter().for_each(|jump| self.land(jump));
+            },
+            InstructionKind::LoopWhile { cond, body } => {
+                self.expr(cond);
+                let to_end = self.jump(Op::JumpIfFalse);
+                let breaks = self.loop_body(body, start);
+                self.ops.push(Op::Jump(start));
+                self.land(to_end);
+                breaks.into_iter().for_each(|jump| self.land(jump));
+            },
+            InstructionKind::LoopFor { cond, body, step } => {
+                self.expr(cond);
+                let to_end = self.jump(Op::JumpIfFalse);
+                let labels = self.breakable(false, |asm| asm.body(body));
+                labels.continues.into_iter().for_each(|jump| self.land(jump));
+                self.body(step);
+                self.ops.push(Op::Jump(start));
+                self.land(to_end);
+                labels.breaks.into_iter().for_each(|jump| self.land(jump));
+            },
+            InstructionKind::Repeat { count, body } => {
+                // Continuing a repeat loop doesn't count it down, like when it was interpreted
+                self.expr(count);
+                self.ops.push(Op::RepeatStart);
+                let check = self.jump(Op::RepeatCheck);
+                let breaks = self.loop_body(body, check);
+                self.ops.push(Op::RepeatDecrement);
+                self.ops.push(Op::Jump(check));
+                self.land(check);
+                breaks.into_iter().for_each(|jump| self.land(jump));
+                self.ops.push(Op::RepeatEnd);
+            },
+            InstructionKind::Switch { input, cases, default, body } => {
+                self.expr(input);
+                let mut case_jumps = Vec::with_capacity(cases.len());
+                for (cond, start) in cases.iter() {
+                    self.expr(cond);
+                    case_jumps.push((self.jump(Op::Case), *start));
+                }
+                self.ops.push(Op::Discard);
+                let default_jump = self.jump(Op::Jump);
+
+                // Each case jumps to the line of the body it starts at
+                let labels = self.breakable(true, |asm| {
+                    for (i, instruction) in body.iter().enumerate() {
+                        for (jump, _) in case_jumps.iter().filter(|(_, start)| *start == i) {
+                            asm.land(*jump);
+                        }
+                        if *default == Some(i) {
+                            asm.land(default_jump);
+                        }
+                        asm.instruction(instruction);
+                    }
+                });
+                for (jump, _) in case_jumps.iter().filter(|(_, start)| *start >= body.len()) {
+                    self.land(*jump);
+                }
+                if default.map_or(true, |start| start >= body.len()) {
+                    self.land(default_jump);
+                }
+                labels.breaks.into_iter().for_each(|jump| self.land(jump));
+            },
+            InstructionKind::With { target, body } => {
+                self.expr(target);
+                self.ops.push(Op::WithStart);
+                let next = self.jump(Op::WithNext);
+                let breaks = self.loop_body(body, next);
+                self.ops.push(Op::Jump(next));
+                self.land(next);
+                breaks.into_iter().for_each(|jump| self.land(jump));
+                self.ops.push(Op::WithEnd);
+            },
+            InstructionKind::Return { return_type: ReturnType::Break } => match self.breakables.last_mut() {
+                Some(labels) => {
+                    labels.breaks.push(self.ops.len());
+                    self.ops.push(Op::Jump(usize::MAX));
+                },
+                None => self.ops.push(Op::Return(ReturnType::Break)),
+            },
+            InstructionKind::Return { return_type: ReturnType::Continue } => {
+                let jump = self.jump(Op::Jump);
+                self.continue_jump(jump);
+            },
+            InstructionKind::Return { return_type } => self.ops.push(Op::Return(*return_type)),
+            InstructionKind::GlobalVar { fields } => self.ops.push(Op::GlobalVar(fields.clone())),
+            InstructionKind::RuntimeError { error } => self.ops.push(Op::Error(error.clone())),
+        }
+    }
+
+    /// Hands a continue jump to the innermost loop, or turns it into a return if it isn't in one.
+    /// Switches are skipped, as continuing inside a switch continues whatever loop it's in.
+    fn continue_jump(&mut self, jump: usize) {
+        match self.breakables.iter_mut().rev().find(|b| !b.is_switch) {
+            Some(labels) => labels.continues.push(jump),
+            None => self.ops[jump] = Op::Return(ReturnType::Continue),
+        }
+    }
+
+    fn expr(&mut self, node: &Node) {
+        match node {
+            Node::Literal { value } => self.ops.push(Op::Push(value.clone())),
+            Node::Constant { constant_id } => self.ops.push(Op::Constant(*constant_id)),
+            Node::Function { args, function } => {
+                args.iter().for_each(|arg| self.expr(arg));
+                self.ops.push(Op::Call { function: *function, argc: args.len() });
+            },
+            Node::Script { args, script_id } => {
+                args.iter().for_each(|arg| self.expr(arg));
+                self.ops.push(Op::CallScript { script_id: *script_id, argc: args.len() });
+            },
+            Node::Field { accessor } => {
+                let owner = self.owner(&accessor.owner);
+                let indexed = self.array(&accessor.array);
+                self.ops.push(Op::GetField { index: accessor.index, owner, indexed });
+            },
+            Node::Variable { accessor } => {
+                let owner = self.owner(&accessor.owner);
+                let indexed = self.array(&accessor.array);
+                self.ops.push(Op::GetVariable { var: accessor.var, owner, indexed });
+            },
+            Node::Binary { left, right, operator } => {
+                self.expr(left);
+                self.expr(right);
+                self.ops.push(Op::Binary(*operator));
+            },
+            Node::Unary { child, operator } => {
+                self.expr(child);
+                self.ops.push(Op::Unary(*operator));
+            },
+            Node::RuntimeError { error } => self.ops.push(Op::Error(error.clone())),
+        }
+    }
+
+    fn owner(&mut self, owner: &InstanceIdentifier) -> Owner {
+        match owner {
+            InstanceIdentifier::Unknown => Owner::Unknown,
+            InstanceIdentifier::Own => Owner::Own,
+            InstanceIdentifier::Other => Owner::Other,
+            InstanceIdentifier::Global => Owner::Global,
+            InstanceIdentifier::Local => Owner::Local,
+            InstanceIdentifier::Expression(node) => {
+                // A literal keyword or object ID only has to be converted once instead of every time it's used.
+                // Instance IDs are still looked up before the rest of the op runs, in case that destroys the instance.
+                if let Node::Literal { value } = node.as_ref() {
+                    let id = i32::from(value.clone());
+                    if id < 100_000 {
+                        return Owner::Id(id)
+                    }
+                }
+                self.expr(node);
+                self.ops.push(Op::Target);
+                Owner::Stack
+            },
+        }
+    }
+
+    fn array(&mut self, array: &ArrayAccessor) -> bool {
+        // Literal indices are checked here rather than every time they're used. If they're out of bounds,
+        // the error happens at the same point it would have when checking them at runtime.
+        match array {
+            ArrayAccessor::None => return false,
+            ArrayAccessor::Single(node) => match node.as_ref() {
+                Node::Literal { value } => self.index(array_index(std::slice::from_ref(value))),
+                node => {
+                    self.expr(node);
+                    self.ops.push(Op::ArrayIndex(1));
+                },
+            },
+            ArrayAccessor::Double(node1, node2) => match (node1.as_ref(), node2.as_ref()) {
+                (Node::Literal { value: value1 }, Node::Literal { value: value2 }) => {
+                    self.index(array_index(&[value1.clone(), value2.clone()]))
+                },
+                (node1, node2) => {
+                    self.expr(node1);
+                    self.expr(node2);
+                    self.ops.push(Op::ArrayIndex(2));
+                },
+            },
+        }
+        true
+    }
+
+    fn index(&mut self, index: gml::Result<u32>) {
+        self.ops.push(match index {
+            Ok(index) => Op::Index(index),
+            Err(error) => Op::Error(error),
+        });
+    }
+}
+
+impl fmt::Debug for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, op) in self.ops.iter().enumerate() {
+            writeln!(f, "{:>4}: {:?}", i, op)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gml::Compiler;
+
+    fn assemble(source: &str) -> Program {
+        Program::new(&Compiler::new().compile_instructions(source.as_bytes()).unwrap())
+    }
+
+    fn jump_target(op: &Op) -> Option<usize> {
+        match op {
+            Op::Jump(target) | Op::JumpIfFalse(target) | Op::Case(target) => Some(*target),
+            Op::RepeatCheck(target) | Op::WithNext(target) => Some(*target),
+            _ => None,
+        }
+    }
+
+    /// Counts the plain jumps to an op.
+    fn jumps_to(program: &Program, target: usize) -> usize {
+        program.ops.iter().filter(|op| matches!(op, Op::Jump(t) if *t == target)).count()
+    }
+
+    #[test]
+    fn jumps_land_inside_program() {
+        let program = assemble(
+            "for (i = 0; i < 3; i += 1) {
+                switch (i) { case 0: continue; case 1: break; default: with (all) { if (a) break; else continue } }
+                repeat (2) { while (b) { do { if (c) exit; continue } until (c) } }
+            }",
+        );
+        for (i, op) in program.ops.iter().enumerate() {
+            if let Some(target) = jump_target(op) {
+                assert!(target <= program.ops.len(), "op {} jumps to {}:\n{:?}", i, target, program);
+            }
+        }
+    }
+
+    #[test]
+    fn continue_in_switch_continues_loop() {
+        // Continuing a repeat loop goes back to its check without counting it down
+        let program = assemble("repeat (2) switch (x) { case 1: continue; case 2: break }");
+        let check = program.ops.iter().position(|op| matches!(op, Op::RepeatCheck(_))).unwrap();
+        assert_eq!(jumps_to(&program, check), 2, "{:?}", program);
+
+        let program = assemble("with (all) switch (x) { case 1: continue }");
+        let next = program.ops.iter().position(|op| matches!(op, Op::WithNext(_))).unwrap();
+        assert_eq!(jumps_to(&program, next), 2, "{:?}", program);
+    }
+
+    #[test]
+    fn break_continue_outside_loops() {
+        let returns = |source, return_type| {
+            assemble(source).ops.iter().any(|op| matches!(op, Op::Return(r) if *r == return_type))
+        };
+        assert!(returns("switch (x) { case 1: continue }", ReturnType::Continue));
+        assert!(returns("break", ReturnType::Break));
+        assert!(returns("continue", ReturnType::Continue));
+        assert!(!returns("switch (x) { case 1: break }", ReturnType::Break));
+        assert!(!returns("repeat (2) continue", ReturnType::Continue));
+    }
+
+    #[test]
+    fn cases_land_on_statements() {
+        let program = assemble("switch (x) { case 1: a = 1; case 2: case 3: a = 2; default: a = 3 }");
+        let mut landed = 0;
+        for op in program.ops.iter() {
+            if let Op::Case(target) = op {
+                assert!(matches!(program.ops[*target], Op::Line(_)), "{:?}", program);
+                landed += 1;
+            }
+        }
+        assert_eq!(landed, 3);
+
+        let discard = program.ops.iter().position(|op| matches!(op, Op::Discard)).unwrap();
+        match program.ops[discard + 1] {
+            Op::Jump(default) => assert!(matches!(program.ops[default], Op::Line(_)), "{:?}", program),
+            ref op => panic!("expected a jump to the default case, found {:?}", op),
+        }
+    }
+
+    #[test]
+    fn literal_accessors() {
+        let program = assemble("(3).a[2, 5] = 1; all.b = 2; (100001).c = 3; d[32000] = 4");
+        assert!(program.ops.iter().any(|op| matches!(op, Op::Index(64005))), "{:?}", program);
+        assert!(program.ops.iter().any(|op| matches!(op, Op::SetField { owner: Owner::Id(3), indexed: true, .. })));
+        assert!(program.ops.iter().any(|op| matches!(op, Op::SetField { owner: Owner::Id(gml::ALL), .. })));
+        assert!(!program.ops.iter().any(|op| matches!(op, Op::ArrayIndex(_))), "{:?}", program);
+        assert!(program.ops.iter().any(|op| matches!(op, Op::Error(Error::InvalidArrayIndex(32000)))));
+
+        // Instance IDs are still looked up before the value is worked out
+        let target = program.ops.iter().position(|op| matches!(op, Op::Target)).unwrap();
+        assert!(matches!(program.ops[target - 1], Op::Push(_)), "{:?}", program);
+    }
+}
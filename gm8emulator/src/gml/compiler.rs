@@ -3,10 +3,13 @@ pub mod lexer;
 pub mod mappings;
 pub mod token;
 
+mod optimizer;
+
 use super::{
+    bytecode::Program,
     runtime::{
-        ArrayAccessor, BinaryOperator, FieldAccessor, InstanceIdentifier, Instruction, Node, ReturnType, UnaryOperator,
-        VariableAccessor,
+        ArrayAccessor, BinaryOperator, FieldAccessor, InstanceIdentifier, Instruction, InstructionKind, Node,
+        ReturnType, UnaryOperator, VariableAccessor,
     },
     Value,
 };
@@ -23,6 +26,9 @@ pub struct Compiler {
     /// Table of user-defined constants to IDs
     user_constant_names: HashMap<Box<[u8]>, usize>,
 
+    /// Values of user-defined constants which have been evaluated, by ID
+    user_constant_values: HashMap<usize, Value>,
+
     /// Table of script names to IDs
     script_names: HashMap<Box<[u8]>, usize>,
 
@@ -36,6 +42,7 @@ impl Compiler {
         Self {
             constants: HashMap::new(),
             user_constant_names: HashMap::new(),
+            user_constant_values: HashMap::new(),
             script_names: HashMap::new(),
             fields: Vec::new(),
         }
@@ -72,143 +79,187 @@ impl Compiler {
         self.user_constant_names.insert(name, index);
     }
 
-    /// Compile a GML string into instructions.
-    pub fn compile(&mut self, source: &[u8]) -> Result<Rc<[Instruction]>, ast::Error> {
+    /// Records the value a user-defined constant evaluated to, so code compiled from now on can use it directly.
+    pub fn set_user_constant_value(&mut self, index: usize, value: Value) {
+        self.user_constant_values.insert(index, value);
+    }
+
+    /// Compile a GML string into bytecode.
+    pub fn compile(&mut self, source: &[u8]) -> Result<Rc<Program>, ast::Error> {
+        Ok(Rc::new(Program::new(&self.compile_instructions(source)?)))
+    }
+
+    /// Compile a GML string into instruction trees, as they are before being lowered to bytecode.
+    pub fn compile_instructions(&mut self, source: &[u8]) -> Result<Vec<Instruction>, ast::Error> {
         let ast = ast::AST::new(source)?;
 
         let mut instructions = Vec::new();
         let mut locals: Vec<&[u8]> = Vec::new();
         for node in ast.iter() {
-            self.compile_ast_line(node, &mut instructions, &mut locals);
+            self.compile_ast_line(node, 1, &mut instructions, &mut locals);
         }
-        Ok(instructions.into())
+        Ok(self.optimize_body(instructions))
     }
 
     /// Compile an expression into a format which can be evaluated.
     pub fn compile_expression(&mut self, source: &[u8]) -> Result<Node, ast::Error> {
         let expr = ast::AST::expression(source)?;
-        Ok(self.compile_ast_expr(&expr, &[]))
+        let node = self.compile_ast_expr(&expr, &[]);
+        Ok(self.optimize_node(node))
     }
 
     /// Compile a single line of code from an AST expression.
-    fn compile_ast_line<'a>(&mut self, line: &'a ast::Expr, output: &mut Vec<Instruction>, locals: &mut Vec<&'a [u8]>) {
+    fn compile_ast_line<'a>(
+        &mut self,
+        line: &'a ast::Expr,
+        line_number: usize,
+        output: &mut Vec<Instruction>,
+        locals: &mut Vec<&'a [u8]>,
+    ) {
         match line {
+            // Line number tag, which is given to everything compiled from the line
+            ast::Expr::Line(line) => self.compile_ast_line(&line.expr, line.line, output, locals),
+
             // Line of code identified by an assignment operator
             ast::Expr::Binary(binary_expr) => {
-                output.push(self.binary_to_instruction(binary_expr.as_ref(), &locals));
+                output.push(Instruction {
+                    kind: self.binary_to_instruction(binary_expr.as_ref(), &locals),
+                    line: line_number,
+                });
             },
 
             // Break
             ast::Expr::Break => {
-                output.push(Instruction::Return { return_type: ReturnType::Break });
+                output.push(Instruction {
+                    kind: InstructionKind::Return { return_type: ReturnType::Break },
+                    line: line_number,
+                });
             },
 
             // Continue
             ast::Expr::Continue => {
-                output.push(Instruction::Return { return_type: ReturnType::Continue });
+                output.push(Instruction {
+                    kind: InstructionKind::Return { return_type: ReturnType::Continue },
+                    line: line_number,
+                });
             },
 
             // Exit
             ast::Expr::Exit => {
-                output.push(Instruction::Return { return_type: ReturnType::Exit });
+                output.push(Instruction {
+                    kind: InstructionKind::Return { return_type: ReturnType::Exit },
+                    line: line_number,
+                });
             },
 
             // For loop
             ast::Expr::For(for_expr) => {
-                self.compile_ast_line(&for_expr.start, output, locals);
+                self.compile_ast_line(&for_expr.start, line_number, output, locals);
                 let cond = self.compile_ast_expr(&for_expr.cond, locals);
                 let mut body = Vec::new();
-                self.compile_ast_line(&for_expr.body, &mut body, locals);
+                self.compile_ast_line(&for_expr.body, line_number, &mut body, locals);
                 let mut step = Vec::new();
-                self.compile_ast_line(&for_expr.step, &mut step, locals);
-                output.push(Instruction::LoopFor {
-                    cond,
-                    body: body.into_boxed_slice(),
-                    step: step.into_boxed_slice(),
+                self.compile_ast_line(&for_expr.step, line_number, &mut step, locals);
+                output.push(Instruction {
+                    kind: InstructionKind::LoopFor {
+                        cond,
+                        body: body.into_boxed_slice(),
+                        step: step.into_boxed_slice(),
+                    },
+                    line: line_number,
                 });
             },
 
             // Function or Script
             f @ ast::Expr::Function(_) => {
-                output.push(Instruction::EvalExpression { node: self.compile_ast_expr(f, locals) });
+                output.push(Instruction {
+                    kind: InstructionKind::EvalExpression { node: self.compile_ast_expr(f, locals) },
+                    line: line_number,
+                });
             },
 
             // Group of expressions
             ast::Expr::Group(group) => {
                 for expr in group {
-                    self.compile_ast_line(expr, output, locals);
+                    self.compile_ast_line(expr, line_number, output, locals);
                 }
             },
 
             // If/else body
             ast::Expr::If(if_expr) => {
                 let cond = self.compile_ast_expr(&if_expr.cond, locals);
-                if let Node::Literal { value: v } = cond {
-                    // The "if" condition is constant, so we can optimize this away
-                    if v.is_truthy() {
-                        self.compile_ast_line(&if_expr.body, output, locals);
-                    } else if let Some(expr_else_body) = &if_expr.else_body {
-                        self.compile_ast_line(expr_else_body, output, locals);
-                    }
-                } else {
-                    let mut if_body = Vec::new();
-                    self.compile_ast_line(&if_expr.body, &mut if_body, locals);
-                    let mut else_body = Vec::new();
-                    if let Some(expr_else_body) = &if_expr.else_body {
-                        self.compile_ast_line(expr_else_body, &mut else_body, locals);
-                    }
-                    output.push(Instruction::IfElse {
+                let mut if_body = Vec::new();
+                self.compile_ast_line(&if_expr.body, line_number, &mut if_body, locals);
+                let mut else_body = Vec::new();
+                if let Some(expr_else_body) = &if_expr.else_body {
+                    self.compile_ast_line(expr_else_body, line_number, &mut else_body, locals);
+                }
+                output.push(Instruction {
+                    kind: InstructionKind::IfElse {
                         cond,
                         if_body: if_body.into_boxed_slice(),
                         else_body: else_body.into_boxed_slice(),
-                    });
-                }
+                    },
+                    line: line_number,
+                });
             },
 
             // "repeat" block
             ast::Expr::Repeat(repeat_expr) => {
                 let count = self.compile_ast_expr(&repeat_expr.count, locals);
                 let mut body = Vec::new();
-                self.compile_ast_line(&repeat_expr.body, &mut body, locals);
-                output.push(Instruction::Repeat { count, body: body.into_boxed_slice() });
+                self.compile_ast_line(&repeat_expr.body, line_number, &mut body, locals);
+                output.push(Instruction {
+                    kind: InstructionKind::Repeat { count, body: body.into_boxed_slice() },
+                    line: line_number,
+                });
             },
 
             // Return
             ast::Expr::Return(expr) => {
                 let value = self.compile_ast_expr(&expr, locals);
-                output.push(Instruction::SetReturnValue { value });
-                output.push(Instruction::Return { return_type: ReturnType::Exit });
+                output.push(Instruction { kind: InstructionKind::SetReturnValue { value }, line: line_number });
+                output.push(Instruction {
+                    kind: InstructionKind::Return { return_type: ReturnType::Exit },
+                    line: line_number,
+                });
             },
 
             // "switch" block
             ast::Expr::Switch(switch_expr) => {
                 let input = self.compile_ast_expr(&switch_expr.input, locals);
-                if let ast::Expr::Group(group) = &switch_expr.body {
+                if let ast::Expr::Group(group) = switch_expr.body.without_line() {
                     let mut cases = Vec::new();
                     let mut body = Vec::new();
                     let mut default: Option<usize> = None;
                     for expr in group {
-                        if let ast::Expr::Case(case_expr) = expr {
+                        if let ast::Expr::Case(case_expr) = expr.without_line() {
                             if default.is_none() {
                                 cases.push((self.compile_ast_expr(case_expr, locals), body.len()));
                             }
-                        } else if let ast::Expr::Default = expr {
+                        } else if let ast::Expr::Default = expr.without_line() {
                             if default.is_none() {
                                 default = Some(body.len());
                             }
                         } else {
-                            self.compile_ast_line(expr, &mut body, locals);
+                            self.compile_ast_line(expr, line_number, &mut body, locals);
                         }
                     }
-                    output.push(Instruction::Switch {
-                        input,
-                        cases: cases.into_boxed_slice(),
-                        default,
-                        body: body.into_boxed_slice(),
+                    output.push(Instruction {
+                        kind: InstructionKind::Switch {
+                            input,
+                            cases: cases.into_boxed_slice(),
+                            default,
+                            body: body.into_boxed_slice(),
+                        },
+                        line: line_number,
                     });
                 } else {
-                    output.push(Instruction::RuntimeError {
-                        error: gml::Error::InvalidSwitchBody(switch_expr.body.to_string()),
+                    output.push(Instruction {
+                        kind: InstructionKind::RuntimeError {
+                            error: gml::Error::InvalidSwitchBody(switch_expr.body.to_string()),
+                        },
+                        line: line_number,
                     });
                 }
             },
@@ -217,8 +268,11 @@ impl Compiler {
             ast::Expr::DoUntil(while_expr) => {
                 let cond = self.compile_ast_expr(&while_expr.cond, locals);
                 let mut body = Vec::new();
-                self.compile_ast_line(&while_expr.body, &mut body, locals);
-                output.push(Instruction::LoopUntil { cond, body: body.into_boxed_slice() });
+                self.compile_ast_line(&while_expr.body, line_number, &mut body, locals);
+                output.push(Instruction {
+                    kind: InstructionKind::LoopUntil { cond, body: body.into_boxed_slice() },
+                    line: line_number,
+                });
             },
 
             // "var" declaration
@@ -229,28 +283,37 @@ impl Compiler {
             ast::Expr::GlobalVar(globalvar_expr) => {
                 // globalvar doesn't work on builtins
                 let fields = globalvar_expr.vars.iter().map(|x| self.get_field_id(x)).collect();
-                output.push(Instruction::GlobalVar { fields });
+                output.push(Instruction { kind: InstructionKind::GlobalVar { fields }, line: line_number });
             },
 
             // "while" block
             ast::Expr::While(while_expr) => {
                 let cond = self.compile_ast_expr(&while_expr.cond, locals);
                 let mut body = Vec::new();
-                self.compile_ast_line(&while_expr.body, &mut body, locals);
-                output.push(Instruction::LoopWhile { cond, body: body.into_boxed_slice() });
+                self.compile_ast_line(&while_expr.body, line_number, &mut body, locals);
+                output.push(Instruction {
+                    kind: InstructionKind::LoopWhile { cond, body: body.into_boxed_slice() },
+                    line: line_number,
+                });
             },
 
             // "with" block
             ast::Expr::With(with_expr) => {
                 let target = self.compile_ast_expr(&with_expr.target, locals);
                 let mut body = Vec::new();
-                self.compile_ast_line(&with_expr.body, &mut body, locals);
-                output.push(Instruction::With { target, body: body.into_boxed_slice() });
+                self.compile_ast_line(&with_expr.body, line_number, &mut body, locals);
+                output.push(Instruction {
+                    kind: InstructionKind::With { target, body: body.into_boxed_slice() },
+                    line: line_number,
+                });
             },
 
             // Unknown/invalid AST
             _ => {
-                output.push(Instruction::RuntimeError { error: gml::Error::UnexpectedASTExpr(line.to_string()) });
+                output.push(Instruction {
+                    kind: InstructionKind::RuntimeError { error: gml::Error::UnexpectedASTExpr(line.to_string()) },
+                    line: line_number,
+                });
             },
         }
     }
@@ -347,18 +410,7 @@ impl Compiler {
 
                     let left = self.compile_ast_expr(&binary_expr.left, locals);
                     let right = self.compile_ast_expr(&binary_expr.right, locals);
-
-                    match (left, right) {
-                        (Node::Literal { value: lhs @ _ }, Node::Literal { value: rhs @ _ }) => {
-                            match op_function.call(lhs, rhs) {
-                                Ok(value) => Node::Literal { value },
-                                Err(error) => Node::RuntimeError { error },
-                            }
-                        },
-                        (left, right) => {
-                            Node::Binary { left: Box::new(left), right: Box::new(right), operator: op_function }
-                        },
-                    }
+                    Node::Binary { left: Box::new(left), right: Box::new(right), operator: op_function }
                 },
             },
 
@@ -402,13 +454,7 @@ impl Compiler {
                     Operator::Complement => UnaryOperator::Complement,
                     _ => return Node::RuntimeError { error: gml::Error::InvalidUnaryOperator(unary_expr.op) },
                 };
-                match new_node {
-                    Node::Literal { value } => match operator.call(value) {
-                        Ok(value) => Node::Literal { value },
-                        Err(error) => Node::RuntimeError { error },
-                    },
-                    node => Node::Unary { child: Box::new(node), operator },
-                }
+                Node::Unary { child: Box::new(new_node), operator }
             },
 
             _ => Node::RuntimeError { error: gml::Error::UnexpectedASTExpr(expr.to_string()) },
@@ -432,7 +478,7 @@ impl Compiler {
     }
 
     /// Converts an AST BinaryExpr to an Instruction.
-    fn binary_to_instruction(&mut self, binary_expr: &ast::BinaryExpr, locals: &[&[u8]]) -> Instruction {
+    fn binary_to_instruction(&mut self, binary_expr: &ast::BinaryExpr, locals: &[&[u8]]) -> InstructionKind {
         let modification_type = match binary_expr.op {
             Operator::Assign => None,
             Operator::AssignAdd => Some(BinaryOperator::Add),
@@ -463,14 +509,16 @@ impl Compiler {
                         self.make_set_instruction(string, Some(owner), ArrayAccessor::None, value, locals)
                     }
                 } else {
-                    Instruction::RuntimeError { error: gml::Error::InvalidDeref(binary_expr.right.to_string()) }
+                    InstructionKind::RuntimeError { error: gml::Error::InvalidDeref(binary_expr.right.to_string()) }
                 }
             },
             ast::Expr::Binary(binary_expr) if binary_expr.op == Operator::Index => {
                 if let ast::Expr::Group(dimensions) = &binary_expr.right {
                     let accessor = match self.make_array_accessor(dimensions, locals) {
                         Ok(a) => a,
-                        Err(e) => return Instruction::RuntimeError { error: gml::Error::TooManyArrayDimensions(e) },
+                        Err(e) => {
+                            return InstructionKind::RuntimeError { error: gml::Error::TooManyArrayDimensions(e) }
+                        },
                     };
                     match &binary_expr.left {
                         ast::Expr::LiteralIdentifier(string) => {
@@ -489,20 +537,20 @@ impl Compiler {
                                     self.make_set_instruction(string, Some(owner), accessor, value, locals)
                                 }
                             } else {
-                                Instruction::RuntimeError {
+                                InstructionKind::RuntimeError {
                                     error: gml::Error::InvalidDeref(binary_expr.right.to_string()),
                                 }
                             }
                         },
-                        _ => Instruction::RuntimeError {
+                        _ => InstructionKind::RuntimeError {
                             error: gml::Error::InvalidIndexLhs(binary_expr.left.to_string()),
                         },
                     }
                 } else {
-                    Instruction::RuntimeError { error: gml::Error::InvalidIndex(binary_expr.right.to_string()) }
+                    InstructionKind::RuntimeError { error: gml::Error::InvalidIndex(binary_expr.right.to_string()) }
                 }
             },
-            _ => Instruction::RuntimeError { error: gml::Error::InvalidAssignment(binary_expr.left.to_string()) },
+            _ => InstructionKind::RuntimeError { error: gml::Error::InvalidAssignment(binary_expr.left.to_string()) },
         }
     }
 
@@ -543,7 +591,7 @@ impl Compiler {
         array: ArrayAccessor,
         value: Node,
         locals: &[&[u8]],
-    ) -> Instruction {
+    ) -> InstructionKind {
         let owner = match owner {
             Some(o) => o,
             None => {
@@ -556,10 +604,10 @@ impl Compiler {
         };
 
         if let Some(var) = mappings::get_instance_variable_by_name(identifier) {
-            Instruction::SetVariable { accessor: VariableAccessor { var: *var, array, owner }, value }
+            InstructionKind::SetVariable { accessor: VariableAccessor { var: *var, array, owner }, value }
         } else {
             let index = self.get_field_id(identifier);
-            Instruction::SetField { accessor: FieldAccessor { index, array, owner }, value }
+            InstructionKind::SetField { accessor: FieldAccessor { index, array, owner }, value }
         }
     }
 
@@ -573,7 +621,7 @@ impl Compiler {
         operator: BinaryOperator,
         value: Node,
         locals: &[&[u8]],
-    ) -> Instruction {
+    ) -> InstructionKind {
         let owner = match owner {
             Some(o) => o,
             None => {
@@ -586,7 +634,7 @@ impl Compiler {
         };
 
         if let Some(var) = mappings::get_instance_variable_by_name(identifier) {
-            Instruction::SetVariable {
+            InstructionKind::SetVariable {
                 accessor: VariableAccessor { var: *var, array: array.clone(), owner: owner.clone() },
                 value: Node::Binary {
                     left: Box::new(Node::Variable { accessor: VariableAccessor { var: *var, array, owner } }),
@@ -596,7 +644,7 @@ impl Compiler {
             }
         } else {
             let index = self.get_field_id(identifier);
-            Instruction::SetField {
+            InstructionKind::SetField {
                 accessor: FieldAccessor { index, array: array.clone(), owner: owner.clone() },
                 value: Node::Binary {
                     left: Box::new(Node::Field { accessor: FieldAccessor { index, array, owner } }),
@@ -609,18 +657,7 @@ impl Compiler {
 
     /// Converts an AST node to an InstanceIdentifier.
     fn make_instance_identifier(&mut self, expression: &ast::Expr, locals: &[&[u8]]) -> InstanceIdentifier {
-        let node = self.compile_ast_expr(expression, locals);
-        if let Node::Literal { value: v @ Value::Real(_) } = &node {
-            match v.round() {
-                gml::SELF | gml::SELF2 => InstanceIdentifier::Own,
-                gml::OTHER => InstanceIdentifier::Other,
-                gml::GLOBAL => InstanceIdentifier::Global,
-                gml::LOCAL => InstanceIdentifier::Local,
-                _ => InstanceIdentifier::Expression(Box::new(node)),
-            }
-        } else {
-            InstanceIdentifier::Expression(Box::new(node))
-        }
+        InstanceIdentifier::Expression(Box::new(self.compile_ast_expr(expression, locals)))
     }
 
     /// Converts a list of expressions into an array accessor (or an error message).
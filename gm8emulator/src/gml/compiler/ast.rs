@@ -5,7 +5,7 @@ use super::{
 
 use std::{
     error, fmt,
-    iter::{IntoIterator, Peekable},
+    iter::IntoIterator,
     ops::{Deref, DerefMut},
 };
 
@@ -40,6 +40,15 @@ pub enum Expr<'a> {
     Break,
     Exit,
     Return(Box<Expr<'a>>),
+
+    Line(Box<LineExpr<'a>>),
+}
+
+/// A line of code, tagged with the line number in the source where it starts.
+#[derive(Debug, PartialEq)]
+pub struct LineExpr<'a> {
+    pub line: usize,
+    pub expr: Expr<'a>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -128,6 +137,16 @@ impl Error {
     }
 }
 
+impl<'a> Expr<'a> {
+    /// Returns this expression without its line number tag, if it has one.
+    pub fn without_line(&self) -> &Self {
+        match self {
+            Expr::Line(line) => line.expr.without_line(),
+            expr => expr,
+        }
+    }
+}
+
 impl<'a> fmt::Display for Expr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -188,6 +207,8 @@ impl<'a> fmt::Display for Expr<'a> {
             Expr::Break => write!(f, "(break)"),
             Expr::Exit => write!(f, "(exit)"),
             Expr::Return(e) => write!(f, "(return {})", e),
+
+            Expr::Line(line) => write!(f, "{}", line.expr),
         }
     }
 }
@@ -251,7 +272,7 @@ impl<'a> IntoIterator for AST<'a> {
 
 impl<'a> AST<'a> {
     pub fn new(source: &'a [u8]) -> Result<Self, Error> {
-        let mut lex = Lexer::new(source).peekable();
+        let mut lex = Lexer::new(source);
         let mut expressions = Vec::new();
 
         loop {
@@ -267,12 +288,12 @@ impl<'a> AST<'a> {
     }
 
     pub fn expression(source: &'a [u8]) -> Result<Expr<'a>, Error> {
-        let mut lex = Lexer::new(source).peekable();
+        let mut lex = Lexer::new(source);
         let expr = AST::read_binary_tree(&mut lex, None, false)?;
         Ok(expr)
     }
 
-    fn read_line(lex: &mut Peekable<Lexer<'a>>) -> Result<Option<Expr<'a>>, Error> {
+    fn read_line(lex: &mut Lexer<'a>) -> Result<Option<Expr<'a>>, Error> {
         let token = loop {
             match lex.next() {
                 Some(Token::Separator(Separator::Semicolon)) => continue,
@@ -280,6 +301,7 @@ impl<'a> AST<'a> {
                 None => return Ok(None), // EOF
             }
         };
+        let line = lex.line();
 
         // Use token type to determine what logic we should apply here
         let ret = match token {
@@ -507,11 +529,11 @@ impl<'a> AST<'a> {
             lex.next();
         }
 
-        ret
+        ret.map(|expr| expr.map(|expr| Expr::Line(Box::new(LineExpr { line, expr }))))
     }
 
     fn read_binary_tree(
-        lex: &mut Peekable<Lexer<'a>>,
+        lex: &mut Lexer<'a>,
         first_token: Option<Token<'a>>, // Sometimes we've already parsed the first token, so it should be put here.
         expect_assignment: bool,        // Do we expect the first op to be an assignment?
     ) -> Result<Expr<'a>, Error> {
@@ -524,7 +546,7 @@ impl<'a> AST<'a> {
     }
 
     fn read_binary_tree_recursive(
-        lex: &mut Peekable<Lexer<'a>>,
+        lex: &mut Lexer<'a>,
         first_token: Option<Token<'a>>, // Sometimes we've already parsed the first token, so it should be put here.
         expect_assignment: bool,        // Do we expect the first op to be an assignment?
         lowest_prec: u8,                // We are not allowed to go below this operator precedence in this tree.
@@ -615,7 +637,7 @@ impl<'a> AST<'a> {
         }
     }
 
-    fn read_btree_expression(lex: &mut Peekable<Lexer<'a>>, first_token: Option<Token<'a>>) -> Result<Expr<'a>, Error> {
+    fn read_btree_expression(lex: &mut Lexer<'a>, first_token: Option<Token<'a>>) -> Result<Expr<'a>, Error> {
         // Get first token and match it
         let mut lhs = match if first_token.is_some() { first_token } else { lex.next() } {
             Some(Token::Separator(ref sep)) if *sep == Separator::ParenLeft => {
@@ -705,7 +727,7 @@ impl<'a> AST<'a> {
         Ok(lhs)
     }
 
-    fn read_function_call(lex: &mut Peekable<Lexer<'a>>, function_name: &'a [u8]) -> Result<Expr<'a>, Error> {
+    fn read_function_call(lex: &mut Lexer<'a>, function_name: &'a [u8]) -> Result<Expr<'a>, Error> {
         expect_token!(lex.next(), Separator(Separator::ParenLeft));
 
         let mut params = Vec::new();
@@ -773,18 +795,79 @@ impl<'a> AST<'a> {
 mod tests {
     use super::*;
 
-    /// Helper function for all the AST testcases.
+    /// Helper function for all the AST testcases. Line numbers are checked separately, so they're stripped here.
     fn assert_ast(input: &str, expected_output: Option<Vec<Expr>>) {
         match AST::new(input.as_bytes()) {
             Ok(ast) => {
                 if let Some(e) = expected_output {
-                    assert_eq!(*ast, e);
+                    assert_eq!(ast.0.into_iter().map(strip_lines).collect::<Vec<_>>(), e);
                 }
             },
             Err(e) => panic!("AST test encountered error: '{}' for input: {}", e, input),
         }
     }
 
+    /// Removes all the line number tags from a line of code.
+    fn strip_lines(expr: Expr) -> Expr {
+        match expr {
+            Expr::Line(line) => strip_lines(line.expr),
+            Expr::DoUntil(x) => {
+                let DoUntilExpr { cond, body } = *x;
+                Expr::DoUntil(Box::new(DoUntilExpr { cond, body: strip_lines(body) }))
+            },
+            Expr::For(x) => {
+                let ForExpr { start, cond, step, body } = *x;
+                let (start, step, body) = (strip_lines(start), strip_lines(step), strip_lines(body));
+                Expr::For(Box::new(ForExpr { start, cond, step, body }))
+            },
+            Expr::Group(group) => Expr::Group(group.into_iter().map(strip_lines).collect()),
+            Expr::If(x) => {
+                let IfExpr { cond, body, else_body } = *x;
+                let (body, else_body) = (strip_lines(body), else_body.map(strip_lines));
+                Expr::If(Box::new(IfExpr { cond, body, else_body }))
+            },
+            Expr::Repeat(x) => {
+                let RepeatExpr { count, body } = *x;
+                Expr::Repeat(Box::new(RepeatExpr { count, body: strip_lines(body) }))
+            },
+            Expr::Switch(x) => {
+                let SwitchExpr { input, body } = *x;
+                Expr::Switch(Box::new(SwitchExpr { input, body: strip_lines(body) }))
+            },
+            Expr::With(x) => {
+                let WithExpr { target, body } = *x;
+                Expr::With(Box::new(WithExpr { target, body: strip_lines(body) }))
+            },
+            Expr::While(x) => {
+                let WhileExpr { cond, body } = *x;
+                Expr::While(Box::new(WhileExpr { cond, body: strip_lines(body) }))
+            },
+            x => x,
+        }
+    }
+
+    #[test]
+    fn line_numbers() {
+        let ast = AST::new(b"a = 1;\n\n/* two\nlines */ b = \"x\ny\"\nif c\n{\n    d()\n}").unwrap();
+        let lines = |expr: &Expr| match expr {
+            Expr::Line(line) => line.line,
+            _ => panic!("expected a line, got {}", expr),
+        };
+        assert_eq!(ast.iter().map(lines).collect::<Vec<_>>(), vec![1, 4, 6]);
+        if let Expr::Line(line) = &ast[2] {
+            if let Expr::If(if_expr) = &line.expr {
+                assert_eq!(lines(&if_expr.body), 7);
+                if let Expr::Line(group) = &if_expr.body {
+                    if let Expr::Group(group) = &group.expr {
+                        assert_eq!(lines(&group[0]), 8);
+                        return
+                    }
+                }
+            }
+        }
+        panic!("unexpected AST {:?}", ast);
+    }
+
     #[test]
     fn nothing() {
         // Empty string
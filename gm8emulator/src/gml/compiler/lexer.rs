@@ -12,7 +12,15 @@ pub struct Lexer<'a> {
     /// GML source code to return references to.
     src: &'a [u8],
 
-    line_hint: usize,
+    /// Line number of the last token returned by next().
+    line: usize,
+
+    /// Line number at scan_pos, which is how far newlines have been counted up to.
+    scan_line: usize,
+    scan_pos: usize,
+
+    /// A token which has been peeked but not returned yet, along with its line number.
+    peeked: Option<Option<(Token<'a>, usize)>>,
 
     /// Iterator over the source code as raw bytes.
     iter: Peekable<Enumerate<Copied<slice::Iter<'a, u8>>>>,
@@ -21,38 +29,44 @@ pub struct Lexer<'a> {
 impl<'a> Lexer<'a> {
     /// Creates a new Lexer over GML source code.
     pub fn new(src: &'a [u8]) -> Self {
-        Lexer { src, line_hint: 1, iter: src.iter().copied().enumerate().peekable() }
+        Lexer {
+            src,
+            line: 1,
+            scan_line: 1,
+            scan_pos: 0,
+            peeked: None,
+            iter: src.iter().copied().enumerate().peekable(),
+        }
     }
 
-    /// Returns the current line number in the source code.
+    /// Returns the line number in the source code of the last token returned by next().
     pub fn line(&self) -> usize {
-        self.line_hint
+        self.line
+    }
+
+    /// Returns a reference to the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&Token<'a>> {
+        if self.peeked.is_none() {
+            let token = self.read_token();
+            self.peeked = Some(token.map(|t| (t, self.scan_line)));
+        }
+        self.peeked.as_ref().and_then(|x| x.as_ref()).map(|(token, _)| token)
     }
 
     /// Fast-forwards the internal iterator to the next token, skipping over whitespace.
-    /// Returns how many lines (LF) were skipped in the process.
-    fn fast_forward(&mut self) -> usize {
-        let mut lines_skipped: usize = 0;
-        loop {
-            match self.iter.peek() {
-                Some(&(_, ch)) if ch <= b' ' => {
-                    if ch == b'\n' {
-                        lines_skipped += 1;
-                    }
-                    self.iter.next();
-                },
-                _ => break lines_skipped,
+    fn fast_forward(&mut self) {
+        while let Some(&(_, ch)) = self.iter.peek() {
+            if ch > b' ' {
+                break
             }
+            self.iter.next();
         }
     }
-}
-
-impl<'a> Iterator for Lexer<'a> {
-    type Item = Token<'a>;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Reads the next token from the source code, keeping count of which line it's on.
+    fn read_token(&mut self) -> Option<Token<'a>> {
         // locate next token
-        self.line_hint = self.fast_forward();
+        self.fast_forward();
 
         /// Helper function to reconstruct our byte slices to a string easily.
         /// This is fine since we operate on something that is a &str in a first place,
@@ -63,6 +77,8 @@ impl<'a> Iterator for Lexer<'a> {
         }
 
         let head = *self.iter.peek()?;
+        self.scan_line += self.src[self.scan_pos..head.0].iter().filter(|&&ch| ch == b'\n').count();
+        self.scan_pos = head.0;
 
         #[allow(clippy::match_overlapping_arm)] // quotes overlap with the catch-all ASCII
         Some(match head.1 {
@@ -288,7 +304,7 @@ impl<'a> Iterator for Lexer<'a> {
                                         },
                                     }
                                 }
-                                return self.next()
+                                return self.read_token()
                             },
 
                             _ => return Some(Token::Operator(op)),
@@ -338,7 +354,7 @@ impl<'a> Iterator for Lexer<'a> {
                                 },
                             }
                         }
-                        return self.next()
+                        return self.read_token()
                     } else if op == Operator::LessThan && ch2 == b'>' {
                         // <> is the same as != (let's call it a diamond)
 
@@ -370,4 +386,23 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(Some((token, line))) => {
+                self.line = line;
+                Some(token)
+            },
+            Some(None) => None,
+            None => {
+                let token = self.read_token()?;
+                self.line = self.scan_line;
+                Some(token)
+            },
+        }
+    }
+}
+
 // The lexer is intrinsically tested via the AST tests.
@@ -328,7 +328,7 @@ pub mod constants {
 }
 
 /// Enum of all kernel functions
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Function {
     DisplayGetWidth,
     DisplayGetHeight,
@@ -2902,6 +2902,9 @@ impl Function {
     }
 }
 
+// Function::arg_count and Function::is_implemented, generated from kernel.rs by the build script
+include!(concat!(env!("OUT_DIR"), "/kernel_info.rs"));
+
 /// Standard numerical constants for GML
 pub const CONSTANTS: [(&str, f64); 317] = [
     ("ANSI_CHARSET", constants::ANSI_CHARSET),
@@ -4682,3 +4685,18 @@ pub const FUNCTIONS: [(&str, Function, bool); 1281] = [
     ("d3d_model_wall", Function::D3DModelWall, false),
     ("d3d_model_floor", Function::D3DModelFloor, false),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_info() {
+        assert_eq!(Function::DrawSprite.arg_count(), Some(4));
+        assert_eq!(Function::Random.arg_count(), Some(1));
+        assert_eq!(Function::Choose.arg_count(), None);
+        assert_eq!(Function::MouseWait.arg_count(), Some(0));
+        assert!(Function::DrawSprite.is_implemented());
+        assert!(!Function::MouseWait.is_implemented());
+    }
+}
@@ -0,0 +1,305 @@
+//! Optimization pass, run over compiled code before it's assembled into bytecode.
+//!
+//! Nothing here may change what the code does when it runs. Only expressions without side effects are
+//! ever folded or thrown away, nothing is reordered, and folded values are worked out by the same operators
+//! the runtime uses so they come out exactly the same. An operation which would fail at runtime is folded
+//! into an error node, which fails the same way when it's reached.
+
+use super::Compiler;
+use crate::gml::{
+    self,
+    runtime::{
+        ArrayAccessor, FieldAccessor, InstanceIdentifier, Instruction, InstructionKind, Node, ReturnType,
+        VariableAccessor,
+    },
+    Value,
+};
+
+impl Compiler {
+    /// Optimizes a block of compiled code.
+    pub(super) fn optimize_body(&self, body: impl IntoIterator<Item = Instruction>) -> Vec<Instruction> {
+        let mut output = Vec::new();
+        for instruction in body {
+            self.optimize_instruction(instruction, &mut output);
+        }
+        output
+    }
+
+    /// Optimizes a compiled instruction, adding whatever it turns into to the output.
+    fn optimize_instruction(&self, instruction: Instruction, output: &mut Vec<Instruction>) {
+        let kind = match instruction.kind {
+            InstructionKind::SetField { accessor, value } => {
+                InstructionKind::SetField { accessor: self.optimize_field(accessor), value: self.optimize_node(value) }
+            },
+            InstructionKind::SetVariable { accessor, value } => InstructionKind::SetVariable {
+                accessor: self.optimize_variable(accessor),
+                value: self.optimize_node(value),
+            },
+            InstructionKind::EvalExpression { node } => {
+                InstructionKind::EvalExpression { node: self.optimize_node(node) }
+            },
+            InstructionKind::IfElse { cond, if_body, else_body } => match self.optimize_node(cond) {
+                Node::Literal { value } => {
+                    // The condition is constant, so only one of the branches can ever run
+                    let body = if value.is_truthy() { if_body } else { else_body };
+                    output.extend(self.optimize_body(body.into_vec()));
+                    return
+                },
+                cond => InstructionKind::IfElse {
+                    cond,
+                    if_body: self.optimize_body(if_body.into_vec()).into_boxed_slice(),
+                    else_body: self.optimize_body(else_body.into_vec()).into_boxed_slice(),
+                },
+            },
+            InstructionKind::LoopUntil { cond, body } => InstructionKind::LoopUntil {
+                cond: self.optimize_node(cond),
+                body: self.optimize_body(body.into_vec()).into_boxed_slice(),
+            },
+            InstructionKind::LoopWhile { cond, body } => match self.optimize_node(cond) {
+                Node::Literal { value } if !value.is_truthy() => return,
+                cond => {
+                    InstructionKind::LoopWhile { cond, body: self.optimize_body(body.into_vec()).into_boxed_slice() }
+                },
+            },
+            InstructionKind::LoopFor { cond, body, step } => match self.optimize_node(cond) {
+                Node::Literal { value } if !value.is_truthy() => return,
+                cond => InstructionKind::LoopFor {
+                    cond,
+                    body: self.optimize_body(body.into_vec()).into_boxed_slice(),
+                    step: self.optimize_body(step.into_vec()).into_boxed_slice(),
+                },
+            },
+            InstructionKind::Repeat { count, body } => {
+                let body = self.optimize_body(body.into_vec());
+                match self.optimize_node(count) {
+                    Node::Literal { value } if value.round() <= 0 => return,
+                    Node::Literal { value } if value.round() == 1 && !controls_loop(&body, true) => {
+                        // Running the body once is the same as running it without the loop,
+                        // as long as there's no break or continue in it which would have applied to the loop
+                        output.extend(body);
+                        return
+                    },
+                    count => InstructionKind::Repeat { count, body: body.into_boxed_slice() },
+                }
+            },
+            InstructionKind::SetReturnValue { value } => {
+                InstructionKind::SetReturnValue { value: self.optimize_node(value) }
+            },
+            InstructionKind::Switch { input, cases, default, body } => {
+                // Cases start at an index into the body, which has to be moved to wherever that instruction ends up
+                let body_len = body.len();
+                let mut new_body = Vec::with_capacity(body_len);
+                let mut starts = Vec::with_capacity(body_len + 1);
+                for instruction in body.into_vec() {
+                    starts.push(new_body.len());
+                    self.optimize_instruction(instruction, &mut new_body);
+                }
+                starts.push(new_body.len());
+                let start = |index: usize| starts[index.min(body_len)];
+                InstructionKind::Switch {
+                    input: self.optimize_node(input),
+                    cases: cases
+                        .into_vec()
+                        .into_iter()
+                        .map(|(cond, index)| (self.optimize_node(cond), start(index)))
+                        .collect::<Vec<_>>()
+                        .into_boxed_slice(),
+                    default: default.map(start),
+                    body: new_body.into_boxed_slice(),
+                }
+            },
+            InstructionKind::With { target, body } => InstructionKind::With {
+                target: self.optimize_node(target),
+                body: self.optimize_body(body.into_vec()).into_boxed_slice(),
+            },
+            kind => kind,
+        };
+        output.push(Instruction { kind, line: instruction.line });
+    }
+
+    /// Optimizes a compiled expression, folding anything which can be worked out before it runs.
+    pub(super) fn optimize_node(&self, node: Node) -> Node {
+        match node {
+            Node::Constant { constant_id } => match self.user_constant_values.get(&constant_id) {
+                Some(value) => Node::Literal { value: value.clone() },
+                None => Node::Constant { constant_id },
+            },
+            Node::Function { args, function } => Node::Function { args: self.optimize_nodes(args), function },
+            Node::Script { args, script_id } => Node::Script { args: self.optimize_nodes(args), script_id },
+            Node::Field { accessor } => Node::Field { accessor: self.optimize_field(accessor) },
+            Node::Variable { accessor } => Node::Variable { accessor: self.optimize_variable(accessor) },
+            Node::Binary { left, right, operator } => match (self.optimize_node(*left), self.optimize_node(*right)) {
+                (Node::Literal { value: lhs }, Node::Literal { value: rhs }) => match operator.call(lhs, rhs) {
+                    Ok(value) => Node::Literal { value },
+                    Err(error) => Node::RuntimeError { error },
+                },
+                (left, right) => Node::Binary { left: Box::new(left), right: Box::new(right), operator },
+            },
+            Node::Unary { child, operator } => match self.optimize_node(*child) {
+                Node::Literal { value } => match operator.call(value) {
+                    Ok(value) => Node::Literal { value },
+                    Err(error) => Node::RuntimeError { error },
+                },
+                child => Node::Unary { child: Box::new(child), operator },
+            },
+            node => node,
+        }
+    }
+
+    fn optimize_nodes(&self, nodes: Box<[Node]>) -> Box<[Node]> {
+        nodes.into_vec().into_iter().map(|node| self.optimize_node(node)).collect::<Vec<_>>().into_boxed_slice()
+    }
+
+    fn optimize_field(&self, accessor: FieldAccessor) -> FieldAccessor {
+        FieldAccessor {
+            index: accessor.index,
+            array: self.optimize_array(accessor.array),
+            owner: self.optimize_owner(accessor.owner),
+        }
+    }
+
+    fn optimize_variable(&self, accessor: VariableAccessor) -> VariableAccessor {
+        VariableAccessor {
+            var: accessor.var,
+            array: self.optimize_array(accessor.array),
+            owner: self.optimize_owner(accessor.owner),
+        }
+    }
+
+    fn optimize_array(&self, array: ArrayAccessor) -> ArrayAccessor {
+        match array {
+            ArrayAccessor::None => ArrayAccessor::None,
+            ArrayAccessor::Single(node) => ArrayAccessor::Single(Box::new(self.optimize_node(*node))),
+            ArrayAccessor::Double(node1, node2) => {
+                ArrayAccessor::Double(Box::new(self.optimize_node(*node1)), Box::new(self.optimize_node(*node2)))
+            },
+        }
+    }
+
+    /// Resolves an owner which is a constant keyword, so it doesn't have to be evaluated every time it's accessed.
+    /// Any other owner is left to be evaluated where it is, as moving it could change the order of side effects.
+    fn optimize_owner(&self, owner: InstanceIdentifier) -> InstanceIdentifier {
+        match owner {
+            InstanceIdentifier::Expression(node) => match self.optimize_node(*node) {
+                Node::Literal { value: value @ Value::Real(_) } => match value.round() {
+                    gml::SELF | gml::SELF2 => InstanceIdentifier::Own,
+                    gml::OTHER => InstanceIdentifier::Other,
+                    gml::GLOBAL => InstanceIdentifier::Global,
+                    gml::LOCAL => InstanceIdentifier::Local,
+                    _ => InstanceIdentifier::Expression(Box::new(Node::Literal { value })),
+                },
+                node => InstanceIdentifier::Expression(Box::new(node)),
+            },
+            owner => owner,
+        }
+    }
+}
+
+/// Checks whether any break or continue in some code would apply to a loop around it.
+fn controls_loop(body: &[Instruction], include_break: bool) -> bool {
+    body.iter().any(|instruction| match &instruction.kind {
+        InstructionKind::Return { return_type: ReturnType::Break } => include_break,
+        InstructionKind::Return { return_type: ReturnType::Continue } => true,
+        InstructionKind::IfElse { if_body, else_body, .. } => {
+            controls_loop(if_body, include_break) || controls_loop(else_body, include_break)
+        },
+        // Breaking out of a switch only leaves the switch, but continuing in one continues the loop
+        InstructionKind::Switch { body, .. } => controls_loop(body, false),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(compiler: &mut Compiler, source: &str) -> Vec<Instruction> {
+        compiler.compile_instructions(source.as_bytes()).unwrap()
+    }
+
+    /// Gets the value assigned by a field assignment.
+    fn assigned(instruction: &Instruction) -> &Node {
+        match &instruction.kind {
+            InstructionKind::SetField { value, .. } => value,
+            kind => panic!("expected an assignment, found {:?}", kind),
+        }
+    }
+
+    fn assigned_field(instruction: &Instruction) -> usize {
+        match &instruction.kind {
+            InstructionKind::SetField { accessor, .. } => accessor.index,
+            kind => panic!("expected an assignment, found {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn fold_user_constants() {
+        let mut compiler = Compiler::new();
+        compiler.register_user_constant(b"TEN".to_vec().into_boxed_slice(), 0);
+        compiler.register_user_constant(b"LATER".to_vec().into_boxed_slice(), 1);
+        compiler.set_user_constant_value(0, Value::from(10.0));
+
+        let body = compile(&mut compiler, "a = TEN * 2 + 0.5");
+        match assigned(&body[0]) {
+            Node::Literal { value } => assert_eq!(f64::from(value.clone()), 20.5),
+            node => panic!("constant wasn't folded: {:?}", node),
+        }
+
+        // A constant which hasn't been worked out yet is left to be looked up when the code runs
+        let body = compile(&mut compiler, "a = LATER * 2");
+        assert!(matches!(assigned(&body[0]), Node::Binary { .. }));
+    }
+
+    #[test]
+    fn prune_constant_branches() {
+        let mut compiler = Compiler::new();
+        let body = compile(&mut compiler, "if (1 < 2) a = 1 else b = 1; while (0) c = 1; if (x) d = 1");
+        assert_eq!(body.len(), 2);
+        assert_eq!(assigned_field(&body[0]), compiler.get_field_id(b"a"));
+        assert!(matches!(body[1].kind, InstructionKind::IfElse { .. }));
+    }
+
+    #[test]
+    fn repeat_once() {
+        let mut compiler = Compiler::new();
+        let is_loop = |body: &[Instruction]| matches!(body[0].kind, InstructionKind::Repeat { .. });
+
+        assert!(!is_loop(&compile(&mut compiler, "repeat (1) a = 1")));
+        assert!(compile(&mut compiler, "repeat (0) a = 1").is_empty());
+
+        // Breaking out of a switch doesn't leave the loop, so the loop can go
+        assert!(!is_loop(&compile(&mut compiler, "repeat (1) switch (x) { case 1: break }")));
+
+        // But continuing inside a switch continues the loop, which doesn't count it down, so it has to stay
+        assert!(is_loop(&compile(&mut compiler, "repeat (1) switch (x) { case 1: continue }")));
+        assert!(is_loop(&compile(&mut compiler, "repeat (1) { if (x) break }")));
+    }
+
+    #[test]
+    fn switch_cases_after_removed_branches() {
+        let mut compiler = Compiler::new();
+        let body = compile(
+            &mut compiler,
+            "switch (x) { case 1: if (0) a = 1; b = 1; case 2: if (1) { c = 1; d = 1 } e = 1; default: f = 1 }",
+        );
+        match &body[0].kind {
+            InstructionKind::Switch { cases, default, body, .. } => {
+                let fields = body.iter().map(assigned_field).collect::<Vec<_>>();
+                let expected = ["b", "c", "d", "e", "f"].iter().map(|x| compiler.get_field_id(x.as_bytes()));
+                assert_eq!(fields, expected.collect::<Vec<_>>());
+                assert_eq!(cases.iter().map(|(_, start)| *start).collect::<Vec<_>>(), [0, 1]);
+                assert_eq!(*default, Some(4));
+            },
+            kind => panic!("expected a switch, found {:?}", kind),
+        }
+
+        // A case whose only statement was removed falls through to the next one
+        let body = compile(&mut compiler, "switch (x) { case 1: if (0) a = 1; case 2: b = 1 }");
+        match &body[0].kind {
+            InstructionKind::Switch { cases, .. } => {
+                assert_eq!(cases.iter().map(|(_, start)| *start).collect::<Vec<_>>(), [0, 0])
+            },
+            kind => panic!("expected a switch, found {:?}", kind),
+        }
+    }
+}
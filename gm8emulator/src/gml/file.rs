@@ -1,7 +1,8 @@
+use flate2::read::ZlibDecoder;
 use image::{gif::GifDecoder, AnimationDecoder, ImageError, ImageFormat, Pixel, RgbaImage};
 use std::{
     fs::{File, OpenOptions},
-    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
@@ -24,6 +25,7 @@ pub enum Error {
     InvalidFile(i32),
     IOError(io::Error),
     ImageError(ImageError),
+    InvalidResource(&'static str),
 }
 
 impl From<io::Error> for Error {
@@ -45,6 +47,7 @@ impl std::fmt::Display for Error {
             Self::InvalidFile(handle) => write!(f, "invalid file handle {}", handle),
             Self::IOError(err) => write!(f, "io error: {}", err),
             Self::ImageError(err) => write!(f, "image error: {}", err),
+            Self::InvalidResource(reason) => write!(f, "invalid resource file: {}", reason),
         }
     }
 }
@@ -362,3 +365,96 @@ pub fn save_image<P: AsRef<Path>>(path: P, width: u32, height: u32, data: Box<[u
     }
     Ok(())
 }
+
+/// A sprite exported from the GM8 editor as a .gmspr file.
+pub struct SpriteFile {
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub frames: Vec<RgbaImage>,
+    pub shape: i32,
+    pub tolerance: u8,
+    pub sepmasks: bool,
+    pub bbox_mode: i32,
+    pub bbox_left: i32,
+    pub bbox_right: i32,
+    pub bbox_bottom: i32,
+    pub bbox_top: i32,
+}
+
+/// Reads the resource data from a .gmspr or .gmbck file.
+/// These are a version number (800) followed by a length-prefixed zlib block,
+/// laid out the same way as a single resource in a .gmk file.
+fn read_resource(path: &str) -> Result<Cursor<Vec<u8>>> {
+    let mut file = BufReader::new(File::open(path)?);
+    if read_i32(&mut file)? != 800 {
+        return Err(Error::InvalidResource("unsupported version"))
+    }
+    let len = read_i32(&mut file)?;
+    let mut data = Vec::new();
+    ZlibDecoder::new(file.take(len.max(0) as u64)).read_to_end(&mut data)?;
+    Ok(Cursor::new(data))
+}
+
+fn read_i32(data: &mut impl Read) -> io::Result<i32> {
+    let mut buf = [0u8; 4];
+    data.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+/// Reads a version number, width, height and BGRA pixel data into an image.
+fn read_resource_image(data: &mut impl Read) -> Result<RgbaImage> {
+    if read_i32(data)? != 800 {
+        return Err(Error::InvalidResource("unsupported image version"))
+    }
+    let width = read_i32(data)?;
+    let height = read_i32(data)?;
+    if width <= 0 || height <= 0 {
+        return Err(Error::InvalidResource("empty image"))
+    }
+    let len = read_i32(data)?;
+    if len as usize != width as usize * height as usize * 4 {
+        return Err(Error::InvalidResource("image data has the wrong length"))
+    }
+    let mut pixels = vec![0u8; len as usize];
+    data.read_exact(&mut pixels)?;
+    for px in pixels.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+    Ok(RgbaImage::from_vec(width as _, height as _, pixels).unwrap())
+}
+
+pub fn load_sprite_file(path: &str) -> Result<SpriteFile> {
+    let mut data = read_resource(path)?;
+    if read_i32(&mut data)? != 800 {
+        return Err(Error::InvalidResource("unsupported sprite version"))
+    }
+    let origin_x = read_i32(&mut data)?;
+    let origin_y = read_i32(&mut data)?;
+    let frame_count = read_i32(&mut data)?;
+    if frame_count <= 0 {
+        return Err(Error::InvalidResource("sprite has no frames"))
+    }
+    let frames = (0..frame_count).map(|_| read_resource_image(&mut data)).collect::<Result<Vec<_>>>()?;
+    Ok(SpriteFile {
+        origin_x,
+        origin_y,
+        frames,
+        shape: read_i32(&mut data)?,
+        tolerance: read_i32(&mut data)?.max(0).min(255) as u8,
+        sepmasks: read_i32(&mut data)? != 0,
+        bbox_mode: read_i32(&mut data)?,
+        bbox_left: read_i32(&mut data)?,
+        bbox_right: read_i32(&mut data)?,
+        bbox_bottom: read_i32(&mut data)?,
+        bbox_top: read_i32(&mut data)?,
+    })
+}
+
+pub fn load_background_file(path: &str) -> Result<RgbaImage> {
+    let mut data = read_resource(path)?;
+    // version 710, followed by tileset settings which aren't used at runtime
+    for _ in 0..8 {
+        read_i32(&mut data)?;
+    }
+    read_resource_image(&mut data)
+}
@@ -5,8 +5,17 @@
 use crate::{
     action, asset,
     game::{
-        draw, external, gm_save::GMSave, model, particle, pathfinding, replay, string::RCStr, surface::Surface,
-        transition::UserTransition, view::View, Game, GetAsset, PlayType, SceneChange, Version,
+        dialog::{Dialog, MessageFont},
+        draw, external,
+        gm_save::GMSave,
+        model, particle, pathfinding, replay,
+        splash::{self, Splash, SplashContent},
+        string::RCStr,
+        surface::Surface,
+        transition::UserTransition,
+        view::View,
+        window_state::DisplayMode,
+        Game, GetAsset, PlayType, SceneChange, Version,
     },
     gml::{
         self,
@@ -19,16 +28,14 @@ use crate::{
     math::Real,
     tile::Tile,
 };
-use gmio::{
-    render::{BlendType, Fog, Light, Renderer, RendererOptions, Scaling},
-    window,
-    window::Cursor,
-};
+use gmio::render::{BlendType, Fog, Light, Renderer, Scaling};
 use image::RgbaImage;
-use shared::{input::MouseButton, types::Colour};
+use shared::input::{JoystickAxis, MouseButton};
 use std::{
     io::{Read, Write},
+    path::PathBuf,
     process::Command,
+    rc::Rc,
 };
 
 macro_rules! _arg_into {
@@ -93,69 +100,189 @@ pub fn rgb_to_hsv(colour: i32) -> (i32, i32, i32) {
     (((h / Real::from(360)) * Real::from(255)).round(), (s * Real::from(255)).round(), v.round())
 }
 
+/// Converts a GML joystick id (1 or 2) to the input manager's 0-based index.
+fn joystick_index(id: i32) -> usize {
+    (id as usize).wrapping_sub(1)
+}
+
+/// Builds a sprite from one loaded from a .gmspr file, uploading its frames and making its colliders.
+fn sprite_from_file(
+    renderer: &mut Renderer,
+    name: RCStr,
+    sprite: file::SpriteFile,
+    function: &str,
+) -> gml::Result<asset::Sprite> {
+    let (origin_x, origin_y) = (sprite.origin_x, sprite.origin_y);
+    let mut images = sprite.frames;
+    let (width, height) = images[0].dimensions();
+    for image in images.iter_mut() {
+        asset::sprite::scale(image, width, height);
+    }
+    let bbox = match sprite.bbox_mode {
+        0 => None, // automatic
+        1 => Some(asset::sprite::BoundingBox { left: 0, right: width - 1, top: 0, bottom: height - 1 }),
+        _ => Some(asset::sprite::BoundingBox {
+            left: (sprite.bbox_left.max(0) as u32).min(width - 1),
+            right: (sprite.bbox_right.max(0) as u32).min(width - 1),
+            top: (sprite.bbox_top.max(0) as u32).min(height - 1),
+            bottom: (sprite.bbox_bottom.max(0) as u32).min(height - 1),
+        }),
+    };
+    let colliders = asset::sprite::make_colliders(&images, sprite.shape, sprite.tolerance, sprite.sepmasks, bbox);
+    let frames = images
+        .drain(..)
+        .map(|i| {
+            Ok(asset::sprite::Frame {
+                width,
+                height,
+                atlas_ref: renderer
+                    .upload_sprite(i.into_raw().into_boxed_slice(), width as _, height as _, origin_x, origin_y)
+                    .map_err(|e| gml::Error::FunctionError(function.into(), e))?,
+            })
+        })
+        .collect::<gml::Result<_>>()?;
+    let mut sprite = asset::Sprite {
+        name,
+        frames,
+        colliders,
+        width,
+        height,
+        origin_x,
+        origin_y,
+        per_frame_colliders: sprite.sepmasks,
+        bbox_left: 0,
+        bbox_right: 0,
+        bbox_top: 0,
+        bbox_bottom: 0,
+    };
+    sprite.update_bbox();
+    Ok(sprite)
+}
+
+/// Rasterizes a font from a TrueType file, found by its path or family name.
+/// Returns the characters and the tallest one's height.
+fn create_chars_from_font_name(
+    game: &mut Game,
+    name: &RCStr,
+    size: u32,
+    bold: bool,
+    italic: bool,
+    first: u8,
+    last: u8,
+) -> Result<(Box<[asset::font::Character]>, u32), String> {
+    let name = game.decode_str(name.as_ref()).into_owned();
+    let search_dirs = [&game.program_directory, &game.temp_directory]
+        .iter()
+        .map(|dir| PathBuf::from(game.decode_str(dir.as_ref()).into_owned()))
+        .collect::<Vec<_>>();
+    let (path, bold_face) = asset::font::find_font_file(&name, bold, italic, &search_dirs)
+        .ok_or_else(|| format!("couldn't find a font file for {}", name))?;
+    let data = std::fs::read(&path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    asset::font::create_chars_from_ttf(&data, size, bold && !bold_face, first, last, game.encoding, &mut game.renderer)
+}
+
+/// Point-in-tile check used by tile_find and the tile_layer_*_at functions.
+/// GM8.0 multiplies the tile's size by 0 instead of its scale here, so nothing is ever found.
+fn tile_contains(tile: &Tile, x: Real, y: Real, use_scaling: bool) -> bool {
+    x >= tile.x.get()
+        && x < tile.x.get() + if use_scaling { tile.xscale.get() } else { 0.into() } * tile.width.get().into()
+        && y >= tile.y.get()
+        && y < tile.y.get() + if use_scaling { tile.yscale.get() } else { 0.into() } * tile.height.get().into()
+}
+
 impl Game {
     pub fn display_get_width(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window.display_width().into())
+        Ok(self.window_state.display.width.unwrap_or_else(|| self.window.display_width()).into())
     }
 
     pub fn display_get_height(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window.display_height().into())
+        Ok(self.window_state.display.height.unwrap_or_else(|| self.window.display_height()).into())
     }
 
     pub fn display_get_colordepth(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window.display_colour().into())
+        Ok(self.window_state.display.colour_depth.unwrap_or_else(|| self.window.display_colour()).into())
     }
 
     pub fn display_get_frequency(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window.display_frequency().into())
+        Ok(self.window_state.display.frequency.unwrap_or_else(|| self.window.display_frequency()).into())
     }
 
-    pub fn display_set_size(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function display_set_size")
+    pub fn display_set_size(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (width, height) = expect_args!(args, [any, any])?;
+        self.display_set_all(context, &[width, height, (-1).into(), (-1).into()])
     }
 
-    pub fn display_set_colordepth(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function display_set_colordepth")
+    pub fn display_set_colordepth(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let colour_depth = expect_args!(args, [any])?;
+        self.display_set_all(context, &[(-1).into(), (-1).into(), colour_depth, (-1).into()])
     }
 
-    pub fn display_set_frequency(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function display_set_frequency")
+    pub fn display_set_frequency(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let frequency = expect_args!(args, [any])?;
+        self.display_set_all(context, &[(-1).into(), (-1).into(), (-1).into(), frequency])
     }
 
-    pub fn display_set_all(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function display_set_all")
+    pub fn display_set_all(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (width, height, colour_depth, frequency) = expect_args!(args, [int, int, int, int])?;
+        if DisplayMode::is_valid(width, height, colour_depth, frequency) {
+            self.window_state.display.set(width, height, colour_depth, frequency);
+            Ok(true.into())
+        } else {
+            Ok(false.into())
+        }
     }
 
-    pub fn display_test_all(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function display_test_all")
+    pub fn display_test_all(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (width, height, colour_depth, frequency) = expect_args!(args, [int, int, int, int])?;
+        Ok(DisplayMode::is_valid(width, height, colour_depth, frequency).into())
+    }
+
+    pub fn display_reset(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.window_state.display = Default::default();
+        Ok(Default::default())
     }
 
-    pub fn display_reset(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        unimplemented!("Called unimplemented kernel function display_reset")
+    /// Gets the window's position on the display. This depends on the host, so it's stored when recording,
+    /// and during replay the stored position is used instead of the real window's.
+    fn window_pos(&mut self, function: &str) -> gml::Result<(i32, i32)> {
+        match self.play_type {
+            PlayType::Normal => Ok(self.window.get_pos()),
+            PlayType::Record => {
+                let (x, y) = self.window.get_pos();
+                self.stored_events.push_back(replay::Event::WindowPosition(x, y));
+                Ok((x, y))
+            },
+            PlayType::Replay => {
+                if let Some(replay::Event::WindowPosition(x, y)) = self.stored_events.pop_front() {
+                    Ok((x, y))
+                } else {
+                    Err(gml::Error::ReplayError(function.into()))
+                }
+            },
+        }
     }
 
     pub fn display_mouse_get_x(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok((self.input_manager.mouse_get_location().0 + f64::from(self.window.get_pos().0)).into())
+        let window_x = self.window_pos("display_mouse_get_x")?.0;
+        Ok((self.input_manager.mouse_get_location().0 + f64::from(window_x)).into())
     }
 
     pub fn display_mouse_get_y(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok((self.input_manager.mouse_get_location().1 + f64::from(self.window.get_pos().1)).into())
+        let window_y = self.window_pos("display_mouse_get_y")?.1;
+        Ok((self.input_manager.mouse_get_location().1 + f64::from(window_y)).into())
     }
 
-    pub fn display_mouse_set(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function display_mouse_set")
+    pub fn display_mouse_set(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y) = expect_args!(args, [int, int])?;
+        let (window_x, window_y) = self.window_pos("display_mouse_set")?;
+        self.window_mouse_set(context, &[(x - window_x).into(), (y - window_y).into()])
     }
 
     pub fn window_set_visible(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -169,74 +296,80 @@ impl Game {
         Ok(self.window.get_visible().into())
     }
 
-    pub fn window_set_fullscreen(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function window_set_fullscreen")
+    pub fn window_set_fullscreen(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fullscreen = expect_args!(args, [bool])?;
+        if fullscreen != self.window_state.fullscreen {
+            self.window_state.fullscreen = fullscreen;
+            self.update_window_style();
+            if !fullscreen && self.controls_window() {
+                // going back to windowed mode, so restore the normal window size
+                self.window_default_size();
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn window_get_fullscreen(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_get_fullscreen")
+    pub fn window_get_fullscreen(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        Ok(self.window_state.fullscreen.into())
     }
 
     pub fn window_set_showborder(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let show_border = expect_args!(args, [bool])?;
-        if show_border != self.window_border {
-            self.window_border = show_border;
-            if self.play_type != PlayType::Record {
-                self.window.set_style(match (show_border, self.window_icons) {
-                    (true, true) => window::Style::Regular,
-                    (true, false) => window::Style::Undecorated,
-                    (false, _) => window::Style::Borderless,
-                });
-            }
+        if show_border != self.window_state.border {
+            self.window_state.border = show_border;
+            self.update_window_style();
         }
         Ok(Default::default())
     }
 
     pub fn window_get_showborder(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window_border.into())
+        Ok(self.window_state.border.into())
     }
 
     pub fn window_set_showicons(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let show_icons = expect_args!(args, [bool])?;
-        if show_icons != self.window_icons {
-            self.window_icons = show_icons;
-            if self.play_type != PlayType::Record {
-                self.window.set_style(match (self.window_border, show_icons) {
-                    (true, true) => window::Style::Regular,
-                    (true, false) => window::Style::Undecorated,
-                    (false, _) => window::Style::Borderless,
-                });
-            }
+        if show_icons != self.window_state.icons {
+            self.window_state.icons = show_icons;
+            self.update_window_style();
         }
         Ok(Default::default())
     }
 
     pub fn window_get_showicons(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window_icons.into())
+        Ok(self.window_state.icons.into())
     }
 
-    pub fn window_set_stayontop(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function window_set_stayontop")
+    pub fn window_set_stayontop(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let stay_on_top = expect_args!(args, [bool])?;
+        if stay_on_top != self.window_state.stay_on_top {
+            self.window_state.stay_on_top = stay_on_top;
+            if self.controls_window() {
+                self.window.set_topmost(stay_on_top);
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn window_get_stayontop(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_get_stayontop")
+    pub fn window_get_stayontop(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        Ok(self.window_state.stay_on_top.into())
     }
 
-    pub fn window_set_sizeable(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function window_set_sizeable")
+    pub fn window_set_sizeable(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sizeable = expect_args!(args, [bool])?;
+        if sizeable != self.window_state.sizeable {
+            self.window_state.sizeable = sizeable;
+            self.update_window_style();
+        }
+        Ok(Default::default())
     }
 
-    pub fn window_get_sizeable(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_get_sizeable")
+    pub fn window_get_sizeable(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        Ok(self.window_state.sizeable.into())
     }
 
     pub fn window_set_caption(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -250,14 +383,18 @@ impl Game {
         Ok(self.window.get_title().to_owned().into())
     }
 
-    pub fn window_set_cursor(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function window_set_cursor")
+    pub fn window_set_cursor(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let cursor = expect_args!(args, [int])?;
+        self.window_state.cursor = cursor;
+        if self.controls_window() {
+            self.window.set_cursor(self.window_state.cursor());
+        }
+        Ok(Default::default())
     }
 
-    pub fn window_get_cursor(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_get_cursor")
+    pub fn window_get_cursor(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        Ok(self.window_state.cursor.into())
     }
 
     pub fn window_set_color(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -283,9 +420,14 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn window_set_rectangle(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function window_set_rectangle")
+    pub fn window_set_rectangle(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, width, height) = expect_args!(args, [int, int, int, int])?;
+        if self.controls_window() {
+            // resizing recentres the window, so it has to be moved afterwards
+            self.window.resize(width.max(0) as _, height.max(0) as _);
+            self.window.set_pos(x, y);
+        }
+        Ok(Default::default())
     }
 
     pub fn window_center(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -293,19 +435,23 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn window_default(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_default")
+    pub fn window_default(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        if self.controls_window() {
+            self.window_default_size();
+            self.window.center();
+        }
+        Ok(Default::default())
     }
 
     pub fn window_get_x(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window.get_pos().0.into())
+        Ok(self.window_pos("window_get_x")?.0.into())
     }
 
     pub fn window_get_y(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.window.get_pos().1.into())
+        Ok(self.window_pos("window_get_y")?.1.into())
     }
 
     pub fn window_get_width(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -318,22 +464,35 @@ impl Game {
         Ok(self.window.get_inner_size().1.into())
     }
 
-    pub fn window_set_region_size(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        // unscaled_width and unscaled_height will need to be separated into framebuffer size
-        // and window region size for this to work
-        // probably keep the framebuffer size on the renderer and make a getter?
-        unimplemented!("Called unimplemented kernel function window_set_region_size")
+    pub fn window_set_region_size(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (width, height, adapt_window) = expect_args!(args, [int, int, bool])?;
+        let (region_w, region_h) = (width.max(1) as u32, height.max(1) as u32);
+        self.window_state.region_size = Some((region_w, region_h));
+        self.renderer.resize_framebuffer(region_w, region_h);
+        if self.controls_window() {
+            match self.scaling {
+                // the window always fits the region exactly with fixed scaling
+                Scaling::Fixed(_) => self.window_default_size(),
+                _ if adapt_window => {
+                    let (window_w, window_h) = self.window.get_inner_size();
+                    if region_w > window_w || region_h > window_h {
+                        self.window.resize(region_w.max(window_w), region_h.max(window_h));
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn window_get_region_width(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.unscaled_width.into())
+        Ok(self.window_region_size().0.into())
     }
 
     pub fn window_get_region_height(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(self.unscaled_height.into())
+        Ok(self.window_region_size().1.into())
     }
 
     pub fn window_set_region_scale(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -345,8 +504,8 @@ impl Game {
         };
         self.scaling = scaling;
         if let Scaling::Fixed(n) = scaling {
-            let (region_w, region_h) =
-                ((self.unscaled_width as f64 * n) as u32, (self.unscaled_height as f64 * n) as u32);
+            let (unscaled_w, unscaled_h) = self.window_region_size();
+            let (region_w, region_h) = ((unscaled_w as f64 * n) as u32, (unscaled_h as f64 * n) as u32);
             let (width, height) = if shrink_window {
                 let (window_w, window_h) = self.window.get_inner_size();
                 (region_w.max(window_w), region_h.max(window_h))
@@ -378,39 +537,63 @@ impl Game {
         Ok(self.input_manager.mouse_get_location().1.into())
     }
 
-    pub fn window_mouse_set(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function window_mouse_set")
+    pub fn window_mouse_set(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y) = expect_args!(args, [int, int])?;
+        // the game sees the new position straight away, the real cursor is only moved if we own the window
+        self.input_manager.set_mouse_pos(x.into(), y.into());
+        if self.controls_window() {
+            self.window.set_mouse_pos(x, y);
+        }
+        Ok(Default::default())
     }
 
-    pub fn window_view_mouse_get_x(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function window_view_mouse_get_x")
+    pub fn window_view_mouse_get_x(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let view_id = expect_args!(args, [int])?;
+        let (x, y) = self.input_manager.mouse_get_location();
+        match self.views.get(view_id as usize) {
+            Some(view) if view_id >= 0 => Ok(view.transform_point(x as i32, y as i32).0.into()),
+            _ => Ok(Default::default()),
+        }
     }
 
-    pub fn window_view_mouse_get_y(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function window_view_mouse_get_y")
+    pub fn window_view_mouse_get_y(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let view_id = expect_args!(args, [int])?;
+        let (x, y) = self.input_manager.mouse_get_location();
+        match self.views.get(view_id as usize) {
+            Some(view) if view_id >= 0 => Ok(view.transform_point(x as i32, y as i32).1.into()),
+            _ => Ok(Default::default()),
+        }
     }
 
-    pub fn window_view_mouse_set(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function window_view_mouse_set")
+    pub fn window_view_mouse_set(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (view_id, x, y) = expect_args!(args, [int, int, int])?;
+        match self.views.get(view_id as usize) {
+            Some(view) if view_id >= 0 => {
+                let (x, y) = view.untransform_point(x, y);
+                self.window_mouse_set(context, &[x.into(), y.into()])
+            },
+            _ => Ok(Default::default()),
+        }
     }
 
-    pub fn window_views_mouse_get_x(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_views_mouse_get_x")
+    pub fn window_views_mouse_get_x(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        Ok(self.get_mouse_in_room().0.into())
     }
 
-    pub fn window_views_mouse_get_y(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function window_views_mouse_get_y")
+    pub fn window_views_mouse_get_y(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        Ok(self.get_mouse_in_room().1.into())
     }
 
-    pub fn window_views_mouse_set(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function window_views_mouse_set")
+    pub fn window_views_mouse_set(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y) = expect_args!(args, [int, int])?;
+        // goes through the first visible view, the same one mouse_x and mouse_y fall back on
+        let (x, y) = match self.views.iter().find(|view| self.views_enabled && view.visible) {
+            Some(view) => view.untransform_point(x, y),
+            None => (x, y),
+        };
+        self.window_mouse_set(context, &[x.into(), y.into()])
     }
 
     pub fn set_synchronization(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -447,7 +630,7 @@ impl Game {
     pub fn screen_save(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let fname = expect_args!(args, [string])?;
         self.renderer.flush_queue();
-        let (width, height) = (self.unscaled_width, self.unscaled_height);
+        let (width, height) = self.window_region_size();
         let rgba = self.renderer.get_pixels(0, 0, width as _, height as _);
         match file::save_image(fname.as_ref(), width, height, rgba) {
             Ok(()) => Ok(Default::default()),
@@ -459,8 +642,8 @@ impl Game {
         let (fname, x, y, w, h) = expect_args!(args, [string, int, int, int, int])?;
         let x = x.max(0);
         let y = y.max(0);
-        let w = w.min(self.unscaled_width as i32 - x);
-        let h = h.min(self.unscaled_height as i32 - y);
+        let w = w.min(self.window_region_size().0 as i32 - x);
+        let h = h.min(self.window_region_size().1 as i32 - y);
         self.renderer.flush_queue();
         let rgba = self.renderer.get_pixels(x, y, w, h);
         match file::save_image(fname.as_ref(), w as _, h as _, rgba) {
@@ -1820,6 +2003,7 @@ impl Game {
             tile.tile_y.set(top);
             tile.width.set(width);
             tile.height.set(height);
+            self.tile_list.invalidate_index();
             Ok(Default::default())
         } else {
             Err(gml::Error::FunctionError(
@@ -1835,6 +2019,7 @@ impl Game {
             let tile = self.tile_list.get(handle);
             tile.x.set(x);
             tile.y.set(y);
+            self.tile_list.invalidate_index();
             Ok(Default::default())
         } else {
             Err(gml::Error::FunctionError(
@@ -1848,6 +2033,7 @@ impl Game {
         let (tile_id, depth) = expect_args!(args, [int, real])?;
         if let Some(handle) = self.tile_list.get_by_tileid(tile_id) {
             self.tile_list.get(handle).depth.set(depth);
+            self.tile_list.invalidate_index();
             Ok(Default::default())
         } else {
             Err(gml::Error::FunctionError("tile_set_depth".into(), format!("Tile with ID {} does not exist.", tile_id)))
@@ -1860,6 +2046,7 @@ impl Game {
             let tile = self.tile_list.get(handle);
             tile.xscale.set(xscale);
             tile.yscale.set(yscale);
+            self.tile_list.invalidate_index();
             Ok(Default::default())
         } else {
             Err(gml::Error::FunctionError("tile_set_scale".into(), format!("Tile with ID {} does not exist.", tile_id)))
@@ -1909,9 +2096,16 @@ impl Game {
         Ok(self.last_tile_id.into())
     }
 
-    pub fn tile_find(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function tile_find")
+    pub fn tile_find(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, foreground) = expect_args!(args, [real, real, bool])?;
+        let use_scaling = self.gm_version == Version::GameMaker8_1; // 8.1 bugfix
+        for handle in self.tile_list.tiles_at(x, y) {
+            let tile = self.tile_list.get(handle);
+            if (tile.depth.get() < 0.into()) == foreground && tile_contains(tile, x, y, use_scaling) {
+                return Ok(tile.id.get().into())
+            }
+        }
+        Ok((-1).into())
     }
 
     pub fn tile_exists(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -1929,31 +2123,30 @@ impl Game {
         }
     }
 
-    pub fn tile_delete_at(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function tile_delete_at")
+    pub fn tile_delete_at(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, foreground) = expect_args!(args, [real, real, bool])?;
+        let use_scaling = self.gm_version == Version::GameMaker8_1; // 8.1 bugfix
+        for handle in self.tile_list.tiles_at(x, y) {
+            let tile = self.tile_list.get(handle);
+            if (tile.depth.get() < 0.into()) == foreground && tile_contains(tile, x, y, use_scaling) {
+                self.tile_list.remove(handle);
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn tile_layer_hide(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let depth = expect_args!(args, [real])?;
-        let mut iter_tile = self.tile_list.iter_by_drawing();
-        while let Some(handle) = iter_tile.next(&self.tile_list) {
-            let tile = self.tile_list.get(handle);
-            if tile.depth.get() == depth {
-                tile.visible.set(false);
-            }
+        for handle in self.tile_list.layer(depth) {
+            self.tile_list.get(handle).visible.set(false);
         }
         Ok(Default::default())
     }
 
     pub fn tile_layer_show(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let depth = expect_args!(args, [real])?;
-        let mut iter_tile = self.tile_list.iter_by_drawing();
-        while let Some(handle) = iter_tile.next(&self.tile_list) {
-            let tile = self.tile_list.get(handle);
-            if tile.depth.get() == depth {
-                tile.visible.set(true);
-            }
+        for handle in self.tile_list.layer(depth) {
+            self.tile_list.get(handle).visible.set(true);
         }
         Ok(Default::default())
     }
@@ -1966,29 +2159,21 @@ impl Game {
 
     pub fn tile_layer_shift(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (depth, x, y) = expect_args!(args, [real, real, real])?;
-        let mut iter_tile = self.tile_list.iter_by_drawing();
-        while let Some(handle) = iter_tile.next(&self.tile_list) {
+        for handle in self.tile_list.layer(depth) {
             let tile = self.tile_list.get(handle);
-            if tile.depth.get() == depth {
-                tile.x.set(tile.x.get() + x);
-                tile.y.set(tile.y.get() + y);
-            }
+            tile.x.set(tile.x.get() + x);
+            tile.y.set(tile.y.get() + y);
         }
+        self.tile_list.invalidate_index();
         Ok(Default::default())
     }
 
     pub fn tile_layer_find(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (depth, x, y) = expect_args!(args, [real, real, real])?;
         let use_scaling = self.gm_version == Version::GameMaker8_1; // 8.1 bugfix
-        let mut iter_tile = self.tile_list.iter_by_drawing();
-        while let Some(handle) = iter_tile.next(&self.tile_list) {
+        for handle in self.tile_list.tiles_at(x, y) {
             let tile = self.tile_list.get(handle);
-            if tile.depth.get() == depth
-                && x >= tile.x.get()
-                && x < tile.x.get() + if use_scaling { tile.xscale.get() } else { 0.into() } * tile.width.get().into()
-                && y >= tile.y.get()
-                && y < tile.y.get() + if use_scaling { tile.yscale.get() } else { 0.into() } * tile.height.get().into()
-            {
+            if tile.depth.get() == depth && tile_contains(tile, x, y, use_scaling) {
                 return Ok(tile.id.get().into())
             }
         }
@@ -1998,38 +2183,30 @@ impl Game {
     pub fn tile_layer_delete_at(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (depth, x, y) = expect_args!(args, [real, real, real])?;
         let use_scaling = self.gm_version == Version::GameMaker8_1; // 8.1 bugfix
-        self.tile_list.remove_with(|tile| {
-            tile.depth.get() == depth
-                && x >= tile.x.get()
-                && x < tile.x.get() + if use_scaling { tile.xscale.get() } else { 0.into() } * tile.width.get().into()
-                && y >= tile.y.get()
-                && y < tile.y.get() + if use_scaling { tile.yscale.get() } else { 0.into() } * tile.height.get().into()
-        });
+        for handle in self.tile_list.tiles_at(x, y) {
+            let tile = self.tile_list.get(handle);
+            if tile.depth.get() == depth && tile_contains(tile, x, y, use_scaling) {
+                self.tile_list.remove(handle);
+            }
+        }
         Ok(Default::default())
     }
 
     pub fn tile_layer_depth(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (old_depth, new_depth) = expect_args!(args, [real, real])?;
-        let mut iter_tile = self.tile_list.iter_by_drawing();
-        while let Some(handle) = iter_tile.next(&self.tile_list) {
-            let tile = self.tile_list.get(handle);
-            if tile.depth.get() == old_depth {
-                tile.depth.set(new_depth);
-            }
+        for handle in self.tile_list.layer(old_depth) {
+            self.tile_list.get(handle).depth.set(new_depth);
         }
+        self.tile_list.invalidate_index();
         Ok(Default::default())
     }
 
     pub fn surface_create(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (w, h) = expect_args!(args, [int, int])?;
-        let surf = Surface {
-            width: w as _,
-            height: h as _,
-            atlas_ref: match self.renderer.create_surface(w, h, true) {
-                Ok(atl_ref) => atl_ref,
-                Err(e) => return Err(gml::Error::FunctionError("surface_create".into(), e.into())),
-            },
-        };
+        let surf = Surface::new(w as _, h as _, match self.renderer.create_surface(w, h, true) {
+            Ok(atl_ref) => atl_ref,
+            Err(e) => return Err(gml::Error::FunctionError("surface_create".into(), e.into())),
+        });
         if let Some(id) = self.surfaces.iter().position(|x| x.is_none()) {
             self.surfaces[id] = Some(surf);
             Ok(id.into())
@@ -2039,9 +2216,20 @@ impl Game {
         }
     }
 
-    pub fn surface_create_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function surface_create_ext")
+    pub fn surface_create_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        // same as surface_create, but without a zbuffer
+        let (w, h) = expect_args!(args, [int, int])?;
+        let surf = Surface::new(w as _, h as _, match self.renderer.create_surface(w, h, false) {
+            Ok(atl_ref) => atl_ref,
+            Err(e) => return Err(gml::Error::FunctionError("surface_create_ext".into(), e.into())),
+        });
+        if let Some(id) = self.surfaces.iter().position(|x| x.is_none()) {
+            self.surfaces[id] = Some(surf);
+            Ok(id.into())
+        } else {
+            self.surfaces.push(Some(surf));
+            Ok((self.surfaces.len() - 1).into())
+        }
     }
 
     pub fn surface_free(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -2079,8 +2267,9 @@ impl Game {
 
     pub fn surface_set_target(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let surf_id = expect_args!(args, [int])?;
-        if let Some(surf) = self.surfaces.get_asset(surf_id) {
+        if let Some(surf) = self.surfaces.get_asset_mut(surf_id) {
             self.renderer.set_target(&surf.atlas_ref);
+            surf.pixel_cache = None;
             self.surface_target = Some(surf_id);
         }
         Ok(Default::default())
@@ -2274,24 +2463,92 @@ impl Game {
         }
     }
 
-    pub fn surface_getpixel(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function surface_getpixel")
+    pub fn surface_getpixel(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (surf_id, x, y) = expect_args!(args, [int, int, int])?;
+        let is_target = self.surface_target == Some(surf_id);
+        if let Some(surf) = self.surfaces.get_asset_mut(surf_id) {
+            if x < 0 || y < 0 || x >= surf.width as i32 || y >= surf.height as i32 {
+                return Ok(0.into())
+            }
+            let data = if is_target {
+                // it's being drawn to, so it can't be cached
+                self.renderer.flush_queue();
+                self.renderer.dump_sprite_part(&surf.atlas_ref, x, y, 1, 1)
+            } else {
+                let (renderer, atlas_ref) = (&self.renderer, surf.atlas_ref);
+                let pixels = surf.pixel_cache.get_or_insert_with(|| renderer.dump_sprite(&atlas_ref));
+                let i = (y as usize * surf.width as usize + x as usize) * 4;
+                pixels[i..i + 4].into()
+            };
+            Ok(u32::from_le_bytes([data[0], data[1], data[2], 0]).into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn surface_copy(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function surface_copy")
+    pub fn surface_copy(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, x, y, src_id) = expect_args!(args, [any, any, any, int])?;
+        if let Some(src) = self.surfaces.get_asset(src_id) {
+            let (w, h) = (src.width, src.height);
+            self.surface_copy_part(context, &[dest_id, x, y, src_id.into(), 0.into(), 0.into(), w.into(), h.into()])
+        } else {
+            Ok(Default::default())
+        }
     }
 
-    pub fn surface_copy_part(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function surface_copy_part")
+    pub fn surface_copy_part(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, x, y, src_id, xs, ys, ws, hs) = expect_args!(args, [int, int, int, int, int, int, int, int])?;
+        if let (Some(dest), Some(src)) = (self.surfaces.get_asset(dest_id), self.surfaces.get_asset(src_id)) {
+            // clip the area to both surfaces
+            let (mut x, mut y, mut xs, mut ys, mut ws, mut hs) = (x, y, xs, ys, ws, hs);
+            if xs < 0 {
+                ws += xs;
+                x -= xs;
+                xs = 0;
+            }
+            if ys < 0 {
+                hs += ys;
+                y -= ys;
+                ys = 0;
+            }
+            if x < 0 {
+                ws += x;
+                xs -= x;
+                x = 0;
+            }
+            if y < 0 {
+                hs += y;
+                ys -= y;
+                y = 0;
+            }
+            let ws = ws.min(src.width as i32 - xs).min(dest.width as i32 - x);
+            let hs = hs.min(src.height as i32 - ys).min(dest.height as i32 - y);
+            if ws <= 0 || hs <= 0 {
+                return Ok(Default::default())
+            }
+            let (dest_ref, src_ref) = (dest.atlas_ref, src.atlas_ref);
+            let result = if dest_id == src_id {
+                // copying within a texture is undefined if the areas overlap, so copy from a duplicate
+                let copy = self
+                    .renderer
+                    .duplicate_sprite(&src_ref)
+                    .map_err(|e| gml::Error::FunctionError("surface_copy_part".into(), e))?;
+                let result = self.renderer.copy_surface_part(&dest_ref, x, y, &copy, xs, ys, ws, hs);
+                self.renderer.delete_sprite(copy);
+                result
+            } else {
+                self.renderer.copy_surface_part(&dest_ref, x, y, &src_ref, xs, ys, ws, hs)
+            };
+            result.map_err(|e| gml::Error::FunctionError("surface_copy_part".into(), e))?;
+            self.surfaces.get_asset_mut(dest_id).unwrap().pixel_cache = None;
+        }
+        Ok(Default::default())
     }
 
-    pub fn action_path_old(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function action_path_old")
+    pub fn action_path_old(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        // pre-GM6 version of action_path, which always started the path relative to the instance
+        let (path_id, speed, end_action) = expect_args!(args, [any, any, any])?;
+        self.path_start(context, &[path_id, speed, end_action, false.into()])
     }
 
     pub fn action_set_sprite(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -2456,41 +2713,7 @@ impl Game {
     }
 
     pub fn action_move_random(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
-        let (hsnap, vsnap) = expect_args!(args, [int, int])?;
-        let inst = self.instance_list.get(context.this);
-        let (mut left, mut right, mut top, mut bottom) = (0, self.room_width, 0, self.room_height);
-        if let Some(sprite) = self
-            .assets
-            .sprites
-            .get_asset(inst.sprite_index.get())
-            .or(self.assets.sprites.get_asset(inst.mask_index.get()))
-        {
-            inst.update_bbox(Some(sprite));
-            left = (inst.x.get() - inst.bbox_left.get().into()).round();
-            right = (inst.x.get() + right.into() - inst.bbox_right.get().into()).round();
-            top = (inst.y.get() - inst.bbox_top.get().into()).round();
-            bottom = (inst.y.get() + bottom.into() - inst.bbox_bottom.get().into()).round();
-        };
-        drop(inst); // le borrow
-        let (mut x, mut y) = Default::default();
-        for _ in 0..100 {
-            x = Real::from(self.rand.next_int((right - left - 1) as u32) + left);
-            if hsnap > 0 {
-                x = (x / hsnap.into()).floor() * hsnap.into();
-            }
-            y = Real::from(self.rand.next_int((bottom - top - 1) as u32) + top);
-            if vsnap > 0 {
-                y = (y / vsnap.into()).floor() * vsnap.into();
-            }
-            if self.place_free(context, &[x.into(), y.into()])?.is_truthy() {
-                break
-            }
-        }
-        let inst = self.instance_list.get(context.this);
-        inst.x.set(x);
-        inst.y.set(y);
-        inst.bbox_is_stale.set(true);
-        Ok(Default::default())
+        self.move_random(context, args)
     }
 
     pub fn action_snap(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -2602,9 +2825,18 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn action_linear_step(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function action_linear_step")
+    pub fn action_linear_step(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, step_size, checkall) = expect_args!(args, [real, real, real, bool])?;
+        let instance = self.instance_list.get(context.this);
+        let (x, y) = if context.relative { (instance.x.get() + x, instance.y.get() + y) } else { (x, y) };
+        Ok(pathfinding::linear_step(x, y, step_size, instance, || {
+            if checkall {
+                self.check_collision_any(context.this).is_some()
+            } else {
+                self.check_collision_solid(context.this).is_some()
+            }
+        })
+        .into())
     }
 
     pub fn action_potential_step(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -2898,9 +3130,8 @@ impl Game {
         self.show_message(context, args)
     }
 
-    pub fn action_show_info(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function action_show_info")
+    pub fn action_show_info(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        self.show_info(context, args)
     }
 
     pub fn action_show_video(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -2913,14 +3144,14 @@ impl Game {
         unimplemented!("Called unimplemented kernel function action_splash_video")
     }
 
-    pub fn action_splash_text(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function action_splash_text")
+    pub fn action_splash_text(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [any])?;
+        self.splash_show_text(context, &[fname, 0.into()])
     }
 
-    pub fn action_splash_image(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function action_splash_image")
+    pub fn action_splash_image(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [any])?;
+        self.splash_show_image(context, &[fname, 0.into()])
     }
 
     pub fn action_splash_web(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -2928,9 +3159,15 @@ impl Game {
         unimplemented!("Called unimplemented kernel function action_splash_web")
     }
 
-    pub fn action_splash_settings(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function action_splash_settings")
+    pub fn action_splash_settings(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (caption, fullscreen, close_button, stop_key, stop_mouse) =
+            expect_args!(args, [bytes, bool, bool, bool, bool])?;
+        self.splash_settings.caption = caption;
+        self.splash_settings.fullscreen = fullscreen;
+        self.splash_settings.close_button = close_button;
+        self.splash_settings.stop_key = stop_key;
+        self.splash_settings.stop_mouse = stop_mouse;
+        Ok(Default::default())
     }
 
     pub fn action_end_game(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -3079,7 +3316,7 @@ impl Game {
                 locals: DummyFieldHolder::new(),
                 return_value: Default::default(),
             };
-            self.execute(&instructions, &mut new_context)?;
+            self.execute_script(script_id as usize, &instructions, &mut new_context)?;
             Ok(new_context.return_value)
         } else {
             Err(gml::Error::NonexistentAsset(asset::Type::Script, script_id))
@@ -3479,12 +3716,11 @@ impl Game {
     pub fn action_set_cursor(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (sprite_id, show_window_cursor) = expect_args!(args, [int, bool])?;
         self.cursor_sprite = sprite_id;
-        let cursor = if show_window_cursor {
-            Cursor::default() // GM8 seems to always resets to default cursor on call of this function
-        } else {
-            Cursor::Invisible
-        };
-        self.window.set_cursor(cursor);
+        // GM8 seems to always resets to default cursor on call of this function
+        self.window_state.cursor = if show_window_cursor { 0 } else { -1 };
+        if self.controls_window() {
+            self.window.set_cursor(self.window_state.cursor());
+        }
         Ok(Default::default())
     }
 
@@ -4209,9 +4445,42 @@ impl Game {
         Ok((dir.to_radians().sin() * -len).into())
     }
 
-    pub fn move_random(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function move_random")
+    pub fn move_random(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (hsnap, vsnap) = expect_args!(args, [int, int])?;
+        let inst = self.instance_list.get(context.this);
+        let (mut left, mut right, mut top, mut bottom) = (0, self.room_width, 0, self.room_height);
+        if let Some(sprite) = self
+            .assets
+            .sprites
+            .get_asset(inst.sprite_index.get())
+            .or(self.assets.sprites.get_asset(inst.mask_index.get()))
+        {
+            inst.update_bbox(Some(sprite));
+            left = (inst.x.get() - inst.bbox_left.get().into()).round();
+            right = (inst.x.get() + right.into() - inst.bbox_right.get().into()).round();
+            top = (inst.y.get() - inst.bbox_top.get().into()).round();
+            bottom = (inst.y.get() + bottom.into() - inst.bbox_bottom.get().into()).round();
+        };
+        drop(inst); // le borrow
+        let (mut x, mut y) = Default::default();
+        for _ in 0..100 {
+            x = Real::from(self.rand.next_int((right - left - 1) as u32) + left);
+            if hsnap > 0 {
+                x = (x / hsnap.into()).floor() * hsnap.into();
+            }
+            y = Real::from(self.rand.next_int((bottom - top - 1) as u32) + top);
+            if vsnap > 0 {
+                y = (y / vsnap.into()).floor() * vsnap.into();
+            }
+            if self.place_free(context, &[x.into(), y.into()])?.is_truthy() {
+                break
+            }
+        }
+        let inst = self.instance_list.get(context.this);
+        inst.x.set(x);
+        inst.y.set(y);
+        inst.bbox_is_stale.set(true);
+        Ok(Default::default())
     }
 
     pub fn place_free(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4284,9 +4553,12 @@ impl Game {
         Ok(collision.into())
     }
 
-    pub fn place_snapped(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function place_snapped")
+    pub fn place_snapped(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (hsnap, vsnap) = expect_args!(args, [real, real])?;
+        let instance = self.instance_list.get(context.this);
+        // a snap value of 0 or less means that axis isn't checked
+        let snapped = |pos: Real, snap: Real| snap <= 0.into() || Real::from((pos / snap).round()) * snap == pos;
+        Ok((snapped(instance.x.get(), hsnap) && snapped(instance.y.get(), vsnap)).into())
     }
 
     pub fn move_snap(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4667,9 +4939,16 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn mp_linear_step(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function mp_linear_step")
+    pub fn mp_linear_step(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, step_size, checkall) = expect_args!(args, [real, real, real, bool])?;
+        Ok(pathfinding::linear_step(x, y, step_size, self.instance_list.get(context.this), || {
+            if checkall {
+                self.check_collision_any(context.this).is_some()
+            } else {
+                self.check_collision_solid(context.this).is_some()
+            }
+        })
+        .into())
     }
 
     pub fn mp_linear_path(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -4677,9 +4956,14 @@ impl Game {
         unimplemented!("Called unimplemented kernel function mp_linear_path")
     }
 
-    pub fn mp_linear_step_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function mp_linear_step_object")
+    pub fn mp_linear_step_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, step_size, obj) = expect_args!(args, [real, real, real, int])?;
+        Ok(pathfinding::linear_step(x, y, step_size, self.instance_list.get(context.this), || match obj {
+            gml::SELF => false,
+            gml::OTHER => self.check_collision(context.this, context.other),
+            obj => self.find_instance_with(obj, |handle| self.check_collision(context.this, handle)).is_some(),
+        })
+        .into())
     }
 
     pub fn mp_linear_path_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -5105,9 +5389,14 @@ impl Game {
 
     pub fn instance_change(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let (object_id, run_events) = expect_args!(args, [int, bool])?;
+        self.change_instance(context.this, object_id, run_events)?;
+        Ok(Default::default())
+    }
 
+    /// Replaces an instance with one of another object, keeping its ID and built-in variables.
+    fn change_instance(&mut self, handle: usize, object_id: i32, run_events: bool) -> gml::Result<()> {
         if run_events {
-            self.run_instance_event(gml::ev::DESTROY, 0, context.this, context.this, None)?;
+            self.run_instance_event(gml::ev::DESTROY, 0, handle, handle, None)?;
         }
 
         let object = self
@@ -5115,7 +5404,7 @@ impl Game {
             .objects
             .get_asset(object_id)
             .ok_or(gml::Error::NonexistentAsset(asset::Type::Object, object_id))?;
-        let new_instance = self.instance_list.get(context.this).clone();
+        let new_instance = self.instance_list.get(handle).clone();
         new_instance.object_index.set(object_id);
         new_instance.sprite_index.set(object.sprite_index);
         new_instance.mask_index.set(object.mask_index);
@@ -5135,14 +5424,14 @@ impl Game {
         }
         new_instance.bbox_is_stale.set(true);
 
-        self.instance_list.mark_deleted(context.this);
+        self.instance_list.mark_deleted(handle);
         let handle = self.instance_list.insert(new_instance);
 
         if run_events {
             self.run_instance_event(gml::ev::CREATE, 0, handle, handle, None)?;
         }
 
-        Ok(Default::default())
+        Ok(())
     }
 
     pub fn instance_destroy(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -5152,9 +5441,15 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn instance_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function instance_sprite")
+    pub fn instance_sprite(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        // undocumented: gets the sprite_index of an instance, or the first instance of an object
+        let id = expect_args!(args, [int])?;
+        let handle = match id {
+            gml::SELF => Some(context.this),
+            gml::OTHER => Some(context.other),
+            id => self.find_instance_with(id, |_| true),
+        };
+        Ok(handle.map(|h| self.instance_list.get(h).sprite_index.get()).unwrap_or(-1).into())
     }
 
     pub fn position_empty(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -5184,9 +5479,22 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn position_change(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function position_change")
+    pub fn position_change(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, object_id, run_events) = expect_args!(args, [int, int, int, bool])?;
+        // collect first, so the instances created by the change don't get changed again
+        let mut handles = Vec::new();
+        let mut iter = self.instance_list.iter_by_insertion();
+        while let Some(handle) = iter.next(&self.instance_list) {
+            if self.check_collision_point(handle, x, y, true) {
+                handles.push(handle);
+            }
+        }
+        for handle in handles {
+            if self.instance_list.get(handle).state.get() == InstanceState::Active {
+                self.change_instance(handle, object_id, run_events)?;
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn instance_deactivate_all(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -6323,79 +6631,94 @@ impl Game {
         Ok(self.window.disk_size(path).map(|x| x as f64).unwrap_or(-1f64).into())
     }
 
-    pub fn splash_set_caption(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_caption")
+    pub fn splash_set_caption(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let caption = expect_args!(args, [bytes])?;
+        self.splash_settings.caption = caption;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_fullscreen(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_fullscreen")
+    pub fn splash_set_fullscreen(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fullscreen = expect_args!(args, [bool])?;
+        self.splash_settings.fullscreen = fullscreen;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_border(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_border")
+    pub fn splash_set_border(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let border = expect_args!(args, [bool])?;
+        self.splash_settings.border = border;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_size(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function splash_set_size")
+    pub fn splash_set_size(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (w, h) = expect_args!(args, [int, int])?;
+        self.splash_settings.size = (w, h);
+        Ok(Default::default())
     }
 
-    pub fn splash_set_position(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function splash_set_position")
+    pub fn splash_set_position(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y) = expect_args!(args, [int, int])?;
+        self.splash_settings.position = (x, y);
+        Ok(Default::default())
     }
 
-    pub fn splash_set_adapt(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_adapt")
+    pub fn splash_set_adapt(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let adapt = expect_args!(args, [bool])?;
+        self.splash_settings.adapt = adapt;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_top(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_top")
+    pub fn splash_set_top(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let top = expect_args!(args, [bool])?;
+        self.splash_settings.top = top;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_color(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_color")
+    pub fn splash_set_color(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let colour = expect_args!(args, [int])?;
+        self.splash_settings.colour = colour;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_main(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_main")
+    pub fn splash_set_main(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let main = expect_args!(args, [bool])?;
+        self.splash_settings.main = main;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_scale(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_scale")
+    pub fn splash_set_scale(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let scale = expect_args!(args, [int])?;
+        self.splash_settings.scale = scale;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_cursor(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_cursor")
+    pub fn splash_set_cursor(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let cursor = expect_args!(args, [bool])?;
+        self.splash_settings.cursor = cursor;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_interrupt(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_interrupt")
+    pub fn splash_set_interrupt(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let interrupt = expect_args!(args, [bool])?;
+        self.splash_settings.interrupt = interrupt;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_stop_key(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_stop_key")
+    pub fn splash_set_stop_key(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let stop_key = expect_args!(args, [bool])?;
+        self.splash_settings.stop_key = stop_key;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_close_button(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_close_button")
+    pub fn splash_set_close_button(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let close_button = expect_args!(args, [bool])?;
+        self.splash_settings.close_button = close_button;
+        Ok(Default::default())
     }
 
-    pub fn splash_set_stop_mouse(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function splash_set_stop_mouse")
+    pub fn splash_set_stop_mouse(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let stop_mouse = expect_args!(args, [bool])?;
+        self.splash_settings.stop_mouse = stop_mouse;
+        Ok(Default::default())
     }
 
     pub fn splash_show_video(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -6403,14 +6726,20 @@ impl Game {
         unimplemented!("Called unimplemented kernel function splash_show_video")
     }
 
-    pub fn splash_show_image(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function splash_show_image")
+    pub fn splash_show_image(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (fname, delay) = expect_args!(args, [string, int])?;
+        let content = self.load_splash_image("splash_show_image", fname.as_ref())?;
+        let full = self.splash_settings.fullscreen;
+        self.show_splash("splash_show_image", content, full, delay)?;
+        Ok(Default::default())
     }
 
-    pub fn splash_show_text(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function splash_show_text")
+    pub fn splash_show_text(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (fname, delay) = expect_args!(args, [string, int])?;
+        let content = self.load_splash_text("splash_show_text", fname.as_ref(), self.splash_settings.colour)?;
+        let full = self.splash_settings.fullscreen;
+        self.show_splash("splash_show_text", content, full, delay)?;
+        Ok(Default::default())
     }
 
     pub fn splash_show_web(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -6418,9 +6747,11 @@ impl Game {
         unimplemented!("Called unimplemented kernel function splash_show_web")
     }
 
-    pub fn show_image(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function show_image")
+    pub fn show_image(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (fname, full, delay) = expect_args!(args, [string, bool, int])?;
+        let content = self.load_splash_image("show_image", fname.as_ref())?;
+        self.show_splash("show_image", content, full, delay)?;
+        Ok(Default::default())
     }
 
     pub fn show_video(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -6428,65 +6759,47 @@ impl Game {
         unimplemented!("Called unimplemented kernel function show_video")
     }
 
-    pub fn show_text(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function show_text")
+    pub fn show_text(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (fname, full, backcol, delay) = expect_args!(args, [string, bool, int, int])?;
+        let content = self.load_splash_text("show_text", fname.as_ref(), backcol)?;
+        self.show_splash("show_text", content, full, delay)?;
+        Ok(Default::default())
     }
 
     pub fn show_message(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
-        let _text = expect_args!(args, [string])?;
-        let width = 300;
-        let height = 200;
-
-        let clear_colour = Colour::new(1.0, 142.0 / 255.0, 250.0 / 255.0);
-        let options =
-            RendererOptions { size: (width, height), vsync: false, interpolate_pixels: false, ..Default::default() };
-
-        // TODO: this should block as a dialog, not block the entire fucking thread
-        // otherwise windows thinks it's not responding or whatever
-
-        let wb = window::WindowBuilder::new().with_size(width, height);
-        let mut window = wb.build().map_err(|e| gml::Error::FunctionError("show_message".into(), e))?;
-        let mut renderer = Renderer::new((), &options, &window, clear_colour)
-            .map_err(|e| gml::Error::FunctionError("show_message".into(), e))?;
-        window.set_visible(true);
-        renderer.set_vsync(false);
-
-        loop {
-            window.process_events();
-            if window.close_requested() {
-                break
-            }
-
-            if window.get_inner_size() != (0, 0) {
-                renderer.finish(width, height, clear_colour);
-            }
-        }
-
-        // restore renderer
-        // self.renderer.set_current(); <- TODO, obviously
-
-        Ok(Default::default())
+        let text = expect_args!(args, [bytes])?;
+        self.show_dialog("show_message", Dialog::Message(text))
     }
 
-    pub fn show_question(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function show_question")
+    pub fn show_question(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let text = expect_args!(args, [bytes])?;
+        self.show_dialog("show_question", Dialog::Question(text))
     }
 
-    pub fn show_error(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function show_error")
+    pub fn show_error(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (text, abort) = expect_args!(args, [bytes, bool])?;
+        let message = self.decode_str(text.as_ref()).into_owned();
+        if self.report_error(&message, abort)? {
+            Err(gml::Error::Aborted(Box::new(gml::Error::FunctionError("show_error".into(), message))))
+        } else {
+            Ok(Default::default())
+        }
     }
 
-    pub fn show_info(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function show_info")
+    pub fn show_info(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        let content = SplashContent::Text { text: self.game_info.clone(), background: self.game_info_colour };
+        self.show_blocking_splash("show_info", Splash { content, full: false, frames_left: None }, 0)?;
+        Ok(Default::default())
     }
 
-    pub fn load_info(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function load_info")
+    pub fn load_info(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [string])?;
+        match std::fs::read(fname.as_ref()) {
+            Ok(info) => self.game_info = splash::rtf_to_text(&info).into(),
+            Err(e) => return Err(gml::Error::FunctionError("load_info".into(), e.to_string())),
+        }
+        Ok(Default::default())
     }
 
     pub fn highscore_show(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -6554,113 +6867,106 @@ impl Game {
         unimplemented!("Called unimplemented kernel function draw_highscore")
     }
 
-    pub fn show_message_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function show_message_ext")
+    pub fn show_message_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (text, but1, but2, but3) = expect_args!(args, [bytes, bytes, bytes, bytes])?;
+        self.show_dialog("show_message_ext", Dialog::MessageExt(text, [but1, but2, but3]))
     }
 
-    pub fn message_background(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_background")
-        // TODO
+    pub fn message_background(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let back = expect_args!(args, [int])?;
+        self.message_settings.background = back;
         Ok(Default::default())
     }
 
-    pub fn message_button(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_button")
-        // TODO
+    pub fn message_button(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sprite = expect_args!(args, [int])?;
+        self.message_settings.button_sprite = sprite;
         Ok(Default::default())
     }
 
-    pub fn message_alpha(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_alpha")
-        // TODO
+    pub fn message_alpha(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let alpha = expect_args!(args, [real])?;
+        self.message_settings.alpha = alpha;
         Ok(Default::default())
     }
 
-    pub fn message_text_font(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        //unimplemented!("Called unimplemented kernel function message_text_font")
-        // TODO
+    pub fn message_text_font(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, size, colour, style) = expect_args!(args, [bytes, int, int, int])?;
+        self.message_settings.text_font = MessageFont { name, size, colour, style };
         Ok(Default::default())
     }
 
-    pub fn message_button_font(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        //unimplemented!("Called unimplemented kernel function message_button_font")
-        // TODO
+    pub fn message_button_font(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, size, colour, style) = expect_args!(args, [bytes, int, int, int])?;
+        self.message_settings.button_font = MessageFont { name, size, colour, style };
         Ok(Default::default())
     }
 
-    pub fn message_input_font(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        //unimplemented!("Called unimplemented kernel function message_input_font")
-        // TODO
+    pub fn message_input_font(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, size, colour, style) = expect_args!(args, [bytes, int, int, int])?;
+        self.message_settings.input_font = MessageFont { name, size, colour, style };
         Ok(Default::default())
     }
 
-    pub fn message_text_charset(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_text_charset")
-        // TODO
+    pub fn message_text_charset(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        // Message boxes are drawn with the game's own fonts, which already have their charset baked in
+        let (_kind, _charset) = expect_args!(args, [int, int])?;
         Ok(Default::default())
     }
 
-    pub fn message_mouse_color(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_mouse_color")
-        // TODO
+    pub fn message_mouse_color(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let colour = expect_args!(args, [int])?;
+        self.message_settings.mouse_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_input_color(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_input_color")
-        // TODO
+    pub fn message_input_color(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let colour = expect_args!(args, [int])?;
+        self.message_settings.input_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_position(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_position")
-        // TODO
+    pub fn message_position(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y) = expect_args!(args, [int, int])?;
+        self.message_settings.position = (x, y);
         Ok(Default::default())
     }
 
-    pub fn message_size(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_size")
-        // TODO
+    pub fn message_size(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (w, h) = expect_args!(args, [int, int])?;
+        self.message_settings.size = (w, h);
         Ok(Default::default())
     }
 
-    pub fn message_caption(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_caption")
-        // TODO
+    pub fn message_caption(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (show, caption) = expect_args!(args, [bool, bytes])?;
+        self.message_settings.show_caption = show;
+        self.message_settings.caption = caption;
         Ok(Default::default())
     }
 
-    pub fn show_menu(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function show_menu")
+    pub fn show_menu(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (options, default) = expect_args!(args, [bytes, any])?;
+        let options = options.as_ref().split(|c| *c == b'|').map(RCStr::from).collect();
+        self.show_dialog("show_menu", Dialog::Menu(options, None, default))
     }
 
-    pub fn show_menu_pos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function show_menu_pos")
+    pub fn show_menu_pos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, options, default) = expect_args!(args, [int, int, bytes, any])?;
+        // GM8 takes screen coordinates here, so make them relative to the window
+        let (win_x, win_y) = self.window.get_pos();
+        let options = options.as_ref().split(|c| *c == b'|').map(RCStr::from).collect();
+        self.show_dialog("show_menu_pos", Dialog::Menu(options, Some((x - win_x, y - win_y)), default))
     }
 
-    pub fn get_integer(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function get_integer")
+    pub fn get_integer(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (text, default) = expect_args!(args, [bytes, any])?;
+        self.show_dialog("get_integer", Dialog::Integer(text, default))
     }
 
-    pub fn get_string(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function get_string")
+    pub fn get_string(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (text, default) = expect_args!(args, [bytes, any])?;
+        self.show_dialog("get_string", Dialog::String(text, default))
     }
 
     pub fn get_color(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -6817,102 +7123,77 @@ impl Game {
         Ok(self.input_manager.mouse_check_scroll_down().into())
     }
 
-    pub fn joystick_exists(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_exists")
-        // TODO
-        Ok(gml::FALSE.into())
+    pub fn joystick_exists(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_info(joystick_index(id)).is_some().into())
     }
 
-    pub fn joystick_direction(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_direction")
-        // TODO
-        Ok(101.into())
+    pub fn joystick_direction(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_direction(joystick_index(id)).into())
     }
 
-    pub fn joystick_name(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_name")
-        // TODO
-        Ok("".into())
+    pub fn joystick_name(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_info(joystick_index(id)).map_or("", |info| info.name.as_str()).into())
     }
 
-    pub fn joystick_axes(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_axes")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_axes(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_info(joystick_index(id)).map_or(0, |info| u32::from(info.axes)).into())
     }
 
-    pub fn joystick_buttons(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_buttons")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_buttons(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_info(joystick_index(id)).map_or(0, |info| u32::from(info.buttons)).into())
     }
 
-    pub fn joystick_has_pov(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_has_pov")
-        // TODO
-        Ok(gml::FALSE.into())
+    pub fn joystick_has_pov(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_info(joystick_index(id)).map_or(false, |info| info.has_pov).into())
     }
 
-    pub fn joystick_check_button(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function joystick_check_button")
-        // TODO
-        Ok(gml::FALSE.into())
+    pub fn joystick_check_button(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, button) = expect_args!(args, [int, int])?;
+        if button < 1 {
+            return Ok(gml::FALSE.into())
+        }
+        Ok(self.input_manager.joystick_check_button(joystick_index(id), (button - 1) as usize).into())
     }
 
-    pub fn joystick_xpos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_xpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_xpos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_axis(joystick_index(id), JoystickAxis::X).into())
     }
 
-    pub fn joystick_ypos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_ypos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_ypos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_axis(joystick_index(id), JoystickAxis::Y).into())
     }
 
-    pub fn joystick_zpos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_zpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_zpos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_axis(joystick_index(id), JoystickAxis::Z).into())
     }
 
-    pub fn joystick_rpos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_rpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_rpos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_axis(joystick_index(id), JoystickAxis::R).into())
     }
 
-    pub fn joystick_upos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_upos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_upos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_axis(joystick_index(id), JoystickAxis::U).into())
     }
 
-    pub fn joystick_vpos(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_vpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_vpos(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_axis(joystick_index(id), JoystickAxis::V).into())
     }
 
-    pub fn joystick_pov(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_pov")
-        // TODO
-        Ok((-1).into())
+    pub fn joystick_pov(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input_manager.joystick_pov(joystick_index(id)).into())
     }
 
     pub fn keyboard_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -6959,149 +7240,240 @@ impl Game {
         unimplemented!("Called unimplemented kernel function mouse_wait")
     }
 
-    pub fn mplay_init_ipx(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_init_ipx")
+    /// Runs an mplay_* function which depends on or changes the network state. Its result is stored when recording,
+    /// and during replay the stored result is returned instead, without touching the network at all.
+    fn mplay_result(&mut self, function: &str, f: impl FnOnce(&mut Self) -> Value) -> gml::Result<Value> {
+        match self.play_type {
+            PlayType::Normal => Ok(f(self)),
+            PlayType::Record => {
+                let value = f(self);
+                self.stored_events.push_back(replay::Event::Multiplayer(value.clone()));
+                Ok(value)
+            },
+            PlayType::Replay => {
+                if let Some(replay::Event::Multiplayer(value)) = self.stored_events.pop_front() {
+                    Ok(value)
+                } else {
+                    Err(gml::Error::ReplayError(function.into()))
+                }
+            },
+        }
     }
 
-    pub fn mplay_init_tcpip(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_init_tcpip")
+    pub fn mplay_init_ipx(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        // Only TCP/IP connections are emulated
+        self.mplay_result("mplay_init_ipx", |game| {
+            game.mplay.end();
+            gml::FALSE.into()
+        })
     }
 
-    pub fn mplay_init_modem(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function mplay_init_modem")
+    pub fn mplay_init_tcpip(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let address = expect_args!(args, [string])?;
+        self.mplay_result("mplay_init_tcpip", |game| game.mplay.init_tcpip(address.as_ref()).into())
     }
 
-    pub fn mplay_init_serial(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function mplay_init_serial")
+    pub fn mplay_init_modem(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [any, any])?;
+        self.mplay_result("mplay_init_modem", |game| {
+            game.mplay.end();
+            gml::FALSE.into()
+        })
     }
 
-    pub fn mplay_connect_status(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_connect_status")
+    pub fn mplay_init_serial(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [any, any, any, any, any])?;
+        self.mplay_result("mplay_init_serial", |game| {
+            game.mplay.end();
+            gml::FALSE.into()
+        })
     }
 
-    pub fn mplay_end(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_end")
+    pub fn mplay_connect_status(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        // 2 means TCP/IP
+        self.mplay_result("mplay_connect_status", |game| if game.mplay.is_connected() { 2 } else { 0 }.into())
     }
 
-    pub fn mplay_session_mode(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_session_mode")
+    pub fn mplay_end(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_end", |game| {
+            game.mplay.end();
+            Default::default()
+        })
     }
 
-    pub fn mplay_session_create(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mplay_session_create")
+    pub fn mplay_session_mode(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let move_host = expect_args!(args, [bool])?;
+        self.mplay.move_host = move_host;
+        Ok(Default::default())
     }
 
-    pub fn mplay_session_find(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_session_find")
+    pub fn mplay_session_create(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, max_players, player_name) = expect_args!(args, [string, int, string])?;
+        self.mplay_result("mplay_session_create", |game| {
+            game.mplay.create_session(name.as_ref(), max_players.max(0) as u32, player_name.as_ref()).into()
+        })
     }
 
-    pub fn mplay_session_name(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_session_name")
+    pub fn mplay_session_find(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_session_find", |game| game.mplay.find_sessions().into())
     }
 
-    pub fn mplay_session_join(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function mplay_session_join")
+    pub fn mplay_session_name(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let index = expect_args!(args, [int])?;
+        self.mplay_result("mplay_session_name", |game| {
+            game.mplay.found_session(index as usize).map_or("", |s| s.name.as_str()).into()
+        })
     }
 
-    pub fn mplay_session_status(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_session_status")
+    pub fn mplay_session_join(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (index, player_name) = expect_args!(args, [int, string])?;
+        self.mplay_result("mplay_session_join", |game| {
+            game.mplay.join_session(index as usize, player_name.as_ref()).into()
+        })
     }
 
-    pub fn mplay_session_end(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_session_end")
+    pub fn mplay_session_status(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_session_status", |game| {
+            game.mplay.poll();
+            game.mplay.session_status().into()
+        })
     }
 
-    pub fn mplay_player_find(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_player_find")
+    pub fn mplay_session_end(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_session_end", |game| {
+            game.mplay.end_session();
+            Default::default()
+        })
     }
 
-    pub fn mplay_player_name(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_player_name")
+    pub fn mplay_player_find(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_player_find", |game| {
+            game.mplay.poll();
+            game.mplay.players().len().into()
+        })
     }
 
-    pub fn mplay_player_id(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_player_id")
+    pub fn mplay_player_name(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let index = expect_args!(args, [int])?;
+        self.mplay_result("mplay_player_name", |game| {
+            game.mplay.players().get(index as usize).map_or("", |p| p.name.as_str()).into()
+        })
     }
 
-    pub fn mplay_data_write(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function mplay_data_write")
+    pub fn mplay_player_id(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let index = expect_args!(args, [int])?;
+        self.mplay_result("mplay_player_id", |game| game.mplay.players().get(index as usize).map_or(0, |p| p.id).into())
     }
 
-    pub fn mplay_data_read(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_data_read")
+    pub fn mplay_data_write(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (index, value) = expect_args!(args, [int, any])?;
+        self.mplay_result("mplay_data_write", |game| {
+            game.mplay.poll();
+            if index >= 0 {
+                game.mplay.data_write(index as u32, value);
+            }
+            Default::default()
+        })
     }
 
-    pub fn mplay_data_mode(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_data_mode")
+    pub fn mplay_data_read(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let index = expect_args!(args, [int])?;
+        self.mplay_result("mplay_data_read", |game| {
+            game.mplay.poll();
+            if index >= 0 { game.mplay.data_read(index as u32) } else { Default::default() }
+        })
     }
 
-    pub fn mplay_message_send(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mplay_message_send")
+    pub fn mplay_data_mode(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let guaranteed = expect_args!(args, [bool])?;
+        self.mplay.guaranteed_data = guaranteed;
+        Ok(Default::default())
     }
 
-    pub fn mplay_message_send_guaranteed(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mplay_message_send_guaranteed")
+    pub fn mplay_message_send(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (player, id, value) = expect_args!(args, [any, int, any])?;
+        self.mplay_result("mplay_message_send", |game| {
+            game.mplay.poll();
+            match game.mplay.find_player(&player) {
+                Some(to) => game.mplay.send_message(to, id, value).into(),
+                None => gml::FALSE.into(),
+            }
+        })
     }
 
-    pub fn mplay_message_receive(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_message_receive")
+    pub fn mplay_message_send_guaranteed(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        // All messages are sent over TCP, so they're always guaranteed
+        self.mplay_message_send(context, args)
     }
 
-    pub fn mplay_message_id(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_message_id")
+    pub fn mplay_message_receive(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let player = expect_args!(args, [any])?;
+        self.mplay_result("mplay_message_receive", |game| {
+            game.mplay.poll();
+            match game.mplay.find_player(&player) {
+                Some(from) => game.mplay.receive_message(from).into(),
+                None => gml::FALSE.into(),
+            }
+        })
     }
 
-    pub fn mplay_message_value(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_message_value")
+    pub fn mplay_message_id(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_message_id", |game| game.mplay.current_message.as_ref().map_or(0, |m| m.id).into())
     }
 
-    pub fn mplay_message_player(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_message_player")
+    pub fn mplay_message_value(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_message_value", |game| {
+            game.mplay.current_message.as_ref().map(|m| m.value.clone()).unwrap_or_default()
+        })
     }
 
-    pub fn mplay_message_name(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function mplay_message_name")
+    pub fn mplay_message_player(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_message_player", |game| {
+            game.mplay.current_message.as_ref().map_or(0, |m| m.player).into()
+        })
     }
 
-    pub fn mplay_message_count(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_message_count")
+    pub fn mplay_message_name(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.mplay_result("mplay_message_name", |game| {
+            game.mplay.current_message.as_ref().map_or("", |m| m.name.as_str()).into()
+        })
     }
 
-    pub fn mplay_message_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mplay_message_clear")
+    pub fn mplay_message_count(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let player = expect_args!(args, [any])?;
+        self.mplay_result("mplay_message_count", |game| {
+            game.mplay.poll();
+            game.mplay.find_player(&player).map_or(0, |from| game.mplay.message_count(from)).into()
+        })
+    }
+
+    pub fn mplay_message_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let player = expect_args!(args, [any])?;
+        self.mplay_result("mplay_message_clear", |game| {
+            game.mplay.poll();
+            if let Some(from) = game.mplay.find_player(&player) {
+                game.mplay.clear_messages(from);
+            }
+            Default::default()
+        })
     }
 
     pub fn mplay_ipaddress(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
-        Ok(network::get_local_ip().unwrap_or(std::net::Ipv4Addr::LOCALHOST.into()).to_string().into())
+        self.mplay_result("mplay_ipaddress", |_| {
+            network::get_local_ip().unwrap_or(std::net::Ipv4Addr::LOCALHOST.into()).to_string().into()
+        })
     }
 
     pub fn event_inherited(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -7498,19 +7870,55 @@ impl Game {
         self.variable_local_array_set(context, &[identifier, ((index1 * 32000) + index2).into(), value])
     }
 
-    pub fn clipboard_has_text(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function clipboard_has_text")
-    }
-
-    pub fn clipboard_set_text(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function clipboard_set_text")
+    pub fn clipboard_has_text(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        match self.play_type {
+            PlayType::Normal => Ok(self.clipboard.has_text().into()),
+            PlayType::Record => {
+                let has_text = Value::from(self.clipboard.has_text());
+                self.stored_events.push_back(replay::Event::ClipboardHasText(has_text.clone()));
+                Ok(has_text)
+            },
+            PlayType::Replay => {
+                if let Some(replay::Event::ClipboardHasText(has_text)) = self.stored_events.pop_front() {
+                    Ok(has_text)
+                } else {
+                    Err(gml::Error::ReplayError("clipboard_has_text".into()))
+                }
+            },
+        }
     }
 
-    pub fn clipboard_get_text(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function clipboard_get_text")
+    pub fn clipboard_set_text(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let text = expect_args!(args, [bytes])?;
+        // replays mustn't depend on or change the host's clipboard
+        if self.play_type != PlayType::Replay {
+            let text = self.decode_str(text.as_ref()).into_owned();
+            self.clipboard.set_text(&text);
+        }
+        Ok(Default::default())
+    }
+
+    pub fn clipboard_get_text(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        match self.play_type {
+            PlayType::Normal | PlayType::Record => {
+                let text = self.clipboard.get_text().unwrap_or_default();
+                let encoded = self.encode_str_maybe(&text).unwrap_or_else(|| self.encoding.encode(&text).0);
+                let text = Value::from(encoded.as_ref());
+                if self.play_type == PlayType::Record {
+                    self.stored_events.push_back(replay::Event::ClipboardGetText(text.clone()));
+                }
+                Ok(text)
+            },
+            PlayType::Replay => {
+                if let Some(replay::Event::ClipboardGetText(text)) = self.stored_events.pop_front() {
+                    Ok(text)
+                } else {
+                    Err(gml::Error::ReplayError("clipboard_get_text".into()))
+                }
+            },
+        }
     }
 
     pub fn date_current_datetime(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -7844,14 +8252,59 @@ impl Game {
         }
     }
 
-    pub fn sprite_set_offset(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function sprite_set_offset")
+    pub fn sprite_set_offset(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sprite_id, origin_x, origin_y) = expect_args!(args, [int, int, int])?;
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(sprite_id) {
+            // the origin is part of the atlas ref, so every frame has to be uploaded again
+            for frame in sprite.frames.iter_mut() {
+                let data = self.renderer.dump_sprite(&frame.atlas_ref);
+                self.renderer.delete_sprite(frame.atlas_ref);
+                frame.atlas_ref = self
+                    .renderer
+                    .upload_sprite(data, frame.width as _, frame.height as _, origin_x, origin_y)
+                    .map_err(|e| gml::Error::FunctionError("sprite_set_offset".into(), e))?;
+            }
+            sprite.origin_x = origin_x;
+            sprite.origin_y = origin_y;
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sprite, sprite_id))
+        }
     }
 
-    pub fn sprite_set_alpha_from_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_set_alpha_from_sprite")
+    pub fn sprite_set_alpha_from_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        let alpha_src = match self.assets.sprites.get_asset(src_id) {
+            Some(src) => src
+                .frames
+                .iter()
+                .map(|f| (self.renderer.dump_sprite(&f.atlas_ref), f.width))
+                .collect::<Vec<_>>(),
+            None => return Err(gml::Error::NonexistentAsset(asset::Type::Sprite, src_id)),
+        };
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(dst_id) {
+            let mut images = Vec::with_capacity(sprite.frames.len());
+            for (i, frame) in sprite.frames.iter_mut().enumerate() {
+                let mut dst = self.renderer.dump_sprite(&frame.atlas_ref);
+                self.renderer.delete_sprite(frame.atlas_ref);
+                let (src, src_w) = &alpha_src[i % alpha_src.len()];
+                for (dst_row, src_row) in dst.chunks_mut(frame.width as usize * 4).zip(src.chunks(*src_w as usize * 4)) {
+                    for (dst_col, src_col) in dst_row.chunks_mut(4).zip(src_row.chunks(4)) {
+                        dst_col[3] = src_col[3];
+                    }
+                }
+                images.push(RgbaImage::from_vec(frame.width, frame.height, dst.to_vec()).unwrap());
+                frame.atlas_ref = self
+                    .renderer
+                    .upload_sprite(dst, frame.width as _, frame.height as _, sprite.origin_x, sprite.origin_y)
+                    .map_err(|e| gml::Error::FunctionError("sprite_set_alpha_from_sprite".into(), e))?;
+            }
+            sprite.colliders = asset::sprite::make_colliders_precise(&images, 0, sprite.per_frame_colliders);
+            sprite.update_bbox();
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sprite, dst_id))
+        }
     }
 
     pub fn sprite_create_from_screen(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -7861,8 +8314,8 @@ impl Game {
         // but we need the pixel data to make the colliders
         let x = x.max(0);
         let y = y.max(0);
-        let width = width.min(self.unscaled_width as i32 - x);
-        let height = height.min(self.unscaled_height as i32 - y);
+        let width = width.min(self.window_region_size().0 as i32 - x);
+        let height = height.min(self.window_region_size().1 as i32 - y);
         self.renderer.flush_queue();
         let rgba = self.renderer.get_pixels(x, y, width, height);
         let mut image = RgbaImage::from_vec(width as _, height as _, rgba.into_vec()).unwrap();
@@ -7901,8 +8354,8 @@ impl Game {
             // get image
             let x = x.max(0);
             let y = y.max(0);
-            let width = width.min(self.unscaled_width as i32 - x);
-            let height = height.min(self.unscaled_height as i32 - y);
+            let width = width.min(self.window_region_size().0 as i32 - x);
+            let height = height.min(self.window_region_size().1 as i32 - y);
             self.renderer.flush_queue();
             let rgba = self.renderer.get_pixels(x, y, width, height);
             let mut image = RgbaImage::from_vec(width as _, height as _, rgba.into_vec()).unwrap();
@@ -8155,14 +8608,52 @@ impl Game {
         }
     }
 
-    pub fn sprite_add_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function sprite_add_sprite")
+    pub fn sprite_add_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [string])?;
+        let sprite_file = match file::load_sprite_file(fname.as_ref()) {
+            Ok(sprite) => sprite,
+            Err(e) => {
+                eprintln!("Warning: sprite_add_sprite on {} failed: {}", fname, e);
+                return Ok((-1).into())
+            },
+        };
+        let sprite_id = self.assets.sprites.len();
+        let sprite = sprite_from_file(
+            &mut self.renderer,
+            format!("__newsprite{}", sprite_id).into(),
+            sprite_file,
+            "sprite_add_sprite",
+        )?;
+        self.assets.sprites.push(Some(Box::new(sprite)));
+        Ok(sprite_id.into())
     }
 
-    pub fn sprite_replace_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_replace_sprite")
+    pub fn sprite_replace_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sprite_id, fname) = expect_args!(args, [int, string])?;
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(sprite_id) {
+            let sprite_file = match file::load_sprite_file(fname.as_ref()) {
+                Ok(sprite) => sprite,
+                Err(e) => {
+                    eprintln!("Warning: sprite_replace_sprite on {} failed: {}", fname, e);
+                    return Ok((-1).into())
+                },
+            };
+            for frame in &sprite.frames {
+                self.renderer.delete_sprite(frame.atlas_ref);
+            }
+            *sprite = Box::new(sprite_from_file(
+                &mut self.renderer,
+                sprite.name.clone(),
+                sprite_file,
+                "sprite_replace_sprite",
+            )?);
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::FunctionError(
+                "sprite_replace_sprite".into(),
+                "Trying to replace non-existing sprite.".into(),
+            ))
+        }
     }
 
     pub fn sprite_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8178,19 +8669,117 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn sprite_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function sprite_duplicate")
+    pub fn sprite_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sprite_id = expect_args!(args, [int])?;
+        if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
+            let renderer = &mut self.renderer;
+            let frames = sprite
+                .frames
+                .iter()
+                .map(|f| {
+                    Ok(asset::sprite::Frame {
+                        width: f.width,
+                        height: f.height,
+                        atlas_ref: renderer
+                            .duplicate_sprite(&f.atlas_ref)
+                            .map_err(|e| gml::Error::FunctionError("sprite_duplicate".into(), e))?,
+                    })
+                })
+                .collect::<gml::Result<_>>()?;
+            let new_id = self.assets.sprites.len();
+            let new_sprite =
+                asset::Sprite { name: format!("__newsprite{}", new_id).into(), frames, ..(**sprite).clone() };
+            self.assets.sprites.push(Some(Box::new(new_sprite)));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sprite, sprite_id))
+        }
     }
 
-    pub fn sprite_assign(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_assign")
+    pub fn sprite_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.sprites.get_asset(src_id) {
+            if dst_id >= 0 && self.assets.sprites.len() > dst_id as usize {
+                // duplicate first in case the sprite is being assigned to itself
+                let renderer = &mut self.renderer;
+                let frames = src
+                    .frames
+                    .iter()
+                    .map(|f| {
+                        Ok(asset::sprite::Frame {
+                            width: f.width,
+                            height: f.height,
+                            atlas_ref: renderer
+                                .duplicate_sprite(&f.atlas_ref)
+                                .map_err(|e| gml::Error::FunctionError("sprite_assign".into(), e))?,
+                        })
+                    })
+                    .collect::<gml::Result<_>>()?;
+                let new_sprite = asset::Sprite { frames, ..(**src).clone() };
+                if let Some(sprite) = self.assets.sprites.get_asset(dst_id) {
+                    for frame in &sprite.frames {
+                        self.renderer.delete_sprite(frame.atlas_ref);
+                    }
+                }
+                self.assets.sprites[dst_id as usize] = Some(Box::new(new_sprite));
+                Ok(Default::default())
+            } else {
+                Err(gml::Error::FunctionError("sprite_assign".into(), "Destination sprite has an invalid index".into()))
+            }
+        } else {
+            Err(gml::Error::FunctionError("sprite_assign".into(), "Source sprite does not exist".into()))
+        }
     }
 
-    pub fn sprite_merge(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_merge")
+    pub fn sprite_merge(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        let mut new_images = match self.assets.sprites.get_asset(src_id) {
+            Some(src) => src
+                .frames
+                .iter()
+                .map(|f| {
+                    RgbaImage::from_vec(f.width, f.height, self.renderer.dump_sprite(&f.atlas_ref).into_vec()).unwrap()
+                })
+                .collect::<Vec<_>>(),
+            None => return Err(gml::Error::NonexistentAsset(asset::Type::Sprite, src_id)),
+        };
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(dst_id) {
+            // new frames get stretched to fit the sprite
+            for image in new_images.iter_mut() {
+                asset::sprite::scale(image, sprite.width, sprite.height);
+            }
+            // generate collision
+            let mut images = Vec::with_capacity(sprite.frames.len() + new_images.len());
+            for f in sprite.frames.iter() {
+                images.push(
+                    RgbaImage::from_vec(f.width, f.height, self.renderer.dump_sprite(&f.atlas_ref).into_vec()).unwrap(),
+                );
+            }
+            let old_len = images.len();
+            images.append(&mut new_images);
+            sprite.colliders = asset::sprite::make_colliders_precise(&images, 0, sprite.per_frame_colliders);
+            sprite.update_bbox();
+            // upload frames
+            for image in images.drain(old_len..) {
+                sprite.frames.push(asset::sprite::Frame {
+                    width: sprite.width,
+                    height: sprite.height,
+                    atlas_ref: self
+                        .renderer
+                        .upload_sprite(
+                            image.into_raw().into_boxed_slice(),
+                            sprite.width as _,
+                            sprite.height as _,
+                            sprite.origin_x,
+                            sprite.origin_y,
+                        )
+                        .map_err(|e| gml::Error::FunctionError("sprite_merge".into(), e))?,
+                });
+            }
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sprite, dst_id))
+        }
     }
 
     pub fn sprite_save(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8212,9 +8801,27 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn sprite_save_strip(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_save_strip")
+    pub fn sprite_save_strip(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sprite_id, fname) = expect_args!(args, [int, string])?;
+        if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
+            // frames are laid out side by side, the same way sprite_add splits them
+            let strip_width = sprite.width as usize * sprite.frames.len();
+            let mut strip = vec![0u8; strip_width * sprite.height as usize * 4];
+            for (i, frame) in sprite.frames.iter().enumerate() {
+                let data = self.renderer.dump_sprite(&frame.atlas_ref);
+                let frame_row = frame.width as usize * 4;
+                for (y, row) in data.chunks(frame_row).take(sprite.height as usize).enumerate() {
+                    let start = (y * strip_width + i * sprite.width as usize) * 4;
+                    let len = row.len().min(sprite.width as usize * 4);
+                    strip[start..start + len].copy_from_slice(&row[..len]);
+                }
+            }
+            if let Err(e) = file::save_image(fname.as_ref(), strip_width as _, sprite.height, strip.into_boxed_slice())
+            {
+                return Err(gml::Error::FunctionError("sprite_save_strip".into(), e.to_string()))
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn sprite_collision_mask(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8251,29 +8858,9 @@ impl Game {
                 .collect::<Vec<RgbaImage>>();
 
             // make colliders
-            sprite.colliders = match kind {
-                0 => asset::sprite::make_colliders_precise(&frames, tolerance, sepmasks), // precise
-                _ => asset::sprite::make_colliders_shaped(&frames, tolerance, sepmasks, bbox, match kind {
-                    1 => Some(asset::sprite::ColliderShape::Rectangle),
-                    2 => Some(asset::sprite::ColliderShape::Ellipse),
-                    3 => Some(asset::sprite::ColliderShape::Diamond),
-                    _ => None,
-                }),
-            };
-
-            // set bbox variables manually if needed (even if using precise collision)
-            if let Some(bbox) = bbox {
-                for c in &mut sprite.colliders {
-                    c.bbox_left = bbox.left;
-                    c.bbox_top = bbox.top;
-                    c.bbox_right = bbox.right;
-                    c.bbox_bottom = bbox.bottom;
-                }
-            }
-            sprite.bbox_left = sprite.colliders.iter().map(|c| c.bbox_left).min().unwrap();
-            sprite.bbox_top = sprite.colliders.iter().map(|c| c.bbox_top).min().unwrap();
-            sprite.bbox_right = sprite.colliders.iter().map(|c| c.bbox_right).max().unwrap();
-            sprite.bbox_bottom = sprite.colliders.iter().map(|c| c.bbox_bottom).max().unwrap();
+            sprite.colliders = asset::sprite::make_colliders(&frames, kind, tolerance, sepmasks, bbox);
+            sprite.per_frame_colliders = sepmasks;
+            sprite.update_bbox();
         }
         Ok(Default::default())
     }
@@ -8356,8 +8943,8 @@ impl Game {
         let (x, y, width, height, removeback, smooth) = expect_args!(args, [int, int, int, int, bool, bool])?;
         let x = x.max(0);
         let y = y.max(0);
-        let width = width.min(self.unscaled_width as i32 - x);
-        let height = height.min(self.unscaled_height as i32 - y);
+        let width = width.min(self.window_region_size().0 as i32 - x);
+        let height = height.min(self.window_region_size().1 as i32 - y);
         self.renderer.flush_queue();
         let rgba = self.renderer.get_pixels(x, y, width, height);
         let mut image = RgbaImage::from_vec(width as _, height as _, rgba.into_vec()).unwrap();
@@ -8426,9 +9013,49 @@ impl Game {
         Ok(background_id.into())
     }
 
-    pub fn background_create_gradient(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function background_create_gradient")
+    pub fn background_create_gradient(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (w, h, col1, col2, kind) = expect_args!(args, [int, int, int, int, int])?;
+        let (w, h) = (w.max(1), h.max(1));
+        // how far each pixel is from col1 to col2, from 0 to 1
+        let position = |x: i32, y: i32| -> Real {
+            let fx = if w > 1 { Real::from(x) / Real::from(w - 1) } else { Real::from(0.0) };
+            let fy = if h > 1 { Real::from(y) / Real::from(h - 1) } else { Real::from(0.0) };
+            // distance from the centre, with the edges at 1
+            let dx = (fx * Real::from(2.0) - Real::from(1.0)).abs();
+            let dy = (fy * Real::from(2.0) - Real::from(1.0)).abs();
+            match kind {
+                0 => fx,                                                         // horizontal
+                1 => fy,                                                         // vertical
+                2 => Real::from(1.0) - dx.max(dy),                               // rectangle
+                3 => Real::from(1.0) - (dx * dx + dy * dy).sqrt().min(1.into()), // ellipse
+                4 => Real::from(1.0) - dx,                                       // double horizontal
+                _ => Real::from(1.0) - dy,                                       // double vertical
+            }
+        };
+        let mut data = Vec::with_capacity((w * h * 4) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let amount = position(x, y);
+                for shift in [0, 8, 16].iter() {
+                    let c1 = Real::from((col1 >> shift) & 255);
+                    let c2 = Real::from((col2 >> shift) & 255);
+                    data.push((c1 * (Real::from(1) - amount) + c2 * amount).round() as u8);
+                }
+                data.push(255);
+            }
+        }
+        let background_id = self.assets.backgrounds.len();
+        self.assets.backgrounds.push(Some(Box::new(asset::Background {
+            name: format!("__newbackground{}", background_id).into(),
+            width: w as _,
+            height: h as _,
+            atlas_ref: Some(
+                self.renderer
+                    .upload_sprite(data.into_boxed_slice(), w, h, 0, 0)
+                    .map_err(|e| gml::Error::FunctionError("background_create_gradient".into(), e))?,
+            ),
+        })));
+        Ok(background_id.into())
     }
 
     pub fn background_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8489,14 +9116,60 @@ impl Game {
         }
     }
 
-    pub fn background_add_background(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function background_add_background")
+    pub fn background_add_background(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [string])?;
+        let image = match file::load_background_file(fname.as_ref()) {
+            Ok(im) => im,
+            Err(e) => {
+                eprintln!("Warning: background_add_background on {} failed: {}", fname, e);
+                return Ok((-1).into())
+            },
+        };
+        let width = image.width();
+        let height = image.height();
+        let atlas_ref = self
+            .renderer
+            .upload_sprite(image.into_raw().into_boxed_slice(), width as _, height as _, 0, 0)
+            .map_err(|e| gml::Error::FunctionError("background_add_background".into(), e))?;
+        let background_id = self.assets.backgrounds.len();
+        self.assets.backgrounds.push(Some(Box::new(asset::Background {
+            name: format!("__newbackground{}", background_id).into(),
+            width,
+            height,
+            atlas_ref: Some(atlas_ref),
+        })));
+        Ok(background_id.into())
     }
 
-    pub fn background_replace_background(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function background_replace_background")
+    pub fn background_replace_background(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (background_id, fname) = expect_args!(args, [int, string])?;
+        if let Some(background) = self.assets.backgrounds.get_asset_mut(background_id) {
+            let image = match file::load_background_file(fname.as_ref()) {
+                Ok(im) => im,
+                Err(e) => {
+                    eprintln!("Warning: background_replace_background on {} failed: {}", fname, e);
+                    return Ok((-1).into())
+                },
+            };
+            if let Some(atlas_ref) = background.atlas_ref {
+                self.renderer.delete_sprite(atlas_ref);
+            }
+            let width = image.width();
+            let height = image.height();
+            let atlas_ref = self
+                .renderer
+                .upload_sprite(image.into_raw().into_boxed_slice(), width as _, height as _, 0, 0)
+                .map_err(|e| gml::Error::FunctionError("background_replace_background".into(), e))?;
+            background.atlas_ref = Some(atlas_ref);
+            background.width = width;
+            background.height = height;
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::FunctionError(
+                "background_replace_background".into(),
+                "Trying to replace non-existing background.".into(),
+            ))
+        }
     }
 
     pub fn background_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8515,9 +9188,29 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn background_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function background_duplicate")
+    pub fn background_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let background_id = expect_args!(args, [int])?;
+        if let Some(background) = self.assets.backgrounds.get_asset(background_id) {
+            let atlas_ref = match background.atlas_ref.as_ref() {
+                Some(ar) => Some(
+                    self.renderer
+                        .duplicate_sprite(ar)
+                        .map_err(|e| gml::Error::FunctionError("background_duplicate".into(), e))?,
+                ),
+                None => None,
+            };
+            let new_id = self.assets.backgrounds.len();
+            let new_background = asset::Background {
+                name: format!("__newbackground{}", new_id).into(),
+                width: background.width,
+                height: background.height,
+                atlas_ref,
+            };
+            self.assets.backgrounds.push(Some(Box::new(new_background)));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Background, background_id))
+        }
     }
 
     pub fn background_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8643,44 +9336,124 @@ impl Game {
         Ok(self.assets.fonts.get_asset(asset_id).map(|x| x.name.clone().into()).unwrap_or("<undefined>".into()))
     }
 
-    pub fn font_get_fontname(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_fontname")
+    pub fn font_get_fontname(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(font.sys_name.clone().into())
+        } else {
+            Ok("".into())
+        }
     }
 
-    pub fn font_get_size(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_size")
+    pub fn font_get_size(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(font.size.into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_get_bold(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_bold")
+    pub fn font_get_bold(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(font.bold.into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_get_italic(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_italic")
+    pub fn font_get_italic(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(font.italic.into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_get_first(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_first")
+    pub fn font_get_first(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(u32::from(font.first).into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_get_last(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_last")
+    pub fn font_get_last(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(u32::from(font.last).into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function font_add")
+    pub fn font_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, size, bold, italic, first, last) = expect_args!(args, [bytes, int, bool, bool, int, int])?;
+        let size = size.max(1) as u32;
+        let first = first.max(0).min(255) as u8;
+        let last = last.max(first.into()).min(255) as u8;
+        match create_chars_from_font_name(self, &name, size, bold, italic, first, last) {
+            Ok((chars, tallest_char_height)) => {
+                let font_id = self.assets.fonts.len();
+                self.assets.fonts.push(Some(Box::new(asset::Font {
+                    name: format!("__newfont{}", font_id).into(),
+                    sys_name: name,
+                    charset: 1,
+                    size,
+                    bold,
+                    italic,
+                    first,
+                    last,
+                    tallest_char_height,
+                    chars,
+                    own_graphics: true,
+                })));
+                Ok(font_id.into())
+            },
+            Err(e) => {
+                eprintln!("Warning: font_add on {} failed: {}", self.decode_str(name.as_ref()), e);
+                Ok((-1).into())
+            },
+        }
     }
 
-    pub fn font_replace(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function font_replace")
+    pub fn font_replace(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (font_id, name, size, bold, italic, first, last) =
+            expect_args!(args, [int, bytes, int, bool, bool, int, int])?;
+        if self.assets.fonts.get_asset(font_id).is_none() {
+            return Err(gml::Error::NonexistentAsset(asset::Type::Font, font_id))
+        }
+        let size = size.max(1) as u32;
+        let first = first.max(0).min(255) as u8;
+        let last = last.max(first.into()).min(255) as u8;
+        match create_chars_from_font_name(self, &name, size, bold, italic, first, last) {
+            Ok((chars, tallest_char_height)) => {
+                let font = self.assets.fonts.get_asset_mut(font_id).unwrap();
+                if font.own_graphics {
+                    for c in font.chars.iter() {
+                        self.renderer.delete_sprite(c.atlas_ref);
+                    }
+                }
+                font.sys_name = name;
+                font.charset = 1;
+                font.size = size;
+                font.bold = bold;
+                font.italic = italic;
+                font.first = first;
+                font.last = last;
+                font.tallest_char_height = tallest_char_height;
+                font.chars = chars;
+                font.own_graphics = true;
+                Ok(Default::default())
+            },
+            Err(e) => {
+                eprintln!("Warning: font_replace on {} failed: {}", self.decode_str(name.as_ref()), e);
+                Ok((-1).into())
+            },
+        }
     }
 
     pub fn font_add_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8714,8 +9487,7 @@ impl Game {
         if let Some(font) = self.assets.fonts.get_asset_mut(font_id) {
             if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
                 if font.own_graphics {
-                    // font_add isn't in yet but atm for ttfs all characters are on the same texture
-                    if let Some(c) = font.get_char(font.first) {
+                    for c in font.chars.iter() {
                         self.renderer.delete_sprite(c.atlas_ref);
                     }
                 }
@@ -8737,9 +9509,19 @@ impl Game {
         }
     }
 
-    pub fn font_delete(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_delete")
+    pub fn font_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            if font.own_graphics {
+                for c in font.chars.iter() {
+                    self.renderer.delete_sprite(c.atlas_ref);
+                }
+            }
+        } else {
+            return Err(gml::Error::FunctionError("font_delete".into(), "Trying to delete non-existing font".into()))
+        }
+        self.assets.fonts[font_id as usize] = None;
+        Ok(Default::default())
     }
 
     pub fn script_name(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8783,7 +9565,7 @@ impl Game {
                     locals: DummyFieldHolder::new(),
                     return_value: Default::default(),
                 };
-                self.execute(&instructions, &mut new_context)?;
+                self.execute_script(script_id as usize, &instructions, &mut new_context)?;
                 Ok(new_context.return_value)
             } else {
                 Err(gml::Error::NonexistentAsset(asset::Type::Script, script_id))
@@ -8957,19 +9739,42 @@ impl Game {
         Ok(path_id.into())
     }
 
-    pub fn path_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_duplicate")
+    pub fn path_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset(path_id) {
+            let new_id = self.assets.paths.len();
+            let mut new_path = path.clone();
+            new_path.name = format!("__newpath{}", new_id).into();
+            self.assets.paths.push(Some(new_path));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::FunctionError("path_duplicate".into(), format!("Path {} does not exist", path_id)))
+        }
     }
 
-    pub fn path_assign(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_assign")
+    pub fn path_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.paths.get_asset(src_id) {
+            let src = src.clone();
+            if let Some(dest) = self.assets.paths.get_asset_mut(dest_id) {
+                let name = dest.name.clone();
+                **dest = *src;
+                dest.name = name;
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_append(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_append")
+    pub fn path_append(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.paths.get_asset(src_id) {
+            let points = src.points.clone();
+            if let Some(dest) = self.assets.paths.get_asset_mut(dest_id) {
+                dest.points.extend_from_slice(&points);
+                dest.update();
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn path_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8989,9 +9794,14 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn path_insert_point(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function path_insert_point")
+    pub fn path_insert_point(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, n, x, y, speed) = expect_args!(args, [int, int, real, real, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            let n = n.max(0).min(path.points.len() as i32) as usize;
+            path.points.insert(n, asset::path::Point { x, y, speed });
+            path.update();
+        }
+        Ok(Default::default())
     }
 
     pub fn path_change_point(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9009,44 +9819,72 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn path_delete_point(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_delete_point")
+    pub fn path_delete_point(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, n) = expect_args!(args, [int, int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            if n >= 0 && (n as usize) < path.points.len() {
+                path.points.remove(n as usize);
+                path.update();
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_clear_points(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_clear_points")
+    pub fn path_clear_points(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points.clear();
+            path.update();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_reverse(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_reverse")
+    pub fn path_reverse(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.reverse();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_mirror(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_mirror")
+    pub fn path_mirror(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.mirror();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_flip(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_flip")
+    pub fn path_flip(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.flip();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_rotate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_rotate")
+    pub fn path_rotate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, angle) = expect_args!(args, [int, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.rotate(angle);
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_scale(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function path_scale")
+    pub fn path_scale(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xscale, yscale) = expect_args!(args, [int, real, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.scale(xscale, yscale);
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_shift(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function path_shift")
+    pub fn path_shift(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xshift, yshift) = expect_args!(args, [int, real, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.shift(xshift, yshift);
+        }
+        Ok(Default::default())
     }
 
     pub fn timeline_name(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9063,29 +9901,56 @@ impl Game {
         Ok(self.assets.timelines.get_asset(asset_id).map(|x| x.name.clone().into()).unwrap_or("<undefined>".into()))
     }
 
-    pub fn timeline_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function timeline_add")
+    pub fn timeline_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        let timeline_id = self.assets.timelines.len();
+        self.assets.timelines.push(Some(Box::new(asset::Timeline {
+            name: format!("__newtimeline{}", timeline_id).into(),
+            moments: Default::default(),
+        })));
+        Ok(timeline_id.into())
     }
 
-    pub fn timeline_delete(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function timeline_delete")
+    pub fn timeline_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let timeline_id = expect_args!(args, [int])?;
+        if self.assets.timelines.get_asset(timeline_id).is_some() {
+            self.assets.timelines[timeline_id as usize] = None;
+        }
+        Ok(Default::default())
     }
 
-    pub fn timeline_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function timeline_clear")
+    pub fn timeline_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let timeline_id = expect_args!(args, [int])?;
+        if let Some(timeline) = self.assets.timelines.get_asset(timeline_id) {
+            timeline.moments.borrow_mut().clear();
+        }
+        Ok(Default::default())
     }
 
-    pub fn timeline_moment_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function timeline_moment_clear")
+    pub fn timeline_moment_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (timeline_id, step) = expect_args!(args, [int, int])?;
+        if let Some(timeline) = self.assets.timelines.get_asset(timeline_id) {
+            timeline.moments.borrow_mut().remove(&step);
+        }
+        Ok(Default::default())
     }
 
-    pub fn timeline_moment_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function timeline_moment_add")
+    pub fn timeline_moment_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (timeline_id, step, code) = expect_args!(args, [int, int, bytes])?;
+        if let Some(timeline) = self.assets.timelines.get_asset(timeline_id) {
+            let instrs = match self.compiler.compile(code.as_ref()) {
+                Ok(instrs) => instrs,
+                Err(e) => return Err(gml::Error::FunctionError("timeline_moment_add".into(), e.message)),
+            };
+            let mut moments = timeline.moments.borrow_mut();
+            match moments.get(&step) {
+                Some(tree) => tree.borrow_mut().push_code(instrs),
+                None => {
+                    moments.insert(step, action::Tree::new_from_code(instrs));
+                },
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn object_name(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9328,14 +10193,23 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn room_set_persistent(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function room_set_persistent")
+    pub fn room_set_persistent(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, persistent) = expect_args!(args, [int, bool])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            room.persistent = persistent;
+        }
+        Ok(Default::default())
     }
 
-    pub fn room_set_code(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function room_set_code")
+    pub fn room_set_code(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, code) = expect_args!(args, [int, bytes])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            match self.compiler.compile(code.as_ref()) {
+                Ok(instrs) => room.creation_code = Ok(instrs),
+                Err(e) => return Err(gml::Error::FunctionError("room_set_code".into(), e.message)),
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn room_set_background_color(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9421,44 +10295,117 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn room_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function room_add")
+    pub fn room_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        // Like in GM8, new rooms aren't added to the room order
+        let room_id = self.assets.rooms.len();
+        self.assets.rooms.push(Some(Box::new(asset::Room::new(format!("__newroom{}", room_id).into()))));
+        Ok(room_id.into())
     }
 
-    pub fn room_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function room_duplicate")
+    pub fn room_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let room_id = expect_args!(args, [int])?;
+        if let Some(room) = self.assets.rooms.get_asset(room_id) {
+            let new_id = self.assets.rooms.len();
+            let mut new_room = room.clone();
+            new_room.name = format!("__newroom{}", new_id).into();
+            self.assets.rooms.push(Some(new_room));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::FunctionError("room_duplicate".into(), format!("Room {} does not exist", room_id)))
+        }
     }
 
-    pub fn room_assign(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function room_assign")
+    pub fn room_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.rooms.get_asset(src_id) {
+            let src = src.clone();
+            if let Some(dest) = self.assets.rooms.get_asset_mut(dest_id) {
+                let name = dest.name.clone();
+                **dest = *src;
+                dest.name = name;
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn room_instance_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function room_instance_add")
+    pub fn room_instance_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, x, y, object) = expect_args!(args, [int, int, int, int])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            self.last_instance_id += 1;
+            room.instances.push(asset::room::Instance {
+                x,
+                y,
+                object,
+                id: self.last_instance_id,
+                creation: Ok(Rc::new([])),
+            });
+            Ok(self.last_instance_id.into())
+        } else {
+            Ok(gml::NOONE.into())
+        }
     }
 
-    pub fn room_instance_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function room_instance_clear")
+    pub fn room_instance_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let room_id = expect_args!(args, [int])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            room.instances.clear();
+        }
+        Ok(Default::default())
     }
 
-    pub fn room_tile_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 9
-        unimplemented!("Called unimplemented kernel function room_tile_add")
+    pub fn room_tile_add(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, background_index, tile_x, tile_y, width, height, x, y, depth) =
+            expect_args!(args, [any, any, any, any, any, any, any, any, any])?;
+        self.room_tile_add_ext(context, &[
+            room_id,
+            background_index,
+            tile_x,
+            tile_y,
+            width,
+            height,
+            x,
+            y,
+            depth,
+            1.into(),
+            1.into(),
+            1.into(),
+        ])
     }
 
-    pub fn room_tile_add_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 12
-        unimplemented!("Called unimplemented kernel function room_tile_add_ext")
+    pub fn room_tile_add_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, background_index, tile_x, tile_y, width, height, x, y, depth, xscale, yscale, alpha) =
+            expect_args!(args, [int, int, int, int, int, int, real, real, real, real, real, real])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            self.last_tile_id += 1;
+            room.tiles.push(Tile {
+                x: x.into(),
+                y: y.into(),
+                background_index: background_index.into(),
+                tile_x: tile_x.into(),
+                tile_y: tile_y.into(),
+                width: width.into(),
+                height: height.into(),
+                depth: depth.into(),
+                id: self.last_tile_id.into(),
+                alpha: alpha.into(),
+                blend: 0xffffff.into(),
+                xscale: xscale.into(),
+                yscale: yscale.into(),
+                visible: true.into(),
+            });
+            Ok(self.last_tile_id.into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn room_tile_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function room_tile_clear")
+    pub fn room_tile_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let room_id = expect_args!(args, [int])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            room.tiles.clear();
+        }
+        Ok(Default::default())
     }
 
     pub fn part_type_create(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -1,4 +1,12 @@
-use std::{io, net};
+use crate::gml::Value;
+use serde::{Deserialize, Serialize};
+use shared::message::MessageStream;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read},
+    net::{self, IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
 
 pub fn get_local_ip() -> io::Result<net::IpAddr> {
     // For the meaning of 0.0.0.0, see 'INADDR_ANY'. Port 0 states that we don't expect any
@@ -42,3 +50,634 @@ pub fn get_local_ip() -> io::Result<net::IpAddr> {
     socket.connect(&broadcast[..])?;
     Ok(socket.local_addr()?.ip())
 }
+
+/// UDP port which session hosts listen on for queries. This is the one DirectPlay uses too.
+const DISCOVERY_PORT: u16 = 47624;
+
+/// How long mplay_session_find waits for hosts to respond.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long mplay_session_join waits to connect and be let in.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait for the rest of a packet once it's started arriving.
+const PACKET_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The largest packet accepted from another player. Anything bigger is treated as bad data.
+const MAX_PACKET_SIZE: usize = 1 << 20;
+
+/// Data slots which mplay_data_write and mplay_data_read can use.
+pub const DATA_SLOT_COUNT: u32 = 10000;
+
+/// A UDP message used to find sessions on the network.
+#[derive(Serialize, Deserialize)]
+enum Datagram {
+    FindSessions,
+    SessionInfo { name: String, port: u16, players: u32, max_players: u32 },
+}
+
+/// A message sent over a TCP connection between a session host and one of its clients.
+#[derive(Serialize, Deserialize)]
+enum Packet {
+    /// Client -> host, asking to be let in. The port is where the client will accept connections if it becomes host.
+    Join { name: String, port: u16 },
+    /// Client -> new host after a host migration, keeping its old player ID.
+    Rejoin { id: u32, name: String, port: u16 },
+    /// Host -> client, letting it in and telling it the state of the session.
+    Welcome { id: u32, players: Vec<Player>, data: Vec<(u32, Value)> },
+    /// Host -> client, the session is full.
+    Refused,
+    PlayerJoined(Player),
+    PlayerLeft(u32),
+    /// Host -> clients, the host is leaving and the given player will be taking over at the given address.
+    Migrate { id: u32, address: SocketAddr },
+    /// Host -> clients, the host left and the session is over.
+    SessionEnded,
+    Data { index: u32, value: Value },
+    /// A message to be delivered to a player, or to every other player if `to` is 0.
+    Message { from: u32, to: u32, id: i32, value: Value },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Player {
+    pub id: u32,
+    pub name: String,
+}
+
+/// A message received from another player.
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub player: u32,
+    pub name: String,
+    pub id: i32,
+    pub value: Value,
+}
+
+pub struct FoundSession {
+    pub name: String,
+    address: SocketAddr,
+    pub players: u32,
+    pub max_players: u32,
+}
+
+/// A client as seen by the host.
+struct Client {
+    id: u32,
+    stream: TcpStream,
+    migrate_address: SocketAddr,
+}
+
+enum Role {
+    Host {
+        listener: TcpListener,
+        discovery: Option<UdpSocket>,
+        /// Connections which haven't sent a Join or Rejoin yet
+        pending: Vec<TcpStream>,
+        clients: Vec<Client>,
+        max_players: u32,
+        next_id: u32,
+    },
+    Client {
+        stream: TcpStream,
+        /// Accepts the other players if the host migrates to us
+        listener: TcpListener,
+    },
+}
+
+struct Session {
+    name: String,
+    own_id: u32,
+    players: Vec<Player>,
+    role: Role,
+}
+
+/// The state of the mplay_* functions, which emulate a DirectPlay TCP/IP connection.
+/// Sessions use a star topology where the host relays messages and data between clients.
+#[derive(Default)]
+pub struct Multiplayer {
+    /// The address given to mplay_init_tcpip, None meaning broadcast to the LAN. Outer None if there's no connection.
+    connection: Option<Option<IpAddr>>,
+    session: Option<Session>,
+    sessions_found: Vec<FoundSession>,
+    /// Whether the host should be moved to another player when it leaves, rather than ending the session
+    pub move_host: bool,
+    /// Whether data writes are guaranteed to arrive. They always are over TCP, so this is only remembered.
+    pub guaranteed_data: bool,
+    data: HashMap<u32, Value>,
+    messages: VecDeque<Message>,
+    pub current_message: Option<Message>,
+    read_buffer: Vec<u8>,
+}
+
+/// Reads one packet from a non-blocking stream. Returns Err if the connection was closed or sent something invalid,
+/// either of which means it should be dropped.
+fn receive_packet(stream: &mut TcpStream, read_buffer: &mut Vec<u8>) -> io::Result<Option<Packet>> {
+    let mut len_buffer = [0u8; 4];
+    let start = match stream.read(&mut len_buffer) {
+        Ok(0) => return Err(io::ErrorKind::ConnectionAborted.into()),
+        Ok(len) => len,
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    // Once a packet has started, wait for the rest of it, but not forever
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(PACKET_TIMEOUT))?;
+    let result = (|| -> io::Result<Packet> {
+        stream.read_exact(&mut len_buffer[start..])?;
+        let len = u32::from_le_bytes(len_buffer) as usize;
+        if len > MAX_PACKET_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "packet too large"))
+        }
+        read_buffer.resize(len, 0);
+        stream.read_exact(read_buffer)?;
+        bincode::deserialize(read_buffer).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    })();
+    stream.set_nonblocking(true)?;
+    result.map(Some)
+}
+
+fn send_datagram(socket: &UdpSocket, datagram: &Datagram, address: SocketAddr) -> io::Result<()> {
+    let bytes = bincode::serialize(datagram).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    socket.send_to(&bytes, address).map(|_| ())
+}
+
+impl Multiplayer {
+    pub fn new() -> Self {
+        Self { guaranteed_data: true, ..Default::default() }
+    }
+
+    /// Sets up a TCP/IP connection. An empty address means sessions are searched for on the local network.
+    pub fn init_tcpip(&mut self, address: &str) -> bool {
+        self.end();
+        let address = address.trim();
+        if address.is_empty() {
+            self.connection = Some(None);
+            true
+        } else {
+            match (address, 0).to_socket_addrs().ok().and_then(|mut a| a.next()) {
+                Some(addr) => {
+                    self.connection = Some(Some(addr.ip()));
+                    true
+                },
+                None => false,
+            }
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Ends the session and closes the connection.
+    pub fn end(&mut self) {
+        self.end_session();
+        self.connection = None;
+        self.sessions_found.clear();
+    }
+
+    /// Returns 0 if not in a session, 1 if hosting one, and 2 if joined to one.
+    pub fn session_status(&self) -> u32 {
+        match self.session.as_ref().map(|s| &s.role) {
+            None => 0,
+            Some(Role::Host { .. }) => 1,
+            Some(Role::Client { .. }) => 2,
+        }
+    }
+
+    /// Hosts a new session. A max_players of 0 means there's no limit.
+    pub fn create_session(&mut self, name: &str, max_players: u32, player_name: &str) -> bool {
+        if self.connection.is_none() {
+            return false
+        }
+        self.end_session();
+        let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+            Ok(l) => l,
+            Err(_) => return false,
+        };
+        if listener.set_nonblocking(true).is_err() {
+            return false
+        }
+        // If something else is already answering queries on this machine, the session can still be joined directly
+        let discovery = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+            .and_then(|s| s.set_nonblocking(true).map(|_| s))
+            .ok();
+        self.data.clear();
+        self.messages.clear();
+        self.session = Some(Session {
+            name: name.into(),
+            own_id: 1,
+            players: vec![Player { id: 1, name: player_name.into() }],
+            role: Role::Host { listener, discovery, pending: Vec::new(), clients: Vec::new(), max_players, next_id: 2 },
+        });
+        true
+    }
+
+    /// Searches for sessions, returning how many were found.
+    pub fn find_sessions(&mut self) -> usize {
+        self.sessions_found.clear();
+        let targets: Vec<SocketAddr> = match self.connection {
+            None => return 0,
+            Some(Some(ip)) => vec![(ip, DISCOVERY_PORT).into()],
+            Some(None) => {
+                vec![(Ipv4Addr::BROADCAST, DISCOVERY_PORT).into(), (Ipv4Addr::LOCALHOST, DISCOVERY_PORT).into()]
+            },
+        };
+        let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+            Ok(s) => s,
+            Err(_) => return 0,
+        };
+        let _ = socket.set_broadcast(true);
+        for target in targets {
+            let _ = send_datagram(&socket, &Datagram::FindSessions, target);
+        }
+
+        // If we're hosting, the socket we'd answer with is our own, so answer ourselves
+        self.poll();
+
+        let start = Instant::now();
+        let mut buf = [0u8; 1024];
+        while let Some(remaining) = DISCOVERY_TIMEOUT.checked_sub(start.elapsed()) {
+            if remaining == Duration::from_secs(0) || socket.set_read_timeout(Some(remaining)).is_err() {
+                break
+            }
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(x) => x,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => break,
+                Err(_) => continue,
+            };
+            if let Ok(Datagram::SessionInfo { name, port, players, max_players }) = bincode::deserialize(&buf[..len]) {
+                let address = SocketAddr::new(from.ip(), port);
+                // Broadcasts can reach the same host through several interfaces
+                if !self.sessions_found.iter().any(|s| s.address.port() == port && s.name == name) {
+                    self.sessions_found.push(FoundSession { name, address, players, max_players });
+                }
+            }
+        }
+        self.sessions_found.len()
+    }
+
+    pub fn found_session(&self, index: usize) -> Option<&FoundSession> {
+        self.sessions_found.get(index)
+    }
+
+    /// Joins a session found by find_sessions.
+    pub fn join_session(&mut self, index: usize, player_name: &str) -> bool {
+        let address = match self.sessions_found.get(index) {
+            Some(s) => s.address,
+            None => return false,
+        };
+        let name = self.sessions_found[index].name.clone();
+        self.end_session();
+        let result = (|| -> io::Result<Option<Session>> {
+            let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+            listener.set_nonblocking(true)?;
+            let port = listener.local_addr()?.port();
+            let mut stream = TcpStream::connect_timeout(&address, JOIN_TIMEOUT)?;
+            stream.set_nonblocking(true)?;
+            stream.set_nodelay(true)?;
+            stream.send_message(&Packet::Join { name: player_name.into(), port })?;
+
+            let start = Instant::now();
+            while start.elapsed() < JOIN_TIMEOUT {
+                match receive_packet(&mut stream, &mut self.read_buffer)? {
+                    Some(Packet::Welcome { id, players, data }) => {
+                        self.data = data.into_iter().collect();
+                        return Ok(Some(Session { name, own_id: id, players, role: Role::Client { stream, listener } }))
+                    },
+                    Some(Packet::Refused) => return Ok(None),
+                    Some(_) => (),
+                    None => std::thread::sleep(Duration::from_millis(1)),
+                }
+            }
+            Ok(None)
+        })();
+        match result {
+            Ok(Some(session)) => {
+                self.messages.clear();
+                self.session = Some(session);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Leaves the current session. If we're the host, the session either ends or moves to another player.
+    pub fn end_session(&mut self) {
+        if let Some(session) = self.session.take() {
+            if let Role::Host { mut clients, .. } = session.role {
+                if self.move_host && !clients.is_empty() {
+                    let packet = Packet::Migrate { id: clients[0].id, address: clients[0].migrate_address };
+                    for client in clients.iter_mut() {
+                        let _ = client.stream.send_message(&packet);
+                    }
+                } else {
+                    for client in clients.iter_mut() {
+                        let _ = client.stream.send_message(&Packet::SessionEnded);
+                    }
+                }
+            }
+        }
+        self.data.clear();
+    }
+
+    /// Gets the list of players in the current session, including us.
+    pub fn players(&self) -> &[Player] {
+        self.session.as_ref().map_or(&[][..], |s| s.players.as_slice())
+    }
+
+    /// Finds a player by ID or name, as given to the GML functions.
+    pub fn find_player(&self, player: &Value) -> Option<u32> {
+        match player {
+            Value::Real(id) => Some(id.round() as u32),
+            Value::Str(name) => {
+                let name = name.decode_utf8();
+                self.players().iter().find(|p| p.name == name.as_ref()).map(|p| p.id)
+            },
+        }
+    }
+
+    pub fn data_read(&self, index: u32) -> Value {
+        self.data.get(&index).cloned().unwrap_or_default()
+    }
+
+    pub fn data_write(&mut self, index: u32, value: Value) {
+        if index >= DATA_SLOT_COUNT {
+            return
+        }
+        self.data.insert(index, value.clone());
+        let packet = Packet::Data { index, value };
+        match self.session.as_mut().map(|s| &mut s.role) {
+            Some(Role::Host { clients, .. }) => {
+                for client in clients.iter_mut() {
+                    let _ = client.stream.send_message(&packet);
+                }
+            },
+            Some(Role::Client { stream, .. }) => {
+                let _ = stream.send_message(&packet);
+            },
+            None => (),
+        }
+    }
+
+    /// Sends a message to a player, or to all other players if `to` is 0. Returns false if not in a session.
+    pub fn send_message(&mut self, to: u32, id: i32, value: Value) -> bool {
+        let session = match self.session.as_mut() {
+            Some(s) => s,
+            None => return false,
+        };
+        let packet = Packet::Message { from: session.own_id, to, id, value };
+        match &mut session.role {
+            Role::Host { clients, .. } => {
+                for client in clients.iter_mut().filter(|c| to == 0 || c.id == to) {
+                    let _ = client.stream.send_message(&packet);
+                }
+            },
+            Role::Client { stream, .. } => {
+                let _ = stream.send_message(&packet);
+            },
+        }
+        true
+    }
+
+    /// Takes the next message from the given player, or from anyone if `from` is 0, into `current_message`.
+    pub fn receive_message(&mut self, from: u32) -> bool {
+        match self.messages.iter().position(|m| from == 0 || m.player == from) {
+            Some(index) => {
+                self.current_message = self.messages.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn message_count(&self, from: u32) -> usize {
+        self.messages.iter().filter(|m| from == 0 || m.player == from).count()
+    }
+
+    pub fn clear_messages(&mut self, from: u32) {
+        self.messages.retain(|m| from != 0 && m.player != from);
+    }
+
+    /// Handles all incoming network traffic. Should be called before anything which reads the session state.
+    pub fn poll(&mut self) {
+        let Self { session, data, messages, read_buffer, .. } = self;
+        let session_over = match session.as_mut() {
+            Some(s) if matches!(s.role, Role::Host { .. }) => {
+                Self::poll_host(s, data, messages, read_buffer);
+                false
+            },
+            Some(s) => Self::poll_client(s, data, messages, read_buffer),
+            None => false,
+        };
+        if session_over {
+            *session = None;
+            data.clear();
+        }
+    }
+
+    fn poll_host(
+        session: &mut Session,
+        data: &mut HashMap<u32, Value>,
+        messages: &mut VecDeque<Message>,
+        read_buffer: &mut Vec<u8>,
+    ) {
+        let own_id = session.own_id;
+        let (listener, discovery, pending, clients, max_players, next_id) = match &mut session.role {
+            Role::Host { listener, discovery, pending, clients, max_players, next_id } => {
+                (listener, discovery, pending, clients, *max_players, next_id)
+            },
+            Role::Client { .. } => return,
+        };
+        let players = &mut session.players;
+
+        // Answer anyone looking for sessions
+        if let Some(socket) = discovery {
+            let mut buf = [0u8; 64];
+            while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                if let (Ok(Datagram::FindSessions), Ok(addr)) =
+                    (bincode::deserialize(&buf[..len]), listener.local_addr())
+                {
+                    let info = Datagram::SessionInfo {
+                        name: session.name.clone(),
+                        port: addr.port(),
+                        players: players.len() as u32,
+                        max_players,
+                    };
+                    let _ = send_datagram(socket, &info, from);
+                }
+            }
+        }
+
+        // Accept new connections, then let them in once they've said who they are
+        while let Ok((stream, _)) = listener.accept() {
+            if stream.set_nonblocking(true).is_ok() && stream.set_nodelay(true).is_ok() {
+                pending.push(stream);
+            }
+        }
+        let mut i = 0;
+        while i < pending.len() {
+            let (id, name, port) = match receive_packet(&mut pending[i], read_buffer) {
+                Ok(Some(Packet::Join { name, port })) => {
+                    *next_id += 1;
+                    (*next_id - 1, name, port)
+                },
+                Ok(Some(Packet::Rejoin { id, name, port })) => (id, name, port),
+                Ok(_) => {
+                    i += 1;
+                    continue
+                },
+                Err(_) => {
+                    pending.remove(i);
+                    continue
+                },
+            };
+            let mut stream = pending.remove(i);
+            let rejoining = players.iter().any(|p| p.id == id);
+            if !rejoining && max_players != 0 && players.len() as u32 >= max_players {
+                let _ = stream.send_message(&Packet::Refused);
+                continue
+            }
+            let migrate_address = match stream.peer_addr() {
+                Ok(addr) => SocketAddr::new(addr.ip(), port),
+                Err(_) => continue,
+            };
+            let player = Player { id, name };
+            if !rejoining {
+                for client in clients.iter_mut() {
+                    let _ = client.stream.send_message(&Packet::PlayerJoined(player.clone()));
+                }
+                players.push(player);
+            }
+            let welcome = Packet::Welcome {
+                id,
+                players: players.clone(),
+                data: data.iter().map(|(k, v)| (*k, v.clone())).collect(),
+            };
+            if stream.send_message(&welcome).is_ok() {
+                *next_id = (*next_id).max(id + 1);
+                clients.push(Client { id, stream, migrate_address });
+            }
+        }
+
+        // Read from clients, relaying anything which isn't only for us
+        let mut left = Vec::new();
+        let mut relay = Vec::new();
+        for client in clients.iter_mut() {
+            loop {
+                match receive_packet(&mut client.stream, read_buffer) {
+                    Ok(Some(Packet::Data { index, value })) => {
+                        data.insert(index, value.clone());
+                        relay.push((client.id, 0, Packet::Data { index, value }));
+                    },
+                    Ok(Some(Packet::Message { to, id, value, .. })) => {
+                        let from = client.id;
+                        if to == 0 || to == own_id {
+                            let name = players.iter().find(|p| p.id == from).map_or("", |p| p.name.as_str()).into();
+                            messages.push_back(Message { player: from, name, id, value: value.clone() });
+                        }
+                        if to != own_id {
+                            relay.push((from, to, Packet::Message { from, to, id, value }));
+                        }
+                    },
+                    Ok(Some(_)) => (),
+                    Ok(None) => break,
+                    Err(_) => {
+                        left.push(client.id);
+                        break
+                    },
+                }
+            }
+        }
+        for (from, to, packet) in relay {
+            for client in clients.iter_mut().filter(|c| c.id != from && (to == 0 || c.id == to)) {
+                let _ = client.stream.send_message(&packet);
+            }
+        }
+        for id in left {
+            clients.retain(|c| c.id != id);
+            players.retain(|p| p.id != id);
+            for client in clients.iter_mut() {
+                let _ = client.stream.send_message(&Packet::PlayerLeft(id));
+            }
+        }
+    }
+
+    /// Returns true if the session is over.
+    fn poll_client(
+        session: &mut Session,
+        data: &mut HashMap<u32, Value>,
+        messages: &mut VecDeque<Message>,
+        read_buffer: &mut Vec<u8>,
+    ) -> bool {
+        loop {
+            let stream = match &mut session.role {
+                Role::Client { stream, .. } => stream,
+                Role::Host { .. } => return false,
+            };
+            match receive_packet(stream, read_buffer) {
+                Ok(Some(Packet::PlayerJoined(player))) => session.players.push(player),
+                Ok(Some(Packet::PlayerLeft(id))) => session.players.retain(|p| p.id != id),
+                Ok(Some(Packet::Data { index, value })) => {
+                    data.insert(index, value);
+                },
+                Ok(Some(Packet::Message { from, id, value, .. })) => {
+                    let name = session.players.iter().find(|p| p.id == from).map_or("", |p| p.name.as_str()).into();
+                    messages.push_back(Message { player: from, name, id, value });
+                },
+                Ok(Some(Packet::Migrate { id, address })) => {
+                    // The old host is gone either way
+                    let old_host = session.players.first().map(|p| p.id);
+                    session.players.retain(|p| Some(p.id) != old_host);
+                    return !Self::migrate(session, id, address)
+                },
+                Ok(Some(Packet::SessionEnded)) | Err(_) => return true,
+                Ok(Some(_)) => (),
+                Ok(None) => return false,
+            }
+        }
+    }
+
+    /// Moves the session to a new host. Returns false if that couldn't be done.
+    fn migrate(session: &mut Session, host_id: u32, address: SocketAddr) -> bool {
+        let listener = match &session.role {
+            Role::Client { listener, .. } => match listener.try_clone() {
+                Ok(l) => l,
+                Err(_) => return false,
+            },
+            Role::Host { .. } => return false,
+        };
+        // Put the new host at the front of the list, like the original host was
+        if let Some(pos) = session.players.iter().position(|p| p.id == host_id) {
+            let host = session.players.remove(pos);
+            session.players.insert(0, host);
+        }
+        if host_id == session.own_id {
+            let discovery = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+                .and_then(|s| s.set_nonblocking(true).map(|_| s))
+                .ok();
+            let next_id = session.players.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+            session.role =
+                Role::Host { listener, discovery, pending: Vec::new(), clients: Vec::new(), max_players: 0, next_id };
+            true
+        } else {
+            let own_id = session.own_id;
+            let name = session.players.iter().find(|p| p.id == own_id).map_or(String::new(), |p| p.name.clone());
+            let result = (|| -> io::Result<TcpStream> {
+                let port = listener.local_addr()?.port();
+                let mut stream = TcpStream::connect_timeout(&address, JOIN_TIMEOUT)?;
+                stream.set_nonblocking(true)?;
+                stream.set_nodelay(true)?;
+                stream.send_message(&Packet::Rejoin { id: session.own_id, name, port })?;
+                Ok(stream)
+            })();
+            match result {
+                Ok(stream) => {
+                    session.role = Role::Client { stream, listener };
+                    true
+                },
+                Err(_) => false,
+            }
+        }
+    }
+}
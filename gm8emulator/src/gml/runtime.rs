@@ -1,16 +1,19 @@
 use crate::{
     asset,
-    game::{Game, GetAsset, SceneChange, Version},
+    game::{profiler::Timed, Game, GetAsset, SceneChange, Version},
     gml::{
         self,
+        bytecode::{Op, Owner, Program},
         compiler::{mappings, mappings::constants as gml_constants, token::Operator},
         datetime::DateTime,
         Context, InstanceVariable, Value,
     },
     instance::{DummyFieldHolder, Field},
+    instancelist::{ILIterInsertOrder, IdentityIter},
     math::Real,
 };
 use serde::{Deserialize, Serialize};
+use shared::{message::CodeLocation, types::ID};
 use std::{
     fmt::{self, Display},
     time,
@@ -18,9 +21,16 @@ use std::{
 
 const DEFAULT_ALARM: i32 = -1;
 
-/// A compiled runtime instruction. Generally represents a line of code.
+/// A compiled runtime instruction, along with the line of source code it was compiled from.
 #[derive(Serialize, Deserialize)]
-pub enum Instruction {
+pub struct Instruction {
+    pub kind: InstructionKind,
+    pub line: usize,
+}
+
+/// The operation performed by an instruction. Generally represents a line of code.
+#[derive(Serialize, Deserialize)]
+pub enum InstructionKind {
     SetField { accessor: FieldAccessor, value: Node },
     SetVariable { accessor: VariableAccessor, value: Node },
     EvalExpression { node: Node },
@@ -158,6 +168,77 @@ pub enum Error {
     FunctionError(String, String),
     ReplayError(String),
     BadDirectoryError(String),
+    Located(Box<LocatedError>),
+    Aborted(Box<Error>), // the player chose to end the game because of this error
+}
+
+/// A runtime error along with where in the game's code it happened.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocatedError {
+    pub error: Error,
+
+    /// Line the error happened on in the innermost code which isn't on the call stack yet
+    pub line: Option<usize>,
+
+    /// The code which was running when the error happened, innermost first
+    pub call_stack: Vec<StackFrame>,
+}
+
+/// A piece of code on the call stack of an error, and the line in it which was running.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StackFrame {
+    pub source: CodeSource,
+    pub line: Option<usize>,
+}
+
+/// Names a piece of code the way GM8's error messages do.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CodeSource {
+    Action { action: usize, event: String, object: String },
+    Script(String),
+    Trigger(String),
+    RoomCreation(String),
+    InstanceCreation(i32),
+}
+
+impl Error {
+    /// Whether this error should end the game without being reported, either because it's already been reported
+    /// or because it means the game can't carry on.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::Aborted(_) | Self::ReplayError(_) => true,
+            Self::Located(located) => located.error.is_fatal(),
+            _ => false,
+        }
+    }
+
+    /// Records the line this error happened on, unless it's already been found.
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            Self::Located(mut located) => {
+                located.line = located.line.or(Some(line));
+                Self::Located(located)
+            },
+            Self::Aborted(_) => self,
+            error => Self::Located(Box::new(LocatedError { error, line: Some(line), call_stack: Vec::new() })),
+        }
+    }
+
+    /// Records the code this error happened in, which is then added to the call stack.
+    pub fn in_code(self, source: CodeSource) -> Self {
+        match self {
+            Self::Located(mut located) => {
+                located.call_stack.push(StackFrame { source, line: located.line.take() });
+                Self::Located(located)
+            },
+            Self::Aborted(_) => self,
+            error => Self::Located(Box::new(LocatedError {
+                error,
+                line: None,
+                call_stack: vec![StackFrame { source, line: None }],
+            })),
+        }
+    }
 }
 
 impl std::error::Error for Error {}
@@ -202,6 +283,38 @@ impl Display for Error {
             Self::FunctionError(fname, s) => write!(f, "{}: {}", fname, s),
             Self::ReplayError(s) => write!(f, "{}", s),
             Self::BadDirectoryError(s) => write!(f, "cannot encode working directory {} with current encoding", s),
+            Self::Located(located) => write!(f, "{}", located),
+            Self::Aborted(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Display for LocatedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, frame) in self.call_stack.iter().rev().enumerate() {
+            writeln!(f, "{} {}:", if i == 0 { "ERROR in" } else { "In" }, frame)?;
+        }
+        if let Some(line) = self.line {
+            writeln!(f, "Error in code at line {}:", line)?;
+        }
+        write!(f, "{}", self.error)
+    }
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            CodeSource::Action { action, event, object } => {
+                write!(f, "action number {} of {} for object {}", action + 1, event, object)?
+            },
+            CodeSource::Script(name) => write!(f, "script {}", name)?,
+            CodeSource::Trigger(name) => write!(f, "condition of trigger {}", name)?,
+            CodeSource::RoomCreation(name) => write!(f, "creation code of room {}", name)?,
+            CodeSource::InstanceCreation(id) => write!(f, "creation code of instance {}", id)?,
+        }
+        match self.line {
+            Some(line) => write!(f, " at line {}", line),
+            None => Ok(()),
         }
     }
 }
@@ -215,27 +328,95 @@ enum Target {
     Local,
 }
 
+/// A with statement which is running.
+struct With<I> {
+    old_this: usize,
+    old_other: usize,
+    instances: I,
+}
+
+/// The instances a with statement has left to go through.
+enum WithInstances {
+    Once(Option<usize>),
+    All(ILIterInsertOrder),
+    Object(IdentityIter),
+}
+
+/// Everything the bytecode needs from whatever it's running in. This is the game, except in tests.
+trait Machine {
+    /// The instances a with statement has left to go through
+    type Instances;
+
+    /// Called at the start of each statement.
+    fn line(&mut self, line: usize, context: &mut Context);
+
+    fn constant(&self, constant_id: usize) -> gml::Result<Value>;
+
+    fn call(&mut self, function: gml::Function, args: &[Value], context: &mut Context) -> gml::Result<Value>;
+
+    fn call_script(&mut self, script_id: usize, args: &[Value], context: &Context) -> gml::Result<Value>;
+
+    /// Whether a field has been declared with globalvar.
+    fn is_globalvar(&self, index: usize) -> bool;
+
+    fn declare_globalvars(&mut self, fields: &[usize]);
+
+    fn target_from_id(&self, id: i32, context: &Context) -> Target;
+
+    fn read_field(&self, target: Target, index: usize, array_index: u32, context: &Context) -> gml::Result<Value>;
+
+    fn write_field(&mut self, target: Target, index: usize, array_index: u32, value: Value, context: &mut Context);
+
+    fn read_var(
+        &self,
+        target: Target,
+        var: &InstanceVariable,
+        array_index: u32,
+        context: &Context,
+    ) -> gml::Result<Value>;
+
+    fn write_var(
+        &mut self,
+        target: Target,
+        var: &InstanceVariable,
+        array_index: u32,
+        value: Value,
+        context: &mut Context,
+    ) -> gml::Result<()>;
+
+    /// Works out which instances a with statement goes through, before it changes self and other.
+    fn with_instances(&self, id: i32, context: &Context) -> Self::Instances;
+
+    fn next_instance(&self, instances: &mut Self::Instances) -> Option<usize>;
+}
+
 impl fmt::Debug for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.line, self.kind)
+    }
+}
+
+impl fmt::Debug for InstructionKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Instruction::SetField { accessor, value } => write!(f, "SetField({:?}, {:?})", accessor, value),
-            Instruction::SetVariable { accessor, value } => write!(f, "SetVariable({:?}, {:?})", accessor, value),
-            Instruction::EvalExpression { node } => write!(f, "EvalExpression({:?})", node),
-            Instruction::IfElse { cond, if_body, else_body } => {
+            InstructionKind::SetField { accessor, value } => write!(f, "SetField({:?}, {:?})", accessor, value),
+            InstructionKind::SetVariable { accessor, value } => write!(f, "SetVariable({:?}, {:?})", accessor, value),
+            InstructionKind::EvalExpression { node } => write!(f, "EvalExpression({:?})", node),
+            InstructionKind::IfElse { cond, if_body, else_body } => {
                 write!(f, "IfElse({:?}, if={:?}, else={:?}", cond, if_body, else_body)
             },
-            Instruction::LoopUntil { cond, body } => write!(f, "LoopUntil({:?}, {:?})", cond, body),
-            Instruction::LoopWhile { cond, body } => write!(f, "LoopWhile({:?}, {:?})", cond, body),
-            Instruction::LoopFor { cond, body, step } => write!(f, "LoopFor({:?}, {:?}, {:?})", cond, body, step),
-            Instruction::Return { return_type } => write!(f, "Return({:?})", return_type),
-            Instruction::Repeat { count, body } => write!(f, "Repeat({:?}, {:?})", count, body),
-            Instruction::SetReturnValue { value } => write!(f, "SetReturnValue({:?})", value),
-            Instruction::Switch { input, cases, default, body } => {
+            InstructionKind::LoopUntil { cond, body } => write!(f, "LoopUntil({:?}, {:?})", cond, body),
+            InstructionKind::LoopWhile { cond, body } => write!(f, "LoopWhile({:?}, {:?})", cond, body),
+            InstructionKind::LoopFor { cond, body, step } => write!(f, "LoopFor({:?}, {:?}, {:?})", cond, body, step),
+            InstructionKind::Return { return_type } => write!(f, "Return({:?})", return_type),
+            InstructionKind::Repeat { count, body } => write!(f, "Repeat({:?}, {:?})", count, body),
+            InstructionKind::SetReturnValue { value } => write!(f, "SetReturnValue({:?})", value),
+            InstructionKind::Switch { input, cases, default, body } => {
                 write!(f, "Switch({:?}, cases={:?}, default={:?}, {:?}", input, cases, default, body)
             },
-            Instruction::With { target, body } => write!(f, "With({:?}, {:?})", target, body),
-            Instruction::GlobalVar { fields } => write!(f, "GlobalVar({:?})", fields),
-            Instruction::RuntimeError { error } => write!(f, "RuntimeError({:?})", error),
+            InstructionKind::With { target, body } => write!(f, "With({:?}, {:?})", target, body),
+            InstructionKind::GlobalVar { fields } => write!(f, "GlobalVar({:?})", fields),
+            InstructionKind::RuntimeError { error } => write!(f, "RuntimeError({:?})", error),
         }
     }
 }
@@ -299,262 +480,27 @@ impl UnaryOperator {
 }
 
 impl Game {
-    pub fn execute(&mut self, instructions: &[Instruction], context: &mut Context) -> gml::Result<ReturnType> {
-        for instruction in instructions.iter() {
-            match self.exec_instruction(instruction, context)? {
-                ReturnType::Normal => (),
-                r => return Ok(r),
-            }
-        }
-        Ok(ReturnType::Normal)
+    /// Runs some compiled code.
+    pub fn execute(&mut self, program: &Program, context: &mut Context) -> gml::Result<ReturnType> {
+        let mut line = 0;
+        run_ops(self, &program.ops, context, &mut line).map_err(|e| e.at_line(line))
     }
 
-    fn exec_instruction(&mut self, instruction: &Instruction, context: &mut Context) -> gml::Result<ReturnType> {
-        match instruction {
-            Instruction::SetField { accessor, value } => {
-                let target = self.get_target(context, &accessor.owner, self.globalvars.contains(&accessor.index))?;
-                let array_index = self.get_array_index(&accessor.array, context)?;
-                let value = self.eval(value, context)?;
-                context.return_value = value.clone();
-                match target {
-                    Target::Single(None) => (),
-                    Target::Single(Some(instance)) => {
-                        self.set_instance_field(instance, accessor.index, array_index, value);
-                    },
-                    Target::Objects(index) => {
-                        if let Some(Some(object)) = self.assets.objects.get(index as usize) {
-                            let ids = object.children.clone();
-                            let mut iter = self.instance_list.iter_by_identity(ids);
-                            while let Some(instance) = iter.next(&self.instance_list) {
-                                self.set_instance_field(instance, accessor.index, array_index, value.clone());
-                            }
-                        }
-                    },
-                    Target::All => {
-                        let mut iter = self.instance_list.iter_by_insertion();
-                        while let Some(instance) = iter.next(&self.instance_list) {
-                            self.set_instance_field(instance, accessor.index, array_index, value.clone());
-                        }
-                    },
-                    Target::Global => {
-                        if let Some(field) = self.globals.fields.get_mut(&accessor.index) {
-                            field.set(array_index, value)
-                        } else {
-                            self.globals.fields.insert(accessor.index, Field::new(array_index, value));
-                        }
-                    },
-                    Target::Local => {
-                        if let Some(field) = context.locals.fields.get_mut(&accessor.index) {
-                            field.set(array_index, value)
-                        } else {
-                            context.locals.fields.insert(accessor.index, Field::new(array_index, value));
-                        }
-                    },
-                }
-            },
-            Instruction::SetVariable { accessor, value } => {
-                let target = self.get_target(context, &accessor.owner, false)?;
-                let array_index = self.get_array_index(&accessor.array, context)?;
-                let value = self.eval(value, context)?;
-                context.return_value = value.clone();
-                match target {
-                    Target::Single(None) => (),
-                    Target::Single(Some(instance)) => {
-                        self.set_instance_var(instance, &accessor.var, array_index, value, context)?;
-                    },
-                    Target::Objects(index) => {
-                        if let Some(Some(object)) = self.assets.objects.get(index as usize) {
-                            let ids = object.children.clone();
-                            let mut iter = self.instance_list.iter_by_identity(ids);
-                            while let Some(instance) = iter.next(&self.instance_list) {
-                                self.set_instance_var(instance, &accessor.var, array_index, value.clone(), context)?;
-                            }
-                        }
-                    },
-                    Target::All => {
-                        let mut iter = self.instance_list.iter_by_insertion();
-                        while let Some(instance) = iter.next(&self.instance_list) {
-                            self.set_instance_var(instance, &accessor.var, array_index, value.clone(), context)?;
-                        }
-                    },
-                    Target::Global => {
-                        if let Some(field) = self.globals.vars.get_mut(&accessor.var) {
-                            field.set(array_index, value)
-                        } else {
-                            self.globals.vars.insert(accessor.var, Field::new(array_index, value));
-                        }
-                    },
-                    Target::Local => {
-                        if let Some(field) = context.locals.vars.get_mut(&accessor.var) {
-                            field.set(array_index, value)
-                        } else {
-                            context.locals.vars.insert(accessor.var, Field::new(array_index, value));
-                        }
-                    },
-                }
-            },
-            Instruction::EvalExpression { node } => {
-                context.return_value = self.eval(node, context)?;
-            },
-            Instruction::IfElse { cond, if_body, else_body } => {
-                let return_type = if self.eval(cond, context)?.is_truthy() {
-                    self.execute(if_body, context)
-                } else {
-                    self.execute(else_body, context)
-                }?;
-                if return_type != ReturnType::Normal {
-                    return Ok(return_type)
-                }
-            },
-            Instruction::LoopUntil { cond, body } => loop {
-                match self.execute(body, context)? {
-                    ReturnType::Normal => (),
-                    ReturnType::Continue => continue,
-                    ReturnType::Break => break,
-                    ReturnType::Exit => return Ok(ReturnType::Exit),
-                }
-                if self.eval(cond, context)?.is_truthy() {
-                    break
-                }
-            },
-            Instruction::LoopWhile { cond, body } => {
-                while self.eval(cond, context)?.is_truthy() {
-                    match self.execute(body, context)? {
-                        ReturnType::Normal => (),
-                        ReturnType::Continue => continue,
-                        ReturnType::Break => break,
-                        ReturnType::Exit => return Ok(ReturnType::Exit),
-                    }
-                }
-            },
-            Instruction::LoopFor { cond, body, step } => {
-                while self.eval(cond, context)?.is_truthy() {
-                    match self.execute(body, context)? {
-                        ReturnType::Normal => {
-                            self.execute(step, context)?;
-                        },
-                        ReturnType::Continue => {
-                            self.execute(step, context)?;
-                            continue
-                        },
-                        ReturnType::Break => break,
-                        ReturnType::Exit => return Ok(ReturnType::Exit),
-                    }
-                }
-            },
-            Instruction::Return { return_type } => return Ok(*return_type),
-            Instruction::Repeat { count, body } => {
-                let mut count = self.eval(count, context)?.round();
-                while count > 0 {
-                    match self.execute(body, context)? {
-                        ReturnType::Normal => (),
-                        ReturnType::Continue => continue,
-                        ReturnType::Break => break,
-                        ReturnType::Exit => return Ok(ReturnType::Exit),
-                    }
-                    count -= 1;
-                }
-            },
-            Instruction::SetReturnValue { value } => {
-                context.return_value = self.eval(value, context)?;
-            },
-            Instruction::Switch { input, cases, default, body } => {
-                let input = self.eval(input, context)?;
-                for (cond, start) in cases.iter() {
-                    if self.eval(cond, context)?.almost_equals(&input) {
-                        return Ok(match self.execute(&body[*start..], context)? {
-                            ReturnType::Break => ReturnType::Normal,
-                            x => x,
-                        })
-                    }
-                }
-                if let Some(start) = default {
-                    return Ok(match self.execute(&body[*start..], context)? {
-                        ReturnType::Break => ReturnType::Normal,
-                        x => x,
-                    })
-                }
-            },
-            Instruction::With { target, body } => {
-                let old_this = context.this;
-                let old_other = context.other;
-
-                let target_id = i32::from(self.eval(target, context)?);
-                context.other = context.this;
-
-                match target_id {
-                    gml::SELF | gml::SELF2 => {
-                        if self.execute(body, context)? == ReturnType::Exit {
-                            context.other = old_other;
-                            return Ok(ReturnType::Exit)
-                        }
-                    },
-                    gml::OTHER => {
-                        context.this = old_other;
-                        if self.execute(body, context)? == ReturnType::Exit {
-                            context.this = old_this;
-                            context.other = old_other;
-                            return Ok(ReturnType::Exit)
-                        }
-                    },
-                    gml::ALL => {
-                        let mut iter = self.instance_list.iter_by_insertion();
-                        while let Some(instance) = iter.next(&self.instance_list) {
-                            context.this = instance;
-                            match self.execute(body, context)? {
-                                ReturnType::Normal => (),
-                                ReturnType::Continue => continue,
-                                ReturnType::Break => break,
-                                ReturnType::Exit => {
-                                    context.this = old_this;
-                                    context.other = old_other;
-                                    return Ok(ReturnType::Exit)
-                                },
-                            }
-                        }
-                    },
-                    i if i < 0 => (),
-                    i if i < 100_000 => {
-                        if let Some(Some(object)) = self.assets.objects.get(i as usize) {
-                            let mut iter = self.instance_list.iter_by_identity(object.children.clone());
-                            while let Some(instance) = iter.next(&self.instance_list) {
-                                context.this = instance;
-                                match self.execute(body, context)? {
-                                    ReturnType::Normal => (),
-                                    ReturnType::Continue => continue,
-                                    ReturnType::Break => break,
-                                    ReturnType::Exit => {
-                                        context.this = old_this;
-                                        context.other = old_other;
-                                        return Ok(ReturnType::Exit)
-                                    },
-                                }
-                            }
-                        }
-                    },
-                    i => {
-                        if let Some(instance) = self.instance_list.get_by_instid(i) {
-                            context.this = instance;
-                            match self.execute(body, context)? {
-                                ReturnType::Exit => {
-                                    context.this = old_this;
-                                    context.other = old_other;
-                                    return Ok(ReturnType::Exit)
-                                },
-                                _ => (),
-                            }
-                        }
-                    },
-                }
-
-                context.this = old_this;
-                context.other = old_other;
-            },
-            Instruction::GlobalVar { fields } => self.globalvars.extend(fields),
-            Instruction::RuntimeError { error } => return Err(error.clone()),
-        }
-
-        Ok(ReturnType::Normal)
+    /// Executes a script's compiled code, adding the script to the call stack of any error it causes.
+    pub fn execute_script(
+        &mut self,
+        script_id: usize,
+        program: &Program,
+        context: &mut Context,
+    ) -> gml::Result<ReturnType> {
+        let location = CodeLocation::Script { script: script_id as ID };
+        self.profile(Timed::Script(script_id), |game| game.execute_at(location, program, context)).map_err(|e| {
+            let name = match self.assets.scripts.get(script_id) {
+                Some(Some(script)) => self.decode_str(script.name.as_ref()).into_owned(),
+                _ => script_id.to_string(),
+            };
+            e.in_code(CodeSource::Script(name))
+        })
     }
 
     pub fn eval(&mut self, node: &Node, context: &mut Context) -> gml::Result<Value> {
@@ -572,205 +518,22 @@ impl Game {
                 for (src, dest) in args.iter().zip(arg_values.iter_mut()) {
                     *dest = self.eval(src, context)?;
                 }
-                function.call(self, context, &arg_values[..args.len()])
+                let args = &arg_values[..args.len()];
+                self.profile(Timed::Function(*function), |game| function.call(game, context, args))
             },
             Node::Script { args, script_id } => {
-                if let Some(Some(script)) = self.assets.scripts.get(*script_id) {
-                    let instructions = script.compiled.clone();
-
-                    let mut arg_values: [Value; 16] = Default::default();
-                    for (src, dest) in args.iter().zip(arg_values.iter_mut()) {
-                        *dest = self.eval(src, context)?;
-                    }
-
-                    let mut new_context = Context {
-                        this: context.this,
-                        other: context.other,
-                        event_action: context.event_action,
-                        relative: context.relative,
-                        event_type: context.event_type,
-                        event_number: context.event_number,
-                        event_object: context.event_object,
-                        arguments: arg_values,
-                        argument_count: args.len(),
-                        locals: DummyFieldHolder::new(),
-                        return_value: Default::default(),
-                    };
-                    self.execute(&instructions, &mut new_context)?;
-                    Ok(new_context.return_value)
-                } else {
-                    Err(Error::NonexistentAsset(asset::Type::Script, *script_id as i32))
-                }
+                let args = args.iter().map(|arg| self.eval(arg, context)).collect::<gml::Result<Vec<_>>>()?;
+                self.call_script(*script_id, &args, context)
             },
             Node::Field { accessor } => {
                 let target = self.get_target(context, &accessor.owner, self.globalvars.contains(&accessor.index))?;
                 let array_index = self.get_array_index(&accessor.array, context)?;
-                match target {
-                    Target::Single(None) if self.uninit_fields_are_zero => Ok(Default::default()),
-                    Target::Single(None) => Err(Error::UninitializedVariable(
-                        self.compiler.get_field_name(accessor.index).unwrap(),
-                        array_index,
-                    )),
-                    Target::Single(Some(instance)) => self.get_instance_field(instance, accessor.index, array_index),
-                    Target::Objects(index) => {
-                        if let Some(instance) = self.assets.objects.get(index as usize).and_then(|x| match x {
-                            Some(x) => {
-                                self.instance_list.iter_by_identity(x.children.clone()).next(&self.instance_list)
-                            },
-                            None => None,
-                        }) {
-                            self.get_instance_field(instance, accessor.index, array_index)
-                        } else {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                Err(Error::UninitializedVariable(
-                                    self.compiler.get_field_name(accessor.index).unwrap(),
-                                    array_index,
-                                ))
-                            }
-                        }
-                    },
-                    Target::All => {
-                        if let Some(instance) = self.instance_list.iter_by_insertion().next(&self.instance_list) {
-                            self.get_instance_field(instance, accessor.index, array_index)
-                        } else {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                Err(Error::UninitializedVariable(
-                                    self.compiler.get_field_name(accessor.index).unwrap(),
-                                    array_index,
-                                ))
-                            }
-                        }
-                    },
-                    Target::Global => match self.globals.fields.get(&accessor.index).and_then(|x| x.get(array_index)) {
-                        Some(i) => Ok(i),
-                        None => {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                return Err(Error::UninitializedVariable(
-                                    self.compiler.get_field_name(accessor.index).unwrap(),
-                                    array_index,
-                                ))
-                            }
-                        },
-                    },
-                    Target::Local => {
-                        match context.locals.fields.get(&accessor.index).and_then(|x| x.get(array_index)) {
-                            Some(i) => Ok(i),
-                            None => {
-                                if self.uninit_fields_are_zero {
-                                    Ok(Default::default())
-                                } else {
-                                    return Err(Error::UninitializedVariable(
-                                        self.compiler.get_field_name(accessor.index).unwrap(),
-                                        array_index,
-                                    ))
-                                }
-                            },
-                        }
-                    },
-                }
+                self.read_field(target, accessor.index, array_index, context)
             },
             Node::Variable { accessor } => {
                 let target = self.get_target(context, &accessor.owner, false)?;
                 let array_index = self.get_array_index(&accessor.array, context)?;
-                match target {
-                    Target::Single(None) if self.uninit_fields_are_zero => Ok(Default::default()),
-                    Target::Single(None) => Err(Error::UninitializedVariable(
-                        String::from(mappings::INSTANCE_VARIABLES.iter().find(|(_, x)| x == &accessor.var).unwrap().0),
-                        array_index,
-                    )),
-                    Target::Single(Some(instance)) => {
-                        self.get_instance_var(instance, &accessor.var, array_index, context)
-                    },
-                    Target::Objects(index) => {
-                        if let Some(instance) = self.assets.objects.get(index as usize).and_then(|x| match x {
-                            Some(x) => {
-                                self.instance_list.iter_by_identity(x.children.clone()).next(&self.instance_list)
-                            },
-                            None => None,
-                        }) {
-                            self.get_instance_var(instance, &accessor.var, array_index, context)
-                        } else {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                Err(Error::UninitializedVariable(
-                                    String::from(
-                                        mappings::INSTANCE_VARIABLES
-                                            .iter()
-                                            .find(|(_, x)| x == &accessor.var)
-                                            .unwrap()
-                                            .0,
-                                    ),
-                                    array_index,
-                                ))
-                            }
-                        }
-                    },
-                    Target::All => {
-                        if let Some(instance) = self.instance_list.iter_by_insertion().next(&self.instance_list) {
-                            self.get_instance_var(instance, &accessor.var, array_index, context)
-                        } else {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                Err(Error::UninitializedVariable(
-                                    String::from(
-                                        mappings::INSTANCE_VARIABLES
-                                            .iter()
-                                            .find(|(_, x)| x == &accessor.var)
-                                            .unwrap()
-                                            .0,
-                                    ),
-                                    array_index,
-                                ))
-                            }
-                        }
-                    },
-                    Target::Global => match self.globals.vars.get(&accessor.var).and_then(|x| x.get(array_index)) {
-                        Some(i) => Ok(i),
-                        None => {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                return Err(Error::UninitializedVariable(
-                                    String::from(
-                                        mappings::INSTANCE_VARIABLES
-                                            .iter()
-                                            .find(|(_, x)| x == &accessor.var)
-                                            .unwrap()
-                                            .0,
-                                    ),
-                                    array_index,
-                                ))
-                            }
-                        },
-                    },
-                    Target::Local => match context.locals.vars.get(&accessor.var).and_then(|x| x.get(array_index)) {
-                        Some(i) => Ok(i),
-                        None => {
-                            if self.uninit_fields_are_zero {
-                                Ok(Default::default())
-                            } else {
-                                return Err(Error::UninitializedVariable(
-                                    String::from(
-                                        mappings::INSTANCE_VARIABLES
-                                            .iter()
-                                            .find(|(_, x)| x == &accessor.var)
-                                            .unwrap()
-                                            .0,
-                                    ),
-                                    array_index,
-                                ))
-                            }
-                        },
-                    },
-                }
+                self.read_var(target, &accessor.var, array_index, context)
             },
             Node::Binary { left, right, operator } => {
                 operator.call(self.eval(left, context)?, self.eval(right, context)?)
@@ -784,20 +547,11 @@ impl Game {
     fn get_array_index(&mut self, accessor: &ArrayAccessor, context: &mut Context) -> gml::Result<u32> {
         match accessor {
             ArrayAccessor::None => Ok(0),
-            ArrayAccessor::Single(node) => {
-                let index = self.eval(node, context)?.round();
-                if index < 0 || index >= 32000 { Err(Error::InvalidArrayIndex(index)) } else { Ok(index as u32) }
-            },
+            ArrayAccessor::Single(node) => array_index(&[self.eval(node, context)?]),
             ArrayAccessor::Double(node1, node2) => {
-                let index1 = self.eval(node1, context)?.round();
-                let index2 = self.eval(node2, context)?.round();
-                if index1 < 0 || index1 >= 32000 {
-                    Err(Error::InvalidArrayIndex(index1))
-                } else if index2 < 0 || index2 >= 32000 {
-                    Err(Error::InvalidArrayIndex(index2))
-                } else {
-                    Ok(((index1 * 32000) + index2) as u32)
-                }
+                let index1 = self.eval(node1, context)?;
+                let index2 = self.eval(node2, context)?;
+                array_index(&[index1, index2])
             },
         }
     }
@@ -1001,7 +755,9 @@ impl Game {
             InstanceVariable::RoomHeight => Ok(self.room_height.into()),
             InstanceVariable::RoomCaption => Ok(self.caption.clone().into()),
             InstanceVariable::RoomSpeed => Ok(self.room_speed.into()),
-            InstanceVariable::RoomPersistent => todo!("room_persistent getter"),
+            InstanceVariable::RoomPersistent => {
+                Ok(self.assets.rooms.get_asset(self.room_id).map(|r| r.persistent).unwrap_or(false).into())
+            },
             InstanceVariable::BackgroundColor => Ok(self.room_colour.as_decimal().into()),
             InstanceVariable::BackgroundShowcolor => Ok(self.show_room_colour.into()),
             InstanceVariable::BackgroundVisible => {
@@ -1332,7 +1088,11 @@ impl Game {
                 }
                 self.room_speed = speed as _
             },
-            InstanceVariable::RoomPersistent => todo!("room_persistent setter"),
+            InstanceVariable::RoomPersistent => {
+                if let Some(room) = self.assets.rooms.get_asset_mut(self.room_id) {
+                    room.persistent = value.is_truthy();
+                }
+            },
             InstanceVariable::BackgroundColor => self.room_colour = (value.round() as u32).into(),
             InstanceVariable::BackgroundShowcolor => self.show_room_colour = value.is_truthy(),
             InstanceVariable::BackgroundVisible => match self.backgrounds.get_mut(array_index as usize) {
@@ -1559,17 +1319,982 @@ impl Game {
             },
             InstanceIdentifier::Expression(node) => {
                 let value = self.eval(node, context).map(i32::from)?;
-                match value {
-                    gml::SELF | gml::SELF2 => Ok(Target::Single(Some(context.this))),
-                    gml::OTHER => Ok(Target::Single(Some(context.other))),
-                    gml::ALL => Ok(Target::All),
-                    gml::NOONE => Ok(Target::Single(None)),
-                    gml::GLOBAL => Ok(Target::Global),
-                    gml::LOCAL => Ok(Target::Local),
-                    i if i >= 100_000 => Ok(Target::Single(self.instance_list.get_by_instid(i))),
-                    i => Ok(Target::Objects(i)),
+                Ok(self.target_from_id(value, context))
+            },
+        }
+    }
+}
+
+impl Machine for Game {
+    type Instances = WithInstances;
+
+    fn line(&mut self, line: usize, context: &mut Context) {
+        if self.debugger.is_some() {
+            self.debug_line(line, context);
+        }
+    }
+
+    fn constant(&self, constant_id: usize) -> gml::Result<Value> {
+        match self.constants.get(constant_id) {
+            Some(value) => Ok(value.clone()),
+            None => Err(gml::Error::NonexistentAsset(asset::Type::Constant, constant_id as i32)),
+        }
+    }
+
+    fn call(&mut self, function: gml::Function, args: &[Value], context: &mut Context) -> gml::Result<Value> {
+        self.profile(Timed::Function(function), |game| function.call(game, context, args))
+    }
+
+    // Calls a script with some arguments, returning the value it returned
+    fn call_script(&mut self, script_id: usize, args: &[Value], context: &Context) -> gml::Result<Value> {
+        if let Some(Some(script)) = self.assets.scripts.get(script_id) {
+            let program = script.compiled.clone();
+
+            let mut arguments: [Value; 16] = Default::default();
+            for (src, dest) in args.iter().zip(arguments.iter_mut()) {
+                *dest = src.clone();
+            }
+
+            let mut new_context = Context {
+                this: context.this,
+                other: context.other,
+                event_action: context.event_action,
+                relative: context.relative,
+                event_type: context.event_type,
+                event_number: context.event_number,
+                event_object: context.event_object,
+                arguments,
+                argument_count: args.len(),
+                locals: DummyFieldHolder::new(),
+                return_value: Default::default(),
+            };
+            self.execute_script(script_id, &program, &mut new_context)?;
+            Ok(new_context.return_value)
+        } else {
+            Err(Error::NonexistentAsset(asset::Type::Script, script_id as i32))
+        }
+    }
+
+    // Sets a field on every instance a target refers to
+    fn write_field(&mut self, target: Target, index: usize, array_index: u32, value: Value, context: &mut Context) {
+        match target {
+            Target::Single(None) => (),
+            Target::Single(Some(instance)) => {
+                self.set_instance_field(instance, index, array_index, value);
+            },
+            Target::Objects(object_id) => {
+                if let Some(Some(object)) = self.assets.objects.get(object_id as usize) {
+                    let ids = object.children.clone();
+                    let mut iter = self.instance_list.iter_by_identity(ids);
+                    while let Some(instance) = iter.next(&self.instance_list) {
+                        self.set_instance_field(instance, index, array_index, value.clone());
+                    }
+                }
+            },
+            Target::All => {
+                let mut iter = self.instance_list.iter_by_insertion();
+                while let Some(instance) = iter.next(&self.instance_list) {
+                    self.set_instance_field(instance, index, array_index, value.clone());
+                }
+            },
+            Target::Global => {
+                if let Some(field) = self.globals.fields.get_mut(&index) {
+                    field.set(array_index, value)
+                } else {
+                    self.globals.fields.insert(index, Field::new(array_index, value));
+                }
+            },
+            Target::Local => {
+                if let Some(field) = context.locals.fields.get_mut(&index) {
+                    field.set(array_index, value)
+                } else {
+                    context.locals.fields.insert(index, Field::new(array_index, value));
+                }
+            },
+        }
+    }
+
+    // Sets an instance variable on every instance a target refers to
+    fn write_var(
+        &mut self,
+        target: Target,
+        var: &InstanceVariable,
+        array_index: u32,
+        value: Value,
+        context: &mut Context,
+    ) -> gml::Result<()> {
+        match target {
+            Target::Single(None) => (),
+            Target::Single(Some(instance)) => {
+                self.set_instance_var(instance, var, array_index, value, context)?;
+            },
+            Target::Objects(object_id) => {
+                if let Some(Some(object)) = self.assets.objects.get(object_id as usize) {
+                    let ids = object.children.clone();
+                    let mut iter = self.instance_list.iter_by_identity(ids);
+                    while let Some(instance) = iter.next(&self.instance_list) {
+                        self.set_instance_var(instance, var, array_index, value.clone(), context)?;
+                    }
+                }
+            },
+            Target::All => {
+                let mut iter = self.instance_list.iter_by_insertion();
+                while let Some(instance) = iter.next(&self.instance_list) {
+                    self.set_instance_var(instance, var, array_index, value.clone(), context)?;
+                }
+            },
+            Target::Global => {
+                if let Some(field) = self.globals.vars.get_mut(var) {
+                    field.set(array_index, value)
+                } else {
+                    self.globals.vars.insert(*var, Field::new(array_index, value));
+                }
+            },
+            Target::Local => {
+                if let Some(field) = context.locals.vars.get_mut(var) {
+                    field.set(array_index, value)
+                } else {
+                    context.locals.vars.insert(*var, Field::new(array_index, value));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    // Gets the value of a field from whatever a target refers to
+    fn read_field(&self, target: Target, index: usize, array_index: u32, context: &Context) -> gml::Result<Value> {
+        match target {
+            Target::Single(None) if self.uninit_fields_are_zero => Ok(Default::default()),
+            Target::Single(None) => {
+                Err(Error::UninitializedVariable(self.compiler.get_field_name(index).unwrap(), array_index))
+            },
+            Target::Single(Some(instance)) => self.get_instance_field(instance, index, array_index),
+            Target::Objects(object_id) => {
+                if let Some(instance) = self.assets.objects.get(object_id as usize).and_then(|x| match x {
+                    Some(x) => self.instance_list.iter_by_identity(x.children.clone()).next(&self.instance_list),
+                    None => None,
+                }) {
+                    self.get_instance_field(instance, index, array_index)
+                } else {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        Err(Error::UninitializedVariable(self.compiler.get_field_name(index).unwrap(), array_index))
+                    }
+                }
+            },
+            Target::All => {
+                if let Some(instance) = self.instance_list.iter_by_insertion().next(&self.instance_list) {
+                    self.get_instance_field(instance, index, array_index)
+                } else {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        Err(Error::UninitializedVariable(self.compiler.get_field_name(index).unwrap(), array_index))
+                    }
+                }
+            },
+            Target::Global => match self.globals.fields.get(&index).and_then(|x| x.get(array_index)) {
+                Some(i) => Ok(i),
+                None => {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        return Err(Error::UninitializedVariable(
+                            self.compiler.get_field_name(index).unwrap(),
+                            array_index,
+                        ))
+                    }
+                },
+            },
+            Target::Local => match context.locals.fields.get(&index).and_then(|x| x.get(array_index)) {
+                Some(i) => Ok(i),
+                None => {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        return Err(Error::UninitializedVariable(
+                            self.compiler.get_field_name(index).unwrap(),
+                            array_index,
+                        ))
+                    }
+                },
+            },
+        }
+    }
+
+    // Gets the value of an instance variable from whatever a target refers to
+    fn read_var(
+        &self,
+        target: Target,
+        var: &InstanceVariable,
+        array_index: u32,
+        context: &Context,
+    ) -> gml::Result<Value> {
+        match target {
+            Target::Single(None) if self.uninit_fields_are_zero => Ok(Default::default()),
+            Target::Single(None) => Err(Error::UninitializedVariable(
+                String::from(mappings::INSTANCE_VARIABLES.iter().find(|(_, x)| x == var).unwrap().0),
+                array_index,
+            )),
+            Target::Single(Some(instance)) => self.get_instance_var(instance, var, array_index, context),
+            Target::Objects(object_id) => {
+                if let Some(instance) = self.assets.objects.get(object_id as usize).and_then(|x| match x {
+                    Some(x) => self.instance_list.iter_by_identity(x.children.clone()).next(&self.instance_list),
+                    None => None,
+                }) {
+                    self.get_instance_var(instance, var, array_index, context)
+                } else {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        Err(Error::UninitializedVariable(
+                            String::from(mappings::INSTANCE_VARIABLES.iter().find(|(_, x)| x == var).unwrap().0),
+                            array_index,
+                        ))
+                    }
+                }
+            },
+            Target::All => {
+                if let Some(instance) = self.instance_list.iter_by_insertion().next(&self.instance_list) {
+                    self.get_instance_var(instance, var, array_index, context)
+                } else {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        Err(Error::UninitializedVariable(
+                            String::from(mappings::INSTANCE_VARIABLES.iter().find(|(_, x)| x == var).unwrap().0),
+                            array_index,
+                        ))
+                    }
+                }
+            },
+            Target::Global => match self.globals.vars.get(var).and_then(|x| x.get(array_index)) {
+                Some(i) => Ok(i),
+                None => {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        return Err(Error::UninitializedVariable(
+                            String::from(mappings::INSTANCE_VARIABLES.iter().find(|(_, x)| x == var).unwrap().0),
+                            array_index,
+                        ))
+                    }
+                },
+            },
+            Target::Local => match context.locals.vars.get(var).and_then(|x| x.get(array_index)) {
+                Some(i) => Ok(i),
+                None => {
+                    if self.uninit_fields_are_zero {
+                        Ok(Default::default())
+                    } else {
+                        return Err(Error::UninitializedVariable(
+                            String::from(mappings::INSTANCE_VARIABLES.iter().find(|(_, x)| x == var).unwrap().0),
+                            array_index,
+                        ))
+                    }
+                },
+            },
+        }
+    }
+
+    fn is_globalvar(&self, index: usize) -> bool {
+        self.globalvars.contains(&index)
+    }
+
+    fn declare_globalvars(&mut self, fields: &[usize]) {
+        self.globalvars.extend(fields)
+    }
+
+    // Resolves an instance ID, object ID or keyword to a Target
+    fn target_from_id(&self, id: i32, context: &Context) -> Target {
+        match id {
+            gml::SELF | gml::SELF2 => Target::Single(Some(context.this)),
+            gml::OTHER => Target::Single(Some(context.other)),
+            gml::ALL => Target::All,
+            gml::NOONE => Target::Single(None),
+            gml::GLOBAL => Target::Global,
+            gml::LOCAL => Target::Local,
+            i if i >= 100_000 => Target::Single(self.instance_list.get_by_instid(i)),
+            i => Target::Objects(i),
+        }
+    }
+
+    // Works out which instances a with statement goes through
+    fn with_instances(&self, id: i32, context: &Context) -> WithInstances {
+        match id {
+            gml::SELF | gml::SELF2 => WithInstances::Once(Some(context.this)),
+            gml::OTHER => WithInstances::Once(Some(context.other)),
+            gml::ALL => WithInstances::All(self.instance_list.iter_by_insertion()),
+            i if i < 0 => WithInstances::Once(None),
+            i if i < 100_000 => match self.assets.objects.get(i as usize) {
+                Some(Some(object)) => {
+                    WithInstances::Object(self.instance_list.iter_by_identity(object.children.clone()))
+                },
+                _ => WithInstances::Once(None),
+            },
+            i => WithInstances::Once(self.instance_list.get_by_instid(i)),
+        }
+    }
+
+    fn next_instance(&self, instances: &mut WithInstances) -> Option<usize> {
+        match instances {
+            WithInstances::Once(instance) => instance.take(),
+            WithInstances::All(iter) => iter.next(&self.instance_list),
+            WithInstances::Object(iter) => iter.next(&self.instance_list),
+        }
+    }
+}
+
+// Runs bytecode, keeping track of the line it's on so errors can be located
+fn run_ops<M: Machine>(
+    machine: &mut M,
+    ops: &[Op],
+    context: &mut Context,
+    line: &mut usize,
+) -> gml::Result<ReturnType> {
+    let mut stack: Vec<Value> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mut targets: Vec<Target> = Vec::new();
+    let mut counters: Vec<i32> = Vec::new();
+    let mut withs: Vec<With<M::Instances>> = Vec::new();
+    let mut pc = 0;
+    while let Some(op) = ops.get(pc) {
+        pc += 1;
+        match op {
+            Op::Line(l) => {
+                *line = *l;
+                machine.line(*l, context);
+            },
+            Op::Push(value) => stack.push(value.clone()),
+            Op::Constant(constant_id) => stack.push(machine.constant(*constant_id)?),
+            Op::Call { function, argc } => {
+                let start = stack.len() - argc;
+                let value = machine.call(*function, &stack[start..], context)?;
+                stack.truncate(start);
+                stack.push(value);
+            },
+            Op::CallScript { script_id, argc } => {
+                let start = stack.len() - argc;
+                let value = machine.call_script(*script_id, &stack[start..], context)?;
+                stack.truncate(start);
+                stack.push(value);
+            },
+            Op::Target => {
+                let id = i32::from(pop(&mut stack));
+                targets.push(machine.target_from_id(id, context));
+            },
+            Op::ArrayIndex(dims) => {
+                let start = stack.len() - usize::from(*dims);
+                indices.push(array_index(&stack[start..])?);
+                stack.truncate(start);
+            },
+            Op::Index(index) => indices.push(*index),
+            Op::GetField { index, owner, indexed } => {
+                let array_index = if *indexed { indices.pop().unwrap() } else { 0 };
+                let target = owner_target(machine, *owner, &mut targets, context, Some(*index));
+                stack.push(machine.read_field(target, *index, array_index, context)?);
+            },
+            Op::SetField { index, owner, indexed } => {
+                let value = pop(&mut stack);
+                let array_index = if *indexed { indices.pop().unwrap() } else { 0 };
+                let target = owner_target(machine, *owner, &mut targets, context, Some(*index));
+                context.return_value = value.clone();
+                machine.write_field(target, *index, array_index, value, context);
+            },
+            Op::GetVariable { var, owner, indexed } => {
+                let array_index = if *indexed { indices.pop().unwrap() } else { 0 };
+                let target = owner_target(machine, *owner, &mut targets, context, None);
+                stack.push(machine.read_var(target, var, array_index, context)?);
+            },
+            Op::SetVariable { var, owner, indexed } => {
+                let value = pop(&mut stack);
+                let array_index = if *indexed { indices.pop().unwrap() } else { 0 };
+                let target = owner_target(machine, *owner, &mut targets, context, None);
+                context.return_value = value.clone();
+                machine.write_var(target, var, array_index, value, context)?;
+            },
+            Op::Binary(operator) => {
+                let right = pop(&mut stack);
+                let left = pop(&mut stack);
+                stack.push(operator.call(left, right)?);
+            },
+            Op::Unary(operator) => {
+                let value = pop(&mut stack);
+                stack.push(operator.call(value)?);
+            },
+            Op::SetReturnValue => context.return_value = pop(&mut stack),
+            Op::Discard => drop(pop(&mut stack)),
+            Op::Jump(target) => pc = *target,
+            Op::JumpIfFalse(target) => {
+                if !pop(&mut stack).is_truthy() {
+                    pc = *target;
+                }
+            },
+            Op::Case(target) => {
+                let case = pop(&mut stack);
+                if case.almost_equals(stack.last().unwrap()) {
+                    stack.pop();
+                    pc = *target;
                 }
             },
+            Op::RepeatStart => {
+                let count = pop(&mut stack).round();
+                counters.push(count);
+            },
+            Op::RepeatCheck(target) => {
+                if *counters.last().unwrap() <= 0 {
+                    pc = *target;
+                }
+            },
+            Op::RepeatDecrement => *counters.last_mut().unwrap() -= 1,
+            Op::RepeatEnd => drop(counters.pop()),
+            Op::WithStart => {
+                let id = i32::from(pop(&mut stack));
+                let instances = machine.with_instances(id, context);
+                withs.push(With { old_this: context.this, old_other: context.other, instances });
+                context.other = context.this;
+            },
+            Op::WithNext(target) => match machine.next_instance(&mut withs.last_mut().unwrap().instances) {
+                Some(instance) => context.this = instance,
+                None => pc = *target,
+            },
+            Op::WithEnd => {
+                let with = withs.pop().unwrap();
+                context.this = with.old_this;
+                context.other = with.old_other;
+            },
+            Op::GlobalVar(fields) => machine.declare_globalvars(fields),
+            Op::Return(return_type) => {
+                if let Some(with) = withs.first() {
+                    context.this = with.old_this;
+                    context.other = with.old_other;
+                }
+                return Ok(*return_type)
+            },
+            Op::Error(error) => return Err(error.clone()),
+        }
+    }
+    Ok(ReturnType::Normal)
+}
+
+// Pops a value which the bytecode must have pushed
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("bytecode popped an empty stack")
+}
+
+// Combines one or two array indices into a single index, checking they're in range
+pub(super) fn array_index(indices: &[Value]) -> gml::Result<u32> {
+    match indices {
+        [index] => {
+            let index = index.round();
+            if index < 0 || index >= 32000 { Err(Error::InvalidArrayIndex(index)) } else { Ok(index as u32) }
+        },
+        [index1, index2] => {
+            let index1 = index1.round();
+            let index2 = index2.round();
+            if index1 < 0 || index1 >= 32000 {
+                Err(Error::InvalidArrayIndex(index1))
+            } else if index2 < 0 || index2 >= 32000 {
+                Err(Error::InvalidArrayIndex(index2))
+            } else {
+                Ok(((index1 * 32000) + index2) as u32)
+            }
+        },
+        _ => Ok(0),
+    }
+}
+
+// Works out the target of a field op, or a variable op if there's no field index.
+// Only fields with no owner can be globalvars, so that's only looked up for them.
+fn owner_target<M: Machine>(
+    machine: &M,
+    owner: Owner,
+    targets: &mut Vec<Target>,
+    context: &Context,
+    field: Option<usize>,
+) -> Target {
+    match owner {
+        Owner::Unknown if matches!(field, Some(index) if machine.is_globalvar(index)) => Target::Global,
+        Owner::Unknown | Owner::Own => Target::Single(Some(context.this)),
+        Owner::Other => Target::Single(Some(context.other)),
+        Owner::Global => Target::Global,
+        Owner::Local => Target::Local,
+        Owner::Id(id) => machine.target_from_id(id, context),
+        Owner::Stack => targets.pop().expect("bytecode used a target it didn't work out"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gml::Compiler;
+    use std::{collections::HashSet, time::Instant};
+
+    /// The object each test instance is, by handle. Instance IDs are 100000 plus the handle.
+    const OBJECTS: [i32; 3] = [0, 1, 1];
+
+    /// The only script, which records its arguments and returns the first one.
+    const TRACE: usize = 0;
+
+    /// Somewhere to run code which has nothing but a few instances with fields, so it doesn't need a game.
+    #[derive(Default)]
+    struct TestMachine {
+        instances: [DummyFieldHolder; 3],
+        globals: DummyFieldHolder,
+        globalvars: HashSet<usize>,
+        trace: Vec<f64>,
+    }
+
+    impl TestMachine {
+        fn handles(target: &Target) -> Vec<usize> {
+            match target {
+                Target::Single(instance) => instance.iter().copied().collect(),
+                Target::Objects(object) => (0..OBJECTS.len()).filter(|i| OBJECTS[*i] == *object).collect(),
+                Target::All => (0..OBJECTS.len()).collect(),
+                Target::Global | Target::Local => Vec::new(),
+            }
+        }
+
+        fn field(&self, instance: usize, index: usize, array_index: u32) -> Option<f64> {
+            self.instances[instance].fields.get(&index).and_then(|x| x.get(array_index)).map(f64::from)
+        }
+
+        fn global(&self, index: usize) -> Option<f64> {
+            self.globals.fields.get(&index).and_then(|x| x.get(0)).map(f64::from)
+        }
+    }
+
+    fn get(holder: &DummyFieldHolder, index: usize, array_index: u32) -> gml::Result<Value> {
+        match holder.fields.get(&index).and_then(|x| x.get(array_index)) {
+            Some(value) => Ok(value),
+            None => Err(Error::UninitializedVariable(index.to_string(), array_index)),
+        }
+    }
+
+    fn set(holder: &mut DummyFieldHolder, index: usize, array_index: u32, value: Value) {
+        match holder.fields.get_mut(&index) {
+            Some(field) => field.set(array_index, value),
+            None => {
+                holder.fields.insert(index, Field::new(array_index, value));
+            },
+        }
+    }
+
+    impl Machine for TestMachine {
+        type Instances = std::vec::IntoIter<usize>;
+
+        fn line(&mut self, _line: usize, _context: &mut Context) {}
+
+        fn constant(&self, constant_id: usize) -> gml::Result<Value> {
+            Err(Error::NonexistentAsset(asset::Type::Constant, constant_id as i32))
+        }
+
+        fn call(&mut self, function: gml::Function, _args: &[Value], _context: &mut Context) -> gml::Result<Value> {
+            Err(Error::UnknownFunction(format!("{:?}", function)))
+        }
+
+        fn call_script(&mut self, script_id: usize, args: &[Value], _context: &Context) -> gml::Result<Value> {
+            match script_id {
+                TRACE => {
+                    self.trace.extend(args.iter().cloned().map(f64::from));
+                    Ok(args.first().cloned().unwrap_or_default())
+                },
+                _ => Err(Error::NonexistentAsset(asset::Type::Script, script_id as i32)),
+            }
+        }
+
+        fn is_globalvar(&self, index: usize) -> bool {
+            self.globalvars.contains(&index)
+        }
+
+        fn declare_globalvars(&mut self, fields: &[usize]) {
+            self.globalvars.extend(fields)
+        }
+
+        fn target_from_id(&self, id: i32, context: &Context) -> Target {
+            match id {
+                gml::SELF | gml::SELF2 => Target::Single(Some(context.this)),
+                gml::OTHER => Target::Single(Some(context.other)),
+                gml::ALL => Target::All,
+                gml::NOONE => Target::Single(None),
+                gml::GLOBAL => Target::Global,
+                gml::LOCAL => Target::Local,
+                i if i >= 100_000 => Target::Single(Some((i - 100_000) as usize).filter(|i| *i < OBJECTS.len())),
+                i => Target::Objects(i),
+            }
+        }
+
+        fn read_field(&self, target: Target, index: usize, array_index: u32, context: &Context) -> gml::Result<Value> {
+            match target {
+                Target::Single(Some(instance)) => get(&self.instances[instance], index, array_index),
+                Target::Global => get(&self.globals, index, array_index),
+                Target::Local => get(&context.locals, index, array_index),
+                target => match Self::handles(&target).first() {
+                    Some(instance) => get(&self.instances[*instance], index, array_index),
+                    None => Err(Error::UninitializedVariable(index.to_string(), array_index)),
+                },
+            }
+        }
+
+        fn write_field(&mut self, target: Target, index: usize, array_index: u32, value: Value, context: &mut Context) {
+            match target {
+                Target::Single(Some(instance)) => set(&mut self.instances[instance], index, array_index, value),
+                Target::Global => set(&mut self.globals, index, array_index, value),
+                Target::Local => set(&mut context.locals, index, array_index, value),
+                target => {
+                    for instance in Self::handles(&target) {
+                        set(&mut self.instances[instance], index, array_index, value.clone());
+                    }
+                },
+            }
+        }
+
+        fn read_var(
+            &self,
+            _target: Target,
+            var: &InstanceVariable,
+            _array_index: u32,
+            _context: &Context,
+        ) -> gml::Result<Value> {
+            Err(Error::ReadOnlyVariable(*var))
+        }
+
+        fn write_var(
+            &mut self,
+            _target: Target,
+            var: &InstanceVariable,
+            _array_index: u32,
+            _value: Value,
+            _context: &mut Context,
+        ) -> gml::Result<()> {
+            Err(Error::ReadOnlyVariable(*var))
+        }
+
+        fn with_instances(&self, id: i32, context: &Context) -> Self::Instances {
+            let instances = match id {
+                gml::SELF | gml::SELF2 => vec![context.this],
+                gml::OTHER => vec![context.other],
+                id => Self::handles(&self.target_from_id(id, context)),
+            };
+            instances.into_iter()
+        }
+
+        fn next_instance(&self, instances: &mut Self::Instances) -> Option<usize> {
+            instances.next()
+        }
+    }
+
+    fn new_context() -> Context {
+        Context {
+            this: 0,
+            other: 1,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object: 0,
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: DummyFieldHolder::new(),
+            return_value: Default::default(),
+        }
+    }
+
+    fn compiler() -> Compiler {
+        let mut compiler = Compiler::new();
+        compiler.register_script(b"trace".to_vec().into_boxed_slice(), TRACE);
+        compiler
+    }
+
+    /// The result of running some code, with whatever it changed.
+    struct Run {
+        machine: TestMachine,
+        context: Context,
+        result: gml::Result<ReturnType>,
+        compiler: Compiler,
+    }
+
+    impl Run {
+        fn field(&mut self, instance: usize, name: &str) -> Option<f64> {
+            let index = self.compiler.get_field_id(name.as_bytes());
+            self.machine.field(instance, index, 0)
         }
+
+        fn global(&mut self, name: &str) -> Option<f64> {
+            let index = self.compiler.get_field_id(name.as_bytes());
+            self.machine.global(index)
+        }
+    }
+
+    /// Runs some GML as bytecode on instance 0, with instance 1 as other.
+    fn check(source: &str) -> Run {
+        let mut compiler = compiler();
+        let instructions = compiler.compile_instructions(source.as_bytes()).unwrap();
+        let program = Program::new(&instructions);
+
+        let mut machine = TestMachine::default();
+        let mut context = new_context();
+        let mut line = 0;
+        let result = run_ops(&mut machine, &program.ops, &mut context, &mut line);
+        Run { machine, context, result, compiler }
+    }
+
+    /// Sets a field `k` to 10, 11 and 12 on the three instances, so code can tell them apart.
+    const SETUP: &str = "(100000).k = 10; (100001).k = 11; (100002).k = 12;";
+
+    #[test]
+    fn for_break_continue() {
+        let mut run = check("s = 0; for (i = 0; i < 10; i += 1) { if (i == 2) continue; if (i == 5) break; s += i }");
+        assert_eq!(run.field(0, "s"), Some(8.0));
+        assert_eq!(run.field(0, "i"), Some(5.0));
+        assert_eq!(run.result.unwrap(), ReturnType::Normal);
+    }
+
+    #[test]
+    fn while_until_break_continue() {
+        let mut run = check("i = 0; s = 0; while (i < 6) { i += 1; if (i == 2) continue; if (i == 4) break; s += i }");
+        assert_eq!(run.field(0, "s"), Some(4.0));
+
+        let mut run = check("i = 0; s = 0; do { i += 1; if (i == 2) continue; s += i } until (i >= 3)");
+        assert_eq!(run.field(0, "s"), Some(4.0));
+        assert_eq!(run.field(0, "i"), Some(3.0));
+    }
+
+    #[test]
+    fn repeat_break_continue() {
+        // Continuing doesn't count the loop down
+        let mut run = check("i = 0; c = 0; repeat (3) { i += 1; if (i == 2) continue; c += 1 }");
+        assert_eq!(run.field(0, "i"), Some(4.0));
+        assert_eq!(run.field(0, "c"), Some(3.0));
+
+        let mut run = check("i = 0; repeat (10) { i += 1; if (i == 3) break }");
+        assert_eq!(run.field(0, "i"), Some(3.0));
+
+        let mut run = check("i = 0; repeat (2) repeat (3) { i += 1; break }");
+        assert_eq!(run.field(0, "i"), Some(2.0));
+    }
+
+    #[test]
+    fn switch_break_continue() {
+        // Breaking leaves the switch, but continuing continues the loop around it
+        let mut run = check(
+            "s = 0; for (i = 0; i < 5; i += 1) { switch (i) { case 1: continue; case 3: break; default: s += i } s += 10 }",
+        );
+        assert_eq!(run.field(0, "s"), Some(46.0));
+
+        let mut run = check("i = 0; c = 0; repeat (2) { i += 1; switch (i) { case 1: continue; default: c += 1 } }");
+        assert_eq!(run.field(0, "i"), Some(3.0));
+        assert_eq!(run.field(0, "c"), Some(2.0));
+
+        let mut run = check("a = 0; switch (2) { case 1: a += 1; case 2: a += 2; case 3: a += 3; break; case 4: a += 4 }");
+        assert_eq!(run.field(0, "a"), Some(5.0));
+
+        // Outside of a loop, these stop the code
+        assert_eq!(check("switch (1) { case 1: continue } a = 1").result.unwrap(), ReturnType::Continue);
+        assert_eq!(check("switch (1) { case 1: break } a = 1").result.unwrap(), ReturnType::Normal);
+        assert_eq!(check("break; a = 1").result.unwrap(), ReturnType::Break);
+    }
+
+    #[test]
+    fn with_break_continue() {
+        let mut run = check(&format!("{} global.n = 0; with (all) {{ if (k == 11) continue; global.n += k }}", SETUP));
+        assert_eq!(run.global("n"), Some(22.0));
+
+        let mut run = check(&format!("{} global.n = 0; with (all) {{ if (k == 11) break; global.n += k }}", SETUP));
+        assert_eq!(run.global("n"), Some(10.0));
+
+        // A with statement swallows breaks even when it only has one instance
+        let mut run = check("with (100001) break; a = 1");
+        assert_eq!(run.field(0, "a"), Some(1.0));
+        assert_eq!(run.result.unwrap(), ReturnType::Normal);
+
+        let mut run = check("i = 0; repeat (3) { i += 1; with (100001) continue; }");
+        assert_eq!(run.field(0, "i"), Some(3.0));
+    }
+
+    #[test]
+    fn exit_in_with() {
+        let mut run = check(&format!("{} with (all) {{ if (k == 11) exit; seen = 1 }} a = 1", SETUP));
+        assert_eq!(run.field(0, "seen"), Some(1.0));
+        assert_eq!(run.field(2, "seen"), None);
+        assert_eq!(run.field(0, "a"), None);
+        assert_eq!((run.context.this, run.context.other), (0, 1));
+        assert_eq!(run.result.unwrap(), ReturnType::Exit);
+
+        let run = check("with (1) with (0) repeat (2) exit;");
+        assert_eq!(run.result.unwrap(), ReturnType::Exit);
+        assert_eq!((run.context.this, run.context.other), (0, 1));
+    }
+
+    #[test]
+    fn nested_with_targets() {
+        let mut run = check(&format!("{} n = 0; with (1) with (other) n += 1", SETUP));
+        assert_eq!(run.field(0, "n"), Some(2.0));
+        assert_eq!((run.context.this, run.context.other), (0, 1));
+
+        let mut run = check("global.c = 0; with (1) with (all) global.c += 1");
+        assert_eq!(run.global("c"), Some(6.0));
+
+        let mut run = check(&format!("{} with (0) with (1) o = other.k", SETUP));
+        assert_eq!(run.field(1, "o"), Some(10.0));
+        assert_eq!(run.field(2, "o"), Some(10.0));
+
+        // self and other are put back after an inner with statement ends
+        let mut run = check(&format!("{} with (1) {{ with (0) {{}} p = other.k + k }}", SETUP));
+        assert_eq!(run.field(1, "p"), Some(21.0));
+        assert_eq!(run.field(2, "p"), Some(22.0));
+
+        let mut run = check(&format!("{} with (noone) a = 1; with (100005) a = 1; with (7) a = 1;", SETUP));
+        assert_eq!(run.field(0, "a"), None);
+        let mut run = check(&format!("{} with (self) b = k", SETUP));
+        assert_eq!(run.field(0, "b"), Some(10.0));
+    }
+
+    #[test]
+    fn evaluation_order() {
+        // The array index is worked out before the value being assigned
+        let mut run = check("a[trace(1)] = trace(2)");
+        assert_eq!(run.machine.trace, [1.0, 2.0]);
+        let index = run.compiler.get_field_id(b"a");
+        assert_eq!(run.machine.field(0, index, 1), Some(2.0));
+
+        // And the instance before either of them
+        let mut run = check("(trace(100001)).b[trace(1), trace(2)] = trace(3)");
+        assert_eq!(run.machine.trace, [100001.0, 1.0, 2.0, 3.0]);
+        let index = run.compiler.get_field_id(b"b");
+        assert_eq!(run.machine.field(1, index, 32002), Some(3.0));
+
+        let run = check("b[1] = 5; a = (trace(100000)).b[trace(1)] + trace(4) * trace(5); trace(a)");
+        assert_eq!(run.machine.trace, [100000.0, 1.0, 4.0, 5.0, 25.0]);
+
+        let run = check("trace(trace(1), trace(2) + trace(3))");
+        assert_eq!(run.machine.trace, [1.0, 2.0, 3.0, 1.0, 5.0]);
+    }
+
+    #[test]
+    fn folding_matches_runtime() {
+        // Each expression is run once with literals, which get folded, and once with the same values in fields
+        let cases: &[(&str, &[&str])] = &[
+            ("{} + {} * {}", &["0.1", "0.2", "3"]),
+            ("{} / {} * {}", &["1", "3", "3"]),
+            ("{} mod {} + {} div {}", &["-7.5", "2", "7", "-2"]),
+            ("({} & {}) | ({} << {}) ^ ~{}", &["13", "7", "1", "4", "5"]),
+            ("{} == {} && {} < {}", &["0.1 + 0.2", "0.3", "\"a\"", "\"b\""]),
+            ("{} xor {} || !{}", &["2", "0", "0.4"]),
+            ("{} + {}", &["\"abc\"", "\"def\""]),
+            ("-{} * {}", &["0.7", "123456789.123"]),
+            ("{} - {}", &["\"a\"", "1"]),
+            ("{} / {}", &["1", "0"]),
+        ];
+        let assigned = |mut run: Run| {
+            let index = run.compiler.get_field_id(b"a");
+            run.machine.instances[0].fields.get(&index).and_then(|x| x.get(0)).map(|x| format!("{:?}", x))
+        };
+        for (template, values) in cases {
+            let mut folded = template.to_string();
+            let mut unfolded = String::new();
+            let mut expression = template.to_string();
+            for (i, value) in values.iter().enumerate() {
+                folded = folded.replacen("{}", value, 1);
+                unfolded += &format!("v{} = {}; ", i, value);
+                expression = expression.replacen("{}", &format!("v{}", i), 1);
+            }
+
+            let instructions = compiler().compile_instructions(format!("a = {}", folded).as_bytes()).unwrap();
+            match &instructions[0].kind {
+                InstructionKind::SetField { value: Node::Literal { .. }, .. }
+                | InstructionKind::SetField { value: Node::RuntimeError { .. }, .. } => (),
+                kind => panic!("{} wasn't folded: {:?}", folded, kind),
+            }
+
+            let folded_run = check(&format!("a = {}", folded));
+            let runtime_run = check(&format!("{} a = {}", unfolded, expression));
+            assert_eq!(
+                format!("{:?}", folded_run.result),
+                format!("{:?}", runtime_run.result),
+                "{} and {} failed differently",
+                folded,
+                expression,
+            );
+            let (folded_value, runtime_value) = (assigned(folded_run), assigned(runtime_run));
+            assert_eq!(folded_value, runtime_value, "{} and {} gave different results", folded, expression);
+        }
+    }
+
+    #[test]
+    fn repeat_once_with_continue_in_switch() {
+        // Continuing doesn't count the loop down, so this goes round twice even though it's a repeat (1)
+        let mut run = check("i = 0; repeat (1) { i += 1; switch (i) { case 1: continue } }");
+        assert_eq!(run.field(0, "i"), Some(2.0));
+        assert_eq!(run.result.unwrap(), ReturnType::Normal);
+    }
+
+    #[test]
+    fn switch_after_removed_branches() {
+        let source = concat!(
+            "switch (n) { case 1: if (0) a = 1; b = 1; ",
+            "case 2: if (1) { c = 1; d = 1 } e = 1; default: f = 1 }",
+        );
+        let mut run = check(&format!("n = 2; {}", source));
+        let fields = ["a", "b", "c", "d", "e", "f"].iter().map(|x| run.field(0, x).is_some()).collect::<Vec<_>>();
+        assert_eq!(fields, [false, false, true, true, true, true]);
+
+        let mut run = check(&format!("n = 3; {}", source));
+        let fields = ["a", "b", "c", "d", "e", "f"].iter().map(|x| run.field(0, x).is_some()).collect::<Vec<_>>();
+        assert_eq!(fields, [false, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn literal_owners_and_indices() {
+        let mut run = check(&format!(
+            "{} (1).z[1, 2] = 5; a = (1).z[1, 2]; all.w = 1; (noone).v = 1; b = (100002).k; (100001).c[3] = 4",
+            SETUP,
+        ));
+        let z = run.compiler.get_field_id(b"z");
+        assert_eq!(run.machine.field(0, z, 32002), None);
+        assert_eq!(run.machine.field(1, z, 32002), Some(5.0));
+        assert_eq!(run.machine.field(2, z, 32002), Some(5.0));
+        assert_eq!(run.field(0, "a"), Some(5.0));
+        assert_eq!(run.field(2, "w"), Some(1.0));
+        assert_eq!(run.field(0, "v"), None);
+        assert_eq!(run.field(0, "b"), Some(12.0));
+        let c = run.compiler.get_field_id(b"c");
+        assert_eq!(run.machine.field(1, c, 3), Some(4.0));
+
+        assert!(check("a[-1] = trace(1)").result.is_err());
+        assert_eq!(check("a[32000, 0] = trace(1)").machine.trace, []);
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert!(check("a = b").result.is_err());
+        assert!(check("a[32000] = 1").result.is_err());
+        assert!(check("with (all) { k = 1; a = b }").result.is_err());
+    }
+
+    // Prints how long some code takes to run as bytecode
+    fn time(name: &str, source: &str) {
+        let program = Program::new(&compiler().compile_instructions(source.as_bytes()).unwrap());
+        let runs = 10;
+        let start = Instant::now();
+        for _ in 0..runs {
+            run_ops(&mut TestMachine::default(), &program.ops, &mut new_context(), &mut 0).unwrap();
+        }
+        println!("{}: {:?}", name, start.elapsed() / runs);
+    }
+
+    /// Times some heavy loops, to compare changes to the bytecode between commits. Run with `--ignored --nocapture`.
+    /// This leaves out everything the game does, so compare a real game's event times from `--profile` as well.
+    /// Against interpreting instruction trees, the bytecode ran these 1.3x (locals) to 1.85x (with) as fast, and
+    /// script calls 2.3x. What's left in the loops is mostly field lookups and arithmetic, which both paths share.
+    #[test]
+    #[ignore]
+    fn benchmark() {
+        time("with", &format!("{} repeat (20000) with (all) {{ k += 1; j = k * 2 + 1; if (j > 3) j -= 1 }}", SETUP));
+        time("for", "for (i = 0; i < 100000; i += 1) {}");
+        time("locals", "var a, b; a = 1; b = 2; repeat (100000) a = (a + b * 3 - 1) mod 1000");
+        time("switch", "for (i = 0; i < 50000; i += 1) switch (i mod 4) { case 0: s = i; break; default: s -= 1 }");
+        time("scripts", "repeat (100000) trace(1, 2, 3)");
     }
 }
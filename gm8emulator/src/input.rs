@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
-use shared::input::{Key, MouseButton};
+use shared::input::{
+    JoystickAxis, JoystickEvent, JoystickInfo, Key, MouseButton, JOYSTICK_BUTTON_COUNT, JOYSTICK_COUNT,
+};
 use std::convert::identity;
 
 const KEY_COUNT: usize = 256;
 const MOUSE_BUTTON_COUNT: usize = 3;
 
+/// How far a joystick axis must be pushed before it counts as a direction
+pub const JOYSTICK_THRESHOLD: f64 = 0.5;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InputManager {
     // Keyboard
@@ -34,6 +39,32 @@ pub struct InputManager {
     mouse_scroll_down: bool,
     mouse_button: u32,
     mouse_lastbutton: u32,
+
+    // Joysticks
+    joysticks: [Joystick; JOYSTICK_COUNT],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Joystick {
+    info: Option<JoystickInfo>,
+    axes: [f64; 6],
+    held: BoolMap,
+    pressed: BoolMap,
+    released: BoolMap,
+    pov: i32,
+}
+
+impl Joystick {
+    fn new() -> Self {
+        Self {
+            info: None,
+            axes: [0.0; 6],
+            held: BoolMap::with_capacity(JOYSTICK_BUTTON_COUNT),
+            pressed: BoolMap::with_capacity(JOYSTICK_BUTTON_COUNT),
+            released: BoolMap::with_capacity(JOYSTICK_BUTTON_COUNT),
+            pov: -1,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -133,6 +164,7 @@ impl InputManager {
             mouse_scroll_down: false,
             mouse_button: 0,
             mouse_lastbutton: 0,
+            joysticks: [Joystick::new(), Joystick::new()],
         }
     }
 
@@ -401,6 +433,10 @@ impl InputManager {
         self.kb_released.clear();
         self.mouse_pressed.clear();
         self.mouse_released.clear();
+        for joystick in self.joysticks.iter_mut() {
+            joystick.pressed.clear();
+            joystick.released.clear();
+        }
     }
 
     /// Updates previous mouse position to be the current one
@@ -425,6 +461,108 @@ impl InputManager {
         self.mouse_released.clear();
         self.mouse_scroll_up = false;
         self.mouse_scroll_down = false;
+
+        for joystick in self.joysticks.iter_mut() {
+            joystick.held.clear();
+            joystick.pressed.clear();
+            joystick.released.clear();
+        }
+    }
+
+    /// Applies a change in joystick state, whether it came from a real device or a replay
+    pub fn joystick_event(&mut self, event: &JoystickEvent) {
+        match event {
+            JoystickEvent::Connect(id, info) => {
+                if let Some(joystick) = self.joysticks.get_mut(*id) {
+                    *joystick = Joystick::new();
+                    joystick.info = Some(info.clone());
+                }
+            },
+            JoystickEvent::Disconnect(id) => {
+                if let Some(joystick) = self.joysticks.get_mut(*id) {
+                    *joystick = Joystick::new();
+                }
+            },
+            JoystickEvent::Axis(id, axis, pos) => {
+                if let Some(joystick) = self.joysticks.get_mut(*id) {
+                    joystick.axes[*axis as usize] = pos.max(-1.0).min(1.0);
+                }
+            },
+            JoystickEvent::ButtonDown(id, button) => {
+                if let Some(joystick) = self.joysticks.get_mut(*id) {
+                    let button = usize::from(*button);
+                    if button < JOYSTICK_BUTTON_COUNT && !joystick.held.get(button) {
+                        joystick.held.set(button, true);
+                        joystick.pressed.set(button, true);
+                    }
+                }
+            },
+            JoystickEvent::ButtonUp(id, button) => {
+                if let Some(joystick) = self.joysticks.get_mut(*id) {
+                    let button = usize::from(*button);
+                    if joystick.held.get(button) {
+                        joystick.held.set(button, false);
+                        joystick.released.set(button, true);
+                    }
+                }
+            },
+            JoystickEvent::Pov(id, angle) => {
+                if let Some(joystick) = self.joysticks.get_mut(*id) {
+                    joystick.pov = *angle;
+                }
+            },
+        }
+    }
+
+    /// Gets the info of a connected joystick, or None if there isn't one in that slot
+    pub fn joystick_info(&self, id: usize) -> Option<&JoystickInfo> {
+        self.joysticks.get(id).and_then(|j| j.info.as_ref())
+    }
+
+    /// Gets an axis position of a joystick, from -1.0 to 1.0
+    pub fn joystick_axis(&self, id: usize, axis: JoystickAxis) -> f64 {
+        self.joysticks.get(id).map_or(0.0, |j| j.axes[axis as usize])
+    }
+
+    /// Checks if a joystick button is currently held
+    pub fn joystick_check_button(&self, id: usize, button: usize) -> bool {
+        self.joysticks.get(id).map_or(false, |j| j.held.get(button))
+    }
+
+    /// Checks if a joystick button was pressed this frame
+    pub fn joystick_check_button_pressed(&self, id: usize, button: usize) -> bool {
+        self.joysticks.get(id).map_or(false, |j| j.pressed.get(button))
+    }
+
+    /// Checks if a joystick button was released this frame
+    pub fn joystick_check_button_released(&self, id: usize, button: usize) -> bool {
+        self.joysticks.get(id).map_or(false, |j| j.released.get(button))
+    }
+
+    /// Gets the POV angle of a joystick, or -1 if it's centered
+    pub fn joystick_pov(&self, id: usize) -> i32 {
+        self.joysticks.get(id).map_or(-1, |j| j.pov)
+    }
+
+    /// Gets the direction of a joystick's X/Y stick as a numpad keycode, with 101 (numpad 5) meaning centered
+    pub fn joystick_direction(&self, id: usize) -> u32 {
+        let x = self.joystick_axis(id, JoystickAxis::X);
+        let y = self.joystick_axis(id, JoystickAxis::Y);
+        let column = if x < -JOYSTICK_THRESHOLD {
+            0
+        } else if x > JOYSTICK_THRESHOLD {
+            2
+        } else {
+            1
+        };
+        let row = if y < -JOYSTICK_THRESHOLD {
+            2
+        } else if y > JOYSTICK_THRESHOLD {
+            0
+        } else {
+            1
+        };
+        97 + row * 3 + column
     }
 
     // fn kb_handle_direct(&mut self, key: VirtualKeyCode, held: bool) {
@@ -10,6 +10,7 @@ use std::{
     cell::{Cell, RefCell},
     collections::HashMap,
     f64,
+    hash::{BuildHasherDefault, Hasher},
 };
 
 // Default in GameMaker 8
@@ -77,10 +78,17 @@ pub struct Instance {
     pub bbox_bottom: Cell<i32>,
     pub bbox_is_stale: Cell<bool>,
 
-    pub fields: RefCell<HashMap<usize, Field>>,
+    pub fields: RefCell<FieldMap>,
     pub alarms: RefCell<HashMap<u32, i32>>,
 }
 
+/// Fields by ID. Looking them up is a large part of running GML, so they aren't hashed with SipHash.
+pub type FieldMap = HashMap<usize, Field, BuildHasherDefault<FieldHasher>>;
+
+/// Hasher for field IDs, which are small integers given out in order, so a single multiply spreads them out enough.
+#[derive(Default)]
+pub struct FieldHasher(u64);
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Field {
     Single(Value),
@@ -89,7 +97,7 @@ pub enum Field {
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct DummyFieldHolder {
-    pub fields: HashMap<usize, Field>,
+    pub fields: FieldMap,
     pub vars: HashMap<InstanceVariable, Field>,
 }
 
@@ -145,7 +153,7 @@ impl Instance {
             bbox_right: Cell::new(BBOX_DEFAULT),
             bbox_bottom: Cell::new(BBOX_DEFAULT),
             bbox_is_stale: Cell::new(true),
-            fields: RefCell::new(HashMap::new()),
+            fields: RefCell::new(FieldMap::default()),
             alarms: RefCell::new(HashMap::new()),
         }
     }
@@ -201,7 +209,7 @@ impl Instance {
             bbox_right: Cell::new(BBOX_DEFAULT),
             bbox_bottom: Cell::new(BBOX_DEFAULT),
             bbox_is_stale: Cell::new(true),
-            fields: RefCell::new(HashMap::new()),
+            fields: RefCell::new(FieldMap::default()),
             alarms: RefCell::new(HashMap::new()),
         }
     }
@@ -409,6 +417,24 @@ impl Field {
 
 impl DummyFieldHolder {
     pub fn new() -> Self {
-        Self { fields: HashMap::new(), vars: HashMap::new() }
+        Self { fields: FieldMap::default(), vars: HashMap::new() }
+    }
+}
+
+impl Hasher for FieldHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|byte| self.write_u64(u64::from(*byte)));
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x517C_C1B7_2722_0A95);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
     }
 }
@@ -1,6 +1,7 @@
 use crate::{
     gml,
     instance::{Instance, InstanceState},
+    math::Real,
     tile::Tile,
 };
 use serde::{
@@ -11,7 +12,7 @@ use serde::{
 use shared::types::ID;
 use std::{
     alloc,
-    cell::RefCell,
+    cell::{Ref, RefCell},
     collections::{HashMap, HashSet},
     fmt, ptr,
     rc::Rc,
@@ -329,10 +330,12 @@ impl InstanceList {
 
     pub fn insert(&mut self, el: Instance) -> usize {
         let object_id = el.object_index.get();
+        let inactive = el.state.get() == InstanceState::Inactive;
         let value = self.chunks.insert(el);
         self.insert_order.push(value);
         self.draw_order.push(value);
-        self.object_id_map.entry(object_id).or_insert(Vec::new()).push(value);
+        let id_map = if inactive { &mut self.inactive_id_map } else { &mut self.object_id_map };
+        id_map.entry(object_id).or_insert(Vec::new()).push(value);
         value
     }
 
@@ -404,12 +407,21 @@ impl InstanceList {
             let chunks = &self.chunks;
             self.draw_order.retain(|idx| chunks.get(*idx).is_some());
             self.insert_order.retain(|idx| chunks.get(*idx).is_some());
-            for instances in self.object_id_map.values_mut() {
+            for instances in self.object_id_map.values_mut().chain(self.inactive_id_map.values_mut()) {
                 instances.retain(|idx| chunks.get(*idx).is_some());
             }
             self.object_id_map.retain(|_, list| !list.is_empty());
+            self.inactive_id_map.retain(|_, list| !list.is_empty());
         }
     }
+
+    /// Removes every instance matching the predicate and returns them in insertion order.
+    pub fn take_with(&mut self, f: impl Fn(&Instance) -> bool) -> Vec<Instance> {
+        let handles = self.insert_order.iter().copied().filter(|&idx| f(self.get(idx))).collect::<Vec<_>>();
+        let instances = handles.iter().map(|&idx| self.get(idx).clone()).collect();
+        self.remove_with(f);
+        instances
+    }
 }
 
 #[derive(Clone, Deserialize)]
@@ -417,6 +429,61 @@ pub struct TileList {
     chunks: ChunkList<Tile>,
     insert_order: Vec<usize>,
     draw_order: Vec<usize>,
+    #[serde(skip)]
+    id_map: RefCell<Option<HashMap<ID, usize>>>,
+    #[serde(skip)]
+    index: RefCell<Option<TileIndex>>,
+}
+
+/// Width and height of a cell in the tile spatial index, in room pixels.
+const TILE_CELL_SIZE: f64 = 256.0;
+
+/// Tiles covering more grid cells than this are kept in a separate list rather than in every cell.
+const TILE_CELL_LIMIT: f64 = 64.0;
+
+/// Depth and position lookup over a TileList. Built lazily and thrown away whenever tiles move.
+#[derive(Clone)]
+struct TileIndex {
+    /// Handles sorted by depth, highest first - this is the drawing order after the next draw_sort.
+    by_depth: Vec<usize>,
+    /// How many handles at the start of `by_depth` have a depth that isn't NaN (NaNs are sorted last).
+    ordered_len: usize,
+    /// Grid cell -> (position in `by_depth`, handle) of every tile overlapping that cell.
+    cells: HashMap<(i32, i32), Vec<(usize, usize)>>,
+    /// Tiles which are too big, or have non-finite bounds, so aren't in the grid.
+    oversized: Vec<(usize, usize)>,
+}
+
+impl TileIndex {
+    fn build(chunks: &ChunkList<Tile>, draw_order: &[usize]) -> Self {
+        let tile = |idx: usize| chunks.get(idx).unwrap();
+        let mut by_depth = draw_order.to_vec();
+        by_depth.sort_by(|&idx1, &idx2| tile(idx2).depth.get().cmp_nan_first(&tile(idx1).depth.get()));
+        let ordered_len = by_depth.iter().take_while(|&&idx| !tile(idx).depth.get().into_inner().is_nan()).count();
+
+        let mut cells: HashMap<(i32, i32), Vec<(usize, usize)>> = HashMap::new();
+        let mut oversized = Vec::new();
+        for (rank, &idx) in by_depth.iter().enumerate() {
+            let (left, top, right, bottom) = tile(idx).bbox();
+            let (x1, y1) = (Self::cell(left), Self::cell(top));
+            let (x2, y2) = (Self::cell(right), Self::cell(bottom));
+            if [x1, y1, x2, y2].iter().all(|c| c.is_finite()) && (x2 - x1 + 1.0) * (y2 - y1 + 1.0) <= TILE_CELL_LIMIT {
+                for cell_y in (y1 as i32)..=(y2 as i32) {
+                    for cell_x in (x1 as i32)..=(x2 as i32) {
+                        cells.entry((cell_x, cell_y)).or_default().push((rank, idx));
+                    }
+                }
+            } else {
+                oversized.push((rank, idx));
+            }
+        }
+
+        Self { by_depth, ordered_len, cells, oversized }
+    }
+
+    fn cell(coord: f64) -> f64 {
+        (coord / TILE_CELL_SIZE).floor()
+    }
 }
 
 // generic purpose non-borrowing iterators
@@ -435,7 +502,13 @@ impl TLIterInsertOrder {
 
 impl TileList {
     pub fn new() -> Self {
-        Self { chunks: ChunkList::new(), insert_order: Vec::new(), draw_order: Vec::new() }
+        Self {
+            chunks: ChunkList::new(),
+            insert_order: Vec::new(),
+            draw_order: Vec::new(),
+            id_map: RefCell::new(None),
+            index: RefCell::new(None),
+        }
     }
 
     pub fn get(&self, idx: usize) -> &Tile {
@@ -443,7 +516,47 @@ impl TileList {
     }
 
     pub fn get_by_tileid(&self, tile_id: ID) -> Option<usize> {
-        self.insert_order.iter().copied().find(|&inst| self.get(inst).id.get() == tile_id)
+        let mut id_map = self.id_map.borrow_mut();
+        let id_map = id_map.get_or_insert_with(|| {
+            // reversed so that the first tile inserted with a given ID wins
+            self.insert_order.iter().rev().map(|&idx| (self.get(idx).id.get(), idx)).collect()
+        });
+        id_map.get(&tile_id).copied()
+    }
+
+    /// Handles of all the tiles at the given depth, in drawing order.
+    pub fn layer(&self, depth: Real) -> Vec<usize> {
+        if depth.into_inner().is_nan() {
+            return Vec::new()
+        }
+        let index = self.index();
+        let ordered = &index.by_depth[..index.ordered_len];
+        let start = ordered.partition_point(|&idx| self.get(idx).depth.get() > depth);
+        let end = ordered.partition_point(|&idx| self.get(idx).depth.get() >= depth);
+        ordered[start..end].to_vec()
+    }
+
+    /// Handles of the tiles whose area might contain the given point, in drawing order.
+    /// This is only a broad check, so callers still need to test each tile themselves.
+    pub fn tiles_at(&self, x: Real, y: Real) -> Vec<usize> {
+        let index = self.index();
+        let cell = (TileIndex::cell(x.into_inner()) as i32, TileIndex::cell(y.into_inner()) as i32);
+        let mut found = index.cells.get(&cell).map(|c| c.as_slice()).unwrap_or(&[]).to_vec();
+        found.extend_from_slice(&index.oversized);
+        found.sort_unstable_by_key(|&(rank, _)| rank);
+        found.into_iter().map(|(_, idx)| idx).collect()
+    }
+
+    /// Must be called after changing the position, size, scale or depth of any tile in this list.
+    pub fn invalidate_index(&self) {
+        self.index.replace(None);
+    }
+
+    fn index(&self) -> Ref<TileIndex> {
+        if self.index.borrow().is_none() {
+            self.index.replace(Some(TileIndex::build(&self.chunks, &self.draw_order)));
+        }
+        Ref::map(self.index.borrow(), |index| index.as_ref().unwrap())
     }
 
     pub const fn iter_by_drawing(&self) -> TLIterDrawOrder {
@@ -469,6 +582,7 @@ impl TileList {
         let value = self.chunks.insert(el);
         self.insert_order.push(value);
         self.draw_order.push(value);
+        self.forget_indices();
         value
     }
 
@@ -476,6 +590,7 @@ impl TileList {
         self.chunks.remove(idx);
         self.insert_order.retain(|&i| i != idx);
         self.draw_order.retain(|&i| i != idx);
+        self.forget_indices();
     }
 
     pub fn remove_with(&mut self, f: impl Fn(&Tile) -> bool) {
@@ -491,6 +606,7 @@ impl TileList {
             let chunks = &self.chunks;
             self.draw_order.retain(|idx| chunks.get(*idx).is_some());
             self.insert_order.retain(|idx| chunks.get(*idx).is_some());
+            self.forget_indices();
         }
     }
 
@@ -498,6 +614,12 @@ impl TileList {
         self.chunks.clear();
         self.insert_order.clear();
         self.draw_order.clear();
+        self.forget_indices();
+    }
+
+    fn forget_indices(&mut self) {
+        *self.id_map.get_mut() = None;
+        *self.index.get_mut() = None;
     }
 }
 
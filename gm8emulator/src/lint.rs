@@ -0,0 +1,502 @@
+//! Static checks over all the GML in a game, for finding likely bugs without having to run it.
+
+use crate::{
+    action::{self, execution_type, kind},
+    game::events::describe_event,
+    gml::compiler::{
+        ast,
+        mappings::{self, Function},
+        token::Operator,
+    },
+};
+use gm8exe::{
+    asset::{etc::CodeAction, PascalString},
+    GameAssets,
+};
+use shared::types::ID;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, str,
+};
+
+/// A likely bug found in a game's GML.
+pub struct Issue {
+    /// The code the issue was found in, described the same way GML error messages describe it
+    pub source: String,
+
+    /// The line in that code, if the issue is on a particular line
+    pub line: Option<usize>,
+
+    pub kind: IssueKind,
+}
+
+pub enum IssueKind {
+    /// The code doesn't parse, so the game can't start
+    SyntaxError(String),
+
+    /// Something which parses but can't be compiled as a statement
+    InvalidStatement(String),
+
+    /// A field is read, but nothing in the game ever assigns it
+    UnassignedField(String),
+
+    /// A kernel function is called which the emulator doesn't implement yet
+    UnimplementedFunction(String),
+
+    /// A function is called which isn't a script or a kernel function
+    UnknownFunction(String),
+
+    /// A kernel function is called with the wrong number of arguments
+    WrongArgumentCount { function: String, expected: usize, given: usize },
+
+    /// A statement comes after an exit, return, break or continue, so it can never run
+    UnreachableCode,
+}
+
+impl IssueKind {
+    /// A short name for this kind of issue, for summaries.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::SyntaxError(_) => "syntax errors",
+            Self::InvalidStatement(_) => "invalid statements",
+            Self::UnassignedField(_) => "unassigned fields",
+            Self::UnimplementedFunction(_) => "unimplemented functions",
+            Self::UnknownFunction(_) => "unknown functions",
+            Self::WrongArgumentCount { .. } => "wrong argument counts",
+            Self::UnreachableCode => "unreachable code",
+        }
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SyntaxError(message) => write!(f, "syntax error: {}", message),
+            Self::InvalidStatement(statement) => write!(f, "invalid statement: {}", statement),
+            Self::UnassignedField(name) => write!(f, "{} is read but never assigned", name),
+            Self::UnimplementedFunction(name) => write!(f, "{} is not implemented yet", name),
+            Self::UnknownFunction(name) => write!(f, "unknown function or script: {}", name),
+            Self::WrongArgumentCount { function, expected, given } => {
+                write!(f, "{} expects {} arguments, but is given {}", function, expected, given)
+            },
+            Self::UnreachableCode => write!(f, "unreachable code"),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} at line {}: {}", self.source, line, self.kind),
+            None => write!(f, "{}: {}", self.source, self.kind),
+        }
+    }
+}
+
+/// Checks all the code in a game: scripts, trigger conditions, object events, timeline moments,
+/// and room and instance creation code.
+pub fn lint(assets: &GameAssets) -> Vec<Issue> {
+    let mut linter = Linter::new(assets);
+    let name = |name: &PascalString| String::from_utf8_lossy(&name.0).into_owned();
+    let object_name = |id: ID| match assets.objects.get(id as usize).and_then(|o| o.as_ref()) {
+        Some(object) => name(&object.name),
+        None => format!("<undefined {}>", id),
+    };
+    let trigger_name = |id: ID| match assets.triggers.get(id as usize).and_then(|t| t.as_ref()) {
+        Some(trigger) => name(&trigger.name),
+        None => id.to_string(),
+    };
+
+    for script in assets.scripts.iter().flatten() {
+        linter.unit(format!("script {}", name(&script.name)));
+        linter.code(&script.source.0);
+    }
+    for trigger in assets.triggers.iter().flatten() {
+        linter.unit(format!("condition of trigger {}", name(&trigger.name)));
+        linter.code(&trigger.condition.0);
+    }
+    for object in assets.objects.iter().flatten() {
+        for (event_type, events) in object.events.iter().enumerate() {
+            for (event_number, actions) in events {
+                let event = describe_event(event_type, *event_number as usize, &object_name, &trigger_name);
+                linter.actions(actions, |action| {
+                    format!("action number {} of {} for object {}", action + 1, event, name(&object.name))
+                });
+            }
+        }
+    }
+    for timeline in assets.timelines.iter().flatten() {
+        for (moment, actions) in timeline.moments.iter() {
+            linter.actions(actions, |action| {
+                format!("action number {} of moment {} for timeline {}", action + 1, moment, name(&timeline.name))
+            });
+        }
+    }
+    for room in assets.rooms.iter().flatten() {
+        linter.unit(format!("creation code of room {}", name(&room.name)));
+        linter.code(&room.creation_code.0);
+        for instance in room.instances.iter() {
+            linter.unit(format!("creation code of instance {}", instance.id));
+            linter.code(&instance.creation_code.0);
+        }
+    }
+
+    linter.finish()
+}
+
+/// A piece of code being checked, along with what's been found in it so far.
+struct Unit {
+    source: String,
+    issues: Vec<(Option<usize>, IssueKind)>,
+
+    /// Fields read in this code, which can only be checked once every assignment in the game has been seen
+    reads: Vec<(Option<usize>, Vec<u8>)>,
+}
+
+struct Linter<'a> {
+    /// Identifiers which aren't fields: asset names, user constants, builtin constants and builtin variables
+    names: HashSet<&'a [u8]>,
+    scripts: HashSet<&'a [u8]>,
+
+    /// Every field name assigned anywhere in the game
+    assigned: HashSet<Vec<u8>>,
+    units: Vec<Unit>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(assets: &'a GameAssets) -> Self {
+        fn names<'a, T>(assets: &'a [Option<Box<T>>], get_name: fn(&T) -> &PascalString) -> Vec<&'a [u8]> {
+            assets.iter().flatten().map(|x| get_name(x).0.as_ref()).collect()
+        }
+
+        let mut linter = Self {
+            names: HashSet::new(),
+            scripts: names(&assets.scripts, |x| &x.name).into_iter().collect(),
+            assigned: HashSet::new(),
+            units: Vec::new(),
+        };
+        linter.names.extend(names(&assets.objects, |x| &x.name));
+        linter.names.extend(names(&assets.sprites, |x| &x.name));
+        linter.names.extend(names(&assets.sounds, |x| &x.name));
+        linter.names.extend(names(&assets.backgrounds, |x| &x.name));
+        linter.names.extend(names(&assets.paths, |x| &x.name));
+        linter.names.extend(names(&assets.fonts, |x| &x.name));
+        linter.names.extend(names(&assets.timelines, |x| &x.name));
+        linter.names.extend(names(&assets.scripts, |x| &x.name));
+        linter.names.extend(names(&assets.rooms, |x| &x.name));
+        linter.names.extend(names(&assets.triggers, |x| &x.constant_name));
+        linter.names.extend(assets.constants.iter().map(|c| c.name.0.as_ref()));
+        linter.names.extend(mappings::CONSTANTS.iter().map(|(name, _)| name.as_bytes()));
+        linter
+    }
+
+    /// Starts checking a new piece of code.
+    fn unit(&mut self, source: String) {
+        self.units.push(Unit { source, issues: Vec::new(), reads: Vec::new() });
+    }
+
+    fn issue(&mut self, line: Option<usize>, kind: IssueKind) {
+        if let Some(unit) = self.units.last_mut() {
+            unit.issues.push((line, kind));
+        }
+    }
+
+    /// Checks a list of actions, each of which is its own piece of code.
+    fn actions(&mut self, actions: &[CodeAction], source: impl Fn(usize) -> String) {
+        for (index, action) in actions.iter().enumerate() {
+            self.unit(source(index));
+            if let Some(code) = action::action_code(action) {
+                self.code(&code);
+            }
+            match action.action_kind {
+                kind::NORMAL if action.execution_type != execution_type::NONE => {
+                    if action.execution_type == execution_type::FUNCTION {
+                        match str::from_utf8(&action.fn_name.0)
+                            .ok()
+                            .and_then(|name| mappings::FUNCTIONS.iter().find(|(n, _, _)| *n == name))
+                        {
+                            Some((name, function, _)) => self.function(None, name, function, action.param_count),
+                            None => self.issue(
+                                None,
+                                IssueKind::UnknownFunction(String::from_utf8_lossy(&action.fn_name.0).into_owned()),
+                            ),
+                        }
+                    }
+                    let params = action.param_strings.iter().zip(action.param_types.iter()).take(action.param_count);
+                    for (param, _) in params.filter(|(_, t)| !matches!(**t, 1 | 2)) {
+                        self.param(&param.0);
+                    }
+                },
+                kind::REPEAT => self.param(&action.param_strings[0].0),
+                _ => (),
+            }
+        }
+    }
+
+    /// Checks a piece of GML code.
+    fn code(&mut self, code: &[u8]) {
+        match ast::AST::new(code) {
+            Ok(ast) => self.block(&ast, Some(1)),
+            Err(e) => self.issue(None, IssueKind::SyntaxError(e.message)),
+        }
+    }
+
+    /// Checks an action parameter, which is a single expression.
+    fn param(&mut self, code: &[u8]) {
+        match ast::AST::expression(code) {
+            Ok(expr) => self.expression(&expr, None),
+            Err(e) => self.issue(None, IssueKind::SyntaxError(e.message)),
+        }
+    }
+
+    /// Checks a group of statements, including whether any of them come after something that leaves the group.
+    fn block(&mut self, exprs: &[ast::Expr], line: Option<usize>) {
+        // Whether control can't reach the current statement, and whether that's already been reported
+        let mut unreachable = false;
+        let mut reported = false;
+        for expr in exprs {
+            let line = match expr {
+                ast::Expr::Line(line) => Some(line.line),
+                _ => line,
+            };
+            match expr.without_line() {
+                // Switch cases can be jumped to, so code after them is reachable again
+                ast::Expr::Case(_) | ast::Expr::Default => {
+                    unreachable = false;
+                    reported = false;
+                },
+                _ if unreachable && !reported => {
+                    self.issue(line, IssueKind::UnreachableCode);
+                    reported = true;
+                },
+                ast::Expr::Exit | ast::Expr::Return(_) | ast::Expr::Break | ast::Expr::Continue => unreachable = true,
+                _ => (),
+            }
+            self.statement(expr, line);
+        }
+    }
+
+    fn statement(&mut self, expr: &ast::Expr, line: Option<usize>) {
+        match expr {
+            ast::Expr::Line(line) => self.statement(&line.expr, Some(line.line)),
+            ast::Expr::Binary(binary) => self.assignment(binary, line),
+            ast::Expr::Function(_) => self.expression(expr, line),
+            ast::Expr::Group(group) => self.block(group, line),
+            ast::Expr::If(if_expr) => {
+                self.expression(&if_expr.cond, line);
+                self.statement(&if_expr.body, line);
+                if let Some(else_body) = &if_expr.else_body {
+                    self.statement(else_body, line);
+                }
+            },
+            ast::Expr::For(for_expr) => {
+                self.statement(&for_expr.start, line);
+                self.expression(&for_expr.cond, line);
+                self.statement(&for_expr.step, line);
+                self.statement(&for_expr.body, line);
+            },
+            ast::Expr::DoUntil(do_until) => {
+                self.statement(&do_until.body, line);
+                self.expression(&do_until.cond, line);
+            },
+            ast::Expr::While(while_expr) => {
+                self.expression(&while_expr.cond, line);
+                self.statement(&while_expr.body, line);
+            },
+            ast::Expr::Repeat(repeat) => {
+                self.expression(&repeat.count, line);
+                self.statement(&repeat.body, line);
+            },
+            ast::Expr::With(with) => {
+                self.expression(&with.target, line);
+                self.statement(&with.body, line);
+            },
+            ast::Expr::Switch(switch) => {
+                self.expression(&switch.input, line);
+                match switch.body.without_line() {
+                    ast::Expr::Group(body) => self.block(body, line),
+                    body => self.issue(line, IssueKind::InvalidStatement(body.to_string())),
+                }
+            },
+            ast::Expr::Case(expr) | ast::Expr::Return(expr) => self.expression(expr, line),
+            ast::Expr::Var(_)
+            | ast::Expr::GlobalVar(_)
+            | ast::Expr::Default
+            | ast::Expr::Continue
+            | ast::Expr::Break
+            | ast::Expr::Exit => (),
+            expr => self.issue(line, IssueKind::InvalidStatement(expr.to_string())),
+        }
+    }
+
+    fn assignment(&mut self, binary: &ast::BinaryExpr, line: Option<usize>) {
+        self.expression(&binary.right, line);
+        let mut left = binary.left.without_line();
+        if let ast::Expr::Binary(index) = left {
+            if index.op == Operator::Index {
+                if let ast::Expr::Group(dimensions) = &index.right {
+                    for dimension in dimensions {
+                        self.expression(dimension, line);
+                    }
+                }
+                left = &index.left;
+            }
+        }
+        if let ast::Expr::Binary(deref) = left {
+            if deref.op == Operator::Deref {
+                self.expression(&deref.left, line);
+                left = &deref.right;
+            }
+        }
+        match left {
+            ast::Expr::LiteralIdentifier(name) => {
+                self.assigned.insert(name.to_vec());
+            },
+            left => self.issue(line, IssueKind::InvalidStatement(left.to_string())),
+        }
+    }
+
+    fn expression(&mut self, expr: &ast::Expr, line: Option<usize>) {
+        match expr {
+            ast::Expr::LiteralIdentifier(name) => self.read(name, line),
+            ast::Expr::Unary(unary) => self.expression(&unary.child, line),
+            ast::Expr::Binary(binary) => match binary.op {
+                Operator::Deref => {
+                    self.expression(&binary.left, line);
+                    if let ast::Expr::LiteralIdentifier(name) = &binary.right {
+                        self.read(name, line);
+                    }
+                },
+                Operator::Index => {
+                    if let ast::Expr::Group(dimensions) = &binary.right {
+                        for dimension in dimensions {
+                            self.expression(dimension, line);
+                        }
+                    }
+                    self.expression(&binary.left, line);
+                },
+                _ => {
+                    self.expression(&binary.left, line);
+                    self.expression(&binary.right, line);
+                },
+            },
+            ast::Expr::Function(call) => self.call(call, line),
+            ast::Expr::Line(line) => self.expression(&line.expr, Some(line.line)),
+            _ => (),
+        }
+    }
+
+    fn call(&mut self, call: &ast::FunctionExpr, line: Option<usize>) {
+        for param in &call.params {
+            self.expression(param, line);
+        }
+        if self.scripts.contains(call.name) {
+            return
+        }
+        match str::from_utf8(call.name).ok().and_then(|name| mappings::FUNCTIONS.iter().find(|(n, _, _)| *n == name)) {
+            Some((name, function, _)) => {
+                self.function(line, name, function, call.params.len());
+                // Fields can be assigned by name, which is the only way some games ever assign them
+                if name.starts_with("variable_") && name.ends_with("_set") {
+                    if let Some(ast::Expr::LiteralString(field)) = call.params.first() {
+                        self.assigned.insert(field.to_vec());
+                    }
+                }
+            },
+            None => self.issue(line, IssueKind::UnknownFunction(String::from_utf8_lossy(call.name).into_owned())),
+        }
+    }
+
+    /// Checks a call to a kernel function.
+    fn function(&mut self, line: Option<usize>, name: &str, function: &Function, given: usize) {
+        if !function.is_implemented() {
+            self.issue(line, IssueKind::UnimplementedFunction(name.into()));
+        } else if let Some(expected) = function.arg_count().filter(|expected| *expected != given) {
+            self.issue(line, IssueKind::WrongArgumentCount { function: name.into(), expected, given });
+        }
+    }
+
+    fn read(&mut self, name: &[u8], line: Option<usize>) {
+        if self.names.contains(name) || mappings::get_instance_variable_by_name(name).is_some() {
+            return
+        }
+        if let Some(unit) = self.units.last_mut() {
+            unit.reads.push((line, name.to_vec()));
+        }
+    }
+
+    /// Checks the reads that have been seen against every assignment, and gathers up all the issues found.
+    fn finish(self) -> Vec<Issue> {
+        let assigned = self.assigned;
+        let mut issues = Vec::new();
+        for mut unit in self.units {
+            // Only the first read of each field in a piece of code is reported
+            let mut seen = HashMap::new();
+            for (line, name) in unit.reads {
+                if !assigned.contains(&name) {
+                    seen.entry(name).or_insert(line);
+                }
+            }
+            let mut unassigned = seen.into_iter().collect::<Vec<_>>();
+            unassigned.sort_by(|(name1, line1), (name2, line2)| line1.cmp(line2).then_with(|| name1.cmp(name2)));
+            let issue = |(name, line): (Vec<u8>, _)| {
+                (line, IssueKind::UnassignedField(String::from_utf8_lossy(&name).into_owned()))
+            };
+            unit.issues.extend(unassigned.into_iter().map(issue));
+            unit.issues.sort_by_key(|(line, _)| *line);
+            let source = unit.source;
+            issues.extend(unit.issues.into_iter().map(|(line, kind)| Issue { source: source.clone(), line, kind }));
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lints each piece of code as its own unit, as if they were all in one game with no assets.
+    fn lint_code(code: &[&str]) -> Vec<(String, Option<usize>, String)> {
+        let mut linter =
+            Linter { names: HashSet::new(), scripts: HashSet::new(), assigned: HashSet::new(), units: Vec::new() };
+        linter.names.extend(mappings::CONSTANTS.iter().map(|(name, _)| name.as_bytes()));
+        for (i, code) in code.iter().enumerate() {
+            linter.unit(format!("code {}", i));
+            linter.code(code.as_bytes());
+        }
+        linter.finish().into_iter().map(|issue| (issue.source, issue.line, issue.kind.to_string())).collect()
+    }
+
+    #[test]
+    fn unreachable_code() {
+        assert_eq!(lint_code(&["exit\nn = 1\nn = 2"]), [("code 0".into(), Some(2), "unreachable code".into())]);
+        assert!(lint_code(&["n = 0\nswitch n {\ncase 0:\nexit\ncase 1:\nn = 1\nbreak\ndefault:\nn = 2\n}"]).is_empty());
+        assert_eq!(
+            lint_code(&["n = 0\nswitch n {\ncase 0:\nexit\nn = 3\ncase 1:\nn = 1\n}"]),
+            [("code 0".into(), Some(5), "unreachable code".into())]
+        );
+        assert!(lint_code(&["n = 0\nif n exit\nn = 1\nrepeat 2 { if n continue\nn += 1 }"]).is_empty());
+    }
+
+    #[test]
+    fn unassigned_fields() {
+        let issues = lint_code(&["n = a + b + a\ny = c_white + other.d", "b = 1\nvariable_local_set(\"d\", 2)"]);
+        assert_eq!(issues, [("code 0".into(), Some(1), "a is read but never assigned".into())]);
+        assert_eq!(
+            lint_code(&["n = 1", "m = 1\nm = n[q] + global.r"]),
+            [
+                ("code 1".into(), Some(2), "q is read but never assigned".into()),
+                ("code 1".into(), Some(2), "r is read but never assigned".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_argument_counts() {
+        assert_eq!(
+            lint_code(&["draw_sprite(0, 0, 0)\nn = random(1) + point_distance(0, 0, 1, 1)"]),
+            [("code 0".into(), Some(1), "draw_sprite expects 4 arguments, but is given 3".into())]
+        );
+        assert!(lint_code(&["n = choose(1, 2, 3) + max(1) + string(n)"]).is_empty());
+    }
+}
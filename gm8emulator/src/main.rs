@@ -9,6 +9,7 @@ mod handleman;
 mod input;
 mod instance;
 mod instancelist;
+mod lint;
 mod math;
 mod tile;
 mod util;
@@ -23,14 +24,23 @@ use std::{
 const EXIT_SUCCESS: i32 = 0;
 const EXIT_FAILURE: i32 = 1;
 
-fn help(argv0: &str, opts: getopts::Options) {
-    print!(
-        "{}",
-        opts.usage(&format!("Usage: {} FILE [options]", match Path::new(argv0).file_name() {
-            Some(file) => file.to_str().unwrap_or(argv0),
-            None => argv0,
-        }))
-    );
+fn help(argv0: &str, usage: &str, opts: getopts::Options) {
+    let name = match Path::new(argv0).file_name() {
+        Some(file) => file.to_str().unwrap_or(argv0),
+        None => argv0,
+    };
+    print!("{}", opts.usage(&format!("Usage: {} {}", name, usage)));
+}
+
+fn print_parse_error(fail: getopts::Fail) {
+    use getopts::Fail::*;
+    match fail {
+        ArgumentMissing(arg) => eprintln!("missing argument {}", arg),
+        UnrecognizedOption(opt) => eprintln!("unrecognized option {}", opt),
+        OptionMissing(opt) => eprintln!("missing option {}", opt),
+        OptionDuplicated(opt) => eprintln!("duplicated option {}", opt),
+        UnexpectedArgument(arg) => eprintln!("unexpected argument {}", arg),
+    }
 }
 
 fn main() {
@@ -41,6 +51,10 @@ fn xmain() -> i32 {
     let args: Vec<String> = env::args().collect();
     let process = args[0].clone();
 
+    if args.get(1).map(String::as_str) == Some("lint") {
+        return lint_main(&process, &args[2..])
+    }
+
     let mut opts = getopts::Options::new();
     opts.optflag("h", "help", "prints this help message");
     opts.optflag("s", "strict", "enable various data integrity checks");
@@ -51,24 +65,22 @@ fn xmain() -> i32 {
     opts.optopt("n", "project-name", "name of TAS project to create or load", "NAME");
     opts.optopt("f", "replay-file", "path to savestate file to replay", "FILE");
     opts.optmulti("a", "game-arg", "argument to pass to the game", "ARG");
+    opts.optopt("e", "on-error", "answer GML error messages without asking (ignore or abort)", "POLICY");
+    opts.optopt("P", "profile", "profile the game's GML and write it to FILE as folded stacks on exit", "FILE");
+    opts.optopt("c", "compat-report", "write a JSON report of what the game uses to FILE and exit", "FILE");
+    opts.optflag("C", "console", "run GML from stdin or from an overlay opened with ` and print the results");
+    opts.optopt("d", "dap", "wait for a Debug Adapter Protocol client on this port to debug the game's GML", "PORT");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(matches) => matches,
         Err(fail) => {
-            use getopts::Fail::*;
-            match fail {
-                ArgumentMissing(arg) => eprintln!("missing argument {}", arg),
-                UnrecognizedOption(opt) => eprintln!("unrecognized option {}", opt),
-                OptionMissing(opt) => eprintln!("missing option {}", opt),
-                OptionDuplicated(opt) => eprintln!("duplicated option {}", opt),
-                UnexpectedArgument(arg) => eprintln!("unexpected argument {}", arg),
-            }
+            print_parse_error(fail);
             return EXIT_FAILURE
         },
     };
 
     if args.len() < 2 || matches.opt_present("h") {
-        help(&process, opts);
+        help(&process, "FILE [options]\n(or lint FILE [options] to check the game's GML for likely bugs)", opts);
         return EXIT_SUCCESS
     }
 
@@ -84,6 +96,24 @@ fn xmain() -> i32 {
         },
     }
     .unwrap_or(15560);
+    let dap_port = match matches.opt_str("d").map(|x| x.parse::<u16>()).transpose() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("invalid DAP port provided: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+    let error_policy = match matches.opt_str("e").as_deref() {
+        None | Some("ask") => game::ErrorPolicy::Ask,
+        Some("ignore") => game::ErrorPolicy::Ignore,
+        Some("abort") => game::ErrorPolicy::Abort,
+        Some(policy) => {
+            eprintln!("invalid error policy provided: {} (expected ask, ignore or abort)", policy);
+            return EXIT_FAILURE
+        },
+    };
+    let profile_path = matches.opt_str("P").map(PathBuf::from);
+    let compat_report_path = matches.opt_str("c").map(PathBuf::from);
     let project_path = matches.opt_str("n").map(|name| {
         let mut p = env::current_dir().expect("std::env::current_dir() failed");
         p.push("projects");
@@ -185,6 +215,33 @@ fn xmain() -> i32 {
         },
     };
 
+    if let Some(path) = compat_report_path {
+        let report = serde_json::to_string_pretty(&game::compat::report(&assets)).unwrap();
+        return match fs::write(&path, report) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(e) => {
+                eprintln!("failed to write compatibility report to {}: {}", path.display(), e);
+                EXIT_FAILURE
+            },
+        }
+    }
+
+    let dap_client = match dap_port {
+        Some(port) => {
+            // the sources have to be collected now, as launching the game consumes its assets
+            let sources = game::debugger::dap::collect_sources(&assets);
+            println!("waiting for a DAP client on port {}...", port);
+            match std::net::TcpListener::bind(("127.0.0.1", port)).and_then(|listener| listener.accept()) {
+                Ok((stream, _)) => Some(game::debugger::dap::DapClient::new(stream, sources)),
+                Err(e) => {
+                    eprintln!("failed to accept a DAP client: {}", e);
+                    return EXIT_FAILURE
+                },
+            }
+        },
+        None => None,
+    };
+
     let absolute_path = match file_path.canonicalize() {
         Ok(p) => p,
         Err(e) => {
@@ -221,10 +278,22 @@ fn xmain() -> i32 {
             },
         };
 
-    if let Err(err) = if let Some(path) = project_path {
+    components.error_policy = error_policy;
+    if let Some(client) = dap_client {
+        components.debugger = Some(game::debugger::Debugger::new(Box::new(client)));
+    }
+    if profile_path.is_some() {
+        components.profiler = Some(Default::default());
+    }
+    if matches.opt_present("C") {
+        components.console.enabled = true;
+        components.console.read_stdin();
+    }
+
+    let result = if let Some(path) = project_path {
         components.record(path, port)
     } else {
-        // cache temp_dir and included files because the other functions take ownership
+        // cache temp_dir and included files so they can be cleaned up once the game ends
         let temp_dir: Option<PathBuf> = if can_clear_temp_dir {
             Some(components.decode_str(components.temp_directory.as_ref()).into_owned().into())
         } else {
@@ -244,10 +313,93 @@ fn xmain() -> i32 {
             std::fs::remove_dir_all(temp_dir).ok();
         }
         result
-    } {
+    };
+
+    if let Some(path) = profile_path {
+        if let Err(e) = fs::write(&path, components.profile_folded()) {
+            eprintln!("failed to write profile to {}: {}", path.display(), e);
+        }
+    }
+
+    if let Err(err) = result {
         println!("Runtime error: {}", err);
         EXIT_FAILURE
     } else {
         EXIT_SUCCESS
     }
 }
+
+/// Runs the `lint` subcommand, which reports likely bugs in a game's GML without running it.
+fn lint_main(process: &str, args: &[String]) -> i32 {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "prints this help message");
+    opts.optflag("s", "strict", "enable various data integrity checks");
+    opts.optflag("t", "singlethread", "parse gamedata synchronously");
+
+    let matches = match opts.parse(args) {
+        Ok(matches) => matches,
+        Err(fail) => {
+            print_parse_error(fail);
+            return EXIT_FAILURE
+        },
+    };
+
+    if args.is_empty() || matches.opt_present("h") {
+        help(process, "lint FILE [options]", opts);
+        return EXIT_SUCCESS
+    }
+
+    let input = match matches.free.as_slice() {
+        [input] => input,
+        [] => {
+            eprintln!("no input file");
+            return EXIT_FAILURE
+        },
+        [_, second, ..] => {
+            eprintln!("unexpected second input {}", second);
+            return EXIT_FAILURE
+        },
+    };
+
+    let mut file = match fs::read(input) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("failed to open '{}': {}", input, err);
+            return EXIT_FAILURE
+        },
+    };
+
+    let assets = match gm8exe::reader::from_exe(
+        &mut file,
+        None::<fn(&str)>,
+        matches.opt_present("s"),
+        !matches.opt_present("t"),
+    ) {
+        Ok(assets) => assets,
+        Err(err) => {
+            eprintln!("failed to load '{}' - {}", input, err);
+            return EXIT_FAILURE
+        },
+    };
+
+    let issues = lint::lint(&assets);
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    if issues.is_empty() {
+        println!("no issues found");
+        EXIT_SUCCESS
+    } else {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for issue in &issues {
+            match counts.iter_mut().find(|(category, _)| *category == issue.kind.category()) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((issue.kind.category(), 1)),
+            }
+        }
+        let counts = counts.iter().map(|(category, count)| format!("{} {}", count, category)).collect::<Vec<_>>();
+        println!("{} issues found ({})", issues.len(), counts.join(", "));
+        EXIT_FAILURE
+    }
+}
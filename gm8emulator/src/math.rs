@@ -15,104 +15,112 @@ pub struct Real(f64);
 /// The lenience between values when compared.
 const CMP_EPSILON: f64 = 1e-13;
 
-// Platform-specific implementation of the arithmetic. Should provide:
-// Add, Sub, Mul, Div, sin, cos, tan, round64
+// Implementation of the arithmetic, in 80-bit extended precision like GM8 uses. Should provide:
+// add, sub, mul, div, sin, cos, tan, round64
+// This runs on the FPU where there is one, and is emulated in software elsewhere so that results match across platforms.
+#[cfg(any(test, not(any(target_arch = "x86", target_arch = "x86_64"))))]
+mod soft;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x87;
+
 cfg_if! {
     if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+        use x87 as imp;
+    } else {
+        use soft as imp;
+    }
+}
 
-        impl Real {
-
-            #[inline(always)]
-            pub fn sin(self) -> Self {
-                Self(self.0.sin())
-            }
+impl Real {
+    #[inline(always)]
+    pub fn sin(self) -> Self {
+        Self(imp::sin(self.0))
+    }
 
-            #[inline(always)]
-            pub fn cos(self) -> Self {
-                Self(self.0.cos())
-            }
+    #[inline(always)]
+    pub fn cos(self) -> Self {
+        Self(imp::cos(self.0))
+    }
 
-            #[inline(always)]
-            pub fn tan(self) -> Self {
-                Self(self.0.tan())
-            }
+    #[inline(always)]
+    pub fn tan(self) -> Self {
+        Self(imp::tan(self.0))
+    }
 
-            pub fn arcsin(self) -> Self {
-                Self(self.0.asin())
-            }
+    pub fn arcsin(self) -> Self {
+        Self(self.0.asin())
+    }
 
-            pub fn arccos(self) -> Self {
-                Self(self.0.acos())
-            }
+    pub fn arccos(self) -> Self {
+        Self(self.0.acos())
+    }
 
-            pub fn arctan(self) -> Self {
-                Self(self.0.atan())
-            }
+    pub fn arctan(self) -> Self {
+        Self(self.0.atan())
+    }
 
-            pub fn arctan2(self, other: Real) -> Self {
-                Self(self.0.atan2(other.0))
-            }
+    pub fn arctan2(self, other: Real) -> Self {
+        Self(self.0.atan2(other.0))
+    }
 
-            pub fn exp(self) -> Self {
-                Self(self.0.exp())
-            }
+    pub fn exp(self) -> Self {
+        Self(self.0.exp())
+    }
 
-            pub fn ln(self) -> Self {
-                Self(self.0.ln())
-            }
+    pub fn ln(self) -> Self {
+        Self(self.0.ln())
+    }
 
-            pub fn log2(self) -> Self {
-                Self(self.0.log2())
-            }
+    pub fn log2(self) -> Self {
+        Self(self.0.log2())
+    }
 
-            pub fn log10(self) -> Self {
-                Self(self.0.log10())
-            }
+    pub fn log10(self) -> Self {
+        Self(self.0.log10())
+    }
 
-            pub fn logn(self, other: Real) -> Self {
-                Self(self.0.log(other.0))
-            }
+    pub fn logn(self, other: Real) -> Self {
+        Self(self.0.log(other.0))
+    }
 
-            pub fn sqrt(self) -> Self {
-                Self(self.0.sqrt())
-            }
-        }
+    pub fn sqrt(self) -> Self {
+        Self(self.0.sqrt())
+    }
+}
 
-        impl Add for Real {
-            type Output = Self;
+impl Add for Real {
+    type Output = Self;
 
-            #[inline(always)]
-            fn add(self, other: Self) -> Self {
-                Self(self.0 + other.0)
-            }
-        }
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        Self(imp::add(self.0, other.0))
+    }
+}
 
-        impl Sub for Real {
-            type Output = Self;
+impl Sub for Real {
+    type Output = Self;
 
-            #[inline(always)]
-            fn sub(self, other: Self) -> Self {
-                Self(self.0 - other.0)
-            }
-        }
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self {
+        Self(imp::sub(self.0, other.0))
+    }
+}
 
-        impl Mul for Real {
-            type Output = Self;
+impl Mul for Real {
+    type Output = Self;
 
-            #[inline(always)]
-            fn mul(self, other: Self) -> Self {
-                Self(self.0 * other.0)
-            }
-        }
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        Self(imp::mul(self.0, other.0))
+    }
+}
 
-        impl Div for Real {
-            type Output = Self;
+impl Div for Real {
+    type Output = Self;
 
-            #[inline(always)]
-            fn div(self, other: Self) -> Self {
-                Self(self.0 / other.0)
-            }
-        }
+    #[inline(always)]
+    fn div(self, other: Self) -> Self {
+        Self(imp::div(self.0, other.0))
     }
 }
 
@@ -248,6 +256,13 @@ impl Real {
         util::ieee_round(self.0)
     }
 
+    /// Rounds to the nearest 64-bit integer as the x87 does, with ties to even.
+    /// Out-of-range values and NaN give i64::MIN.
+    #[inline(always)]
+    pub fn round64(self) -> i64 {
+        imp::round64(self.0)
+    }
+
     #[inline(always)]
     pub fn floor(self) -> Self {
         Self(self.0.floor())
@@ -487,4 +502,164 @@ mod tests {
     fn to_radians() {
         assert_eq!(Real(180.0).to_radians(), Real(std::f64::consts::PI));
     }
+
+    /// Compares the software implementation against the FPU on random and edge-case operands.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    mod soft_parity {
+        use super::super::{soft, x87};
+
+        const ITERATIONS: usize = 200_000;
+
+        const SPECIALS: [f64; 22] = [
+            0.0,
+            -0.0,
+            0.5,
+            -1.5,
+            2.5,
+            1.0,
+            f64::MIN_POSITIVE,
+            4.9e-324,
+            -2.2250738585072e-308,
+            f64::MAX,
+            -f64::MAX,
+            1e300,
+            1e-300,
+            9.3e18,
+            9223372036854775808.0,
+            -9223372036854775808.0,
+            std::f64::consts::PI,
+            std::f64::consts::FRAC_PI_2,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            -f64::NAN,
+        ];
+
+        /// xorshift64, so failures are reproducible
+        struct Rng(u64);
+
+        impl Rng {
+            fn next(&mut self) -> u64 {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                self.0
+            }
+
+            /// Alternates between any bit pattern and values in the ranges games actually use.
+            fn operand(&mut self, i: usize) -> f64 {
+                let unit = (self.next() >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0;
+                match i % 4 {
+                    0 => f64::from_bits(self.next()),
+                    1 => unit * 10.0,
+                    2 => unit * 1e6,
+                    _ => unit * 1e-3,
+                }
+            }
+        }
+
+        const SIGNALING_NANS: [u64; 3] = [0x7FF0_0000_0000_0001, 0xFFF4_0000_0000_0000, 0x7FF0_1234_0000_0000];
+
+        fn specials() -> impl Iterator<Item = f64> {
+            SPECIALS.iter().copied().chain(SIGNALING_NANS.iter().map(|&b| f64::from_bits(b)))
+        }
+
+        fn check_binary(name: &str, hardware: fn(f64, f64) -> f64, software: fn(f64, f64) -> f64) {
+            let check = |a: f64, b: f64| {
+                let (expected, actual) = (hardware(a, b), software(a, b));
+                assert_eq!(
+                    expected.to_bits(),
+                    actual.to_bits(),
+                    "{}({:e}, {:e}): x87 gave {:e}, soft gave {:e}",
+                    name,
+                    a,
+                    b,
+                    expected,
+                    actual,
+                );
+            };
+            for a in specials() {
+                for b in specials() {
+                    check(a, b);
+                }
+            }
+            let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+            for i in 0..ITERATIONS {
+                let a = rng.operand(i);
+                let b = if i % 8 == 7 { a * (1.0 + rng.operand(1) * 1e-12) } else { rng.operand(i / 4) };
+                check(a, b);
+            }
+        }
+
+        /// The x87's trig instructions are only accurate to within one unit in the last place of the 80-bit result,
+        /// so storing one as an f64 can round either way when it's that close to halfway, depending on the processor.
+        /// Apart from that, the results have to match exactly.
+        fn check_unary(name: &str, hardware: fn(f64) -> f64, software: fn(f64) -> f64) {
+            let check = |a: f64| {
+                let (expected, actual) = (hardware(a), software(a));
+                if expected.to_bits() == actual.to_bits() {
+                    return
+                }
+                let (low, high) = if a.is_finite() && a != 0.0 { soft::trig_range(name, a) } else { (actual, actual) };
+                assert!(
+                    low != high && (expected == low || expected == high),
+                    "{}({:e}): x87 gave {:e}, soft gave {:e}",
+                    name,
+                    a,
+                    expected,
+                    actual,
+                );
+            };
+            specials().for_each(check);
+            let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+            for i in 0..ITERATIONS {
+                check(rng.operand(i));
+            }
+        }
+
+        #[test]
+        fn add() {
+            check_binary("add", x87::add, soft::add);
+        }
+
+        #[test]
+        fn sub() {
+            check_binary("sub", x87::sub, soft::sub);
+        }
+
+        #[test]
+        fn mul() {
+            check_binary("mul", x87::mul, soft::mul);
+        }
+
+        #[test]
+        fn div() {
+            check_binary("div", x87::div, soft::div);
+        }
+
+        #[test]
+        fn sin() {
+            check_unary("sin", x87::sin, soft::sin);
+        }
+
+        #[test]
+        fn cos() {
+            check_unary("cos", x87::cos, soft::cos);
+        }
+
+        #[test]
+        fn tan() {
+            check_unary("tan", x87::tan, soft::tan);
+        }
+
+        #[test]
+        fn round64() {
+            let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+            let halves = (0..ITERATIONS).map(|i| f64::from(i as i32 - 100_000) * 0.5);
+            let randoms = (0..ITERATIONS).map(|i| rng.operand(i));
+            for a in specials().chain(halves).chain(randoms.collect::<Vec<_>>()) {
+                assert_eq!(x87::round64(a), soft::round64(a), "round64({:e})", a);
+            }
+        }
+    }
 }
@@ -0,0 +1,389 @@
+//! Software emulation of the x87 FPU operations, for platforms which don't have one.
+//! Each operation is evaluated as the x87 does in 64-bit precision mode: the exact result is rounded to an
+//! 80-bit extended float, which is then rounded again when stored back to an f64.
+//! The basic arithmetic and rounding are bit-exact. The trig functions are exact to the x87's argument reduction, and
+//! are then rounded correctly, which is what the x87 does except when the result is within its error bound of
+//! halfway between two f64s. How it rounds those isn't documented and varies between processors.
+
+/// The "real indefinite" NaN which the x87 produces from invalid operations.
+const INDEFINITE: u64 = 0xFFF8_0000_0000_0000;
+
+/// The 66-bit approximation of pi/2 which the x87 uses for argument reduction, scaled by 2^65.
+const HALF_PI_66: u128 = 0x3_243F_6A88_85A3_08D3;
+
+/// 2^63: the limit of both trig operands, which are left unchanged beyond it, and 64-bit integers.
+const TWO_POW_63: f64 = 9223372036854775808.0;
+
+/// Splits a finite, non-zero f64 into its sign, integer mantissa and exponent.
+fn decompose(x: f64) -> (bool, u64, i32) {
+    let bits = x.to_bits();
+    let negative = bits >> 63 != 0;
+    let exponent = ((bits >> 52) & 0x7FF) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    if exponent == 0 {
+        (negative, fraction, -1074)
+    } else {
+        (negative, fraction | (1 << 52), exponent - 1075)
+    }
+}
+
+/// Rounds `mantissa * 2^exponent` (plus a nonzero amount less than one unit if `sticky`) to nearest-even with
+/// `bits` bits of precision, returning the new mantissa and exponent. The mantissa may carry up to `bits + 1` bits.
+fn round_to(mantissa: u128, exponent: i32, sticky: bool, bits: u32) -> (u128, i32) {
+    let width = 128 - mantissa.leading_zeros();
+    if width <= bits {
+        return (mantissa, exponent);
+    }
+    let shift = width - bits;
+    let kept = mantissa >> shift;
+    let rest = mantissa & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    let round_up = rest > half || (rest == half && (sticky || kept & 1 != 0));
+    (kept + round_up as u128, exponent + shift as i32)
+}
+
+/// Rounds `mantissa * 2^exponent` to an 80-bit extended float, then stores that as an f64.
+fn round_extended(negative: bool, mantissa: u128, exponent: i32, sticky: bool) -> f64 {
+    let sign = (negative as u64) << 63;
+    if mantissa == 0 {
+        return f64::from_bits(sign);
+    }
+
+    // The exponent range of the extended format is much wider than anything two f64s can produce,
+    // so only the mantissa needs rounding here.
+    let (mantissa, exponent) = round_to(mantissa, exponent, sticky, 64);
+
+    // Now round that to the f64's precision, which is less for subnormals.
+    let width = 128 - mantissa.leading_zeros() as i32;
+    let top = exponent + width - 1; // exponent of the highest set bit
+    if top > 1023 {
+        return f64::from_bits(sign | 0x7FF0_0000_0000_0000);
+    }
+    let precision = if top >= -1022 { 53 } else { 53 - (-1022 - top) };
+    let (mantissa, exponent) = if precision > 0 {
+        round_to(mantissa, exponent, false, precision as u32)
+    } else if precision == 0 {
+        // Only the rounding bit is left; halfway rounds down to even (zero)
+        let half = 1u128 << (width - 1);
+        (u128::from(mantissa > half), top + 1)
+    } else {
+        (0, 0)
+    };
+    if mantissa == 0 {
+        return f64::from_bits(sign);
+    }
+
+    // Build the bits so that a mantissa which carried over correctly bumps the exponent.
+    let width = 128 - mantissa.leading_zeros() as i32;
+    let top = exponent + width - 1;
+    if top > 1023 {
+        return f64::from_bits(sign | 0x7FF0_0000_0000_0000);
+    }
+    let bits = if top >= -1022 {
+        let normalized = if width > 53 { mantissa >> (width - 53) } else { mantissa << (53 - width) };
+        (((top + 1023) as u64) << 52) | (normalized as u64 & ((1 << 52) - 1))
+    } else {
+        (mantissa << (exponent + 1074)) as u64
+    };
+    f64::from_bits(sign | bits)
+}
+
+fn quiet(x: f64) -> f64 {
+    f64::from_bits(x.to_bits() | (1 << 51))
+}
+
+/// Picks the NaN the x87 would return when at least one operand is NaN. The first operand is always quiet by the
+/// time it's loaded into a register, while a signaling second operand loses to it outright.
+fn propagate_nan(a: f64, b: f64) -> f64 {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => {
+            let a = quiet(a);
+            if b.to_bits() & (1 << 51) == 0 {
+                return a;
+            }
+            // Both quiet: the larger significand wins, then the positive one
+            let key = |x: f64| (x.to_bits() & ((1 << 52) - 1), x.is_sign_positive());
+            if key(a) >= key(b) {
+                a
+            } else {
+                b
+            }
+        },
+        (true, false) => quiet(a),
+        _ => quiet(b),
+    }
+}
+
+/// Handles operands the simple paths don't, by falling back on IEEE arithmetic, which agrees with the x87
+/// except for the sign of invalid-operation NaNs.
+fn special(a: f64, b: f64, result: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        propagate_nan(a, b)
+    } else if result.is_nan() {
+        f64::from_bits(INDEFINITE)
+    } else {
+        result
+    }
+}
+
+pub fn add(a: f64, b: f64) -> f64 {
+    if !a.is_finite() || !b.is_finite() || a == 0.0 || b == 0.0 {
+        return special(a, b, a + b);
+    }
+    let (big, small) = if a.abs() >= b.abs() { (a, b) } else { (b, a) };
+    let (big_neg, big_mant, big_exp) = decompose(big);
+    let (small_neg, small_mant, small_exp) = decompose(small);
+
+    // Leave enough room below the larger operand that anything shifted off the smaller one
+    // can only matter as a sticky bit.
+    const ROOM: u32 = 70;
+    let shift = (big_exp - small_exp) as u32;
+    let big_aligned = u128::from(big_mant) << ROOM;
+    let small_full = u128::from(small_mant) << ROOM;
+    let (small_aligned, sticky) = if shift >= 128 {
+        (0, true)
+    } else {
+        let aligned = small_full >> shift;
+        (aligned, aligned << shift != small_full)
+    };
+    let exponent = big_exp - ROOM as i32;
+    if big_neg == small_neg {
+        round_extended(big_neg, big_aligned + small_aligned, exponent, sticky)
+    } else {
+        // Fold the sticky bit into the lowest bit so that subtracting it borrows correctly.
+        let difference = big_aligned - small_aligned - sticky as u128;
+        if difference == 0 {
+            return 0.0;
+        }
+        round_extended(big_neg, difference | sticky as u128, exponent, false)
+    }
+}
+
+pub fn sub(a: f64, b: f64) -> f64 {
+    if b.is_nan() {
+        return special(a, b, a - b);
+    }
+    add(a, -b)
+}
+
+pub fn mul(a: f64, b: f64) -> f64 {
+    if !a.is_finite() || !b.is_finite() || a == 0.0 || b == 0.0 {
+        return special(a, b, a * b);
+    }
+    let (a_neg, a_mant, a_exp) = decompose(a);
+    let (b_neg, b_mant, b_exp) = decompose(b);
+    round_extended(a_neg != b_neg, u128::from(a_mant) * u128::from(b_mant), a_exp + b_exp, false)
+}
+
+pub fn div(a: f64, b: f64) -> f64 {
+    if !a.is_finite() || !b.is_finite() || a == 0.0 || b == 0.0 {
+        return special(a, b, a / b);
+    }
+    let (a_neg, a_mant, a_exp) = decompose(a);
+    let (b_neg, b_mant, b_exp) = decompose(b);
+    // Normalize both so the quotient always has plenty of bits
+    let a_shift = a_mant.leading_zeros() + 64 - 1;
+    let b_shift = b_mant.leading_zeros() - 11;
+    let numerator = u128::from(a_mant) << a_shift;
+    let denominator = u128::from(b_mant) << b_shift;
+    let quotient = numerator / denominator;
+    let sticky = quotient * denominator != numerator;
+    round_extended(a_neg != b_neg, quotient, a_exp - a_shift as i32 - b_exp + b_shift as i32, sticky)
+}
+
+pub fn round64(a: f64) -> i64 {
+    if a.is_nan() || a.abs() >= TWO_POW_63 {
+        // Out of range, including NaN: the "integer indefinite" value
+        return i64::MIN;
+    }
+    let truncated = a.trunc();
+    let fraction = (a - truncated).abs();
+    let away = truncated + a.signum();
+    let rounded = if fraction > 0.5 || (fraction == 0.5 && truncated % 2.0 != 0.0) { away } else { truncated };
+    // Anything this close to the limit is already an integer, so rounding can't push it out of range
+    rounded as i64
+}
+
+/// A double-double: an unevaluated sum of two f64s, giving about 106 bits of precision.
+#[derive(Clone, Copy)]
+struct DoubleDouble(f64, f64);
+
+impl DoubleDouble {
+    fn two_sum(a: f64, b: f64) -> Self {
+        let s = a + b;
+        let bb = s - a;
+        Self(s, (a - (s - bb)) + (b - bb))
+    }
+
+    fn quick_two_sum(a: f64, b: f64) -> Self {
+        let s = a + b;
+        Self(s, b - (s - a))
+    }
+
+    fn split(a: f64) -> (f64, f64) {
+        let t = 134217729.0 * a; // 2^27 + 1
+        let hi = t - (t - a);
+        (hi, a - hi)
+    }
+
+    fn two_prod(a: f64, b: f64) -> Self {
+        let p = a * b;
+        let (ah, al) = Self::split(a);
+        let (bh, bl) = Self::split(b);
+        Self(p, ((ah * bh - p) + ah * bl + al * bh) + al * bl)
+    }
+
+    fn add(self, other: Self) -> Self {
+        let Self(s, e) = Self::two_sum(self.0, other.0);
+        let Self(t, f) = Self::two_sum(self.1, other.1);
+        let Self(s, e) = Self::quick_two_sum(s, e + t);
+        Self::quick_two_sum(s, e + f)
+    }
+
+    fn neg(self) -> Self {
+        Self(-self.0, -self.1)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let Self(p, e) = Self::two_prod(self.0, other.0);
+        Self::quick_two_sum(p, e + (self.0 * other.1 + self.1 * other.0))
+    }
+
+    fn div(self, other: Self) -> Self {
+        let q1 = self.0 / other.0;
+        let r = self.add(other.mul(Self(q1, 0.0)).neg());
+        let q2 = r.0 / other.0;
+        let r = r.add(other.mul(Self(q2, 0.0)).neg());
+        let q3 = r.0 / other.0;
+        Self::quick_two_sum(q1, q2).add(Self(q3, 0.0))
+    }
+
+    /// Sums a Taylor series, where each term is the last one times r^2 divided by the given function of its index.
+    fn series(first: Self, r2: Self, divisor: impl Fn(f64) -> f64) -> Self {
+        let mut sum = first;
+        let mut term = first;
+        let mut n = 1.0;
+        while term.0 != 0.0 && term.0.abs() > sum.0.abs() * 1e-36 {
+            term = term.mul(r2).neg().div(Self(divisor(n), 0.0));
+            sum = sum.add(term);
+            n += 1.0;
+        }
+        sum
+    }
+
+    fn sin(self) -> Self {
+        Self::series(self, self.mul(self), |n| (2.0 * n) * (2.0 * n + 1.0))
+    }
+
+    fn cos(self) -> Self {
+        Self::series(Self(1.0, 0.0), self.mul(self), |n| (2.0 * n - 1.0) * (2.0 * n))
+    }
+
+    /// Stores this as an f64 the way the x87 would if it had calculated this value.
+    fn store(self) -> f64 {
+        add(self.0, self.1)
+    }
+}
+
+/// Reduces a trig operand modulo pi/2 the way the x87 does, returning the quadrant and the remainder.
+fn reduce(x: f64) -> (u32, DoubleDouble) {
+    let (_, mantissa, exponent) = decompose(x.abs());
+    if exponent + 65 < 0 {
+        // Less than 2^-12, which is well within the first quadrant
+        return (0, DoubleDouble(x.abs(), 0.0));
+    }
+    let numerator = u128::from(mantissa) << (exponent + 65);
+    let quadrant = (numerator + HALF_PI_66 / 2) / HALF_PI_66;
+    let remainder = numerator as i128 - (quadrant * HALF_PI_66) as i128;
+    let hi = remainder as f64;
+    let lo = (remainder - hi as i128) as f64;
+    let scale = 2f64.powi(-65);
+    (quadrant as u32 & 3, DoubleDouble(hi * scale, lo * scale))
+}
+
+/// Handles the operands which trig functions don't reduce, returning None if the operand is normal.
+fn trig_special(x: f64) -> Option<f64> {
+    if x.is_nan() {
+        Some(quiet(x))
+    } else if x.is_infinite() {
+        Some(f64::from_bits(INDEFINITE))
+    } else if x.abs() >= TWO_POW_63 {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+// These calculate the trig functions to about 106 bits, for finite, non-zero operands which the x87 reduces.
+
+fn sin_precise(x: f64) -> DoubleDouble {
+    let (quadrant, r) = reduce(x);
+    let result = match quadrant {
+        0 => r.sin(),
+        1 => r.cos(),
+        2 => r.sin().neg(),
+        _ => r.cos().neg(),
+    };
+    if x < 0.0 { result.neg() } else { result }
+}
+
+fn cos_precise(x: f64) -> DoubleDouble {
+    let (quadrant, r) = reduce(x);
+    match quadrant {
+        0 => r.cos(),
+        1 => r.sin().neg(),
+        2 => r.cos().neg(),
+        _ => r.sin(),
+    }
+}
+
+fn tan_precise(x: f64) -> DoubleDouble {
+    let (quadrant, r) = reduce(x);
+    let result = if quadrant & 1 == 0 { r.sin().div(r.cos()) } else { r.cos().div(r.sin()).neg() };
+    if x < 0.0 { result.neg() } else { result }
+}
+
+pub fn sin(x: f64) -> f64 {
+    if let Some(result) = trig_special(x) {
+        return result
+    }
+    if x == 0.0 {
+        return x
+    }
+    sin_precise(x).store()
+}
+
+pub fn cos(x: f64) -> f64 {
+    if let Some(result) = trig_special(x) {
+        return result
+    }
+    if x == 0.0 {
+        return 1.0
+    }
+    cos_precise(x).store()
+}
+
+pub fn tan(x: f64) -> f64 {
+    if let Some(result) = trig_special(x) {
+        return result
+    }
+    if x == 0.0 {
+        return x
+    }
+    tan_precise(x).store()
+}
+
+/// The lowest and highest f64 which the x87 could store for a trig result. Its trig instructions are only accurate to
+/// within one unit in the last place of the 80-bit result, so when the exact value is that close to halfway between
+/// two f64s, either could come out depending on the processor. Otherwise both are the same as what's returned here.
+#[cfg(test)]
+pub fn trig_range(function: &str, x: f64) -> (f64, f64) {
+    let precise = match function {
+        "sin" => sin_precise(x),
+        "cos" => cos_precise(x),
+        _ => tan_precise(x),
+    };
+    let unit = 2f64.powi(precise.0.abs().log2().floor() as i32 - 63);
+    let (low, high) = (precise.add(DoubleDouble(-unit, 0.0)).store(), precise.add(DoubleDouble(unit, 0.0)).store());
+    if low <= high { (low, high) } else { (high, low) }
+}
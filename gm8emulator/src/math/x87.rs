@@ -0,0 +1,91 @@
+//! x87 FPU implementation of the extended-precision operations.
+//! The control word is set to 64-bit precision for each operation, as GM8 runs with it, and restored afterwards.
+
+use std::arch::asm;
+
+/// All exceptions masked, 64-bit precision, round to nearest.
+const CONTROL_WORD: u16 = 0x037F;
+
+macro_rules! binary_op {
+    ($name: ident, $instruction: literal) => {
+        #[inline]
+        pub fn $name(a: f64, b: f64) -> f64 {
+            let mut operands = [a, b];
+            let mut control = [CONTROL_WORD, 0];
+            unsafe {
+                asm!(
+                    "fnstcw word ptr [{cw} + 2]",
+                    "fldcw word ptr [{cw}]",
+                    "fld qword ptr [{ops}]",
+                    concat!($instruction, " qword ptr [{ops} + 8]"),
+                    "fstp qword ptr [{ops}]",
+                    "fldcw word ptr [{cw} + 2]",
+                    cw = in(reg) control.as_mut_ptr(),
+                    ops = in(reg) operands.as_mut_ptr(),
+                    out("st(0)") _, out("st(1)") _, out("st(2)") _, out("st(3)") _,
+                    out("st(4)") _, out("st(5)") _, out("st(6)") _, out("st(7)") _,
+                    options(nostack),
+                );
+            }
+            operands[0]
+        }
+    };
+}
+
+macro_rules! unary_op {
+    ($name: ident, $($instruction: literal),*) => {
+        #[inline]
+        pub fn $name(a: f64) -> f64 {
+            let mut operand = a;
+            let mut control = [CONTROL_WORD, 0];
+            unsafe {
+                asm!(
+                    "fnstcw word ptr [{cw} + 2]",
+                    "fldcw word ptr [{cw}]",
+                    "fld qword ptr [{op}]",
+                    $($instruction,)*
+                    "fstp qword ptr [{op}]",
+                    "fldcw word ptr [{cw} + 2]",
+                    cw = in(reg) control.as_mut_ptr(),
+                    op = in(reg) &mut operand,
+                    out("ax") _,
+                    out("st(0)") _, out("st(1)") _, out("st(2)") _, out("st(3)") _,
+                    out("st(4)") _, out("st(5)") _, out("st(6)") _, out("st(7)") _,
+                    options(nostack),
+                );
+            }
+            operand
+        }
+    };
+}
+
+binary_op!(add, "fadd");
+binary_op!(sub, "fsub");
+binary_op!(mul, "fmul");
+binary_op!(div, "fdiv");
+unary_op!(sin, "fsin");
+unary_op!(cos, "fcos");
+// fptan pushes 1.0 after the result, unless the operand was out of range and left as-is
+unary_op!(tan, "fptan", "fnstsw ax", "test ah, 4", "jnz 2f", "fstp st(0)", "2:");
+
+pub fn round64(a: f64) -> i64 {
+    let mut operand = a;
+    let mut result = 0i64;
+    let mut control = [CONTROL_WORD, 0];
+    unsafe {
+        asm!(
+            "fnstcw word ptr [{cw} + 2]",
+            "fldcw word ptr [{cw}]",
+            "fld qword ptr [{op}]",
+            "fistp qword ptr [{out}]",
+            "fldcw word ptr [{cw} + 2]",
+            cw = in(reg) control.as_mut_ptr(),
+            op = in(reg) &mut operand,
+            out = in(reg) &mut result,
+            out("st(0)") _, out("st(1)") _, out("st(2)") _, out("st(3)") _,
+            out("st(4)") _, out("st(5)") _, out("st(6)") _, out("st(7)") _,
+            options(nostack),
+        );
+    }
+    result
+}
@@ -48,3 +48,13 @@ pub struct Tile {
     /// Whether this tile will be drawn
     pub visible: Cell<bool>,
 }
+
+impl Tile {
+    /// The area this tile covers in the room as (left, top, right, bottom), taking negative scales into account.
+    pub fn bbox(&self) -> (f64, f64, f64, f64) {
+        let (x, y) = (self.x.get().into_inner(), self.y.get().into_inner());
+        let x2 = x + f64::from(self.width.get()) * self.xscale.get().into_inner();
+        let y2 = y + f64::from(self.height.get()) * self.yscale.get().into_inner();
+        (x.min(x2), y.min(y2), x.max(x2), y.max(y2))
+    }
+}
@@ -0,0 +1,45 @@
+//! Host clipboard access.
+
+pub mod win32;
+pub mod xorg;
+
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(target_os = "linux")]
+use xorg as platform;
+
+pub struct Clipboard(Box<dyn ClipboardTrait>);
+
+pub trait ClipboardTrait {
+    fn get_text(&mut self) -> Option<String>;
+    fn set_text(&mut self, text: &str);
+}
+
+impl Clipboard {
+    /// Creates a handle to the system clipboard. Nothing is opened until it's first used.
+    pub fn new() -> Self {
+        Self(Box::new(platform::ClipboardImpl::default()))
+    }
+
+    /// Gets the text currently on the clipboard, if there is any.
+    pub fn get_text(&mut self) -> Option<String> {
+        self.0.get_text()
+    }
+
+    /// Returns whether there's any text on the clipboard.
+    pub fn has_text(&mut self) -> bool {
+        matches!(self.0.get_text(), Some(text) if !text.is_empty())
+    }
+
+    /// Replaces the contents of the clipboard with the given text.
+    pub fn set_text(&mut self, text: &str) {
+        self.0.set_text(text)
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
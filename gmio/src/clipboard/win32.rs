@@ -0,0 +1,67 @@
+#![cfg(target_os = "windows")]
+
+use super::ClipboardTrait;
+use std::{ptr, slice};
+use winapi::{
+    ctypes::wchar_t,
+    um::{
+        winbase::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+        winuser::{
+            CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard,
+            SetClipboardData, CF_UNICODETEXT,
+        },
+    },
+};
+
+#[derive(Default)]
+pub struct ClipboardImpl;
+
+impl ClipboardTrait for ClipboardImpl {
+    fn get_text(&mut self) -> Option<String> {
+        unsafe {
+            if IsClipboardFormatAvailable(CF_UNICODETEXT) == 0 || OpenClipboard(ptr::null_mut()) == 0 {
+                return None
+            }
+            let handle = GetClipboardData(CF_UNICODETEXT);
+            let data = if handle.is_null() { ptr::null_mut() } else { GlobalLock(handle) as *const wchar_t };
+            let text = if data.is_null() {
+                None
+            } else {
+                let mut len = 0;
+                while *data.add(len) != 0 {
+                    len += 1;
+                }
+                let text = String::from_utf16_lossy(slice::from_raw_parts(data, len));
+                GlobalUnlock(handle);
+                Some(text)
+            };
+            CloseClipboard();
+            text
+        }
+    }
+
+    fn set_text(&mut self, text: &str) {
+        let wide: Vec<wchar_t> = text.encode_utf16().chain(Some(0x00)).collect();
+        unsafe {
+            if OpenClipboard(ptr::null_mut()) == 0 {
+                return
+            }
+            EmptyClipboard();
+            let handle = GlobalAlloc(GMEM_MOVEABLE, wide.len() * std::mem::size_of::<wchar_t>());
+            if !handle.is_null() {
+                let data = GlobalLock(handle) as *mut wchar_t;
+                if data.is_null() {
+                    GlobalFree(handle);
+                } else {
+                    ptr::copy_nonoverlapping(wide.as_ptr(), data, wide.len());
+                    GlobalUnlock(handle);
+                    // the clipboard owns the memory after this, unless it fails
+                    if SetClipboardData(CF_UNICODETEXT, handle).is_null() {
+                        GlobalFree(handle);
+                    }
+                }
+            }
+            CloseClipboard();
+        }
+    }
+}
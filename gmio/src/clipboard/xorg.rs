@@ -0,0 +1,218 @@
+#![cfg(target_os = "linux")]
+
+use super::ClipboardTrait;
+use std::{
+    ffi::CStr,
+    mem, ptr, slice,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use x11::xlib;
+
+/// How long to wait for the selection owner to send the clipboard contents.
+const READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Connection to the X server used for one side of a selection transfer, with a hidden window to transfer through.
+struct Connection {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    clipboard: xlib::Atom,
+    utf8_string: xlib::Atom,
+    targets: xlib::Atom,
+}
+
+impl Connection {
+    fn open() -> Option<Self> {
+        unsafe {
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return None
+            }
+            let root = xlib::XDefaultRootWindow(display);
+            let window = xlib::XCreateSimpleWindow(display, root, 0, 0, 1, 1, 0, 0, 0);
+            let atom = |name: &CStr| xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+            Some(Self {
+                display,
+                window,
+                clipboard: atom(CStr::from_bytes_with_nul_unchecked(b"CLIPBOARD\0")),
+                utf8_string: atom(CStr::from_bytes_with_nul_unchecked(b"UTF8_STRING\0")),
+                targets: atom(CStr::from_bytes_with_nul_unchecked(b"TARGETS\0")),
+            })
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XDestroyWindow(self.display, self.window);
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+// The connection is only ever used by one thread at a time.
+unsafe impl Send for Connection {}
+
+/// Reads the CLIPBOARD selection directly, and owns it from a background thread when text is set.
+/// X11 has no clipboard storage of its own, so the owner has to keep answering requests for as long as it owns it.
+#[derive(Default)]
+pub struct ClipboardImpl {
+    reader: Option<Connection>,
+    /// Text we currently own the selection with. Set back to None by the owner thread when someone else takes it.
+    owned: Arc<Mutex<Option<String>>>,
+}
+
+impl ClipboardTrait for ClipboardImpl {
+    fn get_text(&mut self) -> Option<String> {
+        if let Some(text) = self.owned.lock().unwrap().as_ref() {
+            return Some(text.clone())
+        }
+        if self.reader.is_none() {
+            self.reader = Connection::open();
+        }
+        let conn = self.reader.as_ref()?;
+        unsafe {
+            xlib::XConvertSelection(
+                conn.display,
+                conn.clipboard,
+                conn.utf8_string,
+                conn.utf8_string,
+                conn.window,
+                xlib::CurrentTime,
+            );
+            xlib::XFlush(conn.display);
+
+            let start = Instant::now();
+            let mut event: xlib::XEvent = mem::zeroed();
+            while xlib::XCheckTypedWindowEvent(conn.display, conn.window, xlib::SelectionNotify, &mut event) == 0 {
+                if start.elapsed() > READ_TIMEOUT {
+                    return None
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            if event.selection.property == 0 {
+                // the owner couldn't convert to text
+                return None
+            }
+
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut item_count = 0;
+            let mut bytes_after = 0;
+            let mut data = ptr::null_mut();
+            xlib::XGetWindowProperty(
+                conn.display,
+                conn.window,
+                event.selection.property,
+                0,
+                i64::from(i32::MAX) / 4,
+                xlib::True,
+                xlib::AnyPropertyType as _,
+                &mut actual_type,
+                &mut actual_format,
+                &mut item_count,
+                &mut bytes_after,
+                &mut data,
+            );
+            if data.is_null() {
+                return None
+            }
+            // anything not sent in one go (INCR transfers) isn't supported
+            let text = if actual_format == 8 && actual_type != 0 {
+                Some(String::from_utf8_lossy(slice::from_raw_parts(data, item_count as usize)).into_owned())
+            } else {
+                None
+            };
+            xlib::XFree(data.cast());
+            text
+        }
+    }
+
+    fn set_text(&mut self, text: &str) {
+        let mut owned = self.owned.lock().unwrap();
+        if owned.is_some() {
+            // the owner thread is still running, so it'll start giving out the new text
+            *owned = Some(text.to_owned());
+            return
+        }
+        let conn = match Connection::open() {
+            Some(conn) => conn,
+            None => return,
+        };
+        unsafe {
+            xlib::XSetSelectionOwner(conn.display, conn.clipboard, conn.window, xlib::CurrentTime);
+            if xlib::XGetSelectionOwner(conn.display, conn.clipboard) != conn.window {
+                return
+            }
+        }
+        *owned = Some(text.to_owned());
+        let owned = self.owned.clone();
+        thread::spawn(move || serve_selection(conn, owned));
+    }
+}
+
+/// Answers requests for the selection until another client takes it over.
+fn serve_selection(conn: Connection, owned: Arc<Mutex<Option<String>>>) {
+    unsafe {
+        let mut event: xlib::XEvent = mem::zeroed();
+        loop {
+            xlib::XNextEvent(conn.display, &mut event);
+            match event.get_type() {
+                xlib::SelectionClear => {
+                    *owned.lock().unwrap() = None;
+                    break
+                },
+                xlib::SelectionRequest => {
+                    let request = event.selection_request;
+                    // obsolete clients don't give a property, in which case the target is used
+                    let property = if request.property == 0 { request.target } else { request.property };
+                    let mut reply = xlib::XSelectionEvent {
+                        type_: xlib::SelectionNotify,
+                        serial: 0,
+                        send_event: xlib::True,
+                        display: conn.display,
+                        requestor: request.requestor,
+                        selection: request.selection,
+                        target: request.target,
+                        property: 0,
+                        time: request.time,
+                    };
+                    if request.target == conn.targets {
+                        let targets = [conn.targets, conn.utf8_string, xlib::XA_STRING];
+                        xlib::XChangeProperty(
+                            conn.display,
+                            request.requestor,
+                            property,
+                            xlib::XA_ATOM,
+                            32,
+                            xlib::PropModeReplace,
+                            targets.as_ptr().cast(),
+                            targets.len() as _,
+                        );
+                        reply.property = property;
+                    } else if request.target == conn.utf8_string || request.target == xlib::XA_STRING {
+                        if let Some(text) = owned.lock().unwrap().as_ref() {
+                            xlib::XChangeProperty(
+                                conn.display,
+                                request.requestor,
+                                property,
+                                request.target,
+                                8,
+                                xlib::PropModeReplace,
+                                text.as_ptr(),
+                                text.len() as _,
+                            );
+                            reply.property = property;
+                        }
+                    }
+                    let mut reply = xlib::XEvent { selection: reply };
+                    xlib::XSendEvent(conn.display, request.requestor, xlib::False, 0, &mut reply);
+                    xlib::XFlush(conn.display);
+                },
+                _ => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Joystick input.
+
+pub mod evdev;
+
+use shared::input::JoystickEvent;
+use std::slice;
+
+#[cfg(target_os = "linux")]
+use evdev as platform;
+
+pub struct Joysticks(Box<dyn JoystickTrait>);
+
+pub trait JoystickTrait {
+    fn process_events<'a>(&'a mut self) -> slice::Iter<'a, JoystickEvent>;
+}
+
+impl Joysticks {
+    /// Creates a new joystick handler. Joysticks which are already plugged in will be
+    /// reported as connected by the first call to process_events.
+    pub fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            Self(Box::new(platform::JoystickImpl::default()))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Self(Box::new(NoJoysticks))
+        }
+    }
+
+    /// Polls all joysticks and returns a one-time Iterator to any changes since the last call.
+    pub fn process_events<'a>(&'a mut self) -> slice::Iter<'a, JoystickEvent> {
+        self.0.process_events()
+    }
+}
+
+impl Default for Joysticks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Used on platforms which don't have a joystick backend yet. Never reports any joysticks.
+#[allow(dead_code)]
+struct NoJoysticks;
+
+impl JoystickTrait for NoJoysticks {
+    fn process_events<'a>(&'a mut self) -> slice::Iter<'a, JoystickEvent> {
+        [].iter()
+    }
+}
@@ -0,0 +1,225 @@
+#![cfg(target_os = "linux")]
+
+use super::JoystickTrait;
+use shared::input::{JoystickAxis, JoystickEvent, JoystickInfo, JOYSTICK_BUTTON_COUNT, JOYSTICK_COUNT};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read},
+    mem,
+    os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+    path::PathBuf,
+    ptr, slice,
+    time::{Duration, Instant},
+};
+
+// Constants from linux/input.h and linux/input-event-codes.h
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const KEY_MAX: usize = 0x2ff;
+const ABS_MAX: usize = 0x3f;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+const BTN_JOYSTICK: usize = 0x120;
+const BTN_DIGI: usize = 0x140;
+const BTN_TRIGGER_HAPPY: usize = 0x2c0;
+
+/// Evdev axis codes for each GM8 joystick axis, in the same order as winmm reports them.
+const AXES: [(u16, JoystickAxis); 6] = [
+    (0x00, JoystickAxis::X), // ABS_X
+    (0x01, JoystickAxis::Y), // ABS_Y
+    (0x02, JoystickAxis::Z), // ABS_Z
+    (0x05, JoystickAxis::R), // ABS_RZ
+    (0x03, JoystickAxis::U), // ABS_RX
+    (0x04, JoystickAxis::V), // ABS_RY
+];
+
+/// How often to look for newly plugged in devices.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+// ioctl request numbers, as generated by the _IOC macro
+const fn ioc_read(nr: u64, size: usize) -> u64 {
+    (2 << 30) | ((size as u64) << 16) | ((b'E' as u64) << 8) | nr
+}
+const fn eviocgname(len: usize) -> u64 {
+    ioc_read(0x06, len)
+}
+const fn eviocgbit(ev: u16, len: usize) -> u64 {
+    ioc_read(0x20 + ev as u64, len)
+}
+const fn eviocgabs(abs: u16) -> u64 {
+    ioc_read(0x40 + abs as u64, mem::size_of::<libc::input_absinfo>())
+}
+
+struct Device {
+    path: PathBuf,
+    file: File,
+    /// Maps evdev button codes to GM8 button indices
+    buttons: Vec<u16>,
+    /// Evdev code, GM8 axis, minimum and maximum value for each axis
+    axes: Vec<(u16, JoystickAxis, i32, i32)>,
+    hat: (i32, i32),
+}
+
+#[derive(Default)]
+pub struct JoystickImpl {
+    devices: [Option<Device>; JOYSTICK_COUNT],
+    last_scan: Option<Instant>,
+    events: Vec<JoystickEvent>,
+}
+
+fn test_bit(bits: &[u8], bit: usize) -> bool {
+    bits.get(bit / 8).map_or(false, |b| b & (1 << (bit % 8)) != 0)
+}
+
+impl Device {
+    /// Opens the given evdev node if it's a joystick or gamepad.
+    fn open(path: PathBuf) -> Option<(Self, JoystickInfo)> {
+        let file = OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(&path).ok()?;
+        let fd = file.as_raw_fd();
+
+        let mut key_bits = [0u8; KEY_MAX / 8 + 1];
+        let mut abs_bits = [0u8; ABS_MAX / 8 + 1];
+        let mut name = [0u8; 256];
+        unsafe {
+            if libc::ioctl(fd, eviocgbit(EV_KEY, key_bits.len()) as _, key_bits.as_mut_ptr()) < 0
+                || libc::ioctl(fd, eviocgbit(EV_ABS, abs_bits.len()) as _, abs_bits.as_mut_ptr()) < 0
+            {
+                return None
+            }
+            if libc::ioctl(fd, eviocgname(name.len()) as _, name.as_mut_ptr()) < 0 {
+                name[0] = 0;
+            }
+        }
+
+        // Only take devices with joystick or gamepad buttons, otherwise we'd pick up keyboards and touchpads
+        if !(BTN_JOYSTICK..BTN_DIGI).any(|b| test_bit(&key_bits, b)) {
+            return None
+        }
+
+        let buttons = (BTN_JOYSTICK..BTN_DIGI)
+            .chain(BTN_TRIGGER_HAPPY..BTN_TRIGGER_HAPPY + 0x28)
+            .filter(|b| test_bit(&key_bits, *b))
+            .take(JOYSTICK_BUTTON_COUNT)
+            .map(|b| b as u16)
+            .collect::<Vec<_>>();
+
+        let axes = AXES
+            .iter()
+            .filter(|(code, _)| test_bit(&abs_bits, usize::from(*code)))
+            .filter_map(|&(code, axis)| {
+                let mut info: libc::input_absinfo = unsafe { mem::zeroed() };
+                if unsafe { libc::ioctl(fd, eviocgabs(code) as _, &mut info as *mut libc::input_absinfo) } < 0 {
+                    None
+                } else {
+                    Some((code, axis, info.minimum, info.maximum))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let name_len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+        let info = JoystickInfo {
+            name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+            axes: axes.len() as u8,
+            buttons: buttons.len() as u8,
+            has_pov: test_bit(&abs_bits, usize::from(ABS_HAT0X)) && test_bit(&abs_bits, usize::from(ABS_HAT0Y)),
+        };
+        Some((Self { path, file, buttons, axes, hat: (0, 0) }, info))
+    }
+
+    /// Reads all pending input from the device. Returns an error if the device was unplugged.
+    fn read_events(&mut self, id: usize, out: &mut Vec<JoystickEvent>) -> io::Result<()> {
+        const EVENT_SIZE: usize = mem::size_of::<libc::input_event>();
+        let mut buf = [0u8; EVENT_SIZE * 64];
+        loop {
+            let len = match self.file.read(&mut buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            for chunk in buf[..len].chunks_exact(EVENT_SIZE) {
+                let event = unsafe { ptr::read_unaligned(chunk.as_ptr() as *const libc::input_event) };
+                match event.type_ {
+                    EV_KEY => {
+                        if let Some(button) = self.buttons.iter().position(|b| *b == event.code) {
+                            out.push(match event.value {
+                                0 => JoystickEvent::ButtonUp(id, button as u8),
+                                1 => JoystickEvent::ButtonDown(id, button as u8),
+                                _ => continue, // key repeat
+                            });
+                        }
+                    },
+                    EV_ABS if event.code == ABS_HAT0X || event.code == ABS_HAT0Y => {
+                        if event.code == ABS_HAT0X {
+                            self.hat.0 = event.value.signum();
+                        } else {
+                            self.hat.1 = event.value.signum();
+                        }
+                        let angle = match self.hat {
+                            (0, 0) => -1,
+                            (x, y) => (f64::from(x).atan2(-f64::from(y)).to_degrees().round() as i32 + 360) % 360,
+                        };
+                        out.push(JoystickEvent::Pov(id, angle));
+                    },
+                    EV_ABS => {
+                        if let Some(&(_, axis, min, max)) = self.axes.iter().find(|a| a.0 == event.code) {
+                            let pos = if max > min {
+                                f64::from(event.value - min) / f64::from(max - min) * 2.0 - 1.0
+                            } else {
+                                0.0
+                            };
+                            out.push(JoystickEvent::Axis(id, axis, pos));
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+impl JoystickImpl {
+    /// Opens joysticks in /dev/input until all the slots are full.
+    fn scan(&mut self) {
+        let mut paths = match fs::read_dir("/dev/input") {
+            Ok(dir) => dir
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.file_name().and_then(|n| n.to_str()).map_or(false, |n| n.starts_with("event")))
+                .collect::<Vec<_>>(),
+            Err(_) => return,
+        };
+        paths.sort();
+        for path in paths {
+            if self.devices.iter().flatten().any(|d| d.path == path) {
+                continue
+            }
+            let slot = match self.devices.iter().position(Option::is_none) {
+                Some(slot) => slot,
+                None => break,
+            };
+            if let Some((device, info)) = Device::open(path) {
+                self.devices[slot] = Some(device);
+                self.events.push(JoystickEvent::Connect(slot, info));
+            }
+        }
+    }
+}
+
+impl JoystickTrait for JoystickImpl {
+    fn process_events<'a>(&'a mut self) -> slice::Iter<'a, JoystickEvent> {
+        self.events.clear();
+        if self.last_scan.map_or(true, |t| t.elapsed() >= RESCAN_INTERVAL) {
+            self.last_scan = Some(Instant::now());
+            self.scan();
+        }
+        for (id, slot) in self.devices.iter_mut().enumerate() {
+            if let Some(device) = slot {
+                if device.read_events(id, &mut self.events).is_err() {
+                    *slot = None;
+                    self.events.push(JoystickEvent::Disconnect(id));
+                }
+            }
+        }
+        self.events.iter()
+    }
+}
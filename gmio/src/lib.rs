@@ -1,3 +1,5 @@
 pub mod atlas;
+pub mod clipboard;
+pub mod joystick;
 pub mod render;
 pub mod window;
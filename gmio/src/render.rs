@@ -332,6 +332,17 @@ pub trait RendererTrait {
 
     fn create_sprite_colour(&mut self, width: i32, height: i32, col: Colour) -> Result<AtlasRef, String>;
     fn create_surface(&mut self, w: i32, h: i32, has_zbuffer: bool) -> Result<AtlasRef, String>;
+    fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        src_x: i32,
+        src_y: i32,
+        w: i32,
+        h: i32,
+    ) -> Result<(), String>;
     fn set_target(&mut self, atlas_ref: &AtlasRef);
     fn reset_target(&mut self);
 
@@ -885,6 +896,22 @@ impl Renderer {
         self.0.create_surface(w, h, has_zbuffer)
     }
 
+    /// Copies part of one surface onto another, replacing the colour and alpha but leaving the z-buffer as it is.
+    /// The source and destination must be different textures.
+    pub fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        src_x: i32,
+        src_y: i32,
+        w: i32,
+        h: i32,
+    ) -> Result<(), String> {
+        self.0.copy_surface_part(dest, dest_x, dest_y, src, src_x, src_y, w, h)
+    }
+
     pub fn set_target(&mut self, atlas_ref: &AtlasRef) {
         self.0.set_target(atlas_ref)
     }
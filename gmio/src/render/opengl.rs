@@ -890,6 +890,52 @@ impl RendererTrait for RendererImpl {
         Ok(new_sprite)
     }
 
+    fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        src_x: i32,
+        src_y: i32,
+        w: i32,
+        h: i32,
+    ) -> Result<(), String> {
+        // anything queued might be drawing to either surface
+        self.flush_queue();
+        unsafe {
+            // store previous
+            let mut prev_read_fbo = 0;
+            self.gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut prev_read_fbo);
+            let mut prev_tex2d = 0;
+            self.gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut prev_tex2d);
+
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo_ids[src.atlas_id as usize].unwrap());
+            self.gl.BindTexture(gl::TEXTURE_2D, self.texture_ids[dest.atlas_id as usize].unwrap());
+
+            // only copies the colour attachment, so the destination's zbuffer is untouched
+            self.gl.CopyTexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                dest.x + dest_x,
+                dest.y + dest_y,
+                src.x + src_x,
+                src.y + src_y,
+                w as _,
+                h as _,
+            );
+
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, prev_read_fbo as _);
+            self.gl.BindTexture(gl::TEXTURE_2D, prev_tex2d as _);
+
+            // verify it actually worked
+            match self.gl.GetError() {
+                0 => Ok(()),
+                err => Err(format!("Failed to copy surface! (OpenGL code {})", err)),
+            }
+        }
+    }
+
     fn delete_sprite(&mut self, atlas_ref: AtlasRef) {
         // this only deletes sprites created with upload_sprite
         self.flush_queue();
@@ -87,6 +87,8 @@ pub trait WindowTrait {
     fn get_cursor(&self) -> Cursor;
     fn set_cursor(&mut self, cursor: Cursor);
     fn set_style(&mut self, style: Style);
+    fn set_topmost(&mut self, topmost: bool);
+    fn set_mouse_pos(&mut self, x: i32, y: i32);
     fn get_title(&self) -> &str;
     fn set_title(&mut self, title: &str);
     fn get_visible(&self) -> bool;
@@ -164,6 +166,16 @@ impl Window {
         self.0.set_style(style)
     }
 
+    /// Sets whether the window stays on top of all other windows.
+    pub fn set_topmost(&mut self, topmost: bool) {
+        self.0.set_topmost(topmost)
+    }
+
+    /// Moves the mouse cursor to a position relative to the top-left of the window's inner area.
+    pub fn set_mouse_pos(&mut self, x: i32, y: i32) {
+        self.0.set_mouse_pos(x, y)
+    }
+
     /// Gets the window caption.
     pub fn get_title(&self) -> &str {
         self.0.get_title()
@@ -31,16 +31,16 @@ use winapi::{
             AdjustWindowRect, ClientToScreen, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyWindow,
             DispatchMessageW, EnumDisplaySettingsW, GetCursorPos, GetSystemMetrics, GetWindowLongPtrW, GetWindowRect,
             InsertMenuA, LoadImageW, PeekMessageW, RegisterClassExW, ReleaseCapture, SetCapture, SetCursor,
-            SetForegroundWindow, SetWindowLongPtrW, SetWindowPos, SetWindowTextW, ShowWindow, TrackPopupMenu,
-            TranslateMessage, UnregisterClassW, COLOR_BACKGROUND, CS_OWNDC, ENUM_CURRENT_SETTINGS,
-            GET_WHEEL_DELTA_WPARAM, GWLP_USERDATA, GWL_STYLE, HWND_TOP, IDC_APPSTARTING, IDC_ARROW, IDC_CROSS,
-            IDC_HAND, IDC_IBEAM, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, IDC_UPARROW,
-            IDC_WAIT, IMAGE_CURSOR, LR_DEFAULTSIZE, LR_SHARED, MF_BYPOSITION, MF_STRING, MSG, PM_REMOVE, SM_CXSCREEN,
-            SM_CYSCREEN, SWP_NOMOVE, SWP_SHOWWINDOW, SW_HIDE, SW_SHOW, TME_LEAVE, TPM_LEFTALIGN, TPM_TOPALIGN,
-            TRACKMOUSEEVENT, WM_CLOSE, WM_COMMAND, WM_ERASEBKGND, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP,
-            WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSELEAVE, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP,
-            WM_SETCURSOR, WM_SIZE, WM_SIZING, WNDCLASSEXW, WS_CAPTION, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_POPUP,
-            WS_SYSMENU, WS_THICKFRAME,
+            SetCursorPos, SetForegroundWindow, SetWindowLongPtrW, SetWindowPos, SetWindowTextW, ShowWindow,
+            TrackPopupMenu, TranslateMessage, UnregisterClassW, COLOR_BACKGROUND, CS_OWNDC, ENUM_CURRENT_SETTINGS,
+            GET_WHEEL_DELTA_WPARAM, GWLP_USERDATA, GWL_STYLE, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, IDC_APPSTARTING,
+            IDC_ARROW, IDC_CROSS, IDC_HAND, IDC_IBEAM, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE,
+            IDC_UPARROW, IDC_WAIT, IMAGE_CURSOR, LR_DEFAULTSIZE, LR_SHARED, MF_BYPOSITION, MF_STRING, MSG, PM_REMOVE,
+            SM_CXSCREEN, SM_CYSCREEN, SWP_NOMOVE, SWP_NOSIZE, SWP_SHOWWINDOW, SW_HIDE, SW_SHOW, TME_LEAVE,
+            TPM_LEFTALIGN, TPM_TOPALIGN, TRACKMOUSEEVENT, WM_CLOSE, WM_COMMAND, WM_ERASEBKGND, WM_KEYDOWN, WM_KEYUP,
+            WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSELEAVE, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+            WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR, WM_SIZE, WM_SIZING, WNDCLASSEXW, WS_CAPTION, WS_MAXIMIZEBOX,
+            WS_MINIMIZEBOX, WS_POPUP, WS_SYSMENU, WS_THICKFRAME,
         },
     },
 };
@@ -403,15 +403,39 @@ impl WindowTrait for WindowImpl {
         let inner_size = self.get_inner_size();
         let (cwidth, cheight) = (inner_size.0 as i32, inner_size.1 as i32);
         unsafe {
-            let window_rect = adjust_window_rect(cwidth as i32, cheight as i32, style);
-            let (width, height) = window_rect_wh(window_rect);
             SetWindowLongPtrW(self.hwnd, GWL_STYLE, get_window_style(style) as LONG_PTR as _);
-            SetWindowPos(self.hwnd, HWND_TOP, 0, 0, width, height, SWP_NOMOVE | SWP_SHOWWINDOW);
-            self.user_data.border_offset = (width - cwidth, height - cheight);
+            if let Style::BorderlessFullscreen = style {
+                // cover the whole primary monitor, resize() is ignored until the style changes again
+                let screen_width = GetSystemMetrics(SM_CXSCREEN);
+                let screen_height = GetSystemMetrics(SM_CYSCREEN);
+                SetWindowPos(self.hwnd, HWND_TOP, 0, 0, screen_width, screen_height, SWP_SHOWWINDOW);
+                self.user_data.client_size = (screen_width, screen_height);
+                self.user_data.border_offset = (0, 0);
+            } else {
+                let window_rect = adjust_window_rect(cwidth as i32, cheight as i32, style);
+                let (width, height) = window_rect_wh(window_rect);
+                SetWindowPos(self.hwnd, HWND_TOP, 0, 0, width, height, SWP_NOMOVE | SWP_SHOWWINDOW);
+                self.user_data.border_offset = (width - cwidth, height - cheight);
+            }
             self.style = style;
         }
     }
 
+    fn set_topmost(&mut self, topmost: bool) {
+        let insert_after = if topmost { HWND_TOPMOST } else { HWND_NOTOPMOST };
+        unsafe {
+            SetWindowPos(self.hwnd, insert_after, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE);
+        }
+    }
+
+    fn set_mouse_pos(&mut self, x: i32, y: i32) {
+        unsafe {
+            let mut point = POINT { x, y };
+            ClientToScreen(self.hwnd, &mut point);
+            SetCursorPos(point.x, point.y);
+        }
+    }
+
     fn get_title(&self) -> &str {
         &self.title
     }
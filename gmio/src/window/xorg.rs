@@ -103,6 +103,53 @@ impl WindowTrait for WindowImpl {
         todo!()
     }
 
+    fn set_topmost(&mut self, topmost: bool) {
+        unsafe {
+            let wm_state = xlib::XInternAtom(self.display, b"_NET_WM_STATE\0".as_ptr() as *const _, xlib::False);
+            let above = xlib::XInternAtom(self.display, b"_NET_WM_STATE_ABOVE\0".as_ptr() as *const _, xlib::False);
+            if self.visible {
+                // Once the window is mapped, its state belongs to the window manager, so ask that to change it
+                let mut message: xlib::XClientMessageEvent = std::mem::zeroed();
+                message.type_ = xlib::ClientMessage;
+                message.window = self.window_id;
+                message.message_type = wm_state;
+                message.format = 32;
+                message.data.set_long(0, topmost as _); // _NET_WM_STATE_ADD or _NET_WM_STATE_REMOVE
+                message.data.set_long(1, above as _);
+                let mut event = xlib::XEvent::from(message);
+                let root = xlib::XRootWindow(self.display, self.screen_id);
+                xlib::XSendEvent(
+                    self.display,
+                    root,
+                    xlib::False,
+                    xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                    &mut event,
+                );
+            } else if topmost {
+                // The window manager reads this when the window gets mapped
+                xlib::XChangeProperty(
+                    self.display,
+                    self.window_id,
+                    wm_state,
+                    xlib::XA_ATOM,
+                    32,
+                    xlib::PropModeReplace,
+                    &above as *const _ as *const u8,
+                    1,
+                );
+            } else {
+                xlib::XDeleteProperty(self.display, self.window_id, wm_state);
+            }
+            xlib::XFlush(self.display);
+        }
+    }
+
+    fn set_mouse_pos(&mut self, x: i32, y: i32) {
+        unsafe {
+            xlib::XWarpPointer(self.display, 0, self.window_id, 0, 0, 0, 0, x, y);
+        }
+    }
+
     fn get_title(&self) -> &str {
         todo!()
     }
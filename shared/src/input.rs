@@ -269,3 +269,43 @@ impl MouseButton {
         })
     }
 }
+
+/// The number of joysticks GM8 supports.
+pub const JOYSTICK_COUNT: usize = 2;
+
+/// The number of buttons GM8 reads from a joystick.
+pub const JOYSTICK_BUTTON_COUNT: usize = 32;
+
+/// One of the six axes GM8 reads from a joystick.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JoystickAxis {
+    X,
+    Y,
+    Z,
+    R,
+    U,
+    V,
+}
+
+/// The properties of a joystick which can be queried from GML.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JoystickInfo {
+    pub name: String,
+    pub axes: u8,
+    pub buttons: u8,
+    pub has_pov: bool,
+}
+
+/// A change to the state of a joystick.
+/// Joystick IDs here are 0 and 1, whereas GML calls them 1 and 2. Buttons are also 0-based.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JoystickEvent {
+    Connect(usize, JoystickInfo),
+    Disconnect(usize),
+    /// Axis positions range from -1.0 to 1.0.
+    Axis(usize, JoystickAxis, f64),
+    ButtonDown(usize, u8),
+    ButtonUp(usize, u8),
+    /// POV angle in degrees clockwise from forwards, or -1 if centered.
+    Pov(usize, i32),
+}
@@ -1,6 +1,6 @@
 use crate::{input, types::ID};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, io};
+use std::{collections::HashMap, io, time::Duration};
 
 /// A message sent from the controller to the client.
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,6 +13,7 @@ pub enum Message {
         key_inputs: Vec<(input::Key, bool)>,
         mouse_inputs: Vec<(input::MouseButton, bool)>,
         mouse_location: (f64, f64),
+        joystick_inputs: Vec<input::JoystickEvent>,
         keys_requested: Vec<input::Key>,
         mouse_buttons_requested: Vec<input::MouseButton>,
         instance_requested: Option<ID>,
@@ -32,6 +33,27 @@ pub enum Message {
         mouse_buttons_requested: Vec<input::MouseButton>,
         instance_requested: Option<ID>,
     },
+
+    /// Replaces the debugger's breakpoints
+    SetBreakpoints { breakpoints: Vec<Breakpoint> },
+
+    /// Tells the debugger to pause at the next line of GML the game runs
+    DebugPause,
+
+    /// Tells the debugger to carry on from where it's paused, pausing again once the given step is done
+    DebugResume { step: DebugStep },
+
+    /// Asks the debugger for the variables visible to the code it's paused in
+    DebugInspect,
+
+    /// Asks the debugger to evaluate a GML expression in the context of the code it's paused in
+    DebugEvaluate { expression: String },
+
+    /// Turns the GML profiler on or off. Turning it on starts a new profile
+    SetProfiling { enabled: bool },
+
+    /// Asks for everything the profiler has measured so far
+    ProfileReport,
 }
 
 /// A message sent from the client to the controller.
@@ -45,6 +67,7 @@ pub enum Information {
         frame_count: usize,
         seed: i32,
         instance: Option<InstanceDetails>,
+        joystick: Option<JoystickDetails>,
     },
 
     /// Tells the controller that a key was pressed while the game window was focused
@@ -64,6 +87,23 @@ pub enum Information {
 
     /// Sends the controller some general info which should be shown to the user
     General { message: String },
+
+    /// Tells the controller that the debugger has paused the game, and where. The call stack is innermost first.
+    DebugPaused { call_stack: Vec<DebugFrame> },
+
+    /// Sends the controller the variables visible to the paused code, as (name, value) pairs
+    DebugVariables {
+        locals: Vec<(String, String)>,
+        arguments: Vec<String>,
+        self_fields: Vec<(String, String)>,
+        other_fields: Vec<(String, String)>,
+    },
+
+    /// Sends the controller the result of a DebugEvaluate, or the reason it couldn't be evaluated
+    DebugEvaluated { result: Result<String, String> },
+
+    /// Sends the controller the profiler's results, slowest first, along with the same data as folded stacks
+    ProfileReport { entries: Vec<ProfileEntry>, folded: String },
 }
 
 /// The details of an instance sent to the control panel for display.
@@ -84,6 +124,73 @@ pub struct InstanceDetails {
     pub bbox_bottom: i32,
 }
 
+/// The state of the first joystick sent to the control panel for display, if one is connected.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JoystickDetails {
+    pub x: f64,
+    pub y: f64,
+    pub buttons_held: Vec<u8>,
+}
+
+/// A piece of GML code which the debugger can stop in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodeLocation {
+    /// A code action. The object is the one the event belongs to, which may be a parent of the running instance.
+    Action { object: ID, event_type: usize, event_number: usize, action: usize },
+
+    /// A script
+    Script { script: ID },
+
+    /// The condition of a trigger
+    Trigger { trigger: ID },
+
+    /// A room's creation code
+    RoomCreation { room: ID },
+
+    /// The creation code of an instance placed in a room
+    InstanceCreation { instance: ID },
+}
+
+/// A line of GML code to pause at.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub location: CodeLocation,
+    pub line: usize,
+}
+
+/// How far to run before the debugger pauses again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DebugStep {
+    /// Only pause at a breakpoint
+    Continue,
+    /// Pause at the next line, including in any code called from this one
+    Into,
+    /// Pause at the next line of this code or any code which called it
+    Over,
+    /// Pause at the next line of the code which called this one
+    Out,
+}
+
+/// A piece of code on the call stack of the paused game.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DebugFrame {
+    pub location: CodeLocation,
+    pub line: usize,
+    /// The location written out the way GM8's error messages do
+    pub description: String,
+}
+
+/// The time spent in one object event, script or builtin function while the profiler was on.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub calls: u64,
+    /// Time spent in it, including anything it called
+    pub inclusive: Duration,
+    /// Time spent in it, not including anything it called
+    pub exclusive: Duration,
+}
+
 pub trait MessageStream {
     /// Serializes an object using bincode, then writes it as a length-tagged byte stream.
     fn send_message<S>(&mut self, s: S) -> io::Result<()>